@@ -9,7 +9,10 @@ use crate::cfhdb::bt::PreCheckedBtProfile;
 use crate::cfhdb::dmi::PreCheckedDmiProfile;
 use crate::{
     build_ui::color_badge::ColorBadge,
-    cfhdb::{pci::PreCheckedPciProfile, usb::PreCheckedUsbProfile},
+    cfhdb::{
+        pci::PreCheckedPciProfile, policy::PROFILE_POLICY, usb::PreCheckedUsbProfile,
+        PreCheckedProfile,
+    },
 };
 
 use super::{bt, dmi, pci, usb};
@@ -30,6 +33,10 @@ pub fn all_profile_dialog(
     let hide_noncompatible_profiles_checkbutton = gtk::CheckButton::builder()
         .label(t!("hide_noncompatible_profiles_checkbutton_label"))
         .build();
+    let hide_denied_profiles_checkbutton = gtk::CheckButton::builder()
+        .label(t!("hide_denied_profiles_checkbutton_label"))
+        .active(PROFILE_POLICY.hide_denied)
+        .build();
     let profiles_checkbutton_box = gtk::Box::builder()
         .orientation(Orientation::Horizontal)
         .spacing(5)
@@ -40,6 +47,7 @@ pub fn all_profile_dialog(
         .build();
     profiles_checkbutton_box.append(&hide_noninstalled_profiles_checkbutton);
     profiles_checkbutton_box.append(&hide_noncompatible_profiles_checkbutton);
+    profiles_checkbutton_box.append(&hide_denied_profiles_checkbutton);
     let boxedlist = gtk::ListBox::builder()
         .vexpand(true)
         .hexpand(true)
@@ -116,6 +124,16 @@ pub fn all_profile_dialog(
         profile_expander_row.add_suffix(&profile_status_icon);
         profile_expander_row.set_title(&profile_content.i18n_desc);
         profile_expander_row.set_subtitle(&profile_content.codename);
+        let profile_policy_denial = PROFILE_POLICY.check(&profile.summary());
+        if let Some(reason) = &profile_policy_denial {
+            let profile_locked_icon = gtk::Image::builder()
+                .icon_name("changes-prevent-symbolic")
+                .tooltip_text(t!("policy_locked_icon_tooltip_text"))
+                .build();
+            profile_expander_row.add_suffix(&profile_locked_icon);
+            profile_content_row.set_subtitle(reason);
+            profile_install_button.set_tooltip_text(Some(reason));
+        }
         //
         let color_badge_experimental = ColorBadge::new();
         color_badge_experimental.set_label0(textwrap::fill(&t!("profile_experimental"), 10));
@@ -200,17 +218,26 @@ pub fn all_profile_dialog(
             hide_noncompatible_profiles_checkbutton,
             #[strong]
             hide_noninstalled_profiles_checkbutton,
+            #[strong]
+            hide_denied_profiles_checkbutton,
+            #[strong]
+            profile_policy_denial,
             move |_: &CheckButton| {
-                profile_expander_row.set_visible(recheck_hide(
-                    hide_noncompatible_profiles_checkbutton.is_active(),
-                    hide_noninstalled_profiles_checkbutton.is_active(),
-                    *profile.used.lock().unwrap(),
-                    profile.installed(),
-                ));
+                profile_expander_row.set_visible(
+                    recheck_hide(
+                        hide_noncompatible_profiles_checkbutton.is_active(),
+                        hide_noninstalled_profiles_checkbutton.is_active(),
+                        *profile.used.lock().unwrap(),
+                        profile.installed(),
+                    ) && !(hide_denied_profiles_checkbutton.is_active()
+                        && profile_policy_denial.is_some()
+                        && !profile.installed()),
+                );
             }
         );
         hide_noninstalled_profiles_checkbutton.connect_toggled(recheck_hide_closure.clone());
         hide_noncompatible_profiles_checkbutton.connect_toggled(recheck_hide_closure.clone());
+        hide_denied_profiles_checkbutton.connect_toggled(recheck_hide_closure.clone());
         //
         boxedlist.append(&profile_expander_row);
         //
@@ -219,7 +246,8 @@ pub fn all_profile_dialog(
             hide_noninstalled_profiles_checkbutton,
             move |_, _| {
                 let profile_status = profile.installed();
-                profile_install_button.set_sensitive(!profile_status);
+                profile_install_button
+                    .set_sensitive(!profile_status && profile_policy_denial.is_none());
                 if profile_content.removable {
                     profile_remove_button.set_sensitive(profile_status);
                 } else {
@@ -277,6 +305,16 @@ pub fn all_profile_dialog(
         profile_expander_row.add_suffix(&profile_status_icon);
         profile_expander_row.set_title(&profile_content.i18n_desc);
         profile_expander_row.set_subtitle(&profile_content.codename);
+        let profile_policy_denial = PROFILE_POLICY.check(&profile.summary());
+        if let Some(reason) = &profile_policy_denial {
+            let profile_locked_icon = gtk::Image::builder()
+                .icon_name("changes-prevent-symbolic")
+                .tooltip_text(t!("policy_locked_icon_tooltip_text"))
+                .build();
+            profile_expander_row.add_suffix(&profile_locked_icon);
+            profile_content_row.set_subtitle(reason);
+            profile_install_button.set_tooltip_text(Some(reason));
+        }
         //
         let color_badge_experimental = ColorBadge::new();
         color_badge_experimental.set_label0(textwrap::fill(&t!("profile_experimental"), 10));
@@ -361,17 +399,26 @@ pub fn all_profile_dialog(
             hide_noncompatible_profiles_checkbutton,
             #[strong]
             hide_noninstalled_profiles_checkbutton,
+            #[strong]
+            hide_denied_profiles_checkbutton,
+            #[strong]
+            profile_policy_denial,
             move |_: &CheckButton| {
-                profile_expander_row.set_visible(recheck_hide(
-                    hide_noncompatible_profiles_checkbutton.is_active(),
-                    hide_noninstalled_profiles_checkbutton.is_active(),
-                    *profile.used.lock().unwrap(),
-                    profile.installed(),
-                ));
+                profile_expander_row.set_visible(
+                    recheck_hide(
+                        hide_noncompatible_profiles_checkbutton.is_active(),
+                        hide_noninstalled_profiles_checkbutton.is_active(),
+                        *profile.used.lock().unwrap(),
+                        profile.installed(),
+                    ) && !(hide_denied_profiles_checkbutton.is_active()
+                        && profile_policy_denial.is_some()
+                        && !profile.installed()),
+                );
             }
         );
         hide_noninstalled_profiles_checkbutton.connect_toggled(recheck_hide_closure.clone());
         hide_noncompatible_profiles_checkbutton.connect_toggled(recheck_hide_closure.clone());
+        hide_denied_profiles_checkbutton.connect_toggled(recheck_hide_closure.clone());
         //
         boxedlist.append(&profile_expander_row);
         //
@@ -380,7 +427,8 @@ pub fn all_profile_dialog(
             hide_noninstalled_profiles_checkbutton,
             move |_, _| {
                 let profile_status = profile.installed();
-                profile_install_button.set_sensitive(!profile_status);
+                profile_install_button
+                    .set_sensitive(!profile_status && profile_policy_denial.is_none());
                 if profile_content.removable {
                     profile_remove_button.set_sensitive(profile_status);
                 } else {
@@ -438,6 +486,16 @@ pub fn all_profile_dialog(
         profile_expander_row.add_suffix(&profile_status_icon);
         profile_expander_row.set_title(&profile_content.i18n_desc);
         profile_expander_row.set_subtitle(&profile_content.codename);
+        let profile_policy_denial = PROFILE_POLICY.check(&profile.summary());
+        if let Some(reason) = &profile_policy_denial {
+            let profile_locked_icon = gtk::Image::builder()
+                .icon_name("changes-prevent-symbolic")
+                .tooltip_text(t!("policy_locked_icon_tooltip_text"))
+                .build();
+            profile_expander_row.add_suffix(&profile_locked_icon);
+            profile_content_row.set_subtitle(reason);
+            profile_install_button.set_tooltip_text(Some(reason));
+        }
         //
         let color_badge_experimental = ColorBadge::new();
         color_badge_experimental.set_label0(textwrap::fill(&t!("profile_experimental"), 10));
@@ -522,17 +580,26 @@ pub fn all_profile_dialog(
             hide_noncompatible_profiles_checkbutton,
             #[strong]
             hide_noninstalled_profiles_checkbutton,
+            #[strong]
+            hide_denied_profiles_checkbutton,
+            #[strong]
+            profile_policy_denial,
             move |_: &CheckButton| {
-                profile_expander_row.set_visible(recheck_hide(
-                    hide_noncompatible_profiles_checkbutton.is_active(),
-                    hide_noninstalled_profiles_checkbutton.is_active(),
-                    *profile.used.lock().unwrap(),
-                    profile.installed(),
-                ));
+                profile_expander_row.set_visible(
+                    recheck_hide(
+                        hide_noncompatible_profiles_checkbutton.is_active(),
+                        hide_noninstalled_profiles_checkbutton.is_active(),
+                        *profile.used.lock().unwrap(),
+                        profile.installed(),
+                    ) && !(hide_denied_profiles_checkbutton.is_active()
+                        && profile_policy_denial.is_some()
+                        && !profile.installed()),
+                );
             }
         );
         hide_noninstalled_profiles_checkbutton.connect_toggled(recheck_hide_closure.clone());
         hide_noncompatible_profiles_checkbutton.connect_toggled(recheck_hide_closure.clone());
+        hide_denied_profiles_checkbutton.connect_toggled(recheck_hide_closure.clone());
         //
         boxedlist.append(&profile_expander_row);
         //
@@ -541,7 +608,8 @@ pub fn all_profile_dialog(
             hide_noninstalled_profiles_checkbutton,
             move |_, _| {
                 let profile_status = profile.installed();
-                profile_install_button.set_sensitive(!profile_status);
+                profile_install_button
+                    .set_sensitive(!profile_status && profile_policy_denial.is_none());
                 if profile_content.removable {
                     profile_remove_button.set_sensitive(profile_status);
                 } else {
@@ -599,6 +667,16 @@ pub fn all_profile_dialog(
         profile_expander_row.add_suffix(&profile_status_icon);
         profile_expander_row.set_title(&profile_content.i18n_desc);
         profile_expander_row.set_subtitle(&profile_content.codename);
+        let profile_policy_denial = PROFILE_POLICY.check(&profile.summary());
+        if let Some(reason) = &profile_policy_denial {
+            let profile_locked_icon = gtk::Image::builder()
+                .icon_name("changes-prevent-symbolic")
+                .tooltip_text(t!("policy_locked_icon_tooltip_text"))
+                .build();
+            profile_expander_row.add_suffix(&profile_locked_icon);
+            profile_content_row.set_subtitle(reason);
+            profile_install_button.set_tooltip_text(Some(reason));
+        }
         //
         let color_badge_experimental = ColorBadge::new();
         color_badge_experimental.set_label0(textwrap::fill(&t!("profile_experimental"), 10));
@@ -683,17 +761,26 @@ pub fn all_profile_dialog(
             hide_noncompatible_profiles_checkbutton,
             #[strong]
             hide_noninstalled_profiles_checkbutton,
+            #[strong]
+            hide_denied_profiles_checkbutton,
+            #[strong]
+            profile_policy_denial,
             move |_: &CheckButton| {
-                profile_expander_row.set_visible(recheck_hide(
-                    hide_noncompatible_profiles_checkbutton.is_active(),
-                    hide_noninstalled_profiles_checkbutton.is_active(),
-                    *profile.used.lock().unwrap(),
-                    profile.installed(),
-                ));
+                profile_expander_row.set_visible(
+                    recheck_hide(
+                        hide_noncompatible_profiles_checkbutton.is_active(),
+                        hide_noninstalled_profiles_checkbutton.is_active(),
+                        *profile.used.lock().unwrap(),
+                        profile.installed(),
+                    ) && !(hide_denied_profiles_checkbutton.is_active()
+                        && profile_policy_denial.is_some()
+                        && !profile.installed()),
+                );
             }
         );
         hide_noninstalled_profiles_checkbutton.connect_toggled(recheck_hide_closure.clone());
         hide_noncompatible_profiles_checkbutton.connect_toggled(recheck_hide_closure.clone());
+        hide_denied_profiles_checkbutton.connect_toggled(recheck_hide_closure.clone());
         //
         boxedlist.append(&profile_expander_row);
         //
@@ -702,7 +789,8 @@ pub fn all_profile_dialog(
             hide_noninstalled_profiles_checkbutton,
             move |_, _| {
                 let profile_status = profile.installed();
-                profile_install_button.set_sensitive(!profile_status);
+                profile_install_button
+                    .set_sensitive(!profile_status && profile_policy_denial.is_none());
                 if profile_content.removable {
                     profile_remove_button.set_sensitive(profile_status);
                 } else {
@@ -0,0 +1,161 @@
+use crate::cfhdb::{
+    get_profile_modify_commands, recommendation::Recommendation, PreCheckedProfile,
+};
+use adw::prelude::*;
+use gtk::{
+    gio,
+    glib::{self, clone},
+};
+use log::info;
+use std::sync::Arc;
+
+use super::{error_dialog, privileged_script_dialog::privileged_script_dialog};
+
+/// Installs or removes several profiles, possibly from different buses, in one privileged script.
+pub fn batch_profile_modify(
+    window: &adw::ApplicationWindow,
+    update_device_status_action: &gio::SimpleAction,
+    jobs: Vec<(Arc<dyn PreCheckedProfile>, String)>,
+    all_profiles: Vec<Arc<dyn PreCheckedProfile>>,
+) {
+    let mut script = String::new();
+    let mut denied = vec![];
+    for (profile, opreation) in &jobs {
+        let profile_summary = profile.summary();
+        info!(
+            target: "executor",
+            "Queuing {} of {} profile {}",
+            opreation,
+            profile_summary.bus.to_uppercase(),
+            profile_summary.codename
+        );
+        match get_profile_modify_commands(&profile_summary, opreation) {
+            Ok(Some(t)) => script.push_str(&format!(
+                "echo '==> {} {}'\n{}\n",
+                opreation, profile_summary.codename, t
+            )),
+            Ok(None) => {}
+            Err(reason) => denied.push(format!("{}: {}", profile_summary.codename, reason)),
+        }
+    }
+
+    // Nothing is run if any part of the batch is refused by the site policy
+    if !denied.is_empty() {
+        error_dialog(
+            window.clone(),
+            &t!("policy_denied_dialog_heading"),
+            &denied.join("\n"),
+        );
+        return;
+    }
+
+    privileged_script_dialog(
+        window,
+        &t!("batch_profile_modify_dialog_heading"),
+        &t!("batch_profile_modify_dialog_body_successful"),
+        &t!("batch_profile_modify_dialog_body_failed"),
+        (!script.is_empty()).then_some(script),
+        true,
+        clone!(
+            #[strong]
+            update_device_status_action,
+            move || {
+                for a_profile in all_profiles.iter() {
+                    a_profile.refresh_installed();
+                }
+                update_device_status_action.activate(None);
+            }
+        ),
+    );
+}
+
+/// Lists the pending recommendations and installs all of them once the user confirms.
+pub fn install_recommended_dialog(
+    window: &adw::ApplicationWindow,
+    update_device_status_action: &gio::SimpleAction,
+    recommendations: Vec<Recommendation>,
+    all_profiles: Vec<Arc<dyn PreCheckedProfile>>,
+) {
+    let install_recommended_dialog = adw::AlertDialog::builder()
+        .heading(t!("install_recommended_dialog_heading"))
+        .build();
+
+    if recommendations.is_empty() {
+        install_recommended_dialog.set_body(&t!("install_recommended_dialog_body_empty"));
+        install_recommended_dialog.add_response(
+            "install_recommended_dialog_ok",
+            &t!("install_recommended_dialog_ok_label"),
+        );
+        install_recommended_dialog.present(Some(window));
+        return;
+    }
+
+    install_recommended_dialog.set_body(&t!(
+        "install_recommended_dialog_body",
+        count = recommendations.len()
+    ));
+
+    let recommendations_list = gtk::ListBox::builder()
+        .selection_mode(gtk::SelectionMode::None)
+        .build();
+    recommendations_list.add_css_class("boxed-list");
+    for recommendation in &recommendations {
+        let profile_summary = recommendation.profile.summary();
+        let recommendation_row = adw::ActionRow::builder()
+            .title(glib::markup_escape_text(&profile_summary.i18n_desc))
+            .subtitle(glib::markup_escape_text(&format!(
+                "{} - {}",
+                profile_summary.codename, recommendation.device
+            )))
+            .build();
+        recommendation_row.add_prefix(
+            &gtk::Image::builder()
+                .icon_name(&profile_summary.icon_name)
+                .pixel_size(32)
+                .build(),
+        );
+        recommendations_list.append(&recommendation_row);
+    }
+    install_recommended_dialog.set_extra_child(Some(
+        &gtk::ScrolledWindow::builder()
+            .hscrollbar_policy(gtk::PolicyType::Never)
+            .propagate_natural_height(true)
+            .max_content_height(300)
+            .child(&recommendations_list)
+            .build(),
+    ));
+
+    install_recommended_dialog.add_response(
+        "install_recommended_dialog_cancel",
+        &t!("install_recommended_dialog_cancel_label"),
+    );
+    install_recommended_dialog.add_response(
+        "install_recommended_dialog_install",
+        &t!("install_recommended_dialog_install_label"),
+    );
+    install_recommended_dialog.set_response_appearance(
+        "install_recommended_dialog_install",
+        adw::ResponseAppearance::Suggested,
+    );
+
+    let jobs: Vec<(Arc<dyn PreCheckedProfile>, String)> = recommendations
+        .into_iter()
+        .map(|x| (x.profile, String::from("install")))
+        .collect();
+
+    install_recommended_dialog.choose(
+        window,
+        gio::Cancellable::NONE,
+        clone!(
+            #[strong]
+            window,
+            #[strong]
+            update_device_status_action,
+            move |choice: glib::GString| {
+                if choice == "install_recommended_dialog_install" {
+                    batch_profile_modify(&window, &update_device_status_action, jobs, all_profiles);
+                }
+            }
+        ),
+    );
+}
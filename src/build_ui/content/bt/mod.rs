@@ -1,23 +1,34 @@
 use crate::{
     build_ui::{color_badge::ColorBadge, colored_circle::ColoredCircle},
-    cfhdb::bt::{PreCheckedBtDevice, PreCheckedBtProfile},
-    config::distro_package_manager,
+    cfhdb::{
+        bluez::{
+            get_device_battery, is_device_object, remove_device, set_device_alias,
+            subscribe_bluez_changes,
+        },
+        bt::{PreCheckedBtDevice, PreCheckedBtProfile},
+        bt_audio::{get_bt_audio_card, is_audio_class, set_bt_audio_codec, set_bt_audio_profile},
+        get_profile_modify_commands,
+        policy::PROFILE_POLICY,
+        recommendation::recommended_profile,
+        PreCheckedProfile,
+    },
     ChannelMsg,
 };
 use adw::{prelude::*, *};
 use gtk::{
     gdk::RGBA,
-    glib::{clone, MainContext},
+    glib::{self, clone, MainContext},
     Align, Orientation,
     Orientation::Vertical,
     ScrolledWindow, SelectionMode,
 };
 use libcfhdb::bt::CfhdbBtDevice;
-use std::{process::Command, rc::Rc, sync::Arc, thread};
+use log::info;
+use std::{cell::RefCell, process::Command, rc::Rc, sync::Arc, thread};
 
 use users::get_current_username;
 
-use super::{error_dialog, run_in_lock_script};
+use super::{add_profile_row_badges, error_dialog, run_in_lock_script};
 
 pub fn create_bt_class(
     window: &ApplicationWindow,
@@ -25,6 +36,7 @@ pub fn create_bt_class(
     class: &str,
     theme_changed_action: &gio::SimpleAction,
     update_device_status_action: &gio::SimpleAction,
+    push_device_page_action: &gio::SimpleAction,
 ) -> ScrolledWindow {
     // Update all profiles' installation status before creating the UI
     for device in devices {
@@ -55,6 +67,7 @@ pub fn create_bt_class(
     );
     let devices_navigation_page = adw::NavigationPage::builder()
         .title(class)
+        .tag("devices_list")
         .child(&devices_navigation_page_toolbar)
         .build();
     //
@@ -66,6 +79,17 @@ pub fn create_bt_class(
         .hscrollbar_policy(gtk::PolicyType::Never)
         .child(&navigation_view)
         .build();
+    push_device_page_action.connect_activate(clone!(
+        #[weak]
+        navigation_view,
+        move |_, param| {
+            if let Some(device_tag) = param.and_then(|x| x.get::<String>()) {
+                if navigation_view.find_page(&device_tag).is_some() {
+                    navigation_view.replace_with_tags(&["devices_list", &device_tag]);
+                }
+            }
+        }
+    ));
     //
     for device in devices {
         let device_content = &device.device;
@@ -90,6 +114,7 @@ pub fn create_bt_class(
         );
         let device_navigation_page = adw::NavigationPage::builder()
             .title(&device_title)
+            .tag(&device_content.address)
             .child(&device_navigation_page_toolbar)
             .build();
         navigation_view.add(&device_navigation_page);
@@ -112,6 +137,31 @@ pub fn create_bt_class(
         ));
         action_row.add_suffix(&device_status_indicator);
         devices_list_row.append(&action_row);
+        update_device_status_action.connect_activate(clone!(
+            #[weak]
+            navigation_view,
+            #[weak]
+            device_navigation_page,
+            #[weak]
+            action_row,
+            #[strong(rename_to = address)]
+            device_content.address,
+            move |_, _| match CfhdbBtDevice::get_device_from_address(&address) {
+                Ok(updated_device) => {
+                    let device_title =
+                        format!("{} - {}", &updated_device.alias, &updated_device.name);
+                    action_row.set_title(&device_title);
+                    device_navigation_page.set_title(&device_title);
+                }
+                // Forgotten, BlueZ no longer knows the device
+                Err(_) => {
+                    action_row.set_visible(false);
+                    if navigation_view.visible_page().as_ref() == Some(&device_navigation_page) {
+                        navigation_view.pop();
+                    }
+                }
+            }
+        ));
     }
     scroll
 }
@@ -226,6 +276,15 @@ fn bt_device_page(
     product_id_color_badge.set_theme_changed_action(theme_changed_action);
 
     color_badges_vec.push(&product_id_color_badge);
+
+    let battery_color_badge = ColorBadge::new();
+    battery_color_badge.set_label0(textwrap::fill(&t!("device_battery"), 10));
+    battery_color_badge.set_css_style("background-accent-bg");
+    battery_color_badge.set_group_size0(&color_badges_size_group0);
+    battery_color_badge.set_group_size1(&color_badges_size_group1);
+    battery_color_badge.set_theme_changed_action(theme_changed_action);
+
+    color_badges_vec.push(&battery_color_badge);
     //
     let mut last_widget: (Option<&ColorBadge>, i32) = (None, 0);
     let row_count = (color_badges_vec.len() / 2) as i32;
@@ -341,6 +400,17 @@ fn bt_device_page(
         .height_request(48)
         .tooltip_text(t!("device_control_unblock"))
         .build();
+    let control_button_forget_device_button = gtk::Button::builder()
+        .child(
+            &gtk::Image::builder()
+                .icon_name("user-trash-symbolic")
+                .pixel_size(32)
+                .build(),
+        )
+        .width_request(48)
+        .height_request(48)
+        .tooltip_text(t!("device_control_forget"))
+        .build();
 
     let available_profiles_list_row = adw::PreferencesGroup::builder()
         .margin_top(20)
@@ -357,6 +427,7 @@ fn bt_device_page(
     let rows_size_group = gtk::SizeGroup::new(gtk::SizeGroupMode::Both);
 
     let mut profiles = device.profiles.clone();
+    let recommended_profile = recommended_profile(&device.profiles);
     profiles.sort_by_key(|x| x.profile().priority);
 
     control_button_pair_device_button.connect_clicked(clone!(
@@ -468,10 +539,94 @@ fn bt_device_page(
         }
     ));
 
+    let update_battery = clone!(
+        #[strong]
+        device_content,
+        #[strong]
+        battery_color_badge,
+        move || match get_device_battery(&device_content.adapter, &device_content.address) {
+            Some(percentage) => {
+                battery_color_badge.set_label1(format!("{}%", percentage));
+                battery_color_badge.set_css_style(if percentage > 20 {
+                    "background-accent-bg"
+                } else {
+                    "background-red-bg"
+                });
+            }
+            None => {
+                battery_color_badge.set_label1(textwrap::fill(&t!("device_battery_unknown"), 10));
+                battery_color_badge.set_css_style("background-accent-bg");
+            }
+        }
+    );
+
+    // Battery levels change on their own, so follow BlueZ instead of waiting for a refresh
+    subscribe_bluez_changes(clone!(
+        #[strong]
+        device_content,
+        #[strong]
+        update_battery,
+        move |object_path| {
+            if is_device_object(
+                object_path,
+                &device_content.adapter,
+                &device_content.address,
+            ) {
+                update_battery();
+            }
+        }
+    ));
+
+    control_button_forget_device_button.connect_clicked(clone!(
+        #[strong]
+        device_content,
+        #[strong]
+        window,
+        #[strong]
+        update_device_status_action,
+        move |_| forget_device_dialog(&window, &update_device_status_action, &device_content)
+    ));
+
+    let alias_group = adw::PreferencesGroup::builder()
+        .margin_start(20)
+        .margin_end(20)
+        .build();
+    let alias_entry_row = adw::EntryRow::builder()
+        .title(t!("device_alias_title"))
+        .text(&device_content.alias)
+        .show_apply_button(true)
+        .build();
+    alias_entry_row.connect_apply(clone!(
+        #[strong]
+        device_content,
+        #[strong]
+        window,
+        #[strong]
+        update_device_status_action,
+        move |entry_row| {
+            let alias = entry_row.text().trim().to_string();
+            info!(
+                target: "bt",
+                "Setting alias of {} to \"{}\"",
+                device_content.address,
+                alias
+            );
+            match set_device_alias(&device_content.adapter, &device_content.address, &alias) {
+                Ok(_) => update_device_status_action.activate(None),
+                Err(e) => error_dialog(window.clone(), &t!("device_alias_error"), &e),
+            }
+        }
+    ));
+    alias_group.add(&alias_entry_row);
+
     let mut normal_profiles = vec![];
     let mut veiled_profiles = vec![];
 
     for profile in profiles.clone() {
+        // Installed profiles stay listed so they can still be removed
+        if PROFILE_POLICY.hides(&profile.summary()) && !profile.installed() {
+            continue;
+        }
         let profile_content = profile.profile();
         let (profiles_color_badges_size_group0, profiles_color_badges_size_group1) = (
             gtk::SizeGroup::new(gtk::SizeGroupMode::Both),
@@ -514,6 +669,15 @@ fn bt_device_page(
         profile_expander_row.add_suffix(&profile_status_icon);
         profile_expander_row.set_title(&profile_content.i18n_desc);
         profile_expander_row.set_subtitle(&profile_content.codename);
+        let profile_policy_denial = add_profile_row_badges(
+            &profile_expander_row,
+            &profile_content_row,
+            &profile_install_button,
+            &profile.summary(),
+            recommended_profile
+                .as_ref()
+                .is_some_and(|x| Arc::ptr_eq(x, &profile)),
+        );
         //
         let color_badge_experimental = ColorBadge::new();
         color_badge_experimental.set_label0(textwrap::fill(&t!("profile_experimental"), 10));
@@ -598,7 +762,8 @@ fn bt_device_page(
         //
         update_device_status_action.connect_activate(clone!(move |_, _| {
             let profile_status = profile.installed();
-            profile_install_button.set_sensitive(!profile_status);
+            profile_install_button
+                .set_sensitive(!profile_status && profile_policy_denial.is_none());
             if profile_content.removable {
                 profile_remove_button.set_sensitive(profile_status);
             } else {
@@ -627,9 +792,14 @@ fn bt_device_page(
         control_button_block_device_button,
         #[strong]
         control_button_unblock_device_button,
+        #[strong]
+        update_battery,
         move |_, _| {
-            let updated_device =
-                CfhdbBtDevice::get_device_from_address(&device_content.address).unwrap();
+            let Ok(updated_device) =
+                CfhdbBtDevice::get_device_from_address(&device_content.address)
+            else {
+                return;
+            };
             let (paired, connected, trusted, blocked) = (
                 updated_device.paired,
                 updated_device.connected,
@@ -715,6 +885,7 @@ fn bt_device_page(
                 &device_content.modalias_product_id.as_str(),
                 10,
             ));
+            update_battery();
         }
     ));
 
@@ -734,8 +905,18 @@ fn bt_device_page(
 
     device_controls_box.append(&control_button_unblock_device_button);
 
+    device_controls_box.append(&control_button_forget_device_button);
+
     content_box.append(&color_badges_grid);
     content_box.append(&device_controls_box);
+    content_box.append(&alias_group);
+    if is_audio_class(&device_content.class_id) {
+        content_box.append(&audio_group(
+            window,
+            &device_content.address,
+            update_device_status_action,
+        ));
+    }
     for widget in normal_profiles {
         available_profiles_list_row.add(&widget);
     }
@@ -769,6 +950,186 @@ fn bt_device_page(
     content_box
 }
 
+/// Card profile and codec of a headset or speaker, which decide between call and music quality.
+fn audio_group(
+    window: &ApplicationWindow,
+    address: &str,
+    update_device_status_action: &gio::SimpleAction,
+) -> PreferencesGroup {
+    let audio_group = PreferencesGroup::builder()
+        .margin_top(20)
+        .margin_start(20)
+        .margin_end(20)
+        .title(t!("bt_audio_title"))
+        .description(t!("bt_audio_subtitle"))
+        .hexpand(true)
+        .build();
+
+    let audio_rows: Rc<RefCell<Vec<adw::PreferencesRow>>> = Rc::new(RefCell::new(vec![]));
+    let address = address.to_string();
+    let update_audio = clone!(
+        #[strong]
+        window,
+        #[strong]
+        audio_group,
+        #[strong]
+        update_device_status_action,
+        move || {
+            for row in audio_rows.borrow_mut().drain(..) {
+                audio_group.remove(&row);
+            }
+            let card = match get_bt_audio_card(&address) {
+                Ok(Some(t)) => t,
+                Ok(None) => {
+                    let not_connected_row = ActionRow::builder()
+                        .title(t!("bt_audio_not_connected"))
+                        .build();
+                    audio_group.add(&not_connected_row);
+                    audio_rows.borrow_mut().push(not_connected_row.upcast());
+                    return;
+                }
+                Err(e) => {
+                    let error_row = ActionRow::builder()
+                        .title(t!("bt_audio_unavailable"))
+                        .subtitle(glib::markup_escape_text(&e))
+                        .build();
+                    audio_group.add(&error_row);
+                    audio_rows.borrow_mut().push(error_row.upcast());
+                    return;
+                }
+            };
+            let card_name = Rc::new(card.name.clone());
+
+            let profiles: Vec<(String, String)> = card
+                .profiles
+                .iter()
+                .filter(|x| x.available || card.active_profile.as_ref() == Some(&x.name))
+                .map(|x| (x.name.clone(), x.description.clone()))
+                .collect();
+            let profile_combo_row = audio_combo_row(
+                &t!("bt_audio_profile_title"),
+                &profiles,
+                card.active_profile.as_deref(),
+            );
+            profile_combo_row.connect_selected_notify(clone!(
+                #[strong]
+                window,
+                #[strong]
+                update_device_status_action,
+                #[strong]
+                card_name,
+                move |combo_row| {
+                    let Some((profile, _)) = profiles.get(combo_row.selected() as usize) else {
+                        return;
+                    };
+                    match set_bt_audio_profile(&card_name, profile) {
+                        Ok(_) => update_device_status_action.activate(None),
+                        Err(e) => error_dialog(window.clone(), &t!("bt_audio_profile_error"), &e),
+                    }
+                }
+            ));
+            audio_group.add(&profile_combo_row);
+            audio_rows.borrow_mut().push(profile_combo_row.upcast());
+
+            // Only A2DP profiles can switch codecs
+            if card.codecs.is_empty() {
+                return;
+            }
+            let codecs = card.codecs;
+            let codec_combo_row = audio_combo_row(
+                &t!("bt_audio_codec_title"),
+                &codecs,
+                card.active_codec.as_deref(),
+            );
+            codec_combo_row.connect_selected_notify(clone!(
+                #[strong]
+                window,
+                #[strong]
+                update_device_status_action,
+                #[strong]
+                card_name,
+                move |combo_row| {
+                    let Some((codec, _)) = codecs.get(combo_row.selected() as usize) else {
+                        return;
+                    };
+                    match set_bt_audio_codec(&card_name, codec) {
+                        Ok(_) => update_device_status_action.activate(None),
+                        Err(e) => error_dialog(window.clone(), &t!("bt_audio_codec_error"), &e),
+                    }
+                }
+            ));
+            audio_group.add(&codec_combo_row);
+            audio_rows.borrow_mut().push(codec_combo_row.upcast());
+        }
+    );
+    update_audio();
+    update_device_status_action.connect_activate(move |_, _| update_audio());
+
+    audio_group
+}
+
+/// A combo row of (name, description) choices showing the descriptions, with the active one selected.
+fn audio_combo_row(title: &str, choices: &[(String, String)], active: Option<&str>) -> ComboRow {
+    let choices_model =
+        gtk::StringList::new(&choices.iter().map(|x| x.1.as_str()).collect::<Vec<_>>());
+    let combo_row = ComboRow::builder()
+        .title(title)
+        .model(&choices_model)
+        .build();
+    if let Some(position) = choices.iter().position(|x| Some(x.0.as_str()) == active) {
+        combo_row.set_selected(position as u32);
+    }
+    combo_row
+}
+
+fn forget_device_dialog(
+    window: &ApplicationWindow,
+    update_device_status_action: &gio::SimpleAction,
+    device_content: &CfhdbBtDevice,
+) {
+    let forget_device_dialog = adw::AlertDialog::builder()
+        .heading(t!("device_forget_dialog_heading"))
+        .body(t!(
+            "device_forget_dialog_body",
+            device = format!("{} - {}", device_content.alias, device_content.name)
+        ))
+        .build();
+    forget_device_dialog.add_response(
+        "forget_device_dialog_cancel",
+        &t!("install_recommended_dialog_cancel_label"),
+    );
+    forget_device_dialog.add_response(
+        "forget_device_dialog_forget",
+        &t!("device_forget_dialog_forget_label"),
+    );
+    forget_device_dialog.set_response_appearance(
+        "forget_device_dialog_forget",
+        adw::ResponseAppearance::Destructive,
+    );
+    forget_device_dialog.choose(
+        window,
+        gio::Cancellable::NONE,
+        clone!(
+            #[strong]
+            window,
+            #[strong]
+            update_device_status_action,
+            #[strong]
+            device_content,
+            move |choice: glib::GString| {
+                if choice != "forget_device_dialog_forget" {
+                    return;
+                }
+                info!(target: "bt", "Forgetting {}", device_content.address);
+                match remove_device(&device_content.adapter, &device_content.address) {
+                    Ok(_) => update_device_status_action.activate(None),
+                    Err(e) => error_dialog(window, &t!("device_forget_error"), &e),
+                }
+            }
+        ),
+    );
+}
+
 pub fn profile_modify(
     window: ApplicationWindow,
     update_device_status_action: &gio::SimpleAction,
@@ -782,6 +1143,13 @@ pub fn profile_modify(
 
     let profile_content = profile.profile();
 
+    info!(
+        target: "executor",
+        "Starting {} of Bluetooth profile {}",
+        opreation,
+        profile_content.codename
+    );
+
     let profile_modify_log_terminal_buffer = gtk::TextBuffer::builder().build();
 
     let profile_modify_log_terminal = gtk::TextView::builder()
@@ -822,52 +1190,29 @@ pub fn profile_modify(
     //
 
     let string_opreation = String::from(opreation);
+    let profile_summary = profile.summary();
 
     thread::spawn(clone!(
         #[strong]
-        profile_content,
+        profile_summary,
         #[strong]
         string_opreation,
         move || {
-            let script = match string_opreation.as_str() {
-                "install" => profile_content.install_script,
-                "remove" => profile_content.remove_script,
-                _ => panic!(),
-            };
-            match script {
-                Some(t) => match profile_content.packages {
-                    Some(a) => {
-                        let package_list = a.join(" ");
-                        let modify_command =
-                            distro_package_manager(&string_opreation, &package_list);
-                        run_in_lock_script(
-                            &log_loop_sender,
-                            &format!("#! /bin/bash\nset -e\n{}\n{}", modify_command, t),
-                        );
-                    }
-                    None => {
-                        run_in_lock_script(
-                            &log_loop_sender,
-                            &format!("#! /bin/bash\nset -e\n{}", t),
-                        );
-                    }
-                },
-                None => match profile_content.packages {
-                    Some(a) => {
-                        let package_list = a.join(" ");
-                        let modify_command =
-                            distro_package_manager(&string_opreation, &package_list);
-                        run_in_lock_script(
-                            &log_loop_sender,
-                            &format!("#! /bin/bash\nset -e\n{}", modify_command),
-                        );
-                    }
-                    None => {
-                        log_loop_sender
-                            .send_blocking(ChannelMsg::SuccessMsg)
-                            .unwrap();
-                    }
-                },
+            match get_profile_modify_commands(&profile_summary, &string_opreation) {
+                Err(reason) => {
+                    log_loop_sender
+                        .send_blocking(ChannelMsg::OutputLine(reason))
+                        .unwrap();
+                    log_loop_sender.send_blocking(ChannelMsg::FailMsg).unwrap();
+                }
+                Ok(Some(t)) => {
+                    run_in_lock_script(&log_loop_sender, &format!("#! /bin/bash\nset -e\n{}", t));
+                }
+                Ok(None) => {
+                    log_loop_sender
+                        .send_blocking(ChannelMsg::SuccessMsg)
+                        .unwrap();
+                }
             }
         }
     ));
@@ -0,0 +1,158 @@
+use crate::cfhdb::bluez::{
+    get_bluez_objects, set_adapter_property, subscribe_bluez_changes, BluezAdapter,
+};
+use adw::prelude::*;
+use gtk::glib::{self, clone};
+use log::{error, info};
+use std::rc::Rc;
+
+use super::error_dialog;
+
+pub const BT_ADAPTERS_PAGE_NAME: &str = "bt_adapters";
+
+pub fn bt_adapters_page(window: &adw::ApplicationWindow) -> gtk::ScrolledWindow {
+    let adapters_box = gtk::Box::builder()
+        .orientation(gtk::Orientation::Vertical)
+        .margin_top(20)
+        .margin_bottom(20)
+        .margin_start(20)
+        .margin_end(20)
+        .spacing(20)
+        .build();
+
+    let adapters_scroll = gtk::ScrolledWindow::builder()
+        .max_content_width(650)
+        .min_content_width(300)
+        .hscrollbar_policy(gtk::PolicyType::Never)
+        .vexpand(true)
+        .child(&adapters_box)
+        .build();
+
+    let refresh_adapters = Rc::new(clone!(
+        #[strong]
+        window,
+        #[weak]
+        adapters_box,
+        move || {
+            while let Some(child) = adapters_box.first_child() {
+                adapters_box.remove(&child);
+            }
+            let adapters = match get_bluez_objects() {
+                Ok(t) => t.0,
+                Err(e) => {
+                    error!(target: "bt", "Could not read BlueZ objects: {}", e);
+                    vec![]
+                }
+            };
+            if adapters.is_empty() {
+                adapters_box.append(
+                    &adw::StatusPage::builder()
+                        .icon_name("bluetooth-disabled-symbolic")
+                        .title(t!("bt_adapters_empty_title"))
+                        .description(t!("bt_adapters_empty_description"))
+                        .vexpand(true)
+                        .build(),
+                );
+            }
+            for adapter in adapters {
+                adapters_box.append(&adapter_group(&window, adapter));
+            }
+        }
+    ));
+
+    // Only adapter changes matter here, device changes come in far more often while scanning
+    subscribe_bluez_changes(clone!(
+        #[strong]
+        refresh_adapters,
+        #[weak]
+        adapters_scroll,
+        move |object_path| {
+            if adapters_scroll.is_mapped() && object_path.matches('/').count() == 3 {
+                refresh_adapters()
+            }
+        }
+    ));
+
+    adapters_scroll.connect_map(move |_| refresh_adapters());
+
+    adapters_scroll
+}
+
+fn adapter_group(window: &adw::ApplicationWindow, adapter: BluezAdapter) -> adw::PreferencesGroup {
+    let adapter_group = adw::PreferencesGroup::builder()
+        .title(glib::markup_escape_text(&format!(
+            "{} - {}",
+            adapter.name, adapter.address
+        )))
+        .build();
+    let adapter_path = Rc::new(adapter.path.clone());
+
+    let alias_entry_row = adw::EntryRow::builder()
+        .title(t!("bt_adapter_alias_title"))
+        .text(&adapter.alias)
+        .show_apply_button(true)
+        .build();
+    alias_entry_row.connect_apply(clone!(
+        #[strong]
+        window,
+        #[strong]
+        adapter_path,
+        move |entry_row| {
+            let alias = entry_row.text().trim().to_string();
+            info!(target: "bt", "Setting alias of {} to \"{}\"", adapter_path, alias);
+            if let Err(e) = set_adapter_property(&adapter_path, "Alias", alias.to_variant()) {
+                error_dialog(window.clone(), &t!("bt_adapter_error"), &e);
+            }
+        }
+    ));
+    adapter_group.add(&alias_entry_row);
+
+    for (property, active) in [
+        ("Powered", adapter.powered),
+        ("Discoverable", adapter.discoverable),
+        ("Pairable", adapter.pairable),
+    ] {
+        let property_switch_row = adw::SwitchRow::builder()
+            .title(t!(format!("bt_adapter_{}_title", property.to_lowercase())))
+            .subtitle(t!(format!(
+                "bt_adapter_{}_subtitle",
+                property.to_lowercase()
+            )))
+            .active(active)
+            .build();
+        // Discoverable and pairable mean nothing while the adapter is off
+        property_switch_row.set_sensitive(property == "Powered" || adapter.powered);
+        property_switch_row.connect_active_notify(clone!(
+            #[strong]
+            window,
+            #[strong]
+            adapter_path,
+            move |switch_row| {
+                let active = switch_row.is_active();
+                info!(target: "bt", "Setting {} of {} to {}", property, adapter_path, active);
+                if let Err(e) = set_adapter_property(&adapter_path, property, active.to_variant()) {
+                    error_dialog(window.clone(), &t!("bt_adapter_error"), &e);
+                }
+            }
+        ));
+        adapter_group.add(&property_switch_row);
+    }
+
+    if !adapter.controller.is_empty() {
+        let controller_expander_row = adw::ExpanderRow::builder()
+            .title(t!("bt_adapter_controller_title"))
+            .build();
+        for (field, value) in &adapter.controller {
+            let detail_row = adw::ActionRow::builder()
+                .title(t!(format!("bt_adapter_controller_{}", field)))
+                .subtitle(glib::markup_escape_text(value))
+                .subtitle_selectable(true)
+                .build();
+            detail_row.add_css_class("property");
+            controller_expander_row.add_row(&detail_row);
+        }
+        adapter_group.add(&controller_expander_row);
+    }
+
+    adapter_group
+}
@@ -0,0 +1,239 @@
+use crate::cfhdb::bluez::{get_bluez_objects, set_discovery, subscribe_bluez_changes, BluezDevice};
+use adw::prelude::*;
+use gtk::{
+    gio,
+    glib::{self, clone, MainContext},
+};
+use libcfhdb::bt::CfhdbBtDevice;
+use log::{error, info};
+use std::{
+    cell::{Cell, RefCell},
+    rc::Rc,
+    thread,
+};
+
+use super::error_dialog;
+
+pub const BT_SCAN_PAGE_NAME: &str = "bt_scan";
+
+pub fn bt_scan_page(
+    window: &adw::ApplicationWindow,
+    update_device_status_action: &gio::SimpleAction,
+) -> gtk::ScrolledWindow {
+    let scan_box = gtk::Box::builder()
+        .orientation(gtk::Orientation::Vertical)
+        .margin_top(20)
+        .margin_bottom(20)
+        .margin_start(20)
+        .margin_end(20)
+        .spacing(20)
+        .build();
+
+    let scan_scroll = gtk::ScrolledWindow::builder()
+        .max_content_width(650)
+        .min_content_width(300)
+        .hscrollbar_policy(gtk::PolicyType::Never)
+        .vexpand(true)
+        .child(&scan_box)
+        .build();
+
+    let scan_switch = gtk::Switch::builder().valign(gtk::Align::Center).build();
+    let scan_group = adw::PreferencesGroup::builder()
+        .title(t!("bt_scan_title"))
+        .description(t!("bt_scan_subtitle"))
+        .header_suffix(&scan_switch)
+        .build();
+    scan_box.append(&scan_group);
+
+    let devices_group = adw::PreferencesGroup::builder()
+        .title(t!("bt_scan_devices_title"))
+        .build();
+    scan_box.append(&devices_group);
+
+    // Set while we asked for discovery, so leaving the page only stops what we started
+    let scanning = Rc::new(Cell::new(false));
+    let device_rows: Rc<RefCell<Vec<adw::ActionRow>>> = Rc::default();
+
+    let refresh_devices = clone!(
+        #[strong]
+        window,
+        #[strong]
+        update_device_status_action,
+        #[weak]
+        devices_group,
+        #[weak]
+        scan_switch,
+        #[strong]
+        scanning,
+        move || {
+            for row in device_rows.borrow_mut().drain(..) {
+                devices_group.remove(&row);
+            }
+            let (adapters, mut devices) = match get_bluez_objects() {
+                Ok(t) => t,
+                Err(e) => {
+                    error!(target: "bt", "Could not read BlueZ objects: {}", e);
+                    (vec![], vec![])
+                }
+            };
+            scan_switch.set_sensitive(scanning.get() || adapters.iter().any(|x| x.powered));
+
+            devices.retain(|x| !x.paired);
+            // Closest first, devices that dropped out of range last
+            devices.sort_by_key(|x| std::cmp::Reverse(x.rssi.unwrap_or(i16::MIN)));
+            let placeholder = if adapters.is_empty() {
+                Some(t!("bt_scan_no_adapters"))
+            } else if devices.is_empty() {
+                Some(t!("bt_scan_no_devices"))
+            } else {
+                None
+            };
+            if let Some(placeholder) = placeholder {
+                let placeholder_row = adw::ActionRow::builder().title(placeholder).build();
+                devices_group.add(&placeholder_row);
+                device_rows.borrow_mut().push(placeholder_row);
+            }
+            for device in devices {
+                let device_row =
+                    discovered_device_row(&window, &update_device_status_action, device);
+                devices_group.add(&device_row);
+                device_rows.borrow_mut().push(device_row);
+            }
+        }
+    );
+
+    scan_switch.connect_state_set(clone!(
+        #[strong]
+        window,
+        #[strong]
+        scanning,
+        #[strong]
+        refresh_devices,
+        move |_, state| {
+            let adapters = get_bluez_objects().map(|x| x.0).unwrap_or_default();
+            for adapter in adapters.iter().filter(|x| x.powered) {
+                info!(
+                    target: "bt",
+                    "{} discovery on {}",
+                    if state { "Starting" } else { "Stopping" },
+                    adapter.name
+                );
+                if let Err(e) = set_discovery(&adapter.path, state) {
+                    error!(target: "bt", "Could not change discovery on {}: {}", adapter.name, e);
+                    if state {
+                        error_dialog(window.clone(), &t!("bt_scan_error"), &e);
+                    }
+                }
+            }
+            scanning.set(state);
+            refresh_devices();
+            glib::Propagation::Proceed
+        }
+    ));
+
+    subscribe_bluez_changes(clone!(
+        #[strong]
+        refresh_devices,
+        #[weak]
+        scan_scroll,
+        move |_| {
+            if scan_scroll.is_mapped() {
+                refresh_devices()
+            }
+        }
+    ));
+
+    scan_scroll.connect_map(clone!(
+        #[strong]
+        refresh_devices,
+        move |_| refresh_devices()
+    ));
+
+    scan_scroll.connect_unmap(clone!(
+        #[weak]
+        scan_switch,
+        move |_| {
+            if scanning.get() {
+                scan_switch.set_active(false);
+            }
+        }
+    ));
+
+    scan_scroll
+}
+
+/// A nearby device, paired, trusted and connected in one go with the same calls as the device pages.
+fn discovered_device_row(
+    window: &adw::ApplicationWindow,
+    update_device_status_action: &gio::SimpleAction,
+    device: BluezDevice,
+) -> adw::ActionRow {
+    let device_row = adw::ActionRow::builder()
+        .title(glib::markup_escape_text(&device.alias))
+        .subtitle(glib::markup_escape_text(&match device.rssi {
+            Some(rssi) => t!(
+                "bt_scan_device_subtitle",
+                adapter = device.adapter,
+                address = device.address,
+                rssi = rssi
+            )
+            .to_string(),
+            None => format!("{} - {}", device.adapter, device.address),
+        }))
+        .build();
+    device_row.add_prefix(&gtk::Image::from_icon_name(
+        device.icon.as_deref().unwrap_or("bluetooth-symbolic"),
+    ));
+
+    let pair_button = gtk::Button::builder()
+        .valign(gtk::Align::Center)
+        .label(t!("bt_scan_pair_button_label"))
+        .build();
+    pair_button.add_css_class("suggested-action");
+    pair_button.connect_clicked(clone!(
+        #[strong]
+        window,
+        #[strong]
+        update_device_status_action,
+        move |pair_button| {
+            pair_button.set_sensitive(false);
+            info!(target: "bt", "Pairing {} from discovery", device.address);
+            let (result_sender, result_receiver) = async_channel::bounded(1);
+            let address = device.address.clone();
+            thread::spawn(move || {
+                let result = CfhdbBtDevice::get_device_from_address(&address)
+                    .map_err(|e| (t!("device_pair_error").to_string(), e.to_string()))
+                    .and_then(|x| {
+                        x.pair_device()
+                            .map_err(|e| (t!("device_pair_error").to_string(), e.to_string()))?;
+                        x.trust_device()
+                            .map_err(|e| (t!("device_trust_error").to_string(), e.to_string()))?;
+                        x.connect_device()
+                            .map_err(|e| (t!("device_connect_error").to_string(), e.to_string()))
+                    });
+                result_sender
+                    .send_blocking(result)
+                    .expect("Channel needs to be opened.");
+            });
+            MainContext::default().spawn_local(clone!(
+                #[strong]
+                window,
+                #[strong]
+                update_device_status_action,
+                #[strong]
+                pair_button,
+                async move {
+                    if let Ok(Err((heading, e))) = result_receiver.recv().await {
+                        error!(target: "bt", "{}: {}", heading, e);
+                        error_dialog(window, &heading, &e);
+                        pair_button.set_sensitive(true);
+                    }
+                    update_device_status_action.activate(None);
+                }
+            ));
+        }
+    ));
+    device_row.add_suffix(&pair_button);
+
+    device_row
+}
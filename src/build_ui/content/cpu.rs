@@ -0,0 +1,45 @@
+use crate::cfhdb::{cpu::CpuDevice, sysfs_bus::PreCheckedSysfsDevice};
+use adw::prelude::*;
+use gtk::gio;
+
+/// Lists the state of every vulnerability the kernel knows about, vulnerable ones first.
+pub fn cpu_device_groups(
+    _window: &adw::ApplicationWindow,
+    device: &PreCheckedSysfsDevice<CpuDevice>,
+    _update_device_status_action: &gio::SimpleAction,
+) -> Vec<gtk::Widget> {
+    let device = &device.device;
+    if device.vulnerabilities.is_empty() {
+        return vec![];
+    }
+
+    let vulnerabilities_group = adw::PreferencesGroup::builder()
+        .margin_top(20)
+        .margin_bottom(20)
+        .margin_start(20)
+        .margin_end(20)
+        .title(t!("cpu_vulnerabilities_title"))
+        .description(t!("cpu_vulnerabilities_subtitle"))
+        .hexpand(true)
+        .build();
+
+    let mut vulnerabilities = device.vulnerabilities.clone();
+    vulnerabilities.sort_by_key(|(_, state)| !CpuDevice::is_vulnerable(state));
+    for (vulnerability, state) in vulnerabilities {
+        let vulnerability_row = adw::ActionRow::builder()
+            .title(&vulnerability)
+            .subtitle(gtk::glib::markup_escape_text(&state))
+            .subtitle_selectable(true)
+            .build();
+        vulnerability_row.add_prefix(&gtk::Image::from_icon_name(
+            if CpuDevice::is_vulnerable(&state) {
+                "dialog-warning-symbolic"
+            } else {
+                "emblem-default-symbolic"
+            },
+        ));
+        vulnerabilities_group.add(&vulnerability_row);
+    }
+
+    vec![vulnerabilities_group.upcast()]
+}
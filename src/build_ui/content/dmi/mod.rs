@@ -1,27 +1,35 @@
 use crate::{
     build_ui::{color_badge::ColorBadge, colored_circle::ColoredCircle},
-    cfhdb::dmi::{PreCheckedDmiInfo, PreCheckedDmiProfile},
-    config::distro_package_manager,
+    cfhdb::{
+        dmi::{PreCheckedDmiInfo, PreCheckedDmiProfile},
+        get_profile_modify_commands,
+        kargs::{get_cmdline_args, get_pending_kernel_args},
+        policy::PROFILE_POLICY,
+        recommendation::recommended_profile,
+        PreCheckedProfile,
+    },
     ChannelMsg,
 };
 use adw::{prelude::*, *};
 use gtk::{
-    glib::{clone, MainContext},
+    glib::{self, clone, MainContext},
     Align, Orientation,
     Orientation::Vertical,
     ScrolledWindow,
 };
-use std::{process::Command, rc::Rc, sync::Arc, thread};
+use log::info;
+use std::{cell::RefCell, process::Command, rc::Rc, sync::Arc, thread};
 
 use users::get_current_username;
 
-use super::{error_dialog, run_in_lock_script};
+use super::{add_profile_row_badges, error_dialog, run_in_lock_script};
 
 pub fn create_dmi_class(
     window: &ApplicationWindow,
     info: &PreCheckedDmiInfo,
     theme_changed_action: &gio::SimpleAction,
     update_device_status_action: &gio::SimpleAction,
+    all_profiles: &[Arc<dyn PreCheckedProfile>],
 ) -> (String, ScrolledWindow) {
     // Update all profiles' installation status before creating the UI
     for profile in &info.profiles {
@@ -33,6 +41,7 @@ pub fn create_dmi_class(
         &info,
         &theme_changed_action,
         &update_device_status_action,
+        all_profiles,
     );
 
     let scroll = gtk::ScrolledWindow::builder()
@@ -53,6 +62,7 @@ fn dmi_info_page(
     info: &PreCheckedDmiInfo,
     theme_changed_action: &gio::SimpleAction,
     update_info_status_action: &gio::SimpleAction,
+    all_profiles: &[Arc<dyn PreCheckedProfile>],
 ) -> gtk::Box {
     let info_content = &info.info;
     let content_box = gtk::Box::builder()
@@ -271,12 +281,17 @@ fn dmi_info_page(
     let rows_size_group = gtk::SizeGroup::new(gtk::SizeGroupMode::Both);
 
     let mut profiles = info.profiles.clone();
+    let recommended_profile = recommended_profile(&info.profiles);
     profiles.sort_by_key(|x| x.profile().priority);
 
     let mut normal_profiles = vec![];
     let mut veiled_profiles = vec![];
 
     for profile in profiles.clone() {
+        // Installed profiles stay listed so they can still be removed
+        if PROFILE_POLICY.hides(&profile.summary()) && !profile.installed() {
+            continue;
+        }
         let profile_content = profile.profile();
         let (profiles_color_badges_size_group0, profiles_color_badges_size_group1) = (
             gtk::SizeGroup::new(gtk::SizeGroupMode::Both),
@@ -319,6 +334,15 @@ fn dmi_info_page(
         profile_expander_row.add_suffix(&profile_status_icon);
         profile_expander_row.set_title(&profile_content.i18n_desc);
         profile_expander_row.set_subtitle(&profile_content.codename);
+        let profile_policy_denial = add_profile_row_badges(
+            &profile_expander_row,
+            &profile_content_row,
+            &profile_install_button,
+            &profile.summary(),
+            recommended_profile
+                .as_ref()
+                .is_some_and(|x| Arc::ptr_eq(x, &profile)),
+        );
         //
         let color_badge_experimental = ColorBadge::new();
         color_badge_experimental.set_label0(textwrap::fill(&t!("profile_experimental"), 10));
@@ -403,7 +427,8 @@ fn dmi_info_page(
         //
         update_info_status_action.connect_activate(clone!(move |_, _| {
             let profile_status = profile.installed();
-            profile_install_button.set_sensitive(!profile_status);
+            profile_install_button
+                .set_sensitive(!profile_status && profile_policy_denial.is_none());
             if profile_content.removable {
                 profile_remove_button.set_sensitive(profile_status);
             } else {
@@ -445,10 +470,84 @@ fn dmi_info_page(
         }
         content_box.append(&veil_expander);
     }
+    content_box.append(&kernel_cmdline_group(
+        all_profiles,
+        update_info_status_action,
+    ));
 
     content_box
 }
 
+/// The running kernel command line and what installed profiles will change on it after a reboot.
+fn kernel_cmdline_group(
+    all_profiles: &[Arc<dyn PreCheckedProfile>],
+    update_info_status_action: &gio::SimpleAction,
+) -> PreferencesGroup {
+    let kernel_cmdline_group = PreferencesGroup::builder()
+        .margin_top(20)
+        .margin_bottom(20)
+        .margin_start(20)
+        .margin_end(20)
+        .title(t!("kernel_cmdline_title"))
+        .hexpand(true)
+        .build();
+
+    let current_cmdline_row = ActionRow::builder()
+        .title(t!("kernel_cmdline_current_title"))
+        .subtitle_selectable(true)
+        .build();
+    current_cmdline_row.add_css_class("property");
+    kernel_cmdline_group.add(&current_cmdline_row);
+
+    let pending_args_row = ExpanderRow::builder()
+        .title(t!("kernel_cmdline_pending_title"))
+        .build();
+    kernel_cmdline_group.add(&pending_args_row);
+
+    let pending_arg_rows: Rc<RefCell<Vec<ActionRow>>> = Rc::new(RefCell::new(vec![]));
+    let all_profiles = all_profiles.to_vec();
+    let update_cmdline = move || {
+        current_cmdline_row.set_subtitle(&glib::markup_escape_text(&get_cmdline_args().join(" ")));
+        for row in pending_arg_rows.borrow_mut().drain(..) {
+            pending_args_row.remove(&row);
+        }
+        let pending_args = get_pending_kernel_args(&all_profiles);
+        pending_args_row.set_subtitle(&if pending_args.is_empty() {
+            t!("kernel_cmdline_pending_none").to_string()
+        } else {
+            t!("kernel_cmdline_pending_count", count = pending_args.len()).to_string()
+        });
+        pending_args_row.set_enable_expansion(!pending_args.is_empty());
+        for pending_arg in pending_args {
+            let pending_arg_row = ActionRow::builder()
+                .title(glib::markup_escape_text(&pending_arg.arg))
+                .subtitle(if pending_arg.add {
+                    t!(
+                        "kernel_cmdline_pending_add",
+                        codename = pending_arg.codename
+                    )
+                } else {
+                    t!(
+                        "kernel_cmdline_pending_remove",
+                        codename = pending_arg.codename
+                    )
+                })
+                .build();
+            pending_arg_row.add_prefix(&gtk::Image::from_icon_name(if pending_arg.add {
+                "list-add-symbolic"
+            } else {
+                "list-remove-symbolic"
+            }));
+            pending_args_row.add_row(&pending_arg_row);
+            pending_arg_rows.borrow_mut().push(pending_arg_row);
+        }
+    };
+    update_cmdline();
+    update_info_status_action.connect_activate(move |_, _| update_cmdline());
+
+    kernel_cmdline_group
+}
+
 pub fn profile_modify(
     window: ApplicationWindow,
     update_info_status_action: &gio::SimpleAction,
@@ -462,6 +561,13 @@ pub fn profile_modify(
 
     let profile_content = profile.profile();
 
+    info!(
+        target: "executor",
+        "Starting {} of DMI profile {}",
+        opreation,
+        profile_content.codename
+    );
+
     let profile_modify_log_terminal_buffer = gtk::TextBuffer::builder().build();
 
     let profile_modify_log_terminal = gtk::TextView::builder()
@@ -502,52 +608,29 @@ pub fn profile_modify(
     //
 
     let string_opreation = String::from(opreation);
+    let profile_summary = profile.summary();
 
     thread::spawn(clone!(
         #[strong]
-        profile_content,
+        profile_summary,
         #[strong]
         string_opreation,
         move || {
-            let script = match string_opreation.as_str() {
-                "install" => profile_content.install_script,
-                "remove" => profile_content.remove_script,
-                _ => panic!(),
-            };
-            match script {
-                Some(t) => match profile_content.packages {
-                    Some(a) => {
-                        let package_list = a.join(" ");
-                        let modify_command =
-                            distro_package_manager(&string_opreation, &package_list);
-                        run_in_lock_script(
-                            &log_loop_sender,
-                            &format!("#! /bin/bash\nset -e\n{}\n{}", modify_command, t),
-                        );
-                    }
-                    None => {
-                        run_in_lock_script(
-                            &log_loop_sender,
-                            &format!("#! /bin/bash\nset -e\n{}", t),
-                        );
-                    }
-                },
-                None => match profile_content.packages {
-                    Some(a) => {
-                        let package_list = a.join(" ");
-                        let modify_command =
-                            distro_package_manager(&string_opreation, &package_list);
-                        run_in_lock_script(
-                            &log_loop_sender,
-                            &format!("#! /bin/bash\nset -e\n{}", modify_command),
-                        );
-                    }
-                    None => {
-                        log_loop_sender
-                            .send_blocking(ChannelMsg::SuccessMsg)
-                            .unwrap();
-                    }
-                },
+            match get_profile_modify_commands(&profile_summary, &string_opreation) {
+                Err(reason) => {
+                    log_loop_sender
+                        .send_blocking(ChannelMsg::OutputLine(reason))
+                        .unwrap();
+                    log_loop_sender.send_blocking(ChannelMsg::FailMsg).unwrap();
+                }
+                Ok(Some(t)) => {
+                    run_in_lock_script(&log_loop_sender, &format!("#! /bin/bash\nset -e\n{}", t));
+                }
+                Ok(None) => {
+                    log_loop_sender
+                        .send_blocking(ChannelMsg::SuccessMsg)
+                        .unwrap();
+                }
             }
         }
     ));
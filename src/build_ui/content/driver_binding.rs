@@ -0,0 +1,250 @@
+use crate::cfhdb::driver_binding::{
+    get_bind_commands, get_bind_targets, get_bus_drivers, get_driver_override_commands,
+    get_unbind_commands, BindTarget,
+};
+use adw::prelude::*;
+use gtk::{
+    gio,
+    glib::{self, clone},
+};
+use log::info;
+use std::{cell::RefCell, rc::Rc};
+
+use super::{error_dialog, privileged_script_dialog::privileged_script_dialog};
+
+/// The "Driver Binding" group of a PCI or USB device page, to move a device between drivers at runtime.
+pub fn driver_binding_group(
+    window: &adw::ApplicationWindow,
+    bus: &'static str,
+    sysfs_busid: &str,
+    update_device_status_action: &gio::SimpleAction,
+) -> adw::PreferencesGroup {
+    let driver_binding_group = adw::PreferencesGroup::builder()
+        .margin_top(20)
+        .margin_bottom(20)
+        .margin_start(20)
+        .margin_end(20)
+        .title(t!("driver_binding_title"))
+        .description(t!("driver_binding_subtitle"))
+        .hexpand(true)
+        .build();
+
+    let target_rows: Rc<RefCell<Vec<adw::ExpanderRow>>> = Rc::new(RefCell::new(vec![]));
+    let sysfs_busid = sysfs_busid.to_string();
+
+    let update_targets = clone!(
+        #[strong]
+        window,
+        #[strong]
+        driver_binding_group,
+        #[strong]
+        update_device_status_action,
+        move || {
+            for row in target_rows.borrow_mut().drain(..) {
+                driver_binding_group.remove(&row);
+            }
+            let drivers = get_bus_drivers(bus);
+            for target in get_bind_targets(bus, &sysfs_busid) {
+                let target_row =
+                    bind_target_row(&window, &update_device_status_action, bus, &drivers, target);
+                driver_binding_group.add(&target_row);
+                target_rows.borrow_mut().push(target_row);
+            }
+        }
+    );
+
+    update_device_status_action.connect_activate(clone!(
+        #[strong]
+        update_targets,
+        move |_, _| update_targets()
+    ));
+    update_targets();
+
+    driver_binding_group
+}
+
+fn bind_target_row(
+    window: &adw::ApplicationWindow,
+    update_device_status_action: &gio::SimpleAction,
+    bus: &'static str,
+    drivers: &[String],
+    target: BindTarget,
+) -> adw::ExpanderRow {
+    let target_expander_row = adw::ExpanderRow::builder()
+        .title(&target.sysfs_busid)
+        .subtitle(glib::markup_escape_text(&match &target.driver {
+            Some(t) => t!("driver_binding_bound_to", driver = t).to_string(),
+            None => t!("driver_binding_unbound").to_string(),
+        }))
+        .build();
+    let target_busid = Rc::new(target.sysfs_busid.clone());
+
+    let unbind_button = gtk::Button::builder()
+        .valign(gtk::Align::Center)
+        .label(t!("driver_binding_unbind_button_label"))
+        .sensitive(target.driver.is_some())
+        .build();
+    unbind_button.add_css_class("destructive-action");
+    unbind_button.connect_clicked(clone!(
+        #[strong]
+        window,
+        #[strong]
+        update_device_status_action,
+        #[strong]
+        target_busid,
+        move |_| {
+            info!(target: "driver_binding", "Unbinding {} {}", bus.to_uppercase(), target_busid);
+            driver_binding_modify(
+                &window,
+                &update_device_status_action,
+                get_unbind_commands(bus, &target_busid),
+            );
+        }
+    ));
+    let unbind_row = adw::ActionRow::builder()
+        .title(t!("driver_binding_unbind_title"))
+        .subtitle(t!("driver_binding_unbind_subtitle"))
+        .build();
+    unbind_row.add_suffix(&unbind_button);
+    target_expander_row.add_row(&unbind_row);
+
+    let drivers_model =
+        gtk::StringList::new(&drivers.iter().map(|x| x.as_str()).collect::<Vec<_>>());
+    let bind_combo_row = adw::ComboRow::builder()
+        .title(t!("driver_binding_bind_title"))
+        .model(&drivers_model)
+        .enable_search(true)
+        .build();
+    if let Some(position) = target
+        .driver
+        .as_ref()
+        .and_then(|x| drivers.iter().position(|y| y == x))
+    {
+        bind_combo_row.set_selected(position as u32);
+    }
+    let bind_button = gtk::Button::builder()
+        .valign(gtk::Align::Center)
+        .label(t!("driver_binding_bind_button_label"))
+        .sensitive(!drivers.is_empty())
+        .build();
+    bind_button.add_css_class("suggested-action");
+    bind_button.connect_clicked(clone!(
+        #[strong]
+        window,
+        #[strong]
+        update_device_status_action,
+        #[strong]
+        target_busid,
+        #[strong]
+        bind_combo_row,
+        move |_| {
+            let Some(driver) = drivers_model.string(bind_combo_row.selected()) else {
+                return;
+            };
+            info!(
+                target: "driver_binding",
+                "Binding {} {} to {}",
+                bus.to_uppercase(),
+                target_busid,
+                driver
+            );
+            driver_binding_modify(
+                &window,
+                &update_device_status_action,
+                get_bind_commands(bus, &target_busid, &driver),
+            );
+        }
+    ));
+    bind_combo_row.add_suffix(&bind_button);
+    target_expander_row.add_row(&bind_combo_row);
+
+    if let Some(driver_override) = &target.driver_override {
+        let override_entry_row = adw::EntryRow::builder()
+            .title(t!("driver_binding_override_title"))
+            .text(driver_override)
+            .show_apply_button(true)
+            .build();
+        override_entry_row.connect_apply(clone!(
+            #[strong]
+            window,
+            #[strong]
+            update_device_status_action,
+            #[strong]
+            target_busid,
+            move |entry_row| {
+                let driver = entry_row.text().trim().to_string();
+                info!(
+                    target: "driver_binding",
+                    "Setting driver_override of {} {} to \"{}\"",
+                    bus.to_uppercase(),
+                    target_busid,
+                    driver
+                );
+                driver_binding_modify(
+                    &window,
+                    &update_device_status_action,
+                    get_driver_override_commands(bus, &target_busid, &driver),
+                );
+            }
+        ));
+        let clear_override_button = gtk::Button::builder()
+            .valign(gtk::Align::Center)
+            .icon_name("edit-clear-symbolic")
+            .tooltip_text(t!("driver_binding_override_clear_tooltip_text"))
+            .sensitive(!driver_override.is_empty())
+            .build();
+        clear_override_button.add_css_class("flat");
+        clear_override_button.connect_clicked(clone!(
+            #[strong]
+            window,
+            #[strong]
+            update_device_status_action,
+            #[strong]
+            target_busid,
+            move |_| {
+                info!(
+                    target: "driver_binding",
+                    "Clearing driver_override of {} {}",
+                    bus.to_uppercase(),
+                    target_busid
+                );
+                driver_binding_modify(
+                    &window,
+                    &update_device_status_action,
+                    get_driver_override_commands(bus, &target_busid, ""),
+                );
+            }
+        ));
+        override_entry_row.add_suffix(&clear_override_button);
+        target_expander_row.add_row(&override_entry_row);
+    }
+
+    target_expander_row
+}
+
+fn driver_binding_modify(
+    window: &adw::ApplicationWindow,
+    update_device_status_action: &gio::SimpleAction,
+    commands: Result<String, String>,
+) {
+    let script = match commands {
+        Ok(t) => t,
+        Err(e) => {
+            error_dialog(window.clone(), &t!("driver_binding_dialog_heading"), &e);
+            return;
+        }
+    };
+    privileged_script_dialog(
+        window,
+        &t!("driver_binding_dialog_heading"),
+        &t!("driver_binding_dialog_body_successful"),
+        &t!("driver_binding_dialog_body_failed"),
+        Some(script),
+        true,
+        clone!(
+            #[strong]
+            update_device_status_action,
+            move || update_device_status_action.activate(None)
+        ),
+    );
+}
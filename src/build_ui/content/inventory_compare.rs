@@ -0,0 +1,260 @@
+use crate::cfhdb::inventory::{
+    compare_inventory_snapshots, driver_or_none, read_inventory_snapshot, write_inventory_snapshot,
+    InventoryDevice, InventorySnapshot,
+};
+use adw::prelude::*;
+use gtk::{
+    gio,
+    glib::{self, clone},
+};
+use log::{error, info};
+use std::rc::Rc;
+
+use super::{error_dialog, get_icon_for_class, manifest_dialog::json_file_filters};
+
+pub fn save_inventory_dialog(window: &adw::ApplicationWindow, snapshot: InventorySnapshot) {
+    let file_dialog = gtk::FileDialog::builder()
+        .title(t!("inventory_save_dialog_title"))
+        .initial_name("inventory.json")
+        .filters(&json_file_filters())
+        .modal(true)
+        .build();
+
+    file_dialog.save(
+        Some(window),
+        gio::Cancellable::NONE,
+        clone!(
+            #[strong]
+            window,
+            move |result| {
+                let Some(file_path) = result.ok().and_then(|x| x.path()) else {
+                    return;
+                };
+                match write_inventory_snapshot(&file_path, &snapshot) {
+                    Ok(_) => {
+                        info!(
+                            target: "inventory",
+                            "Saved inventory snapshot to {}",
+                            file_path.display()
+                        );
+                    }
+                    Err(e) => {
+                        error!(
+                            target: "inventory",
+                            "Could not write {}: {}",
+                            file_path.display(),
+                            e
+                        );
+                        error_dialog(window.clone(), &t!("inventory_save_failed_heading"), &e);
+                    }
+                }
+            }
+        ),
+    );
+}
+
+pub fn compare_inventory_dialog(
+    window: &adw::ApplicationWindow,
+    local_snapshot: InventorySnapshot,
+) {
+    let file_dialog = gtk::FileDialog::builder()
+        .title(t!("inventory_compare_dialog_title"))
+        .filters(&json_file_filters())
+        .modal(true)
+        .build();
+
+    file_dialog.open(
+        Some(window),
+        gio::Cancellable::NONE,
+        clone!(
+            #[strong]
+            window,
+            move |result| {
+                let Some(file_path) = result.ok().and_then(|x| x.path()) else {
+                    return;
+                };
+                match read_inventory_snapshot(&file_path) {
+                    Ok(other_snapshot) => {
+                        let other_name = file_path
+                            .file_name()
+                            .map(|x| x.to_string_lossy().to_string())
+                            .unwrap_or_default();
+                        inventory_compare_view(
+                            &window,
+                            &local_snapshot,
+                            &other_snapshot,
+                            &other_name,
+                        );
+                    }
+                    Err(e) => {
+                        error!(
+                            target: "inventory",
+                            "Could not read {}: {}",
+                            file_path.display(),
+                            e
+                        );
+                        error_dialog(window.clone(), &t!("inventory_compare_failed_heading"), &e);
+                    }
+                }
+            }
+        ),
+    );
+}
+
+fn device_row(device: &InventoryDevice, subtitle: &str) -> adw::ActionRow {
+    let device_row = adw::ActionRow::builder()
+        .title(glib::markup_escape_text(&device.name))
+        .subtitle(glib::markup_escape_text(subtitle))
+        .build();
+    let class = format!("{}_class_name_{}", device.bus, device.class);
+    device_row.add_prefix(&gtk::Image::from_icon_name(
+        get_icon_for_class(&class).unwrap_or("dialog-question-symbolic"),
+    ));
+    device_row
+}
+
+fn inventory_compare_view(
+    window: &adw::ApplicationWindow,
+    local_snapshot: &InventorySnapshot,
+    other_snapshot: &InventorySnapshot,
+    other_name: &str,
+) {
+    let diff = compare_inventory_snapshots(local_snapshot, other_snapshot);
+    let diff_text = Rc::new(diff.to_text(other_name));
+
+    let compare_page = adw::PreferencesPage::new();
+
+    if diff.is_empty() {
+        let no_differences_group = adw::PreferencesGroup::new();
+        no_differences_group.add(
+            &adw::StatusPage::builder()
+                .icon_name("emblem-default-symbolic")
+                .title(t!("inventory_no_differences"))
+                .build(),
+        );
+        compare_page.add(&no_differences_group);
+    }
+
+    if !diff.dmi.is_empty() {
+        let dmi_group = adw::PreferencesGroup::builder()
+            .title(t!("inventory_dmi_title"))
+            .build();
+        for (field, local_value, other_value) in &diff.dmi {
+            dmi_group.add(
+                &adw::ActionRow::builder()
+                    .title(t!(format!("info_{}", field)))
+                    .subtitle(glib::markup_escape_text(&t!(
+                        "inventory_value_change_subtitle",
+                        local = local_value,
+                        other = other_value
+                    )))
+                    .build(),
+            );
+        }
+        compare_page.add(&dmi_group);
+    }
+
+    for (group_title, devices) in [
+        (t!("inventory_only_local_title"), &diff.only_local),
+        (
+            t!("inventory_only_other_title", name = other_name),
+            &diff.only_other,
+        ),
+    ] {
+        if devices.is_empty() {
+            continue;
+        }
+        let devices_group = adw::PreferencesGroup::builder().title(group_title).build();
+        for device in devices {
+            devices_group.add(&device_row(
+                device,
+                &format!("{} - {}", device.id, driver_or_none(&device.kernel_driver)),
+            ));
+        }
+        compare_page.add(&devices_group);
+    }
+
+    if !diff.driver_differences.is_empty() {
+        let drivers_group = adw::PreferencesGroup::builder()
+            .title(t!("inventory_drivers_title"))
+            .build();
+        for (device, other_driver) in &diff.driver_differences {
+            drivers_group.add(&device_row(
+                device,
+                &t!(
+                    "inventory_value_change_subtitle",
+                    local = driver_or_none(&device.kernel_driver),
+                    other = driver_or_none(other_driver)
+                ),
+            ));
+        }
+        compare_page.add(&drivers_group);
+    }
+
+    if !diff.profiles_only_local.is_empty() || !diff.profiles_only_other.is_empty() {
+        let profiles_group = adw::PreferencesGroup::builder()
+            .title(t!("inventory_profiles_title"))
+            .build();
+        for (profiles, icon_name, subtitle) in [
+            (
+                &diff.profiles_only_local,
+                "list-remove-symbolic",
+                t!("inventory_profile_only_local_subtitle"),
+            ),
+            (
+                &diff.profiles_only_other,
+                "list-add-symbolic",
+                t!("inventory_profile_only_other_subtitle", name = other_name),
+            ),
+        ] {
+            for profile in profiles {
+                let profile_row = adw::ActionRow::builder()
+                    .title(glib::markup_escape_text(profile))
+                    .subtitle(glib::markup_escape_text(&subtitle))
+                    .build();
+                profile_row.add_prefix(&gtk::Image::from_icon_name(icon_name));
+                profiles_group.add(&profile_row);
+            }
+        }
+        compare_page.add(&profiles_group);
+    }
+
+    let copy_button = gtk::Button::builder()
+        .icon_name("edit-copy-symbolic")
+        .tooltip_text(t!("inventory_copy_button_tooltip_text"))
+        .build();
+
+    let compare_headerbar = adw::HeaderBar::builder()
+        .title_widget(
+            &adw::WindowTitle::builder()
+                .title(t!("inventory_compare_view_title"))
+                .subtitle(t!("inventory_compare_view_subtitle", name = other_name))
+                .build(),
+        )
+        .build();
+    compare_headerbar.pack_start(&copy_button);
+
+    let compare_toast_overlay = adw::ToastOverlay::new();
+    compare_toast_overlay.set_child(Some(&compare_page));
+
+    let compare_toolbar = adw::ToolbarView::builder()
+        .content(&compare_toast_overlay)
+        .build();
+    compare_toolbar.add_top_bar(&compare_headerbar);
+
+    copy_button.connect_clicked(clone!(
+        #[strong]
+        compare_toast_overlay,
+        move |button| {
+            button.clipboard().set_text(&diff_text);
+            compare_toast_overlay.add_toast(adw::Toast::new(&t!("inventory_copied_toast")));
+        }
+    ));
+
+    let compare_dialog = adw::Dialog::builder()
+        .child(&compare_toolbar)
+        .content_width(650)
+        .content_height(600)
+        .build();
+    compare_dialog.present(Some(window));
+}
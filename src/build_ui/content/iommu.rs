@@ -0,0 +1,337 @@
+use crate::cfhdb::{
+    iommu::{
+        get_iommu_cmdline_args, get_iommu_groups, plan_vfio_passthrough, IommuGroup, VfioPlan,
+    },
+    pci::PreCheckedPciDevice,
+};
+use adw::prelude::*;
+use gtk::{
+    gio,
+    glib::{self, clone},
+};
+use log::{error, info};
+use std::{collections::HashMap, rc::Rc};
+
+use super::{error_dialog, get_icon_for_class, privileged_script_dialog::privileged_script_dialog};
+
+pub const IOMMU_PAGE_NAME: &str = "iommu";
+
+/// The sidebar page of a known PCI device and its title, to link IOMMU group members to it.
+struct PciPageLink {
+    page_name: String,
+    title: String,
+}
+
+pub fn iommu_page(
+    window: &adw::ApplicationWindow,
+    hashmap_pci: &Option<Vec<(String, Vec<PreCheckedPciDevice>)>>,
+    update_device_status_action: &gio::SimpleAction,
+    show_page_action: &gio::SimpleAction,
+) -> gtk::ScrolledWindow {
+    let iommu_box = gtk::Box::builder()
+        .orientation(gtk::Orientation::Vertical)
+        .margin_top(20)
+        .margin_bottom(20)
+        .margin_start(20)
+        .margin_end(20)
+        .spacing(20)
+        .build();
+
+    let iommu_scroll = gtk::ScrolledWindow::builder()
+        .max_content_width(650)
+        .min_content_width(300)
+        .hscrollbar_policy(gtk::PolicyType::Never)
+        .vexpand(true)
+        .child(&iommu_box)
+        .build();
+
+    let mut pci_page_links = HashMap::new();
+    if let Some(hashmap_pci) = hashmap_pci {
+        for (class, devices) in hashmap_pci {
+            for device in devices {
+                pci_page_links.insert(
+                    device.device.sysfs_busid.clone(),
+                    PciPageLink {
+                        page_name: format!("pci_class_name_{}", class),
+                        title: format!(
+                            "{} - {}",
+                            device.device.vendor_name, device.device.device_name
+                        ),
+                    },
+                );
+            }
+        }
+    }
+    let pci_page_links = Rc::new(pci_page_links);
+
+    let refresh_iommu = clone!(
+        #[weak]
+        iommu_box,
+        #[strong]
+        window,
+        #[strong]
+        update_device_status_action,
+        #[strong]
+        show_page_action,
+        move || {
+            while let Some(child) = iommu_box.first_child() {
+                iommu_box.remove(&child);
+            }
+
+            let groups = get_iommu_groups();
+            let cmdline_args = get_iommu_cmdline_args();
+
+            let status_group = adw::PreferencesGroup::builder()
+                .title(t!("iommu_status_title"))
+                .build();
+            let enabled_row = adw::ActionRow::builder()
+                .title(t!("iommu_enabled_title"))
+                .subtitle(if groups.is_empty() {
+                    t!("iommu_enabled_no")
+                } else {
+                    t!("iommu_enabled_yes", count = groups.len())
+                })
+                .build();
+            enabled_row.add_prefix(&gtk::Image::from_icon_name(if groups.is_empty() {
+                "dialog-warning-symbolic"
+            } else {
+                "emblem-default-symbolic"
+            }));
+            status_group.add(&enabled_row);
+            status_group.add(
+                &adw::ActionRow::builder()
+                    .title(t!("iommu_cmdline_title"))
+                    .subtitle(glib::markup_escape_text(&if cmdline_args.is_empty() {
+                        t!("iommu_cmdline_none").to_string()
+                    } else {
+                        cmdline_args.join(" ")
+                    }))
+                    .subtitle_selectable(true)
+                    .build(),
+            );
+            iommu_box.append(&status_group);
+
+            if groups.is_empty() {
+                iommu_box.append(
+                    &adw::StatusPage::builder()
+                        .icon_name("dialog-information-symbolic")
+                        .title(t!("iommu_no_groups_title"))
+                        .description(t!("iommu_no_groups_description"))
+                        .vexpand(true)
+                        .build(),
+                );
+                return;
+            }
+
+            for group in groups {
+                let group_widget = adw::PreferencesGroup::builder()
+                    .title(t!("iommu_group_title", group = group.id))
+                    .build();
+                for device in &group.devices {
+                    let page_link = pci_page_links.get(&device.sysfs_busid);
+                    let device_row = adw::ActionRow::builder()
+                        .title(glib::markup_escape_text(
+                            &page_link
+                                .map(|x| x.title.clone())
+                                .unwrap_or_else(|| device.sysfs_busid.clone()),
+                        ))
+                        .subtitle(glib::markup_escape_text(&format!(
+                            "{} - {}:{} - {}",
+                            device.sysfs_busid,
+                            device.vendor_id,
+                            device.device_id,
+                            device
+                                .module
+                                .clone()
+                                .unwrap_or_else(|| t!("inventory_no_driver").to_string())
+                        )))
+                        .build();
+                    device_row.add_prefix(&gtk::Image::from_icon_name(
+                        get_icon_for_class(&format!("pci_class_name_{}", device.class_id))
+                            .unwrap_or("dialog-question-symbolic"),
+                    ));
+                    if let Some(page_link) = page_link {
+                        device_row.set_activatable(true);
+                        device_row.add_suffix(&gtk::Image::from_icon_name("go-next-symbolic"));
+                        let (page_name, device_tag) =
+                            (page_link.page_name.clone(), device.sysfs_busid.clone());
+                        device_row.connect_activated(clone!(
+                            #[strong]
+                            show_page_action,
+                            move |_| {
+                                show_page_action.activate(Some(
+                                    &(page_name.clone(), device_tag.clone()).to_variant(),
+                                ));
+                            }
+                        ));
+                    }
+                    group_widget.add(&device_row);
+                }
+                let passthrough_button = gtk::Button::builder()
+                    .label(t!("iommu_passthrough_button_label"))
+                    .valign(gtk::Align::Center)
+                    .sensitive(group.devices.iter().any(|x| !x.is_bridge()))
+                    .build();
+                passthrough_button.connect_clicked(clone!(
+                    #[strong]
+                    window,
+                    #[strong]
+                    update_device_status_action,
+                    move |_| vfio_passthrough_dialog(&window, &update_device_status_action, &group)
+                ));
+                group_widget.set_header_suffix(Some(&passthrough_button));
+                iommu_box.append(&group_widget);
+            }
+        }
+    );
+
+    iommu_scroll.connect_map(clone!(
+        #[strong]
+        refresh_iommu,
+        move |_| refresh_iommu()
+    ));
+
+    update_device_status_action.connect_activate(move |_, _| refresh_iommu());
+
+    iommu_scroll
+}
+
+fn vfio_passthrough_dialog(
+    window: &adw::ApplicationWindow,
+    update_device_status_action: &gio::SimpleAction,
+    group: &IommuGroup,
+) {
+    let plan = match plan_vfio_passthrough(group) {
+        Ok(t) => t,
+        Err(e) => {
+            error!(target: "iommu", "Could not plan passthrough of group {}: {}", group.id, e);
+            error_dialog(window.clone(), &t!("iommu_vfio_dialog_heading"), &e);
+            return;
+        }
+    };
+    info!(
+        target: "iommu",
+        "Planned passthrough of group {}, vfio-pci ids {}",
+        group.id,
+        plan.ids.join(",")
+    );
+
+    let vfio_passthrough_dialog = adw::AlertDialog::builder()
+        .heading(t!("iommu_vfio_dialog_heading"))
+        .body(t!(
+            "iommu_vfio_dialog_body",
+            group = group.id,
+            ids = plan.ids.join(", ")
+        ))
+        .extra_child(
+            &gtk::ScrolledWindow::builder()
+                .hscrollbar_policy(gtk::PolicyType::Never)
+                .propagate_natural_height(true)
+                .max_content_height(400)
+                .width_request(450)
+                .child(&vfio_plan_preview(&plan))
+                .build(),
+        )
+        .build();
+    vfio_passthrough_dialog.add_response(
+        "vfio_passthrough_dialog_cancel",
+        &t!("install_recommended_dialog_cancel_label"),
+    );
+    vfio_passthrough_dialog.add_response(
+        "vfio_passthrough_dialog_apply",
+        &t!("manifest_plan_dialog_apply_label"),
+    );
+    vfio_passthrough_dialog.set_response_appearance(
+        "vfio_passthrough_dialog_apply",
+        adw::ResponseAppearance::Suggested,
+    );
+
+    let script = plan.script;
+    vfio_passthrough_dialog.choose(
+        window,
+        gio::Cancellable::NONE,
+        clone!(
+            #[strong]
+            window,
+            #[strong]
+            update_device_status_action,
+            move |choice: glib::GString| {
+                if choice != "vfio_passthrough_dialog_apply" {
+                    return;
+                }
+                privileged_script_dialog(
+                    &window,
+                    &t!("iommu_vfio_dialog_heading"),
+                    &t!("iommu_vfio_body_successful"),
+                    &t!("iommu_vfio_body_failed"),
+                    Some(script.clone()),
+                    true,
+                    clone!(
+                        #[strong]
+                        update_device_status_action,
+                        move || update_device_status_action.activate(None)
+                    ),
+                );
+            }
+        ),
+    );
+}
+
+/// Every file the plan writes as a diff against its current content, then the other changes.
+fn vfio_plan_preview(plan: &VfioPlan) -> gtk::Box {
+    let preview_box = gtk::Box::builder()
+        .orientation(gtk::Orientation::Vertical)
+        .spacing(12)
+        .build();
+
+    for file_change in &plan.file_changes {
+        let file_group = adw::PreferencesGroup::builder()
+            .title(glib::markup_escape_text(&file_change.path))
+            .description(if file_change.old_content.is_some() {
+                t!("iommu_vfio_file_replaced")
+            } else {
+                t!("iommu_vfio_file_created")
+            })
+            .build();
+        let file_preview = gtk::Label::builder()
+            .label(file_change.preview())
+            .xalign(0.0)
+            .wrap(true)
+            .wrap_mode(gtk::pango::WrapMode::WordChar)
+            .selectable(true)
+            .margin_top(6)
+            .margin_bottom(6)
+            .margin_start(6)
+            .margin_end(6)
+            .build();
+        file_preview.add_css_class("monospace");
+        file_group.add(&file_preview);
+        preview_box.append(&file_group);
+    }
+
+    let other_changes_group = adw::PreferencesGroup::builder()
+        .title(t!("iommu_vfio_other_changes_title"))
+        .build();
+    other_changes_group.add(
+        &adw::ActionRow::builder()
+            .title(t!("iommu_vfio_kernel_args_title"))
+            .subtitle(glib::markup_escape_text(
+                &if plan.kernel_args_add.is_empty() {
+                    t!("iommu_vfio_kernel_args_none").to_string()
+                } else {
+                    plan.kernel_args_add.join(" ")
+                },
+            ))
+            .subtitle_selectable(true)
+            .build(),
+    );
+    other_changes_group.add(
+        &adw::ActionRow::builder()
+            .title(t!("iommu_vfio_initramfs_title"))
+            .subtitle(t!("iommu_vfio_initramfs_subtitle"))
+            .build(),
+    );
+    preview_box.append(&other_changes_group);
+
+    preview_box
+}
@@ -0,0 +1,240 @@
+use crate::cfhdb::kmod::{get_device_kernel_modules, get_module_dropin_commands, KernelModule};
+use adw::prelude::*;
+use gtk::{
+    gio,
+    glib::{self, clone},
+};
+use log::info;
+use std::{cell::RefCell, rc::Rc};
+
+use super::{error_dialog, privileged_script_dialog::privileged_script_dialog};
+
+/// The "Kernel Modules" group of a PCI or USB device page, rebuilt whenever the device status is updated.
+pub fn kernel_modules_group(
+    window: &adw::ApplicationWindow,
+    bus: &'static str,
+    sysfs_busid: &str,
+    update_device_status_action: &gio::SimpleAction,
+) -> adw::PreferencesGroup {
+    let kernel_modules_group = adw::PreferencesGroup::builder()
+        .margin_top(20)
+        .margin_bottom(20)
+        .margin_start(20)
+        .margin_end(20)
+        .title(t!("kernel_modules_title"))
+        .description(t!("kernel_modules_subtitle"))
+        .hexpand(true)
+        .build();
+
+    let module_rows: Rc<RefCell<Vec<adw::PreferencesRow>>> = Rc::new(RefCell::new(vec![]));
+    let sysfs_busid = sysfs_busid.to_string();
+
+    let update_modules = clone!(
+        #[strong]
+        window,
+        #[strong]
+        kernel_modules_group,
+        #[strong]
+        update_device_status_action,
+        move || {
+            for row in module_rows.borrow_mut().drain(..) {
+                kernel_modules_group.remove(&row);
+            }
+            let modules = get_device_kernel_modules(bus, &sysfs_busid);
+            if modules.is_empty() {
+                let no_modules_row = adw::ActionRow::builder()
+                    .title(t!("kernel_modules_none"))
+                    .build();
+                kernel_modules_group.add(&no_modules_row);
+                module_rows.borrow_mut().push(no_modules_row.upcast());
+            }
+            for module in modules {
+                let module_row = kernel_module_row(&window, &update_device_status_action, module);
+                kernel_modules_group.add(&module_row);
+                module_rows.borrow_mut().push(module_row.upcast());
+            }
+        }
+    );
+
+    update_device_status_action.connect_activate(clone!(
+        #[strong]
+        update_modules,
+        move |_, _| update_modules()
+    ));
+    update_modules();
+
+    kernel_modules_group
+}
+
+fn kernel_module_row(
+    window: &adw::ApplicationWindow,
+    update_device_status_action: &gio::SimpleAction,
+    module: KernelModule,
+) -> adw::ExpanderRow {
+    let mut status = vec![if module.bound {
+        t!("kernel_module_status_bound")
+    } else {
+        t!("kernel_module_status_candidate")
+    }];
+    if module.builtin {
+        status.push(t!("kernel_module_status_builtin"));
+    } else if module.loaded {
+        status.push(t!("kernel_module_status_loaded"));
+    }
+    if module.blacklisted {
+        status.push(t!("kernel_module_status_blacklisted"));
+    }
+
+    let module_expander_row = adw::ExpanderRow::builder()
+        .title(&module.name)
+        .subtitle(status.join(", "))
+        .build();
+    module_expander_row.add_prefix(&gtk::Image::from_icon_name(if module.bound {
+        "emblem-default-symbolic"
+    } else if module.blacklisted {
+        "action-unavailable-symbolic"
+    } else {
+        "application-x-addon-symbolic"
+    }));
+
+    if !module.loaded {
+        module_expander_row.add_row(
+            &adw::ActionRow::builder()
+                .title(t!("kernel_module_parameters_not_loaded"))
+                .build(),
+        );
+    } else if module.parameters.is_empty() {
+        module_expander_row.add_row(
+            &adw::ActionRow::builder()
+                .title(t!("kernel_module_parameters_none"))
+                .build(),
+        );
+    }
+    for (parameter, value) in &module.parameters {
+        let parameter_row = adw::ActionRow::builder()
+            .title(glib::markup_escape_text(parameter))
+            .subtitle(glib::markup_escape_text(&value.clone().unwrap_or_else(
+                || t!("kernel_module_parameter_unreadable").to_string(),
+            )))
+            .subtitle_selectable(true)
+            .build();
+        parameter_row.add_css_class("property");
+        module_expander_row.add_row(&parameter_row);
+    }
+
+    if module.builtin {
+        return module_expander_row;
+    }
+
+    let module = Rc::new(module);
+
+    let options_entry_row = adw::EntryRow::builder()
+        .title(t!("kernel_module_options_title"))
+        .text(&module.persistent_options)
+        .show_apply_button(true)
+        .build();
+    options_entry_row.connect_apply(clone!(
+        #[strong]
+        window,
+        #[strong]
+        update_device_status_action,
+        #[strong]
+        module,
+        move |entry_row| {
+            info!(
+                target: "kmod",
+                "Setting persistent options of {} to \"{}\"",
+                module.name,
+                entry_row.text()
+            );
+            module_dropin_modify(
+                &window,
+                &update_device_status_action,
+                &module.name,
+                module.managed_blacklist,
+                &entry_row.text(),
+            );
+        }
+    ));
+    module_expander_row.add_row(&options_entry_row);
+
+    let blacklist_button = gtk::Button::builder()
+        .valign(gtk::Align::Center)
+        .label(if module.managed_blacklist {
+            t!("kernel_module_unblacklist_button_label")
+        } else {
+            t!("kernel_module_blacklist_button_label")
+        })
+        .build();
+    let blacklist_row = adw::ActionRow::builder()
+        .title(t!("kernel_module_blacklist_title"))
+        .subtitle(t!("kernel_module_blacklist_subtitle"))
+        .build();
+    if module.blacklisted && !module.managed_blacklist {
+        // Blacklisted by a file we do not own, so there is nothing to undo here
+        blacklist_button.set_sensitive(false);
+        blacklist_row.set_subtitle(&t!("kernel_module_blacklisted_elsewhere"));
+    } else if !module.managed_blacklist {
+        blacklist_button.add_css_class("destructive-action");
+    }
+    blacklist_button.connect_clicked(clone!(
+        #[strong]
+        window,
+        #[strong]
+        update_device_status_action,
+        #[strong]
+        module,
+        move |_| {
+            info!(
+                target: "kmod",
+                "{} {}",
+                if module.managed_blacklist {
+                    "Unblacklisting"
+                } else {
+                    "Blacklisting"
+                },
+                module.name
+            );
+            module_dropin_modify(
+                &window,
+                &update_device_status_action,
+                &module.name,
+                !module.managed_blacklist,
+                &module.persistent_options,
+            );
+        }
+    ));
+    blacklist_row.add_suffix(&blacklist_button);
+    module_expander_row.add_row(&blacklist_row);
+
+    module_expander_row
+}
+
+fn module_dropin_modify(
+    window: &adw::ApplicationWindow,
+    update_device_status_action: &gio::SimpleAction,
+    module: &str,
+    blacklist: bool,
+    options: &str,
+) {
+    let script = match get_module_dropin_commands(module, blacklist, options) {
+        Ok(t) => t,
+        Err(e) => {
+            error_dialog(window.clone(), &t!("kernel_module_dialog_heading"), &e);
+            return;
+        }
+    };
+    privileged_script_dialog(
+        window,
+        &t!("kernel_module_dialog_heading"),
+        &t!("kernel_module_dialog_body_successful"),
+        &t!("kernel_module_dialog_body_failed"),
+        Some(script),
+        true,
+        clone!(
+            #[strong]
+            update_device_status_action,
+            move || update_device_status_action.activate(None)
+        ),
+    );
+}
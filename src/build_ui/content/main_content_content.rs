@@ -3,6 +3,8 @@ use gtk::{glib::clone, Button, Stack, ToggleButton};
 
 use crate::config::{APP_GIT, APP_ICON, VERSION};
 
+use super::search::{search_results_page, SearchItem, SEARCH_RESULTS_PAGE_NAME};
+
 pub fn main_content_content(
     window: &adw::ApplicationWindow,
     window_banner: &adw::Banner,
@@ -12,6 +14,8 @@ pub fn main_content_content(
     all_profiles_button: Button,
     sidebar_toggle_button: ToggleButton,
     about_action: &gtk::gio::SimpleAction,
+    search_items: Vec<SearchItem>,
+    show_page_action: &gtk::gio::SimpleAction,
 ) -> adw::ToolbarView {
     let window_headerbar = HeaderBar::builder()
         .title_widget(&WindowTitle::builder().title(t!("application_name")).build())
@@ -32,6 +36,14 @@ pub fn main_content_content(
 
     window_headerbar.pack_end(&all_profiles_button);
     window_toolbar.add_top_bar(&window_headerbar);
+    search_bar(
+        window,
+        &window_headerbar,
+        &window_toolbar,
+        stack,
+        search_items,
+        show_page_action,
+    );
     window_toolbar.add_top_bar(&window_banner.clone());
     window_breakpoint.add_setter(&sidebar_toggle_button, "visible", Some(&true.to_value()));
     window_breakpoint.add_setter(&window_headerbar, "show_title", Some(&true.to_value()));
@@ -41,6 +53,75 @@ pub fn main_content_content(
     window_toolbar
 }
 
+fn search_bar(
+    window: &adw::ApplicationWindow,
+    window_headerbar: &adw::HeaderBar,
+    window_toolbar: &adw::ToolbarView,
+    stack: &Stack,
+    search_items: Vec<SearchItem>,
+    show_page_action: &gtk::gio::SimpleAction,
+) {
+    let search_button = gtk::ToggleButton::builder()
+        .icon_name("system-search-symbolic")
+        .tooltip_text(t!("search_button_label"))
+        .build();
+
+    let search_entry = gtk::SearchEntry::builder()
+        .placeholder_text(t!("search_entry_placeholder"))
+        .hexpand(true)
+        .build();
+
+    let search_entry_clamp = adw::Clamp::builder()
+        .maximum_size(650)
+        .child(&search_entry)
+        .build();
+
+    let search_bar = gtk::SearchBar::builder()
+        .child(&search_entry_clamp)
+        .show_close_button(true)
+        .build();
+    search_bar.connect_entry(&search_entry);
+
+    let _search_button_binding = search_bar
+        .bind_property("search-mode-enabled", &search_button, "active")
+        .sync_create()
+        .bidirectional()
+        .build();
+
+    stack.add_named(
+        &search_results_page(
+            stack,
+            &search_bar,
+            &search_entry,
+            search_items,
+            show_page_action,
+        ),
+        Some(SEARCH_RESULTS_PAGE_NAME),
+    );
+
+    // Ctrl+F opens the search from anywhere in the window
+    let search_shortcut_controller = gtk::ShortcutController::new();
+    search_shortcut_controller.set_propagation_phase(gtk::PropagationPhase::Capture);
+    search_shortcut_controller.add_shortcut(gtk::Shortcut::new(
+        gtk::ShortcutTrigger::parse_string("<Control>f"),
+        Some(gtk::CallbackAction::new(clone!(
+            #[strong]
+            search_bar,
+            #[strong]
+            search_entry,
+            move |_, _| {
+                search_bar.set_search_mode(true);
+                search_entry.grab_focus();
+                gtk::glib::Propagation::Stop
+            }
+        ))),
+    ));
+    window.add_controller(search_shortcut_controller);
+
+    window_headerbar.pack_start(&search_button);
+    window_toolbar.add_top_bar(&search_bar);
+}
+
 fn credits_window(
     window: &adw::ApplicationWindow,
     window_headerbar: &adw::HeaderBar,
@@ -3,10 +3,7 @@ use gtk::{glib::clone, ListBox, ListBoxRow, Orientation, PolicyType};
 
 pub fn main_content_sidebar(
     stack: &gtk::Stack,
-    pci_rows: &Vec<ListBoxRow>,
-    usb_rows: &Vec<ListBoxRow>,
-    dmi_row: &ListBoxRow,
-    bt_rows: &Vec<ListBoxRow>,
+    sections: &Vec<(String, Vec<ListBoxRow>)>,
 ) -> adw::ToolbarView {
     let main_content_sidebar_box = gtk::Box::builder()
         .orientation(Orientation::Vertical)
@@ -37,188 +34,74 @@ pub fn main_content_sidebar(
 
     //
 
-    let dmi_rows_listbox = ListBox::builder()
-        .selection_mode(gtk::SelectionMode::Single)
-        .build();
-    dmi_rows_listbox.add_css_class("navigation-sidebar");
-
-    let pci_rows_listbox = ListBox::builder()
-        .selection_mode(gtk::SelectionMode::Single)
-        .build();
-    pci_rows_listbox.add_css_class("navigation-sidebar");
-
-    let usb_rows_listbox = ListBox::builder()
-        .selection_mode(gtk::SelectionMode::Single)
-        .build();
-    usb_rows_listbox.add_css_class("navigation-sidebar");
-
-    let bt_rows_listbox = ListBox::builder()
-        .selection_mode(gtk::SelectionMode::Single)
-        .build();
-    bt_rows_listbox.add_css_class("navigation-sidebar");
-
-    // DMI Devices Section
-    let dmi_label = gtk::Label::builder()
-        .label(&t!("dmi_devices").to_string())
-        .halign(gtk::Align::Start)
-        .margin_start(16)
-        .margin_top(8)
-        .margin_bottom(4)
-        .build();
-    dmi_label.add_css_class("heading");
-
-    main_content_sidebar_box.append(&dmi_label);
-    main_content_sidebar_box.append(&dmi_rows_listbox);
-
-    dmi_rows_listbox.append(dmi_row);
-    dmi_rows_listbox.select_row(Some(dmi_row));
-
-    dmi_rows_listbox.connect_row_activated(clone!(
-        #[strong]
-        pci_rows_listbox,
-        #[strong]
-        usb_rows_listbox,
-        #[strong]
-        bt_rows_listbox,
-        #[strong]
-        stack,
-        move |_, row| {
-            pci_rows_listbox.select_row(None::<&ListBoxRow>);
-            usb_rows_listbox.select_row(None::<&ListBoxRow>);
-            bt_rows_listbox.select_row(None::<&ListBoxRow>);
-            stack.set_visible_child_name(&row.widget_name());
-        }
-    ));
-
-    // Separator between sections
-    let separator = gtk::Separator::builder()
-        .orientation(Orientation::Horizontal)
-        .margin_top(12)
-        .margin_bottom(12)
-        .build();
-
-    // PCI Devices Section
-    let pci_label = gtk::Label::builder()
-        .label(&t!("pci_devices").to_string())
-        .halign(gtk::Align::Start)
-        .margin_start(16)
-        .margin_top(8)
-        .margin_bottom(4)
-        .build();
-    pci_label.add_css_class("heading");
+    let mut sections_listboxes: Vec<ListBox> = vec![];
 
-    if !pci_rows.is_empty() {
-        main_content_sidebar_box.append(&separator);
-        main_content_sidebar_box.append(&pci_label);
-        main_content_sidebar_box.append(&pci_rows_listbox);
-
-        for row in pci_rows {
-            pci_rows_listbox.append(row);
+    for (section_title, section_rows) in sections {
+        if section_rows.is_empty() {
+            continue;
         }
-    }
 
-    pci_rows_listbox.connect_row_activated(clone!(
-        #[strong]
-        usb_rows_listbox,
-        #[strong]
-        dmi_rows_listbox,
-        #[strong]
-        bt_rows_listbox,
-        #[strong]
-        stack,
-        move |_, row| {
-            usb_rows_listbox.select_row(None::<&ListBoxRow>);
-            dmi_rows_listbox.select_row(None::<&ListBoxRow>);
-            bt_rows_listbox.select_row(None::<&ListBoxRow>);
-            stack.set_visible_child_name(&row.widget_name());
+        let section_listbox = ListBox::builder()
+            .selection_mode(gtk::SelectionMode::Single)
+            .build();
+        section_listbox.add_css_class("navigation-sidebar");
+
+        // Separator between sections
+        if !sections_listboxes.is_empty() {
+            let separator = gtk::Separator::builder()
+                .orientation(Orientation::Horizontal)
+                .margin_top(12)
+                .margin_bottom(12)
+                .build();
+            main_content_sidebar_box.append(&separator);
         }
-    ));
 
-    // Separator between sections
-    let separator = gtk::Separator::builder()
-        .orientation(Orientation::Horizontal)
-        .margin_top(12)
-        .margin_bottom(12)
-        .build();
+        let section_label = gtk::Label::builder()
+            .label(section_title)
+            .halign(gtk::Align::Start)
+            .margin_start(16)
+            .margin_top(8)
+            .margin_bottom(4)
+            .build();
+        section_label.add_css_class("heading");
 
-    // USB Devices Section
-    let usb_label = gtk::Label::builder()
-        .label(&t!("usb_devices").to_string())
-        .halign(gtk::Align::Start)
-        .margin_start(16)
-        .margin_top(8)
-        .margin_bottom(4)
-        .build();
-    usb_label.add_css_class("heading");
-
-    if !usb_rows.is_empty() {
-        main_content_sidebar_box.append(&separator);
-        main_content_sidebar_box.append(&usb_label);
-        main_content_sidebar_box.append(&usb_rows_listbox);
-        for row in usb_rows {
-            usb_rows_listbox.append(row);
-        }
-    }
+        main_content_sidebar_box.append(&section_label);
+        main_content_sidebar_box.append(&section_listbox);
 
-    usb_rows_listbox.connect_row_activated(clone!(
-        #[strong]
-        pci_rows_listbox,
-        #[strong]
-        dmi_rows_listbox,
-        #[strong]
-        bt_rows_listbox,
-        #[strong]
-        stack,
-        move |_, row| {
-            pci_rows_listbox.select_row(None::<&ListBoxRow>);
-            dmi_rows_listbox.select_row(None::<&ListBoxRow>);
-            bt_rows_listbox.select_row(None::<&ListBoxRow>);
-            stack.set_visible_child_name(&row.widget_name());
+        for row in section_rows {
+            section_listbox.append(row);
         }
-    ));
 
-    // Separator between sections
-    let separator = gtk::Separator::builder()
-        .orientation(Orientation::Horizontal)
-        .margin_top(12)
-        .margin_bottom(12)
-        .build();
+        section_listbox.connect_row_activated(clone!(
+            #[strong]
+            stack,
+            move |_, row| {
+                stack.set_visible_child_name(&row.widget_name());
+            }
+        ));
 
-    // BT Devices Section
-    let bt_label = gtk::Label::builder()
-        .label(&t!("bt_devices").to_string())
-        .halign(gtk::Align::Start)
-        .margin_start(16)
-        .margin_top(8)
-        .margin_bottom(4)
-        .build();
-    bt_label.add_css_class("heading");
-
-    if !bt_rows.is_empty() {
-        main_content_sidebar_box.append(&separator);
-        main_content_sidebar_box.append(&bt_label);
-        main_content_sidebar_box.append(&bt_rows_listbox);
-        for row in bt_rows {
-            bt_rows_listbox.append(row);
-        }
+        sections_listboxes.push(section_listbox);
     }
 
-    bt_rows_listbox.connect_row_activated(clone!(
-        #[strong]
-        pci_rows_listbox,
-        #[strong]
-        usb_rows_listbox,
-        #[strong]
-        dmi_rows_listbox,
-        #[strong]
-        stack,
-        move |_, row| {
-            pci_rows_listbox.select_row(None::<&ListBoxRow>);
-            dmi_rows_listbox.select_row(None::<&ListBoxRow>);
-            usb_rows_listbox.select_row(None::<&ListBoxRow>);
-            stack.set_visible_child_name(&row.widget_name());
+    // Keep the selected row in sync with the visible page, as pages can also be switched from the search results and the overview
+    let sync_selection = move |stack: &gtk::Stack| {
+        let visible_child_name = stack.visible_child_name().unwrap_or_default();
+        for listbox in &sections_listboxes {
+            let mut selected_row = None;
+            let mut index = 0;
+            while let Some(row) = listbox.row_at_index(index) {
+                if row.widget_name() == visible_child_name {
+                    selected_row = Some(row);
+                    break;
+                }
+                index += 1;
+            }
+            listbox.select_row(selected_row.as_ref());
         }
-    ));
+    };
+
+    sync_selection(stack);
+    stack.connect_visible_child_name_notify(sync_selection);
 
     main_content_sidebar_toolbar
 }
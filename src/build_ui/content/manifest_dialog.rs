@@ -0,0 +1,305 @@
+use crate::cfhdb::{
+    bt::PreCheckedBtDevice,
+    dmi::PreCheckedDmiInfo,
+    manifest::{export_manifest, plan_manifest, read_manifest, write_manifest, ManifestPlan},
+    pci::PreCheckedPciDevice,
+    sysfs_bus::SysfsBuses,
+    usb::PreCheckedUsbDevice,
+    PreCheckedProfile,
+};
+use adw::prelude::*;
+use gtk::{
+    gio,
+    glib::{self, clone},
+};
+use log::{error, info};
+use std::sync::Arc;
+
+use super::{batch_profile_modify::batch_profile_modify, error_dialog};
+
+pub fn json_file_filters() -> gio::ListStore {
+    let json_filter = gtk::FileFilter::new();
+    json_filter.set_name(Some(&t!("manifest_file_filter_name")));
+    json_filter.add_mime_type("application/json");
+    json_filter.add_suffix("json");
+    let filters = gio::ListStore::new::<gtk::FileFilter>();
+    filters.append(&json_filter);
+    filters
+}
+
+pub fn export_manifest_dialog(
+    window: &adw::ApplicationWindow,
+    hashmap_pci: &Option<Vec<(String, Vec<PreCheckedPciDevice>)>>,
+    hashmap_usb: &Option<Vec<(String, Vec<PreCheckedUsbDevice>)>>,
+    dmi_info: &PreCheckedDmiInfo,
+    hashmap_bt: &Option<Vec<(String, Vec<PreCheckedBtDevice>)>>,
+    sysfs_buses: &SysfsBuses,
+    all_profiles: &[Arc<dyn PreCheckedProfile>],
+) {
+    let manifest = export_manifest(
+        hashmap_pci,
+        hashmap_usb,
+        dmi_info,
+        hashmap_bt,
+        sysfs_buses,
+        all_profiles,
+    );
+
+    let file_dialog = gtk::FileDialog::builder()
+        .title(t!("manifest_export_dialog_title"))
+        .initial_name(format!(
+            "{}-manifest.json",
+            dmi_info.info.product_name.replace(['/', ' '], "_")
+        ))
+        .filters(&json_file_filters())
+        .modal(true)
+        .build();
+
+    file_dialog.save(
+        Some(window),
+        gio::Cancellable::NONE,
+        clone!(
+            #[strong]
+            window,
+            move |result| {
+                let Some(file_path) = result.ok().and_then(|x| x.path()) else {
+                    return;
+                };
+                match write_manifest(&file_path, &manifest) {
+                    Ok(_) => {
+                        info!(
+                            target: "manifest",
+                            "Exported {} profile(s) to {}",
+                            manifest.profiles.len(),
+                            file_path.display()
+                        );
+                        let done_dialog = adw::AlertDialog::builder()
+                            .heading(t!("manifest_export_done_heading"))
+                            .body(t!(
+                                "manifest_export_done_body",
+                                count = manifest.profiles.len(),
+                                path = file_path.display()
+                            ))
+                            .build();
+                        done_dialog
+                            .add_response("manifest_export_done_ok", &t!("error_dialog_ok_label"));
+                        done_dialog.present(Some(&window));
+                    }
+                    Err(e) => {
+                        error!(
+                            target: "manifest",
+                            "Could not write {}: {}",
+                            file_path.display(),
+                            e
+                        );
+                        error_dialog(window.clone(), &t!("manifest_export_failed_heading"), &e);
+                    }
+                }
+            }
+        ),
+    );
+}
+
+pub fn apply_manifest_dialog(
+    window: &adw::ApplicationWindow,
+    update_device_status_action: &gio::SimpleAction,
+    hashmap_pci: &Option<Vec<(String, Vec<PreCheckedPciDevice>)>>,
+    hashmap_usb: &Option<Vec<(String, Vec<PreCheckedUsbDevice>)>>,
+    dmi_info: &PreCheckedDmiInfo,
+    hashmap_bt: &Option<Vec<(String, Vec<PreCheckedBtDevice>)>>,
+    sysfs_buses: &SysfsBuses,
+    all_profiles: &[Arc<dyn PreCheckedProfile>],
+) {
+    let file_dialog = gtk::FileDialog::builder()
+        .title(t!("manifest_apply_dialog_title"))
+        .filters(&json_file_filters())
+        .modal(true)
+        .build();
+
+    let (hashmap_pci, hashmap_usb, dmi_info, hashmap_bt, sysfs_buses, all_profiles) = (
+        hashmap_pci.clone(),
+        hashmap_usb.clone(),
+        dmi_info.clone(),
+        hashmap_bt.clone(),
+        sysfs_buses.clone(),
+        all_profiles.to_vec(),
+    );
+
+    file_dialog.open(
+        Some(window),
+        gio::Cancellable::NONE,
+        clone!(
+            #[strong]
+            window,
+            #[strong]
+            update_device_status_action,
+            move |result| {
+                let Some(file_path) = result.ok().and_then(|x| x.path()) else {
+                    return;
+                };
+                let manifest = match read_manifest(&file_path) {
+                    Ok(t) => t,
+                    Err(e) => {
+                        error!(target: "manifest", "Could not read {}: {}", file_path.display(), e);
+                        error_dialog(window.clone(), &t!("manifest_apply_failed_heading"), &e);
+                        return;
+                    }
+                };
+                let plan = plan_manifest(
+                    &manifest,
+                    &hashmap_pci,
+                    &hashmap_usb,
+                    &dmi_info,
+                    &hashmap_bt,
+                    &sysfs_buses,
+                    &all_profiles,
+                );
+                info!(
+                    target: "manifest",
+                    "Manifest {}: {} to install, {} to remove, {} not applicable",
+                    file_path.display(),
+                    plan.to_install.len(),
+                    plan.to_remove.len(),
+                    plan.not_applicable.len()
+                );
+                manifest_plan_dialog(
+                    &window,
+                    &update_device_status_action,
+                    plan,
+                    all_profiles.clone(),
+                );
+            }
+        ),
+    );
+}
+
+fn manifest_plan_dialog(
+    window: &adw::ApplicationWindow,
+    update_device_status_action: &gio::SimpleAction,
+    plan: ManifestPlan,
+    all_profiles: Vec<Arc<dyn PreCheckedProfile>>,
+) {
+    let plan_box = gtk::Box::builder()
+        .orientation(gtk::Orientation::Vertical)
+        .spacing(12)
+        .build();
+
+    if !plan.dmi_differences.is_empty() {
+        let dmi_differences_group = adw::PreferencesGroup::builder()
+            .title(t!("manifest_dmi_differences_title"))
+            .description(t!("manifest_dmi_differences_description"))
+            .build();
+        for (field, manifest_value, local_value) in &plan.dmi_differences {
+            dmi_differences_group.add(
+                &adw::ActionRow::builder()
+                    .title(t!(format!("info_{}", field)))
+                    .subtitle(glib::markup_escape_text(&t!(
+                        "manifest_dmi_difference_subtitle",
+                        manifest = manifest_value,
+                        local = local_value
+                    )))
+                    .build(),
+            );
+        }
+        plan_box.append(&dmi_differences_group);
+    }
+
+    for (group_title, profiles, icon_name) in [
+        (
+            t!("manifest_to_install_title"),
+            &plan.to_install,
+            "list-add-symbolic",
+        ),
+        (
+            t!("manifest_to_remove_title"),
+            &plan.to_remove,
+            "list-remove-symbolic",
+        ),
+    ] {
+        if profiles.is_empty() {
+            continue;
+        }
+        let group = adw::PreferencesGroup::builder().title(group_title).build();
+        for profile in profiles {
+            let summary = profile.summary();
+            let row = adw::ActionRow::builder()
+                .title(glib::markup_escape_text(&summary.i18n_desc))
+                .subtitle(glib::markup_escape_text(&format!(
+                    "{} - {}",
+                    summary.bus.to_uppercase(),
+                    summary.codename
+                )))
+                .build();
+            row.add_prefix(&gtk::Image::from_icon_name(icon_name));
+            group.add(&row);
+        }
+        plan_box.append(&group);
+    }
+
+    if !plan.not_applicable.is_empty() {
+        let not_applicable_group = adw::PreferencesGroup::builder()
+            .title(t!("manifest_not_applicable_title"))
+            .build();
+        for (manifest_profile, reason) in &plan.not_applicable {
+            let row = adw::ActionRow::builder()
+                .title(glib::markup_escape_text(&format!(
+                    "{} - {}",
+                    manifest_profile.bus.to_uppercase(),
+                    manifest_profile.codename
+                )))
+                .subtitle(glib::markup_escape_text(reason))
+                .build();
+            row.add_prefix(&gtk::Image::from_icon_name("action-unavailable-symbolic"));
+            not_applicable_group.add(&row);
+        }
+        plan_box.append(&not_applicable_group);
+    }
+
+    let manifest_plan_dialog = adw::AlertDialog::builder()
+        .heading(t!("manifest_plan_dialog_heading"))
+        .extra_child(
+            &gtk::ScrolledWindow::builder()
+                .hscrollbar_policy(gtk::PolicyType::Never)
+                .propagate_natural_height(true)
+                .max_content_height(400)
+                .width_request(400)
+                .child(&plan_box)
+                .build(),
+        )
+        .build();
+
+    if plan.is_empty() {
+        manifest_plan_dialog.set_body(&t!("manifest_plan_dialog_body_empty"));
+    }
+
+    manifest_plan_dialog.add_response(
+        "manifest_plan_dialog_cancel",
+        &t!("install_recommended_dialog_cancel_label"),
+    );
+    manifest_plan_dialog.add_response(
+        "manifest_plan_dialog_apply",
+        &t!("manifest_plan_dialog_apply_label"),
+    );
+    manifest_plan_dialog.set_response_appearance(
+        "manifest_plan_dialog_apply",
+        adw::ResponseAppearance::Suggested,
+    );
+    manifest_plan_dialog.set_response_enabled("manifest_plan_dialog_apply", !plan.is_empty());
+
+    let jobs = plan.jobs();
+    manifest_plan_dialog.choose(
+        window,
+        gio::Cancellable::NONE,
+        clone!(
+            #[strong]
+            window,
+            #[strong]
+            update_device_status_action,
+            move |choice: glib::GString| {
+                if choice == "manifest_plan_dialog_apply" {
+                    batch_profile_modify(&window, &update_device_status_action, jobs, all_profiles);
+                }
+            }
+        ),
+    );
+}
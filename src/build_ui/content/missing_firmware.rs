@@ -0,0 +1,130 @@
+use crate::cfhdb::{
+    firmware::{get_device_missing_firmware, providing_profile, MissingFirmware},
+    sysfs_bus::{PreCheckedSysfsDevice, SysfsBusDevice},
+    PreCheckedProfile, ProfileSummary,
+};
+use adw::prelude::*;
+use gtk::{
+    gio,
+    glib::{self, clone},
+};
+use std::{cell::RefCell, rc::Rc};
+
+// modinfo can list hundreds of files for a module, only the first ones are shown
+const SHOWN_FILES: usize = 3;
+
+/// What is missing and, if a profile provides it, which one.
+pub fn missing_firmware_description(
+    missing_firmware: &MissingFirmware,
+    profiles: &[ProfileSummary],
+) -> String {
+    let mut files = missing_firmware
+        .files
+        .iter()
+        .take(SHOWN_FILES)
+        .cloned()
+        .collect::<Vec<String>>()
+        .join(", ");
+    if missing_firmware.files.len() > SHOWN_FILES {
+        files = t!(
+            "missing_firmware_more_files",
+            files = files,
+            count = missing_firmware.files.len() - SHOWN_FILES
+        )
+        .to_string();
+    }
+    let mut description = if missing_firmware.load_failed {
+        t!("missing_firmware_load_failed", files = files)
+    } else {
+        t!("missing_firmware_not_installed", files = files)
+    }
+    .to_string();
+    if let Some(profile) = providing_profile(missing_firmware, profiles) {
+        description.push('\n');
+        description.push_str(&t!(
+            "missing_firmware_provided_by",
+            profile = profile.i18n_desc,
+            codename = profile.codename
+        ));
+    }
+    description
+}
+
+/// The "Missing Firmware" group of a PCI or USB device page, hidden while nothing is missing.
+pub fn missing_firmware_group(
+    bus: &'static str,
+    sysfs_busid: &str,
+    profiles: Vec<ProfileSummary>,
+    update_device_status_action: &gio::SimpleAction,
+) -> adw::PreferencesGroup {
+    let missing_firmware_group = adw::PreferencesGroup::builder()
+        .margin_top(20)
+        .margin_bottom(20)
+        .margin_start(20)
+        .margin_end(20)
+        .title(t!("missing_firmware_title"))
+        .description(t!("missing_firmware_subtitle"))
+        .hexpand(true)
+        .visible(false)
+        .build();
+
+    let firmware_rows: Rc<RefCell<Vec<adw::ActionRow>>> = Rc::new(RefCell::new(vec![]));
+    let sysfs_busid = sysfs_busid.to_string();
+
+    let update_firmware = clone!(
+        #[strong]
+        missing_firmware_group,
+        move || {
+            for row in firmware_rows.borrow_mut().drain(..) {
+                missing_firmware_group.remove(&row);
+            }
+            let missing_firmware = get_device_missing_firmware(bus, &sysfs_busid);
+            missing_firmware_group.set_visible(!missing_firmware.is_empty());
+            for missing in missing_firmware {
+                let firmware_row = adw::ActionRow::builder()
+                    .title(&missing.module)
+                    .subtitle(glib::markup_escape_text(&missing_firmware_description(
+                        &missing, &profiles,
+                    )))
+                    .subtitle_selectable(true)
+                    .build();
+                firmware_row.add_prefix(&gtk::Image::from_icon_name("dialog-warning-symbolic"));
+                missing_firmware_group.add(&firmware_row);
+                firmware_rows.borrow_mut().push(firmware_row);
+            }
+        }
+    );
+
+    update_device_status_action.connect_activate(clone!(
+        #[strong]
+        update_firmware,
+        move |_, _| update_firmware()
+    ));
+    update_firmware();
+
+    missing_firmware_group
+}
+
+/// The "Missing Firmware" group of a sysfs bus device page, the firmware is the one of the PCI or
+/// USB device it hangs off, which is what the driver is bound to.
+pub fn parent_missing_firmware_groups<D: SysfsBusDevice>(
+    _window: &adw::ApplicationWindow,
+    device: &PreCheckedSysfsDevice<D>,
+    update_device_status_action: &gio::SimpleAction,
+) -> Vec<gtk::Widget> {
+    let Some((parent_bus, parent_busid)) = device.device.parent_device() else {
+        return vec![];
+    };
+    let parent_bus = match parent_bus.as_str() {
+        "pci" => "pci",
+        "usb" => "usb",
+        _ => return vec![],
+    };
+    vec![missing_firmware_group(
+        parent_bus,
+        &parent_busid,
+        device.profiles.iter().map(|x| x.summary()).collect(),
+        update_device_status_action,
+    )
+    .upcast()]
+}
@@ -6,30 +6,67 @@ use gio::SimpleAction;
 use gtk::glib::{self, clone};
 use gtk::*;
 use gtk::{Align, StackTransitionType, ToggleButton};
+use log::{debug, error, info};
 
 use crate::build_ui::content::bt::create_bt_class;
 use crate::build_ui::content::dmi::create_dmi_class;
 use crate::cfhdb::bt::{PreCheckedBtDevice, PreCheckedBtProfile};
 use crate::cfhdb::dmi::{PreCheckedDmiInfo, PreCheckedDmiProfile};
 use crate::cfhdb::pci::{PreCheckedPciDevice, PreCheckedPciProfile};
+use crate::cfhdb::sysfs_bus::SysfsBuses;
 use crate::cfhdb::usb::{PreCheckedUsbDevice, PreCheckedUsbProfile};
+use crate::cfhdb::{
+    inventory::get_inventory_snapshot, policy::PROFILE_POLICY,
+    recommendation::get_pending_recommendations, PreCheckedProfile, ProfileSummary,
+};
 use crate::ChannelMsg;
 
 mod all_profile_dialog;
+mod batch_profile_modify;
 mod bt;
+mod bt_adapters;
+mod bt_scan;
+mod cpu;
 mod dmi;
+mod driver_binding;
 mod internet_check;
+mod inventory_compare;
+mod iommu;
+mod kernel_modules;
 mod main_content_content;
 mod main_content_sidebar;
+mod manifest_dialog;
+mod missing_firmware;
+mod network;
+mod overview;
 mod pci;
+mod privileged_script_dialog;
+mod search;
+mod storage;
+mod sysfs_bus;
 mod usb;
+mod usb_topology;
 
 use all_profile_dialog::all_profile_dialog;
+use batch_profile_modify::install_recommended_dialog;
+use bt_adapters::{bt_adapters_page, BT_ADAPTERS_PAGE_NAME};
+use bt_scan::{bt_scan_page, BT_SCAN_PAGE_NAME};
+use cpu::cpu_device_groups;
 use internet_check::internet_check_loop;
+use inventory_compare::{compare_inventory_dialog, save_inventory_dialog};
+use iommu::{iommu_page, IOMMU_PAGE_NAME};
 use main_content_content::{error_dialog, main_content_content};
 use main_content_sidebar::main_content_sidebar;
+use manifest_dialog::{apply_manifest_dialog, export_manifest_dialog};
+use missing_firmware::parent_missing_firmware_groups;
+use network::network_device_groups;
+use overview::{overview_page, OVERVIEW_PAGE_NAME};
 use pci::create_pci_class;
+use search::build_search_index;
+use storage::storage_device_groups;
+use sysfs_bus::{device_page_names, sysfs_bus_class_rows};
 use usb::create_usb_class;
+use usb_topology::{usb_topology_page, USB_TOPOLOGY_PAGE_NAME};
 use users::get_current_username;
 
 use std::{
@@ -47,8 +84,14 @@ pub fn main_content(
     usb_profiles: Vec<Arc<PreCheckedUsbProfile>>,
     dmi_profiles: Vec<Arc<PreCheckedDmiProfile>>,
     bt_profiles: Vec<Arc<PreCheckedBtProfile>>,
+    sysfs_buses: SysfsBuses,
     about_action: &gtk::gio::SimpleAction,
     showallprofiles_action: &gtk::gio::SimpleAction,
+    installrecommended_action: &gtk::gio::SimpleAction,
+    exportmanifest_action: &gtk::gio::SimpleAction,
+    applymanifest_action: &gtk::gio::SimpleAction,
+    saveinventory_action: &gtk::gio::SimpleAction,
+    compareinventory_action: &gtk::gio::SimpleAction,
 ) -> adw::OverlaySplitView {
     // Start timing the UI building process
     let ui_start = std::time::Instant::now();
@@ -96,6 +139,43 @@ pub fn main_content(
 
     let update_device_status_action = gio::SimpleAction::new("update_device_status", None);
 
+    // Switches to a window_stack page and, if the device tag is not empty, to that device's page
+    let show_page_action =
+        gio::SimpleAction::new("show_page", Some(glib::VariantTy::new("(ss)").unwrap()));
+    let push_device_page_action =
+        gio::SimpleAction::new("push_device_page", Some(glib::VariantTy::STRING));
+
+    show_page_action.connect_activate(clone!(
+        #[strong]
+        window_stack,
+        #[strong]
+        push_device_page_action,
+        move |_, param| {
+            if let Some((page_name, device_tag)) = param.and_then(|x| x.get::<(String, String)>()) {
+                window_stack.set_visible_child_name(&page_name);
+                if !device_tag.is_empty() {
+                    // Class pages are built when first mapped, so push the device page once they exist
+                    glib::idle_add_local_once(clone!(
+                        #[strong]
+                        push_device_page_action,
+                        move || {
+                            push_device_page_action.activate(Some(&device_tag.to_variant()));
+                        }
+                    ));
+                }
+            }
+        }
+    ));
+
+    let search_items = build_search_index(
+        &hashmap_pci,
+        &hashmap_usb,
+        &dmi_info,
+        &hashmap_bt,
+        &sysfs_buses,
+    );
+    let device_page_names = Rc::new(device_page_names(&hashmap_pci, &hashmap_usb, &hashmap_bt));
+
     let mut pci_rows = vec![];
     let mut usb_rows = vec![];
     let mut bt_rows = vec![];
@@ -130,8 +210,259 @@ pub fn main_content(
         }
     ));
 
+    let mut all_profiles: Vec<Arc<dyn PreCheckedProfile>> = vec![];
+    all_profiles.extend(
+        dmi_profiles_rc
+            .iter()
+            .map(|x| x.clone() as Arc<dyn PreCheckedProfile>),
+    );
+    all_profiles.extend(
+        pci_profiles_rc
+            .iter()
+            .map(|x| x.clone() as Arc<dyn PreCheckedProfile>),
+    );
+    all_profiles.extend(
+        usb_profiles_rc
+            .iter()
+            .map(|x| x.clone() as Arc<dyn PreCheckedProfile>),
+    );
+    all_profiles.extend(
+        bt_profiles_rc
+            .iter()
+            .map(|x| x.clone() as Arc<dyn PreCheckedProfile>),
+    );
+    all_profiles.extend(sysfs_buses.all_profiles());
+
+    installrecommended_action.connect_activate(clone!(
+        #[strong]
+        window,
+        #[strong]
+        update_device_status_action,
+        #[strong]
+        hashmap_pci,
+        #[strong]
+        hashmap_usb,
+        #[strong]
+        dmi_info,
+        #[strong]
+        hashmap_bt,
+        #[strong]
+        sysfs_buses,
+        #[strong]
+        all_profiles,
+        move |_, _| {
+            install_recommended_dialog(
+                &window,
+                &update_device_status_action,
+                get_pending_recommendations(
+                    &hashmap_pci,
+                    &hashmap_usb,
+                    &dmi_info,
+                    &hashmap_bt,
+                    &sysfs_buses,
+                ),
+                all_profiles.clone(),
+            );
+        }
+    ));
+
+    exportmanifest_action.connect_activate(clone!(
+        #[strong]
+        window,
+        #[strong]
+        hashmap_pci,
+        #[strong]
+        hashmap_usb,
+        #[strong]
+        dmi_info,
+        #[strong]
+        hashmap_bt,
+        #[strong]
+        sysfs_buses,
+        #[strong]
+        all_profiles,
+        move |_, _| {
+            export_manifest_dialog(
+                &window,
+                &hashmap_pci,
+                &hashmap_usb,
+                &dmi_info,
+                &hashmap_bt,
+                &sysfs_buses,
+                &all_profiles,
+            );
+        }
+    ));
+
+    applymanifest_action.connect_activate(clone!(
+        #[strong]
+        window,
+        #[strong]
+        update_device_status_action,
+        #[strong]
+        hashmap_pci,
+        #[strong]
+        hashmap_usb,
+        #[strong]
+        dmi_info,
+        #[strong]
+        hashmap_bt,
+        #[strong]
+        sysfs_buses,
+        #[strong]
+        all_profiles,
+        move |_, _| {
+            apply_manifest_dialog(
+                &window,
+                &update_device_status_action,
+                &hashmap_pci,
+                &hashmap_usb,
+                &dmi_info,
+                &hashmap_bt,
+                &sysfs_buses,
+                &all_profiles,
+            );
+        }
+    ));
+
+    let get_local_inventory = clone!(
+        #[strong]
+        hashmap_pci,
+        #[strong]
+        hashmap_usb,
+        #[strong]
+        dmi_info,
+        #[strong]
+        hashmap_bt,
+        #[strong]
+        sysfs_buses,
+        #[strong]
+        all_profiles,
+        move || {
+            get_inventory_snapshot(
+                &hashmap_pci,
+                &hashmap_usb,
+                &dmi_info,
+                &hashmap_bt,
+                &sysfs_buses,
+                &all_profiles,
+            )
+        }
+    );
+
+    saveinventory_action.connect_activate(clone!(
+        #[strong]
+        window,
+        #[strong]
+        get_local_inventory,
+        move |_, _| {
+            save_inventory_dialog(&window, get_local_inventory());
+        }
+    ));
+
+    compareinventory_action.connect_activate(clone!(
+        #[strong]
+        window,
+        #[strong]
+        get_local_inventory,
+        move |_, _| {
+            compare_inventory_dialog(&window, get_local_inventory());
+        }
+    ));
+
     theme_changed_thread(&theme_changed_action);
 
+    // Overview, the default page
+
+    window_stack.add_titled(
+        &overview_page(
+            window,
+            hashmap_pci.clone(),
+            hashmap_usb.clone(),
+            hashmap_bt.clone(),
+            &dmi_profiles_rc,
+            &pci_profiles_rc,
+            &usb_profiles_rc,
+            &bt_profiles_rc,
+            all_profiles.iter().map(|x| x.summary()).collect(),
+            &sysfs_buses,
+            &theme_changed_action,
+            &update_device_status_action,
+            &show_page_action,
+        ),
+        Some(OVERVIEW_PAGE_NAME),
+        &t!("overview_row_title"),
+    );
+
+    let overview_row = custom_stack_selection_button(
+        String::from(OVERVIEW_PAGE_NAME),
+        t!("overview_row_title").to_string(),
+        String::from("user-home-symbolic"),
+    );
+
+    // IOMMU groups, for passthrough setups
+
+    window_stack.add_titled(
+        &iommu_page(
+            window,
+            &hashmap_pci,
+            &update_device_status_action,
+            &show_page_action,
+        ),
+        Some(IOMMU_PAGE_NAME),
+        &t!("iommu_row_title"),
+    );
+
+    let iommu_row = custom_stack_selection_button(
+        String::from(IOMMU_PAGE_NAME),
+        t!("iommu_row_title").to_string(),
+        String::from("computer-symbolic"),
+    );
+
+    // USB topology, above the USB class pages
+
+    window_stack.add_titled(
+        &usb_topology_page(
+            &hashmap_usb,
+            &update_device_status_action,
+            &show_page_action,
+        ),
+        Some(USB_TOPOLOGY_PAGE_NAME),
+        &t!("usb_topology_row_title"),
+    );
+
+    usb_rows.push(custom_stack_selection_button(
+        String::from(USB_TOPOLOGY_PAGE_NAME),
+        t!("usb_topology_row_title").to_string(),
+        String::from("network-workgroup-symbolic"),
+    ));
+
+    // Bluetooth adapters and discovery, above the BT class pages
+
+    window_stack.add_titled(
+        &bt_adapters_page(window),
+        Some(BT_ADAPTERS_PAGE_NAME),
+        &t!("bt_adapters_row_title"),
+    );
+
+    bt_rows.push(custom_stack_selection_button(
+        String::from(BT_ADAPTERS_PAGE_NAME),
+        t!("bt_adapters_row_title").to_string(),
+        String::from("bluetooth-symbolic"),
+    ));
+
+    window_stack.add_titled(
+        &bt_scan_page(window, &update_device_status_action),
+        Some(BT_SCAN_PAGE_NAME),
+        &t!("bt_scan_row_title"),
+    );
+
+    bt_rows.push(custom_stack_selection_button(
+        String::from(BT_SCAN_PAGE_NAME),
+        t!("bt_scan_row_title").to_string(),
+        String::from("bluetooth-active-symbolic"),
+    ));
+
     // DMI placeholder
 
     // Create a placeholder page with a loading spinner
@@ -148,6 +479,7 @@ pub fn main_content(
     let window_clone = window.clone();
     let theme_changed_action_clone = theme_changed_action.clone();
     let update_device_status_action_clone = update_device_status_action.clone();
+    let all_profiles_clone = all_profiles.clone();
 
     let dmi_row = custom_stack_selection_button(
         String::from("dmi"),
@@ -172,6 +504,7 @@ pub fn main_content(
             &dmi_info,
             &theme_changed_action_clone,
             &update_device_status_action_clone,
+            &all_profiles_clone,
         );
 
         let mut short_dmi = dmi_name.clone();
@@ -219,6 +552,7 @@ pub fn main_content(
                 let window_clone = window.clone();
                 let theme_changed_action_clone = theme_changed_action.clone();
                 let update_device_status_action_clone = update_device_status_action.clone();
+                let push_device_page_action_clone = push_device_page_action.clone();
                 let class_i18n_clone = class_i18n.clone();
 
                 // Connect to the "map" signal to load content when page becomes visible
@@ -237,6 +571,7 @@ pub fn main_content(
                         &class_i18n_clone,
                         &theme_changed_action_clone,
                         &update_device_status_action_clone,
+                        &push_device_page_action_clone,
                     );
                     content.set_widget_name("content_loaded");
 
@@ -282,6 +617,7 @@ pub fn main_content(
                 let window_clone = window.clone();
                 let theme_changed_action_clone = theme_changed_action.clone();
                 let update_device_status_action_clone = update_device_status_action.clone();
+                let push_device_page_action_clone = push_device_page_action.clone();
                 let class_i18n_clone = class_i18n.clone();
 
                 // Connect to the "map" signal to load content when page becomes visible
@@ -300,6 +636,7 @@ pub fn main_content(
                         &class_i18n_clone,
                         &theme_changed_action_clone,
                         &update_device_status_action_clone,
+                        &push_device_page_action_clone,
                     );
                     content.set_widget_name("content_loaded");
 
@@ -348,6 +685,7 @@ pub fn main_content(
                 let window_clone = window.clone();
                 let theme_changed_action_clone = theme_changed_action.clone();
                 let update_device_status_action_clone = update_device_status_action.clone();
+                let push_device_page_action_clone = push_device_page_action.clone();
                 let class_i18n_clone = class_i18n.clone();
 
                 // Connect to the "map" signal to load content when page becomes visible
@@ -366,6 +704,7 @@ pub fn main_content(
                         &class_i18n_clone,
                         &theme_changed_action_clone,
                         &update_device_status_action_clone,
+                        &push_device_page_action_clone,
                     );
                     content.set_widget_name("content_loaded");
 
@@ -388,6 +727,84 @@ pub fn main_content(
         None => {}
     }
 
+    let cpu_rows = sysfs_bus_class_rows(
+        window,
+        &window_stack,
+        &sysfs_buses.cpu,
+        &theme_changed_action,
+        &update_device_status_action,
+        &push_device_page_action,
+        &show_page_action,
+        &device_page_names,
+        "cpu-symbolic",
+        cpu_device_groups,
+    );
+
+    let network_rows = sysfs_bus_class_rows(
+        window,
+        &window_stack,
+        &sysfs_buses.network,
+        &theme_changed_action,
+        &update_device_status_action,
+        &push_device_page_action,
+        &show_page_action,
+        &device_page_names,
+        "network-wired-symbolic",
+        network_device_groups,
+    );
+
+    let storage_rows = sysfs_bus_class_rows(
+        window,
+        &window_stack,
+        &sysfs_buses.storage,
+        &theme_changed_action,
+        &update_device_status_action,
+        &push_device_page_action,
+        &show_page_action,
+        &device_page_names,
+        "drive-harddisk-symbolic",
+        storage_device_groups,
+    );
+
+    let input_rows = sysfs_bus_class_rows(
+        window,
+        &window_stack,
+        &sysfs_buses.input,
+        &theme_changed_action,
+        &update_device_status_action,
+        &push_device_page_action,
+        &show_page_action,
+        &device_page_names,
+        "input-keyboard-symbolic",
+        |_, _, _| vec![],
+    );
+
+    let display_rows = sysfs_bus_class_rows(
+        window,
+        &window_stack,
+        &sysfs_buses.display,
+        &theme_changed_action,
+        &update_device_status_action,
+        &push_device_page_action,
+        &show_page_action,
+        &device_page_names,
+        "video-display-symbolic",
+        parent_missing_firmware_groups,
+    );
+
+    let sound_rows = sysfs_bus_class_rows(
+        window,
+        &window_stack,
+        &sysfs_buses.sound,
+        &theme_changed_action,
+        &update_device_status_action,
+        &push_device_page_action,
+        &show_page_action,
+        &device_page_names,
+        "audio-card-symbolic",
+        parent_missing_firmware_groups,
+    );
+
     main_content_overlay_split_view.set_content(Some(&main_content_content(
         &window,
         &window_banner,
@@ -397,14 +814,28 @@ pub fn main_content(
         all_profiles_button.clone(),
         sidebar_toggle.clone(),
         &about_action,
+        search_items,
+        &show_page_action,
     )));
 
     main_content_overlay_split_view.set_sidebar(Some(&main_content_sidebar(
         &window_stack,
-        &pci_rows,
-        &usb_rows,
-        &dmi_row,
-        &bt_rows,
+        &vec![
+            (
+                t!("overview_section_title").to_string(),
+                vec![overview_row, iommu_row],
+            ),
+            (t!("dmi_devices").to_string(), vec![dmi_row]),
+            (t!("cpu_devices").to_string(), cpu_rows),
+            (t!("pci_devices").to_string(), pci_rows),
+            (t!("usb_devices").to_string(), usb_rows),
+            (t!("bt_devices").to_string(), bt_rows),
+            (t!("network_devices").to_string(), network_rows),
+            (t!("storage_devices").to_string(), storage_rows),
+            (t!("input_devices").to_string(), input_rows),
+            (t!("display_devices").to_string(), display_rows),
+            (t!("sound_devices").to_string(), sound_rows),
+        ],
     )));
 
     window_breakpoint.add_setter(
@@ -429,11 +860,43 @@ pub fn main_content(
     internet_check_loop(internet_check_closure);
 
     // Print the time taken to build the UI
-    println!("[PERF] Initial UI building took: {:?}", ui_start.elapsed());
+    debug!(target: "loader", "Initial UI building took: {:?}", ui_start.elapsed());
 
     main_content_overlay_split_view
 }
 
+/// Adds the recommended badge and, if the site policy denies the profile, the lock icon to a profile
+/// row of a device page. The denial reason also goes on the content row and the install button, it
+/// is returned so the caller can keep the install button insensitive.
+pub fn add_profile_row_badges(
+    profile_expander_row: &adw::ExpanderRow,
+    profile_content_row: &adw::ActionRow,
+    profile_install_button: &gtk::Button,
+    profile: &ProfileSummary,
+    recommended: bool,
+) -> Option<String> {
+    if recommended {
+        let profile_recommended_badge = gtk::Label::builder()
+            .label(t!("profile_recommended_badge_label"))
+            .tooltip_text(t!("profile_recommended_badge_tooltip_text"))
+            .valign(gtk::Align::Center)
+            .build();
+        profile_recommended_badge.add_css_class("recommended-badge");
+        profile_expander_row.add_suffix(&profile_recommended_badge);
+    }
+    let profile_policy_denial = PROFILE_POLICY.check(profile);
+    if let Some(reason) = &profile_policy_denial {
+        let profile_locked_icon = gtk::Image::builder()
+            .icon_name("changes-prevent-symbolic")
+            .tooltip_text(t!("policy_locked_icon_tooltip_text"))
+            .build();
+        profile_expander_row.add_suffix(&profile_locked_icon);
+        profile_content_row.set_subtitle(reason);
+        profile_install_button.set_tooltip_text(Some(reason));
+    }
+    profile_policy_denial
+}
+
 // Helper function to create a placeholder page with a loading spinner
 fn create_placeholder_page(title: &str) -> gtk::Box {
     let box_container = gtk::Box::builder()
@@ -510,8 +973,10 @@ pub fn exec_duct_with_live_channel_stdout(
         .stdout_file(pipe_writer)
         .start()?;
     for line in BufReader::new(pipe_reader).lines() {
+        let line = line?;
+        info!(target: "executor", "{}", line);
         sender
-            .send_blocking(ChannelMsg::OutputLine(line?))
+            .send_blocking(ChannelMsg::OutputLine(line))
             .expect("Channel needs to be opened.")
     }
     child.wait()?;
@@ -542,6 +1007,8 @@ pub fn run_in_lock_script(log_loop_sender: &async_channel::Sender<ChannelMsg>, s
         std::fs::set_permissions(file_path, perms)
             .expect(&(file_path.to_string() + "cannot be written to"));
     }
+    info!(target: "executor", "Running privileged script {}", file_path);
+    debug!(target: "executor", "Script contents:\n{}", script);
     let final_cmd = if get_current_username().unwrap() == "root" {
         duct::cmd!(file_path)
     } else {
@@ -549,11 +1016,13 @@ pub fn run_in_lock_script(log_loop_sender: &async_channel::Sender<ChannelMsg>, s
     };
     match exec_duct_with_live_channel_stdout(&log_loop_sender, final_cmd) {
         Ok(_) => {
+            info!(target: "executor", "Privileged script finished successfully");
             log_loop_sender
                 .send_blocking(ChannelMsg::SuccessMsg)
                 .unwrap();
         }
-        Err(_) => {
+        Err(e) => {
+            error!(target: "executor", "Privileged script failed: {}", e);
             log_loop_sender.send_blocking(ChannelMsg::FailMsg).unwrap();
         }
     }
@@ -706,6 +1175,44 @@ pub fn get_icon_for_class(class: &str) -> Option<&'static str> {
         "usb_class_name_EF" => Some("dialog-question-symbolic"),
         "usb_class_name_FE" => Some("dialog-question-symbolic"),
         "usb_class_name_FF" => Some("dialog-question-symbolic"),
+        // storage classes
+        "storage_class_name_nvme" => Some("drive-harddisk-solidstate-symbolic"),
+        "storage_class_name_sata" => Some("drive-harddisk-symbolic"),
+        "storage_class_name_usb" => Some("drive-harddisk-usb-symbolic"),
+        "storage_class_name_mmc" => Some("media-flash-symbolic"),
+        "storage_class_name_virtio" => Some("drive-harddisk-system-symbolic"),
+        "storage_class_name_scsi" => Some("drive-harddisk-symbolic"),
+        // input classes
+        "input_class_name_joystick" => Some("input-gaming-symbolic"),
+        "input_class_name_tablet" => Some("input-tablet-symbolic"),
+        "input_class_name_touchscreen" => Some("input-touchscreen-symbolic"),
+        "input_class_name_touchpad" => Some("input-touchpad-symbolic"),
+        "input_class_name_mouse" => Some("input-mouse-symbolic"),
+        "input_class_name_keyboard" => Some("input-keyboard-symbolic"),
+        "input_class_name_accelerometer" => Some("find-location-symbolic"),
+        "input_class_name_switch" => Some("system-shutdown-symbolic"),
+        "input_class_name_keys" => Some("input-dialpad-symbolic"),
+        "input_class_name_other" => Some("dialog-question-symbolic"),
+        // cpu classes
+        "cpu_class_name_intel" | "cpu_class_name_amd" | "cpu_class_name_other" => {
+            Some("cpu-symbolic")
+        }
+        // network classes
+        "network_class_name_wired" => Some("network-wired-symbolic"),
+        "network_class_name_wireless" => Some("network-wireless-symbolic"),
+        "network_class_name_other" => Some("network-workgroup-symbolic"),
+        // display classes
+        "display_class_name_internal" => Some("computer-symbolic"),
+        "display_class_name_displayport" => Some("video-display-symbolic"),
+        "display_class_name_hdmi" => Some("video-display-symbolic"),
+        "display_class_name_dvi" => Some("video-display-symbolic"),
+        "display_class_name_vga" => Some("video-display-symbolic"),
+        "display_class_name_other" => Some("dialog-question-symbolic"),
+        // sound classes
+        "sound_class_name_hda" => Some("audio-card-symbolic"),
+        "sound_class_name_hdmi" => Some("video-display-symbolic"),
+        "sound_class_name_usb" => Some("audio-headphones-symbolic"),
+        "sound_class_name_other" => Some("audio-speakers-symbolic"),
         // bt classes
         "bt_class_name_260"
         | "bt_class_name_131072"
@@ -0,0 +1,76 @@
+use crate::cfhdb::{
+    network::{ethtool_firmware_version, NetworkInterface},
+    sysfs_bus::PreCheckedSysfsDevice,
+};
+use adw::prelude::*;
+use gtk::{
+    gio,
+    glib::{self, clone, MainContext},
+};
+
+use super::missing_firmware::parent_missing_firmware_groups;
+
+/// Shows the firmware version, asking ethtool for it the first time the device page is shown,
+/// and the firmware the network device is missing.
+pub fn network_device_groups(
+    window: &adw::ApplicationWindow,
+    device: &PreCheckedSysfsDevice<NetworkInterface>,
+    update_device_status_action: &gio::SimpleAction,
+) -> Vec<gtk::Widget> {
+    let firmware_group = adw::PreferencesGroup::builder()
+        .margin_top(20)
+        .margin_bottom(20)
+        .margin_start(20)
+        .margin_end(20)
+        .title(t!("network_firmware_title"))
+        .hexpand(true)
+        .build();
+
+    let firmware_version_row = adw::ActionRow::builder()
+        .title(t!("network_detail_firmware_version"))
+        .subtitle(t!("network_firmware_version_loading"))
+        .subtitle_selectable(true)
+        .build();
+    firmware_version_row.add_css_class("property");
+    firmware_group.add(&firmware_version_row);
+
+    let name = device.device.name.clone();
+    let sysfs_firmware_version = device.device.firmware_version.clone();
+    firmware_group.connect_map(clone!(
+        #[strong]
+        firmware_version_row,
+        move |firmware_group| {
+            if firmware_group.widget_name() == "firmware_version_loaded" {
+                return;
+            }
+            firmware_group.set_widget_name("firmware_version_loaded");
+
+            let (version_sender, version_receiver) = async_channel::bounded(1);
+            let name = name.clone();
+            let sysfs_firmware_version = sysfs_firmware_version.clone();
+            std::thread::spawn(move || {
+                let _ = version_sender
+                    .send_blocking(ethtool_firmware_version(&name).or(sysfs_firmware_version));
+            });
+            MainContext::default().spawn_local(clone!(
+                #[strong]
+                firmware_version_row,
+                async move {
+                    if let Ok(firmware_version) = version_receiver.recv().await {
+                        firmware_version_row.set_subtitle(&glib::markup_escape_text(
+                            &firmware_version.unwrap_or(t!("unknown").to_string()),
+                        ));
+                    }
+                }
+            ));
+        }
+    ));
+
+    let mut groups = vec![firmware_group.upcast()];
+    groups.extend(parent_missing_firmware_groups(
+        window,
+        device,
+        update_device_status_action,
+    ));
+    groups
+}
@@ -0,0 +1,675 @@
+use crate::cfhdb::{
+    bt::{PreCheckedBtDevice, PreCheckedBtProfile},
+    dmi::PreCheckedDmiProfile,
+    firmware::get_device_missing_firmware,
+    pci::{PreCheckedPciDevice, PreCheckedPciProfile},
+    sysfs_bus::{SysfsBusData, SysfsBusDevice, SysfsBusDeviceSummary, SysfsBuses},
+    usb::{PreCheckedUsbDevice, PreCheckedUsbProfile},
+    PreCheckedProfile, ProfileSummary,
+};
+use adw::prelude::*;
+use gtk::{
+    gio,
+    glib::{self, clone},
+};
+use libcfhdb::{pci::CfhdbPciDevice, usb::CfhdbUsbDevice};
+use std::{rc::Rc, sync::Arc};
+
+use super::{
+    bt, dmi, get_icon_for_class, missing_firmware::missing_firmware_description, pci, sysfs_bus,
+    usb,
+};
+
+pub const OVERVIEW_PAGE_NAME: &str = "overview";
+// Sysfs buses whose devices are listed with the missing firmware of the PCI or USB device they
+// hang off, in place of that device
+const FIRMWARE_SYSFS_BUSES: &[&str] = &["network", "sound", "display"];
+
+struct OverviewEntry {
+    title: String,
+    subtitle: String,
+    icon_name: String,
+    page_name: String,
+    device_tag: String,
+}
+
+// An installed profile that no present device matched, along with the bus specific way of removing it
+struct OrphanedProfileEntry {
+    title: String,
+    codename: String,
+    icon_name: String,
+    removable: bool,
+    remove: Rc<dyn Fn()>,
+}
+
+#[derive(Default)]
+struct OverviewEntries {
+    no_driver: Vec<OverviewEntry>,
+    missing_firmware: Vec<OverviewEntry>,
+    profiles_available: Vec<OverviewEntry>,
+    inactive: Vec<OverviewEntry>,
+    orphaned_profiles: Vec<OrphanedProfileEntry>,
+}
+
+impl OverviewEntries {
+    fn is_empty(&self) -> bool {
+        self.no_driver.is_empty()
+            && self.missing_firmware.is_empty()
+            && self.profiles_available.is_empty()
+            && self.inactive.is_empty()
+            && self.orphaned_profiles.is_empty()
+    }
+}
+
+fn device_has_driver(kernel_driver: &str) -> bool {
+    !kernel_driver.trim().is_empty()
+}
+
+fn inactive_device_action(started: bool, enabled: bool) -> Option<String> {
+    match (started, enabled) {
+        (true, true) => None,
+        (false, true) => Some(t!("overview_inactive_device_action").to_string()),
+        (true, false) => Some(t!("overview_disabled_device_action").to_string()),
+        (false, false) => Some(t!("overview_inactive_disabled_device_action").to_string()),
+    }
+}
+
+fn uninstalled_profiles_action<P>(
+    profiles: &[Arc<P>],
+    veiled: impl Fn(&P) -> bool,
+    installed: impl Fn(&P) -> bool,
+) -> Option<String> {
+    let visible_profiles: Vec<&Arc<P>> = profiles.iter().filter(|x| !veiled(x)).collect();
+    if visible_profiles.is_empty() || visible_profiles.iter().any(|x| installed(x)) {
+        None
+    } else {
+        Some(
+            t!(
+                "overview_profiles_available_action",
+                count = visible_profiles.len()
+            )
+            .to_string(),
+        )
+    }
+}
+
+/// Adds the installed profiles that no present device matched, `remove` starts the bus specific removal.
+fn push_orphaned_profiles<P: PreCheckedProfile>(
+    orphaned_profiles: &mut Vec<OrphanedProfileEntry>,
+    profiles: &[Arc<P>],
+    used: impl Fn(&P) -> bool,
+    remove: impl Fn(&Arc<P>) -> Rc<dyn Fn()>,
+) {
+    for profile in profiles {
+        if profile.is_installed() && !used(profile) {
+            let summary = profile.summary();
+            orphaned_profiles.push(OrphanedProfileEntry {
+                title: summary.i18n_desc,
+                codename: summary.codename,
+                icon_name: summary.icon_name,
+                removable: summary.removable,
+                remove: remove(profile),
+            });
+        }
+    }
+}
+
+/// The `profile_modify` of the PCI, USB, Bluetooth and DMI pages.
+type ProfileModify<P> = fn(
+    adw::ApplicationWindow,
+    &gio::SimpleAction,
+    &Arc<P>,
+    &Rc<Vec<Arc<P>>>,
+    &str,
+    &gio::SimpleAction,
+);
+
+/// The `remove` of `push_orphaned_profiles` for the buses with their own `profile_modify`.
+fn profile_remover<P: 'static>(
+    window: &adw::ApplicationWindow,
+    update_device_status_action: &gio::SimpleAction,
+    theme_changed_action: &gio::SimpleAction,
+    all_profiles: &Rc<Vec<Arc<P>>>,
+    profile_modify: ProfileModify<P>,
+) -> impl Fn(&Arc<P>) -> Rc<dyn Fn()> {
+    clone!(
+        #[strong]
+        window,
+        #[strong]
+        update_device_status_action,
+        #[strong]
+        theme_changed_action,
+        #[strong]
+        all_profiles,
+        move |profile: &Arc<P>| -> Rc<dyn Fn()> {
+            Rc::new(clone!(
+                #[strong]
+                window,
+                #[strong]
+                update_device_status_action,
+                #[strong]
+                theme_changed_action,
+                #[strong]
+                all_profiles,
+                #[strong]
+                profile,
+                move || {
+                    profile_modify(
+                        window.clone(),
+                        &update_device_status_action,
+                        &profile,
+                        &all_profiles,
+                        "remove",
+                        &theme_changed_action,
+                    )
+                }
+            ))
+        }
+    )
+}
+
+fn push_sysfs_bus_orphaned_profiles<D: SysfsBusDevice>(
+    orphaned_profiles: &mut Vec<OrphanedProfileEntry>,
+    window: &adw::ApplicationWindow,
+    update_device_status_action: &gio::SimpleAction,
+    bus: &SysfsBusData<D>,
+) {
+    let all_profiles = Rc::new(bus.profiles.clone());
+    push_orphaned_profiles(
+        orphaned_profiles,
+        &bus.profiles,
+        |x| *x.used.lock().unwrap(),
+        |profile| {
+            Rc::new(clone!(
+                #[strong]
+                window,
+                #[strong]
+                profile,
+                #[strong]
+                all_profiles,
+                #[strong]
+                update_device_status_action,
+                move || {
+                    sysfs_bus::profile_modify(
+                        &window,
+                        &update_device_status_action,
+                        &profile,
+                        &all_profiles,
+                        "remove",
+                    )
+                }
+            ))
+        },
+    );
+}
+
+pub fn overview_page(
+    window: &adw::ApplicationWindow,
+    hashmap_pci: Option<Vec<(String, Vec<PreCheckedPciDevice>)>>,
+    hashmap_usb: Option<Vec<(String, Vec<PreCheckedUsbDevice>)>>,
+    hashmap_bt: Option<Vec<(String, Vec<PreCheckedBtDevice>)>>,
+    dmi_profiles: &Rc<Vec<Arc<PreCheckedDmiProfile>>>,
+    pci_profiles: &Rc<Vec<Arc<PreCheckedPciProfile>>>,
+    usb_profiles: &Rc<Vec<Arc<PreCheckedUsbProfile>>>,
+    bt_profiles: &Rc<Vec<Arc<PreCheckedBtProfile>>>,
+    profile_summaries: Vec<ProfileSummary>,
+    sysfs_buses: &SysfsBuses,
+    theme_changed_action: &gio::SimpleAction,
+    update_device_status_action: &gio::SimpleAction,
+    show_page_action: &gio::SimpleAction,
+) -> gtk::ScrolledWindow {
+    let overview_box = gtk::Box::builder()
+        .orientation(gtk::Orientation::Vertical)
+        .margin_top(20)
+        .margin_bottom(20)
+        .margin_start(20)
+        .margin_end(20)
+        .spacing(20)
+        .build();
+
+    let overview_scroll = gtk::ScrolledWindow::builder()
+        .max_content_width(650)
+        .min_content_width(300)
+        .hscrollbar_policy(gtk::PolicyType::Never)
+        .vexpand(true)
+        .child(&overview_box)
+        .build();
+
+    // One device per PCI or USB device is enough, a GPU drives several displays
+    let mut firmware_parents: Vec<(String, String)> = vec![];
+    let mut firmware_devices: Vec<(&'static str, SysfsBusDeviceSummary)> = sysfs_buses
+        .buses()
+        .into_iter()
+        .filter(|x| FIRMWARE_SYSFS_BUSES.contains(&x.bus()))
+        .flat_map(|bus| {
+            bus.device_summaries()
+                .into_iter()
+                .map(move |x| (bus.bus(), x))
+        })
+        .collect();
+    firmware_devices.retain(|(_, x)| match &x.parent {
+        Some(parent) if !firmware_parents.contains(parent) => {
+            firmware_parents.push(parent.clone());
+            true
+        }
+        _ => false,
+    });
+
+    let sysfs_buses = sysfs_buses.clone();
+    let collect_entries = clone!(
+        #[strong]
+        window,
+        #[strong]
+        dmi_profiles,
+        #[strong]
+        pci_profiles,
+        #[strong]
+        usb_profiles,
+        #[strong]
+        bt_profiles,
+        #[strong]
+        theme_changed_action,
+        #[strong]
+        update_device_status_action,
+        move || {
+            let mut entries = OverviewEntries::default();
+
+            if let Some(hashmap_pci) = &hashmap_pci {
+                for (class, devices) in hashmap_pci {
+                    let class = format!("pci_class_name_{}", class);
+                    let icon_name =
+                        get_icon_for_class(&class).unwrap_or("dialog-question-symbolic");
+                    for device in devices {
+                        // Devices that went away since the last scan are skipped
+                        let current_device =
+                            match CfhdbPciDevice::get_device_from_busid(&device.device.sysfs_busid)
+                            {
+                                Ok(t) => t,
+                                Err(_) => continue,
+                            };
+                        let new_entry = |subtitle: String| OverviewEntry {
+                            title: format!(
+                                "{} - {}",
+                                current_device.vendor_name, current_device.device_name
+                            ),
+                            subtitle: format!("{} - {}", current_device.sysfs_busid, subtitle),
+                            icon_name: icon_name.to_string(),
+                            page_name: class.clone(),
+                            device_tag: current_device.sysfs_busid.clone(),
+                        };
+                        if !device_has_driver(&current_device.kernel_driver) {
+                            entries
+                                .no_driver
+                                .push(new_entry(t!("overview_no_driver_action").to_string()));
+                        }
+                        let firmware_parent =
+                            (String::from("pci"), current_device.sysfs_busid.clone());
+                        let missing_firmware = match firmware_parents.contains(&firmware_parent) {
+                            true => vec![],
+                            false => {
+                                get_device_missing_firmware("pci", &current_device.sysfs_busid)
+                            }
+                        };
+                        for missing_firmware in missing_firmware {
+                            entries.missing_firmware.push(new_entry(format!(
+                                "{}: {}",
+                                missing_firmware.module,
+                                missing_firmware_description(&missing_firmware, &profile_summaries)
+                            )));
+                        }
+                        if let Some(action) = uninstalled_profiles_action(
+                            &device.profiles,
+                            |x| x.profile().veiled,
+                            |x| x.installed(),
+                        ) {
+                            entries.profiles_available.push(new_entry(action));
+                        }
+                        if let Some(action) = inactive_device_action(
+                            current_device.started.unwrap_or_default(),
+                            current_device.enabled,
+                        ) {
+                            entries.inactive.push(new_entry(action));
+                        }
+                    }
+                }
+            }
+
+            if let Some(hashmap_usb) = &hashmap_usb {
+                for (class, devices) in hashmap_usb {
+                    let class = format!("usb_class_name_{}", class);
+                    let icon_name =
+                        get_icon_for_class(&class).unwrap_or("drive-harddisk-usb-symbolic");
+                    for device in devices {
+                        // Devices that were unplugged since the last scan are skipped
+                        let current_device =
+                            match CfhdbUsbDevice::get_device_from_busid(&device.device.sysfs_busid)
+                            {
+                                Ok(t) => t,
+                                Err(_) => continue,
+                            };
+                        let new_entry = |subtitle: String| OverviewEntry {
+                            title: format!(
+                                "{} - {}",
+                                current_device.manufacturer_string_index,
+                                current_device.product_string_index
+                            ),
+                            subtitle: format!("{} - {}", current_device.sysfs_busid, subtitle),
+                            icon_name: icon_name.to_string(),
+                            page_name: class.clone(),
+                            device_tag: current_device.sysfs_busid.clone(),
+                        };
+                        if !device_has_driver(&current_device.kernel_driver) {
+                            entries
+                                .no_driver
+                                .push(new_entry(t!("overview_no_driver_action").to_string()));
+                        }
+                        let firmware_parent =
+                            (String::from("usb"), current_device.sysfs_busid.clone());
+                        let missing_firmware = match firmware_parents.contains(&firmware_parent) {
+                            true => vec![],
+                            false => {
+                                get_device_missing_firmware("usb", &current_device.sysfs_busid)
+                            }
+                        };
+                        for missing_firmware in missing_firmware {
+                            entries.missing_firmware.push(new_entry(format!(
+                                "{}: {}",
+                                missing_firmware.module,
+                                missing_firmware_description(&missing_firmware, &profile_summaries)
+                            )));
+                        }
+                        if let Some(action) = uninstalled_profiles_action(
+                            &device.profiles,
+                            |x| x.profile().veiled,
+                            |x| x.installed(),
+                        ) {
+                            entries.profiles_available.push(new_entry(action));
+                        }
+                        if let Some(action) = inactive_device_action(
+                            current_device.started.unwrap_or_default(),
+                            current_device.enabled,
+                        ) {
+                            entries.inactive.push(new_entry(action));
+                        }
+                    }
+                }
+            }
+
+            for (bus, device) in &firmware_devices {
+                let Some((parent_bus, parent_busid)) = &device.parent else {
+                    continue;
+                };
+                let class = format!("{}_class_name_{}", bus, device.class);
+                for missing_firmware in get_device_missing_firmware(parent_bus, parent_busid) {
+                    entries.missing_firmware.push(OverviewEntry {
+                        title: device.title.clone(),
+                        subtitle: format!(
+                            "{} - {}: {}",
+                            device.id,
+                            missing_firmware.module,
+                            missing_firmware_description(&missing_firmware, &profile_summaries)
+                        ),
+                        icon_name: get_icon_for_class(&class)
+                            .unwrap_or("dialog-question-symbolic")
+                            .to_string(),
+                        page_name: class.clone(),
+                        device_tag: device.id.clone(),
+                    });
+                }
+            }
+
+            if let Some(hashmap_bt) = &hashmap_bt {
+                for (class, devices) in hashmap_bt {
+                    let class = format!("bt_class_name_{}", class);
+                    let icon_name = get_icon_for_class(&class).unwrap_or("bluetooth-symbolic");
+                    for device in devices {
+                        let device_content = &device.device;
+                        if let Some(action) = uninstalled_profiles_action(
+                            &device.profiles,
+                            |x| x.profile().veiled,
+                            |x| x.installed(),
+                        ) {
+                            entries.profiles_available.push(OverviewEntry {
+                                title: format!(
+                                    "{} - {}",
+                                    device_content.alias, device_content.name
+                                ),
+                                subtitle: format!("{} - {}", device_content.address, action),
+                                icon_name: icon_name.to_string(),
+                                page_name: class.clone(),
+                                device_tag: device_content.address.clone(),
+                            });
+                        }
+                    }
+                }
+            }
+
+            push_orphaned_profiles(
+                &mut entries.orphaned_profiles,
+                &dmi_profiles,
+                |x| *x.used.lock().unwrap(),
+                profile_remover(
+                    &window,
+                    &update_device_status_action,
+                    &theme_changed_action,
+                    &dmi_profiles,
+                    dmi::profile_modify,
+                ),
+            );
+            push_orphaned_profiles(
+                &mut entries.orphaned_profiles,
+                &pci_profiles,
+                |x| *x.used.lock().unwrap(),
+                profile_remover(
+                    &window,
+                    &update_device_status_action,
+                    &theme_changed_action,
+                    &pci_profiles,
+                    pci::profile_modify,
+                ),
+            );
+            push_orphaned_profiles(
+                &mut entries.orphaned_profiles,
+                &usb_profiles,
+                |x| *x.used.lock().unwrap(),
+                profile_remover(
+                    &window,
+                    &update_device_status_action,
+                    &theme_changed_action,
+                    &usb_profiles,
+                    usb::profile_modify,
+                ),
+            );
+            push_orphaned_profiles(
+                &mut entries.orphaned_profiles,
+                &bt_profiles,
+                |x| *x.used.lock().unwrap(),
+                profile_remover(
+                    &window,
+                    &update_device_status_action,
+                    &theme_changed_action,
+                    &bt_profiles,
+                    bt::profile_modify,
+                ),
+            );
+            push_sysfs_bus_orphaned_profiles(
+                &mut entries.orphaned_profiles,
+                &window,
+                &update_device_status_action,
+                &sysfs_buses.cpu,
+            );
+            push_sysfs_bus_orphaned_profiles(
+                &mut entries.orphaned_profiles,
+                &window,
+                &update_device_status_action,
+                &sysfs_buses.network,
+            );
+            push_sysfs_bus_orphaned_profiles(
+                &mut entries.orphaned_profiles,
+                &window,
+                &update_device_status_action,
+                &sysfs_buses.storage,
+            );
+            push_sysfs_bus_orphaned_profiles(
+                &mut entries.orphaned_profiles,
+                &window,
+                &update_device_status_action,
+                &sysfs_buses.input,
+            );
+            push_sysfs_bus_orphaned_profiles(
+                &mut entries.orphaned_profiles,
+                &window,
+                &update_device_status_action,
+                &sysfs_buses.display,
+            );
+            push_sysfs_bus_orphaned_profiles(
+                &mut entries.orphaned_profiles,
+                &window,
+                &update_device_status_action,
+                &sysfs_buses.sound,
+            );
+
+            entries
+        }
+    );
+
+    let refresh_overview = clone!(
+        #[weak]
+        overview_box,
+        #[strong]
+        show_page_action,
+        move || {
+            while let Some(child) = overview_box.first_child() {
+                overview_box.remove(&child);
+            }
+
+            let entries = collect_entries();
+
+            if entries.is_empty() {
+                overview_box.append(
+                    &adw::StatusPage::builder()
+                        .icon_name("emblem-default-symbolic")
+                        .title(t!("overview_all_good_title"))
+                        .description(t!("overview_all_good_description"))
+                        .vexpand(true)
+                        .build(),
+                );
+                return;
+            }
+
+            for (group_title, group_description, group_entries, has_install_recommended) in [
+                (
+                    t!("overview_no_driver_title"),
+                    t!("overview_no_driver_description"),
+                    entries.no_driver,
+                    false,
+                ),
+                (
+                    t!("overview_missing_firmware_title"),
+                    t!("overview_missing_firmware_description"),
+                    entries.missing_firmware,
+                    false,
+                ),
+                (
+                    t!("overview_profiles_available_title"),
+                    t!("overview_profiles_available_description"),
+                    entries.profiles_available,
+                    true,
+                ),
+                (
+                    t!("overview_inactive_title"),
+                    t!("overview_inactive_description"),
+                    entries.inactive,
+                    false,
+                ),
+            ] {
+                if group_entries.is_empty() {
+                    continue;
+                }
+                let group = adw::PreferencesGroup::builder()
+                    .title(group_title)
+                    .description(group_description)
+                    .build();
+                if has_install_recommended {
+                    let install_recommended_button = gtk::Button::builder()
+                        .label(t!("overview_install_recommended_button_label"))
+                        .action_name("app.installrecommended")
+                        .valign(gtk::Align::Center)
+                        .build();
+                    install_recommended_button.add_css_class("suggested-action");
+                    group.set_header_suffix(Some(&install_recommended_button));
+                }
+                for entry in group_entries {
+                    let entry_row = adw::ActionRow::builder()
+                        .title(glib::markup_escape_text(&entry.title))
+                        .subtitle(glib::markup_escape_text(&entry.subtitle))
+                        .activatable(true)
+                        .build();
+                    entry_row.add_prefix(&gtk::Image::from_icon_name(&entry.icon_name));
+                    entry_row.add_suffix(&gtk::Image::from_icon_name("go-next-symbolic"));
+                    entry_row.connect_activated(clone!(
+                        #[strong]
+                        show_page_action,
+                        move |_| {
+                            show_page_action.activate(Some(
+                                &(entry.page_name.clone(), entry.device_tag.clone()).to_variant(),
+                            ));
+                        }
+                    ));
+                    group.add(&entry_row);
+                }
+                overview_box.append(&group);
+            }
+
+            if !entries.orphaned_profiles.is_empty() {
+                let group = adw::PreferencesGroup::builder()
+                    .title(t!("overview_orphaned_profiles_title"))
+                    .description(t!("overview_orphaned_profiles_description"))
+                    .build();
+                for entry in entries.orphaned_profiles {
+                    let entry_row = adw::ActionRow::builder()
+                        .title(glib::markup_escape_text(&entry.title))
+                        .subtitle(glib::markup_escape_text(&format!(
+                            "{} - {}",
+                            entry.codename,
+                            if entry.removable {
+                                t!("overview_orphaned_profile_action")
+                            } else {
+                                t!("overview_orphaned_profile_not_removable")
+                            }
+                        )))
+                        .build();
+                    entry_row.add_prefix(
+                        &gtk::Image::builder()
+                            .icon_name(&entry.icon_name)
+                            .pixel_size(32)
+                            .build(),
+                    );
+                    let remove_button = gtk::Button::builder()
+                        .valign(gtk::Align::Center)
+                        .label(t!("profile_remove_button_label"))
+                        .tooltip_text(t!("profile_remove_button_tooltip_text"))
+                        .sensitive(entry.removable)
+                        .build();
+                    remove_button.add_css_class("destructive-action");
+                    let remove = entry.remove.clone();
+                    remove_button.connect_clicked(move |_| remove());
+                    entry_row.add_suffix(&remove_button);
+                    group.add(&entry_row);
+                }
+                overview_box.append(&group);
+            }
+        }
+    );
+
+    // Profiles get their used state when the device pages are set up, so the first fill happens on map
+    overview_scroll.connect_map(clone!(
+        #[strong]
+        refresh_overview,
+        move |_| refresh_overview()
+    ));
+
+    update_device_status_action.connect_activate(move |_, _| refresh_overview());
+
+    overview_scroll
+}
@@ -1,24 +1,34 @@
 use crate::{
     build_ui::{color_badge::ColorBadge, colored_circle::ColoredCircle},
-    cfhdb::pci::{PreCheckedPciDevice, PreCheckedPciProfile},
-    config::distro_package_manager,
+    cfhdb::{
+        get_profile_modify_commands,
+        pci::{get_pci_advanced_details, PreCheckedPciDevice, PreCheckedPciProfile},
+        policy::PROFILE_POLICY,
+        recommendation::recommended_profile,
+        PreCheckedProfile,
+    },
     ChannelMsg,
 };
 use adw::{prelude::*, *};
 use gtk::{
     gdk::RGBA,
-    glib::{clone, MainContext},
+    glib::{self, clone, MainContext},
     Align, Orientation,
     Orientation::Vertical,
     ScrolledWindow, SelectionMode,
 };
 
 use libcfhdb::pci::CfhdbPciDevice;
-use std::{process::Command, rc::Rc, sync::Arc, thread};
+use log::info;
+use std::{cell::RefCell, process::Command, rc::Rc, sync::Arc, thread};
 
 use users::get_current_username;
 
-use super::{error_dialog, run_in_lock_script};
+use super::{
+    add_profile_row_badges, driver_binding::driver_binding_group, error_dialog,
+    kernel_modules::kernel_modules_group, missing_firmware::missing_firmware_group,
+    run_in_lock_script,
+};
 
 pub fn create_pci_class(
     window: &ApplicationWindow,
@@ -26,6 +36,7 @@ pub fn create_pci_class(
     class: &str,
     theme_changed_action: &gio::SimpleAction,
     update_device_status_action: &gio::SimpleAction,
+    push_device_page_action: &gio::SimpleAction,
 ) -> ScrolledWindow {
     // Update all profiles' installation status before creating the UI
     for device in devices {
@@ -56,6 +67,7 @@ pub fn create_pci_class(
     );
     let devices_navigation_page = adw::NavigationPage::builder()
         .title(class)
+        .tag("devices_list")
         .child(&devices_navigation_page_toolbar)
         .build();
     //
@@ -67,6 +79,17 @@ pub fn create_pci_class(
         .hscrollbar_policy(gtk::PolicyType::Never)
         .child(&navigation_view)
         .build();
+    push_device_page_action.connect_activate(clone!(
+        #[weak]
+        navigation_view,
+        move |_, param| {
+            if let Some(device_tag) = param.and_then(|x| x.get::<String>()) {
+                if navigation_view.find_page(&device_tag).is_some() {
+                    navigation_view.replace_with_tags(&["devices_list", &device_tag]);
+                }
+            }
+        }
+    ));
     //
     for device in devices {
         let device_content = &device.device;
@@ -94,6 +117,7 @@ pub fn create_pci_class(
         );
         let device_navigation_page = adw::NavigationPage::builder()
             .title(&device_title)
+            .tag(&device_content.sysfs_busid)
             .child(&device_navigation_page_toolbar)
             .build();
         navigation_view.add(&device_navigation_page);
@@ -250,12 +274,17 @@ fn pci_device_page(
     let rows_size_group = gtk::SizeGroup::new(gtk::SizeGroupMode::Both);
 
     let mut profiles = device.profiles.clone();
+    let recommended_profile = recommended_profile(&device.profiles);
     profiles.sort_by_key(|x| x.profile().priority);
 
     let mut normal_profiles = vec![];
     let mut veiled_profiles = vec![];
 
     for profile in profiles.clone() {
+        // Installed profiles stay listed so they can still be removed
+        if PROFILE_POLICY.hides(&profile.summary()) && !profile.installed() {
+            continue;
+        }
         let profile_content = profile.profile();
         let (profiles_color_badges_size_group0, profiles_color_badges_size_group1) = (
             gtk::SizeGroup::new(gtk::SizeGroupMode::Both),
@@ -298,6 +327,15 @@ fn pci_device_page(
         profile_expander_row.add_suffix(&profile_status_icon);
         profile_expander_row.set_title(&profile_content.i18n_desc);
         profile_expander_row.set_subtitle(&profile_content.codename);
+        let profile_policy_denial = add_profile_row_badges(
+            &profile_expander_row,
+            &profile_content_row,
+            &profile_install_button,
+            &profile.summary(),
+            recommended_profile
+                .as_ref()
+                .is_some_and(|x| Arc::ptr_eq(x, &profile)),
+        );
         //
         let color_badge_experimental = ColorBadge::new();
         color_badge_experimental.set_label0(textwrap::fill(&t!("profile_experimental"), 10));
@@ -382,7 +420,8 @@ fn pci_device_page(
         //
         update_device_status_action.connect_activate(clone!(move |_, _| {
             let profile_status = profile.installed();
-            profile_install_button.set_sensitive(!profile_status);
+            profile_install_button
+                .set_sensitive(!profile_status && profile_policy_denial.is_none());
             if profile_content.removable {
                 profile_remove_button.set_sensitive(profile_status);
             } else {
@@ -439,7 +478,7 @@ fn pci_device_page(
                 "background-red-bg"
             });
             driver_color_badge
-                .set_label1(textwrap::fill(device_content.kernel_driver.as_str(), 10));
+                .set_label1(textwrap::fill(updated_device.kernel_driver.as_str(), 10));
             sysfs_busid_color_badge
                 .set_label1(textwrap::fill(&device_content.sysfs_busid.as_str(), 10));
             vendor_id_color_badge
@@ -481,10 +520,73 @@ fn pci_device_page(
         }
         content_box.append(&veil_expander);
     }
+    content_box.append(&missing_firmware_group(
+        "pci",
+        &device_content.sysfs_busid,
+        device.profiles.iter().map(|x| x.summary()).collect(),
+        update_device_status_action,
+    ));
+    content_box.append(&advanced_details_group(
+        &device_content.sysfs_busid,
+        update_device_status_action,
+    ));
+    content_box.append(&kernel_modules_group(
+        window,
+        "pci",
+        &device_content.sysfs_busid,
+        update_device_status_action,
+    ));
+    content_box.append(&driver_binding_group(
+        window,
+        "pci",
+        &device_content.sysfs_busid,
+        update_device_status_action,
+    ));
 
     content_box
 }
 
+/// Link, power and resource details, collapsed by default as they are only needed for troubleshooting.
+fn advanced_details_group(
+    sysfs_busid: &str,
+    update_device_status_action: &gio::SimpleAction,
+) -> PreferencesGroup {
+    let advanced_details_group = PreferencesGroup::builder()
+        .margin_top(20)
+        .margin_bottom(20)
+        .margin_start(20)
+        .margin_end(20)
+        .hexpand(true)
+        .build();
+    let advanced_details_expander_row = ExpanderRow::builder()
+        .title(t!("pci_details_title"))
+        .subtitle(t!("pci_details_subtitle"))
+        .build();
+    advanced_details_group.add(&advanced_details_expander_row);
+
+    let detail_rows: Rc<RefCell<Vec<ActionRow>>> = Rc::new(RefCell::new(vec![]));
+    let sysfs_busid = sysfs_busid.to_string();
+    let update_details = move || {
+        for row in detail_rows.borrow_mut().drain(..) {
+            advanced_details_expander_row.remove(&row);
+        }
+        for (field, value) in get_pci_advanced_details(&sysfs_busid) {
+            let detail_row = ActionRow::builder()
+                .title(t!(format!("pci_details_{}", field)))
+                .subtitle(glib::markup_escape_text(&value))
+                .subtitle_selectable(true)
+                .build();
+            detail_row.add_css_class("property");
+            advanced_details_expander_row.add_row(&detail_row);
+            detail_rows.borrow_mut().push(detail_row);
+        }
+    };
+    update_details();
+    update_device_status_action.connect_activate(move |_, _| update_details());
+
+    advanced_details_group
+}
+
 pub fn profile_modify(
     window: ApplicationWindow,
     update_device_status_action: &gio::SimpleAction,
@@ -498,6 +600,13 @@ pub fn profile_modify(
 
     let profile_content = profile.profile();
 
+    info!(
+        target: "executor",
+        "Starting {} of PCI profile {}",
+        opreation,
+        profile_content.codename
+    );
+
     let profile_modify_log_terminal_buffer = gtk::TextBuffer::builder().build();
 
     let profile_modify_log_terminal = gtk::TextView::builder()
@@ -538,52 +647,29 @@ pub fn profile_modify(
     //
 
     let string_opreation = String::from(opreation);
+    let profile_summary = profile.summary();
 
     thread::spawn(clone!(
         #[strong]
-        profile_content,
+        profile_summary,
         #[strong]
         string_opreation,
         move || {
-            let script = match string_opreation.as_str() {
-                "install" => profile_content.install_script,
-                "remove" => profile_content.remove_script,
-                _ => panic!(),
-            };
-            match script {
-                Some(t) => match profile_content.packages {
-                    Some(a) => {
-                        let package_list = a.join(" ");
-                        let modify_command =
-                            distro_package_manager(&string_opreation, &package_list);
-                        run_in_lock_script(
-                            &log_loop_sender,
-                            &format!("#! /bin/bash\nset -e\n{}\n{}", modify_command, t),
-                        );
-                    }
-                    None => {
-                        run_in_lock_script(
-                            &log_loop_sender,
-                            &format!("#! /bin/bash\nset -e\n{}", t),
-                        );
-                    }
-                },
-                None => match profile_content.packages {
-                    Some(a) => {
-                        let package_list = a.join(" ");
-                        let modify_command =
-                            distro_package_manager(&string_opreation, &package_list);
-                        run_in_lock_script(
-                            &log_loop_sender,
-                            &format!("#! /bin/bash\nset -e\n{}", modify_command),
-                        );
-                    }
-                    None => {
-                        log_loop_sender
-                            .send_blocking(ChannelMsg::SuccessMsg)
-                            .unwrap();
-                    }
-                },
+            match get_profile_modify_commands(&profile_summary, &string_opreation) {
+                Err(reason) => {
+                    log_loop_sender
+                        .send_blocking(ChannelMsg::OutputLine(reason))
+                        .unwrap();
+                    log_loop_sender.send_blocking(ChannelMsg::FailMsg).unwrap();
+                }
+                Ok(Some(t)) => {
+                    run_in_lock_script(&log_loop_sender, &format!("#! /bin/bash\nset -e\n{}", t));
+                }
+                Ok(None) => {
+                    log_loop_sender
+                        .send_blocking(ChannelMsg::SuccessMsg)
+                        .unwrap();
+                }
             }
         }
     ));
@@ -0,0 +1,134 @@
+use crate::ChannelMsg;
+use adw::prelude::*;
+use gtk::{
+    gio,
+    glib::{self, clone, MainContext},
+};
+use std::{process::Command, thread};
+use users::get_current_username;
+
+use super::run_in_lock_script;
+
+/// Runs a privileged script while showing its output, then offers to reboot if `reboot_offered`,
+/// scripts that only read the system pass false.
+/// Without a script the dialog reports success right away, `on_close` runs once it is dismissed.
+pub fn privileged_script_dialog(
+    window: &adw::ApplicationWindow,
+    heading: &str,
+    body_successful: &str,
+    body_failed: &str,
+    script: Option<String>,
+    reboot_offered: bool,
+    on_close: impl Fn() + 'static,
+) {
+    let (log_loop_sender, log_loop_receiver) = async_channel::unbounded();
+    let log_loop_sender: async_channel::Sender<ChannelMsg> = log_loop_sender.clone();
+
+    let script_log_terminal_buffer = gtk::TextBuffer::builder().build();
+
+    let script_log_terminal = gtk::TextView::builder()
+        .vexpand(true)
+        .hexpand(true)
+        .editable(false)
+        .buffer(&script_log_terminal_buffer)
+        .build();
+
+    let script_log_terminal_scroll = gtk::ScrolledWindow::builder()
+        .width_request(400)
+        .height_request(200)
+        .vexpand(true)
+        .hexpand(true)
+        .child(&script_log_terminal)
+        .build();
+
+    let script_dialog = adw::AlertDialog::builder()
+        .extra_child(&script_log_terminal_scroll)
+        .width_request(400)
+        .height_request(200)
+        .heading(heading)
+        .can_close(false)
+        .build();
+    script_dialog.add_response("script_dialog_ok", &t!("privileged_script_dialog_ok_label"));
+    if reboot_offered {
+        script_dialog.add_response(
+            "script_dialog_reboot",
+            &t!("privileged_script_dialog_reboot_label"),
+        );
+        script_dialog
+            .set_response_appearance("script_dialog_reboot", adw::ResponseAppearance::Suggested);
+    }
+
+    //
+
+    thread::spawn(move || match script {
+        Some(script) => run_in_lock_script(
+            &log_loop_sender,
+            &format!("#! /bin/bash\nset -e\n{}", script),
+        ),
+        None => log_loop_sender
+            .send_blocking(ChannelMsg::SuccessMsg)
+            .unwrap(),
+    });
+
+    let (body_successful, body_failed) = (body_successful.to_string(), body_failed.to_string());
+    let log_loop_context = MainContext::default();
+    // The main loop executes the asynchronous block
+    log_loop_context.spawn_local(clone!(
+        #[strong]
+        script_log_terminal_buffer,
+        #[strong]
+        script_dialog,
+        async move {
+            while let Ok(state) = log_loop_receiver.recv().await {
+                match state {
+                    ChannelMsg::OutputLine(line) => script_log_terminal_buffer.insert(
+                        &mut script_log_terminal_buffer.end_iter(),
+                        &("\n".to_string() + &line),
+                    ),
+                    ChannelMsg::SuccessMsg => {
+                        if reboot_offered {
+                            script_dialog.set_response_enabled(
+                                "script_dialog_reboot",
+                                get_current_username().unwrap() != "pikaos",
+                            );
+                        }
+                        script_dialog.set_response_enabled("script_dialog_ok", true);
+                        script_dialog.set_body(&body_successful);
+                    }
+                    // The script never sends the device fetch messages, should one arrive the run is treated as failed
+                    ChannelMsg::FailMsg
+                    | ChannelMsg::SuccessMsgDeviceFetch(..)
+                    | ChannelMsg::UpdateMsg => {
+                        script_dialog.set_response_enabled("script_dialog_ok", true);
+                        script_dialog.set_body(&body_failed);
+                        if reboot_offered {
+                            script_dialog.set_response_enabled("script_dialog_reboot", false);
+                        }
+                    }
+                }
+            }
+        }
+    ));
+
+    script_dialog.set_response_enabled("script_dialog_ok", false);
+    if reboot_offered {
+        script_dialog.set_response_enabled("script_dialog_reboot", false);
+    }
+    let dialog_closure = clone!(
+        #[strong]
+        script_dialog,
+        move |choice: glib::GString| match choice.as_str() {
+            "script_dialog_reboot" => {
+                Command::new("systemctl")
+                    .arg("reboot")
+                    .status()
+                    .expect("systemctl reboot failed to start");
+            }
+            _ => {
+                script_dialog.force_close();
+                on_close();
+            }
+        }
+    );
+    script_dialog.choose(window, gio::Cancellable::NONE, dialog_closure);
+}
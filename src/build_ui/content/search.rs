@@ -0,0 +1,373 @@
+use crate::cfhdb::{
+    bt::PreCheckedBtDevice, dmi::PreCheckedDmiInfo, pci::PreCheckedPciDevice,
+    sysfs_bus::SysfsBuses, usb::PreCheckedUsbDevice,
+};
+use adw::prelude::*;
+use gtk::glib::{self, clone};
+use std::{cell::RefCell, rc::Rc};
+
+pub const SEARCH_RESULTS_PAGE_NAME: &str = "search_results";
+
+#[derive(Clone)]
+pub struct SearchItem {
+    pub bus: &'static str,
+    pub page_name: String,
+    pub device_tag: String,
+    pub title: String,
+    pub subtitle: String,
+    pub icon_name: String,
+    haystack: String,
+}
+
+impl SearchItem {
+    fn new(
+        bus: &'static str,
+        page_name: String,
+        device_tag: String,
+        title: String,
+        subtitle: String,
+        icon_name: String,
+        fields: Vec<String>,
+    ) -> Self {
+        Self {
+            bus,
+            page_name,
+            device_tag,
+            title,
+            subtitle,
+            icon_name,
+            haystack: fields.join("\n").to_lowercase(),
+        }
+    }
+
+    pub fn matches(&self, query: &str) -> bool {
+        query
+            .to_lowercase()
+            .split_whitespace()
+            .all(|x| self.haystack.contains(x))
+    }
+}
+
+pub fn build_search_index(
+    hashmap_pci: &Option<Vec<(String, Vec<PreCheckedPciDevice>)>>,
+    hashmap_usb: &Option<Vec<(String, Vec<PreCheckedUsbDevice>)>>,
+    dmi_info: &PreCheckedDmiInfo,
+    hashmap_bt: &Option<Vec<(String, Vec<PreCheckedBtDevice>)>>,
+    sysfs_buses: &SysfsBuses,
+) -> Vec<SearchItem> {
+    let mut search_items = vec![];
+
+    let info = &dmi_info.info;
+    let mut dmi_fields = vec![
+        info.bios_date.clone(),
+        info.bios_release.clone(),
+        info.bios_vendor.clone(),
+        info.bios_version.clone(),
+        info.board_asset_tag.clone(),
+        info.board_name.clone(),
+        info.board_vendor.clone(),
+        info.board_version.clone(),
+        info.product_family.clone(),
+        info.product_name.clone(),
+        info.product_sku.clone(),
+        info.product_version.clone(),
+        info.sys_vendor.clone(),
+    ];
+    for profile in &dmi_info.profiles {
+        let profile_content = profile.profile();
+        dmi_fields.push(profile_content.codename);
+        dmi_fields.push(profile_content.i18n_desc);
+    }
+    search_items.push(SearchItem::new(
+        "dmi",
+        String::from("dmi"),
+        String::new(),
+        info.product_name.clone(),
+        format!("{} - {}", info.sys_vendor, info.board_name),
+        String::from("application-x-firmware-symbolic"),
+        dmi_fields,
+    ));
+
+    if let Some(hashmap_pci) = hashmap_pci {
+        for (class, devices) in hashmap_pci {
+            let class = format!("pci_class_name_{}", class);
+            for device in devices {
+                let device_content = &device.device;
+                let mut fields = vec![
+                    t!(class).to_string(),
+                    device_content.vendor_name.clone(),
+                    device_content.device_name.clone(),
+                    device_content.vendor_id.clone(),
+                    device_content.device_id.clone(),
+                    format!("{}:{}", device_content.vendor_id, device_content.device_id),
+                    device_content.class_id.clone(),
+                    device_content.sysfs_busid.clone(),
+                    device_content.kernel_driver.clone(),
+                ];
+                for profile in &device.profiles {
+                    let profile_content = profile.profile();
+                    fields.push(profile_content.codename);
+                    fields.push(profile_content.i18n_desc);
+                }
+                search_items.push(SearchItem::new(
+                    "pci",
+                    class.clone(),
+                    device_content.sysfs_busid.clone(),
+                    format!(
+                        "{} - {}",
+                        device_content.vendor_name, device_content.device_name
+                    ),
+                    format!("{} - {}", device_content.sysfs_busid, t!(class)),
+                    super::get_icon_for_class(&class)
+                        .unwrap_or("dialog-question-symbolic")
+                        .into(),
+                    fields,
+                ));
+            }
+        }
+    }
+
+    if let Some(hashmap_usb) = hashmap_usb {
+        for (class, devices) in hashmap_usb {
+            let class = format!("usb_class_name_{}", class);
+            for device in devices {
+                let device_content = &device.device;
+                let mut fields = vec![
+                    t!(class).to_string(),
+                    device_content.manufacturer_string_index.clone(),
+                    device_content.product_string_index.clone(),
+                    device_content.vendor_id.clone(),
+                    device_content.product_id.clone(),
+                    format!("{}:{}", device_content.vendor_id, device_content.product_id),
+                    device_content.class_code.clone(),
+                    device_content.sysfs_busid.clone(),
+                    device_content.kernel_driver.clone(),
+                ];
+                for profile in &device.profiles {
+                    let profile_content = profile.profile();
+                    fields.push(profile_content.codename);
+                    fields.push(profile_content.i18n_desc);
+                }
+                search_items.push(SearchItem::new(
+                    "usb",
+                    class.clone(),
+                    device_content.sysfs_busid.clone(),
+                    format!(
+                        "{} - {}",
+                        device_content.manufacturer_string_index,
+                        device_content.product_string_index
+                    ),
+                    format!("{} - {}", device_content.sysfs_busid, t!(class)),
+                    super::get_icon_for_class(&class)
+                        .unwrap_or("drive-harddisk-usb-symbolic")
+                        .into(),
+                    fields,
+                ));
+            }
+        }
+    }
+
+    if let Some(hashmap_bt) = hashmap_bt {
+        for (class, devices) in hashmap_bt {
+            let class = format!("bt_class_name_{}", class);
+            for device in devices {
+                let device_content = &device.device;
+                let mut fields = vec![
+                    t!(class).to_string(),
+                    device_content.name.clone(),
+                    device_content.alias.clone(),
+                    device_content.address.clone(),
+                    device_content.adapter.clone(),
+                    device_content.modalias_vendor_id.clone(),
+                    device_content.modalias_product_id.clone(),
+                    device_content.modalias_device_id.clone(),
+                ];
+                for profile in &device.profiles {
+                    let profile_content = profile.profile();
+                    fields.push(profile_content.codename);
+                    fields.push(profile_content.i18n_desc);
+                }
+                search_items.push(SearchItem::new(
+                    "bt",
+                    class.clone(),
+                    device_content.address.clone(),
+                    format!("{} - {}", device_content.alias, device_content.name),
+                    format!("{} - {}", device_content.address, t!(class)),
+                    super::get_icon_for_class(&class)
+                        .unwrap_or("bluetooth-symbolic")
+                        .into(),
+                    fields,
+                ));
+            }
+        }
+    }
+
+    for sysfs_bus in sysfs_buses.buses() {
+        for device in sysfs_bus.device_summaries() {
+            let class = format!("{}_class_name_{}", sysfs_bus.bus(), device.class);
+            let mut fields = vec![
+                t!(class).to_string(),
+                device.id.clone(),
+                device.title.clone(),
+                device.subtitle.clone(),
+            ];
+            fields.extend(device.fields);
+            search_items.push(SearchItem::new(
+                sysfs_bus.bus(),
+                class.clone(),
+                device.id,
+                device.title,
+                format!("{} - {}", device.subtitle, t!(class)),
+                super::get_icon_for_class(&class)
+                    .unwrap_or("dialog-question-symbolic")
+                    .into(),
+                fields,
+            ));
+        }
+    }
+
+    search_items
+}
+
+pub fn search_results_page(
+    stack: &gtk::Stack,
+    search_bar: &gtk::SearchBar,
+    search_entry: &gtk::SearchEntry,
+    search_items: Vec<SearchItem>,
+    show_page_action: &gtk::gio::SimpleAction,
+) -> gtk::ScrolledWindow {
+    let search_results_box = gtk::Box::builder()
+        .orientation(gtk::Orientation::Vertical)
+        .margin_top(20)
+        .margin_bottom(20)
+        .margin_start(20)
+        .margin_end(20)
+        .spacing(20)
+        .build();
+
+    let search_results_scroll = gtk::ScrolledWindow::builder()
+        .max_content_width(650)
+        .min_content_width(300)
+        .hscrollbar_policy(gtk::PolicyType::Never)
+        .vexpand(true)
+        .child(&search_results_box)
+        .build();
+
+    let search_no_results_page = adw::StatusPage::builder()
+        .icon_name("system-search-symbolic")
+        .title(t!("search_no_results_title"))
+        .description(t!("search_no_results_description"))
+        .vexpand(true)
+        .build();
+
+    // Results are grouped by bus, in the order the index lists the buses
+    let mut buses: Vec<&'static str> = vec![];
+    for item in &search_items {
+        if !buses.contains(&item.bus) {
+            buses.push(item.bus);
+        }
+    }
+
+    // The page that was visible before the search started, restored when the search is cleared
+    let previous_page: Rc<RefCell<Option<String>>> = Rc::new(RefCell::new(None));
+
+    let restore_previous_page = clone!(
+        #[strong]
+        stack,
+        #[strong]
+        previous_page,
+        move || {
+            if stack.visible_child_name().as_deref() == Some(SEARCH_RESULTS_PAGE_NAME) {
+                if let Some(page_name) = previous_page.borrow_mut().take() {
+                    stack.set_visible_child_name(&page_name);
+                }
+            }
+        }
+    );
+
+    search_entry.connect_search_changed(clone!(
+        #[strong]
+        stack,
+        #[strong]
+        search_bar,
+        #[strong]
+        previous_page,
+        #[strong]
+        restore_previous_page,
+        #[strong]
+        show_page_action,
+        move |search_entry| {
+            while let Some(child) = search_results_box.first_child() {
+                search_results_box.remove(&child);
+            }
+
+            let query = search_entry.text().to_string();
+            if query.trim().is_empty() {
+                restore_previous_page();
+                return;
+            }
+
+            if stack.visible_child_name().as_deref() != Some(SEARCH_RESULTS_PAGE_NAME) {
+                *previous_page.borrow_mut() = stack.visible_child_name().map(|x| x.to_string());
+                stack.set_visible_child_name(SEARCH_RESULTS_PAGE_NAME);
+            }
+
+            let mut has_results = false;
+            for bus in &buses {
+                let bus_results: Vec<&SearchItem> = search_items
+                    .iter()
+                    .filter(|x| x.bus == *bus && x.matches(&query))
+                    .collect();
+                if bus_results.is_empty() {
+                    continue;
+                }
+                has_results = true;
+
+                let bus_group = adw::PreferencesGroup::builder()
+                    .title(t!(format!("{}_devices", bus)))
+                    .build();
+                for item in bus_results {
+                    let result_row = adw::ActionRow::builder()
+                        .title(glib::markup_escape_text(&item.title))
+                        .subtitle(glib::markup_escape_text(&item.subtitle))
+                        .activatable(true)
+                        .build();
+                    result_row.add_prefix(&gtk::Image::from_icon_name(&item.icon_name));
+                    result_row.add_suffix(&gtk::Image::from_icon_name("go-next-symbolic"));
+                    result_row.connect_activated(clone!(
+                        #[strong]
+                        search_bar,
+                        #[strong]
+                        show_page_action,
+                        #[strong]
+                        item,
+                        move |_| {
+                            show_page_action.activate(Some(
+                                &(item.page_name.clone(), item.device_tag.clone()).to_variant(),
+                            ));
+                            search_bar.set_search_mode(false);
+                        }
+                    ));
+                    bus_group.add(&result_row);
+                }
+                search_results_box.append(&bus_group);
+            }
+
+            if !has_results {
+                search_results_box.append(&search_no_results_page);
+            }
+        }
+    ));
+
+    search_bar.connect_search_mode_enabled_notify(clone!(
+        #[strong]
+        restore_previous_page,
+        move |search_bar| {
+            if !search_bar.is_search_mode() {
+                restore_previous_page();
+            }
+        }
+    ));
+
+    search_results_scroll
+}
@@ -0,0 +1,89 @@
+use crate::cfhdb::{storage::StorageDevice, sysfs_bus::PreCheckedSysfsDevice};
+use adw::prelude::*;
+use gtk::{gio, glib::clone};
+use log::info;
+
+use super::{error_dialog, privileged_script_dialog::privileged_script_dialog};
+
+// smartctl and nvme are usually in sbin, which is not always in the PATH of the user
+const TOOL_DIRS: &[&str] = &["/usr/sbin", "/usr/bin", "/sbin", "/bin"];
+const HEALTH_CHECK_PROG: &str = r###"
+if command -v smartctl > /dev/null
+then
+    smartctl -H -A /dev/{device}
+elif command -v nvme > /dev/null && [[ {device} == nvme* ]]
+then
+    nvme smart-log /dev/{device}
+else
+    exit 1
+fi
+"###;
+
+fn tool_installed(tool: &str) -> bool {
+    let path = std::env::var("PATH").unwrap_or_default();
+    TOOL_DIRS
+        .iter()
+        .copied()
+        .chain(path.split(':'))
+        .any(|dir| std::path::Path::new(dir).join(tool).is_file())
+}
+
+/// Whether HEALTH_CHECK_PROG has a tool for the drive, the same choice the script makes.
+fn health_tool_available(device: &str) -> bool {
+    tool_installed("smartctl") || (tool_installed("nvme") && device.starts_with("nvme"))
+}
+
+/// SMART data needs raw access to the drive, so it is read through a privileged script.
+pub fn storage_device_groups(
+    window: &adw::ApplicationWindow,
+    device: &PreCheckedSysfsDevice<StorageDevice>,
+    _update_device_status_action: &gio::SimpleAction,
+) -> Vec<gtk::Widget> {
+    let health_group = adw::PreferencesGroup::builder()
+        .margin_top(20)
+        .margin_bottom(20)
+        .margin_start(20)
+        .margin_end(20)
+        .title(t!("storage_health_title"))
+        .description(t!("storage_health_subtitle"))
+        .hexpand(true)
+        .build();
+
+    let health_row = adw::ActionRow::builder()
+        .title(t!("storage_health_check_title"))
+        .subtitle(t!("storage_health_check_subtitle"))
+        .build();
+    let health_button = gtk::Button::builder()
+        .valign(gtk::Align::Center)
+        .label(t!("storage_health_check_button_label"))
+        .build();
+    let name = device.device.name.clone();
+    health_button.connect_clicked(clone!(
+        #[strong]
+        window,
+        move |_| {
+            if !health_tool_available(&name) {
+                error_dialog(
+                    window.clone(),
+                    &t!("storage_health_dialog_heading"),
+                    &t!("storage_health_no_tool"),
+                );
+                return;
+            }
+            info!(target: "storage", "Reading SMART data of {}", name);
+            privileged_script_dialog(
+                &window,
+                &t!("storage_health_dialog_heading"),
+                &t!("storage_health_dialog_body_successful"),
+                &t!("storage_health_dialog_body_failed"),
+                Some(HEALTH_CHECK_PROG.replace("{device}", &name)),
+                false,
+                || {},
+            );
+        }
+    ));
+    health_row.add_suffix(&health_button);
+    health_group.add(&health_row);
+
+    vec![health_group.upcast()]
+}
@@ -0,0 +1,554 @@
+use crate::{
+    build_ui::color_badge::ColorBadge,
+    cfhdb::{
+        bt::PreCheckedBtDevice,
+        get_profile_modify_commands,
+        pci::PreCheckedPciDevice,
+        policy::PROFILE_POLICY,
+        recommendation::recommended_profile,
+        sysfs_bus::{PreCheckedSysfsDevice, PreCheckedSysfsProfile, SysfsBusData, SysfsBusDevice},
+        usb::PreCheckedUsbDevice,
+        PreCheckedProfile,
+    },
+};
+use adw::prelude::*;
+use gtk::{
+    gio,
+    glib::{self, clone},
+    Orientation::Vertical,
+    SelectionMode,
+};
+use log::info;
+use std::{collections::HashMap, rc::Rc, sync::Arc};
+
+use super::{
+    add_profile_row_badges, create_placeholder_page, custom_stack_selection_button, error_dialog,
+    get_icon_for_class, privileged_script_dialog::privileged_script_dialog,
+};
+
+/// Extra groups a bus adds to its device pages, below the details and profiles.
+pub type DeviceGroups<D> =
+    fn(&adw::ApplicationWindow, &PreCheckedSysfsDevice<D>, &gio::SimpleAction) -> Vec<gtk::Widget>;
+
+/// The class page of every PCI and USB device by sysfs bus ID and of every Bluetooth device by
+/// upper case address, none of them collide.
+pub fn device_page_names(
+    hashmap_pci: &Option<Vec<(String, Vec<PreCheckedPciDevice>)>>,
+    hashmap_usb: &Option<Vec<(String, Vec<PreCheckedUsbDevice>)>>,
+    hashmap_bt: &Option<Vec<(String, Vec<PreCheckedBtDevice>)>>,
+) -> HashMap<String, String> {
+    let mut device_page_names = HashMap::new();
+    if let Some(hashmap_pci) = hashmap_pci {
+        for (class, devices) in hashmap_pci {
+            for device in devices {
+                device_page_names.insert(
+                    device.device.sysfs_busid.clone(),
+                    format!("pci_class_name_{}", class),
+                );
+            }
+        }
+    }
+    if let Some(hashmap_usb) = hashmap_usb {
+        for (class, devices) in hashmap_usb {
+            for device in devices {
+                device_page_names.insert(
+                    device.device.sysfs_busid.clone(),
+                    format!("usb_class_name_{}", class),
+                );
+            }
+        }
+    }
+    if let Some(hashmap_bt) = hashmap_bt {
+        for (class, devices) in hashmap_bt {
+            for device in devices {
+                device_page_names.insert(
+                    device.device.address.to_uppercase(),
+                    format!("bt_class_name_{}", class),
+                );
+            }
+        }
+    }
+    device_page_names
+}
+
+/// Adds a lazily built page per class of the bus to `window_stack`, returns their sidebar rows.
+/// `device_page_names` maps parent devices to their class pages, for the parent device links.
+pub fn sysfs_bus_class_rows<D: SysfsBusDevice>(
+    window: &adw::ApplicationWindow,
+    window_stack: &gtk::Stack,
+    bus: &SysfsBusData<D>,
+    theme_changed_action: &gio::SimpleAction,
+    update_device_status_action: &gio::SimpleAction,
+    push_device_page_action: &gio::SimpleAction,
+    show_page_action: &gio::SimpleAction,
+    device_page_names: &Rc<HashMap<String, String>>,
+    default_icon: &'static str,
+    device_groups: DeviceGroups<D>,
+) -> Vec<gtk::ListBoxRow> {
+    let mut rows = vec![];
+    for (class, devices) in &bus.devices {
+        let class = format!("{}_class_name_{}", D::BUS, class);
+        let class_i18n = t!(class).to_string();
+
+        let placeholder = create_placeholder_page(&class_i18n);
+        window_stack.add_titled(&placeholder, Some(&class), &class_i18n);
+
+        for device in devices {
+            for profile in &device.profiles {
+                *profile.used.lock().unwrap() = true;
+            }
+        }
+        let devices = devices.clone();
+
+        placeholder.connect_map(clone!(
+            #[strong]
+            window,
+            #[strong]
+            devices,
+            #[strong]
+            class_i18n,
+            #[strong]
+            theme_changed_action,
+            #[strong]
+            update_device_status_action,
+            #[strong]
+            push_device_page_action,
+            #[strong]
+            show_page_action,
+            #[strong]
+            device_page_names,
+            move |placeholder| {
+                if let Some(child) = placeholder.first_child() {
+                    if child.widget_name() == "content_loaded" {
+                        return;
+                    }
+                }
+
+                let content = create_sysfs_bus_class(
+                    &window,
+                    &devices,
+                    &class_i18n,
+                    &theme_changed_action,
+                    &update_device_status_action,
+                    &push_device_page_action,
+                    &show_page_action,
+                    &device_page_names,
+                    device_groups,
+                );
+                content.set_widget_name("content_loaded");
+
+                while let Some(child) = placeholder.first_child() {
+                    placeholder.remove(&child);
+                }
+                placeholder.append(&content);
+            }
+        ));
+
+        rows.push(custom_stack_selection_button(
+            class.clone(),
+            class_i18n,
+            get_icon_for_class(&class).unwrap_or(default_icon).into(),
+        ));
+    }
+    rows
+}
+
+fn create_sysfs_bus_class<D: SysfsBusDevice>(
+    window: &adw::ApplicationWindow,
+    devices: &[PreCheckedSysfsDevice<D>],
+    class: &str,
+    theme_changed_action: &gio::SimpleAction,
+    update_device_status_action: &gio::SimpleAction,
+    push_device_page_action: &gio::SimpleAction,
+    show_page_action: &gio::SimpleAction,
+    device_page_names: &Rc<HashMap<String, String>>,
+    device_groups: DeviceGroups<D>,
+) -> gtk::ScrolledWindow {
+    for device in devices {
+        for profile in &device.profiles {
+            profile.update_installed();
+        }
+    }
+    let devices_list_row = gtk::ListBox::builder()
+        .margin_top(20)
+        .margin_bottom(20)
+        .margin_start(20)
+        .margin_end(20)
+        .selection_mode(SelectionMode::Browse)
+        .vexpand(true)
+        .hexpand(true)
+        .build();
+    devices_list_row.add_css_class("boxed-list");
+    let devices_navigation_page_toolbar = adw::ToolbarView::builder()
+        .content(&devices_list_row)
+        .build();
+    devices_navigation_page_toolbar.add_top_bar(
+        &adw::HeaderBar::builder()
+            .show_end_title_buttons(false)
+            .show_start_title_buttons(false)
+            .build(),
+    );
+    let devices_navigation_page = adw::NavigationPage::builder()
+        .title(class)
+        .tag("devices_list")
+        .child(&devices_navigation_page_toolbar)
+        .build();
+    let navigation_view = adw::NavigationView::builder().build();
+    navigation_view.add(&devices_navigation_page);
+    let scroll = gtk::ScrolledWindow::builder()
+        .max_content_width(650)
+        .min_content_width(300)
+        .hscrollbar_policy(gtk::PolicyType::Never)
+        .child(&navigation_view)
+        .build();
+    push_device_page_action.connect_activate(clone!(
+        #[weak]
+        navigation_view,
+        move |_, param| {
+            if let Some(device_tag) = param.and_then(|x| x.get::<String>()) {
+                if navigation_view.find_page(&device_tag).is_some() {
+                    navigation_view.replace_with_tags(&["devices_list", &device_tag]);
+                }
+            }
+        }
+    ));
+
+    for device in devices {
+        let device_title = device.device.title();
+        let device_navigation_page_toolbar = adw::ToolbarView::builder()
+            .content(&sysfs_bus_device_page(
+                window,
+                device,
+                theme_changed_action,
+                update_device_status_action,
+                show_page_action,
+                device_page_names,
+                device_groups,
+            ))
+            .build();
+        device_navigation_page_toolbar.add_top_bar(
+            &adw::HeaderBar::builder()
+                .show_end_title_buttons(false)
+                .show_start_title_buttons(false)
+                .build(),
+        );
+        let device_navigation_page = adw::NavigationPage::builder()
+            .title(&device_title)
+            .tag(device.device.id())
+            .child(&device_navigation_page_toolbar)
+            .build();
+        navigation_view.add(&device_navigation_page);
+        let action_row = adw::ActionRow::builder()
+            .title(glib::markup_escape_text(&device_title))
+            .subtitle(glib::markup_escape_text(&device.device.subtitle()))
+            .activatable(true)
+            .build();
+        action_row.connect_activated(clone!(
+            #[weak]
+            navigation_view,
+            #[weak]
+            device_navigation_page,
+            move |_| {
+                navigation_view.push(&device_navigation_page);
+            }
+        ));
+        devices_list_row.append(&action_row);
+    }
+    scroll
+}
+
+fn sysfs_bus_device_page<D: SysfsBusDevice>(
+    window: &adw::ApplicationWindow,
+    device: &PreCheckedSysfsDevice<D>,
+    theme_changed_action: &gio::SimpleAction,
+    update_device_status_action: &gio::SimpleAction,
+    show_page_action: &gio::SimpleAction,
+    device_page_names: &HashMap<String, String>,
+    device_groups: DeviceGroups<D>,
+) -> gtk::Box {
+    let content_box = gtk::Box::builder()
+        .hexpand(true)
+        .vexpand(true)
+        .orientation(Vertical)
+        .build();
+
+    let details_group = adw::PreferencesGroup::builder()
+        .margin_top(20)
+        .margin_bottom(20)
+        .margin_start(20)
+        .margin_end(20)
+        .title(t!("sysfs_bus_details_title"))
+        .hexpand(true)
+        .build();
+    for (key, value) in device.device.details() {
+        let detail_row = adw::ActionRow::builder()
+            .title(t!(key))
+            .subtitle(glib::markup_escape_text(&value))
+            .subtitle_selectable(true)
+            .build();
+        detail_row.add_css_class("property");
+        details_group.add(&detail_row);
+    }
+    if let Some((parent_bus, parent_busid)) = device.device.parent_device() {
+        let parent_row = adw::ActionRow::builder()
+            .title(t!("sysfs_bus_parent_device_title"))
+            .subtitle(glib::markup_escape_text(&format!(
+                "{} - {}",
+                parent_bus.to_uppercase(),
+                parent_busid
+            )))
+            .build();
+        parent_row.add_css_class("property");
+        if let Some(page_name) = device_page_names.get(&parent_busid) {
+            let open_page_button = gtk::Button::builder()
+                .valign(gtk::Align::Center)
+                .icon_name("go-next-symbolic")
+                .tooltip_text(t!("sysfs_bus_open_parent_tooltip_text"))
+                .build();
+            open_page_button.add_css_class("flat");
+            let page_name = page_name.clone();
+            open_page_button.connect_clicked(clone!(
+                #[strong]
+                show_page_action,
+                move |_| {
+                    show_page_action.activate(Some(
+                        &(page_name.clone(), parent_busid.clone()).to_variant(),
+                    ));
+                }
+            ));
+            parent_row.add_suffix(&open_page_button);
+        }
+        details_group.add(&parent_row);
+    }
+    content_box.append(&details_group);
+
+    append_profiles(
+        &content_box,
+        window,
+        &device.profiles,
+        theme_changed_action,
+        update_device_status_action,
+    );
+
+    for widget in device_groups(window, device, update_device_status_action) {
+        content_box.append(&widget);
+    }
+
+    content_box
+}
+
+/// The profile list of a device page, veiled profiles go in an expander below it.
+fn append_profiles<D: SysfsBusDevice>(
+    content_box: &gtk::Box,
+    window: &adw::ApplicationWindow,
+    profiles: &[Arc<PreCheckedSysfsProfile<D>>],
+    theme_changed_action: &gio::SimpleAction,
+    update_device_status_action: &gio::SimpleAction,
+) {
+    let available_profiles_group = adw::PreferencesGroup::builder()
+        .margin_top(20)
+        .margin_bottom(20)
+        .margin_start(20)
+        .margin_end(20)
+        .title(t!("available_profiles_title"))
+        .description(t!("available_profiles_subtitle"))
+        .hexpand(true)
+        .build();
+
+    let recommended_profile = recommended_profile(profiles);
+    let mut profiles = profiles.to_vec();
+    profiles.sort_by_key(|x| x.profile().priority);
+    let profiles_rc = Rc::new(profiles.clone());
+    let mut veiled_profiles = vec![];
+
+    for profile in profiles {
+        // Installed profiles stay listed so they can still be removed
+        if PROFILE_POLICY.hides(&profile.summary()) && !profile.installed() {
+            continue;
+        }
+        let profile_content = profile.profile();
+        let profile_expander_row = adw::ExpanderRow::builder()
+            .title(glib::markup_escape_text(&profile_content.i18n_desc))
+            .subtitle(glib::markup_escape_text(&profile_content.codename))
+            .build();
+        profile_expander_row.add_prefix(
+            &gtk::Image::builder()
+                .icon_name(&profile_content.icon_name)
+                .pixel_size(32)
+                .build(),
+        );
+        let profile_status_icon = gtk::Image::builder()
+            .icon_name("emblem-default")
+            .pixel_size(24)
+            .visible(false)
+            .tooltip_text(t!("profile_status_icon_tooltip_text"))
+            .build();
+        profile_expander_row.add_suffix(&profile_status_icon);
+        let profile_content_row = adw::ActionRow::builder().build();
+        let profile_install_button = gtk::Button::builder()
+            .valign(gtk::Align::Center)
+            .label(t!("profile_install_button_label"))
+            .tooltip_text(t!("profile_install_button_tooltip_text"))
+            .sensitive(false)
+            .build();
+        profile_install_button.add_css_class("suggested-action");
+        let profile_remove_button = gtk::Button::builder()
+            .valign(gtk::Align::Center)
+            .label(t!("profile_remove_button_label"))
+            .tooltip_text(t!("profile_remove_button_tooltip_text"))
+            .sensitive(false)
+            .build();
+        profile_remove_button.add_css_class("destructive-action");
+        let profile_policy_denial = add_profile_row_badges(
+            &profile_expander_row,
+            &profile_content_row,
+            &profile_install_button,
+            &profile.summary(),
+            recommended_profile
+                .as_ref()
+                .is_some_and(|x| Arc::ptr_eq(x, &profile)),
+        );
+
+        let (badges_size_group0, badges_size_group1) = (
+            gtk::SizeGroup::new(gtk::SizeGroupMode::Both),
+            gtk::SizeGroup::new(gtk::SizeGroupMode::Both),
+        );
+        let color_badge_license = ColorBadge::new();
+        color_badge_license.set_label0(textwrap::fill(&t!("profile_license"), 10));
+        color_badge_license.set_label1(profile_content.license.clone());
+        color_badge_license.set_css_style("background-accent-bg");
+        let color_badge_experimental = ColorBadge::new();
+        color_badge_experimental.set_label0(textwrap::fill(&t!("profile_experimental"), 10));
+        if profile_content.experimental {
+            color_badge_experimental.set_label1(t!("status_yes"));
+            color_badge_experimental.set_css_style("background-red-bg");
+        } else {
+            color_badge_experimental.set_label1(t!("status_no"));
+            color_badge_experimental.set_css_style("background-accent-bg");
+        }
+        let badges_warp_box = gtk::Box::new(Vertical, 3);
+        for badge in [&color_badge_license, &color_badge_experimental] {
+            badge.set_group_size0(&badges_size_group0);
+            badge.set_group_size1(&badges_size_group1);
+            badge.set_theme_changed_action(theme_changed_action);
+            badges_warp_box.append(badge);
+        }
+        let profile_action_box = gtk::Box::builder().homogeneous(true).spacing(5).build();
+        profile_action_box.append(&profile_remove_button);
+        profile_action_box.append(&profile_install_button);
+        profile_content_row.add_prefix(&badges_warp_box);
+        profile_content_row.add_suffix(&profile_action_box);
+        profile_expander_row.add_row(&profile_content_row);
+
+        for (button, opreation) in [
+            (&profile_install_button, "install"),
+            (&profile_remove_button, "remove"),
+        ] {
+            button.connect_clicked(clone!(
+                #[strong]
+                window,
+                #[strong]
+                update_device_status_action,
+                #[strong]
+                profile,
+                #[strong]
+                profiles_rc,
+                move |_| {
+                    profile_modify(
+                        &window,
+                        &update_device_status_action,
+                        &profile,
+                        &profiles_rc,
+                        opreation,
+                    );
+                }
+            ));
+        }
+
+        if profile_content.veiled {
+            veiled_profiles.push(profile_expander_row);
+        } else {
+            available_profiles_group.add(&profile_expander_row);
+        }
+
+        update_device_status_action.connect_activate(clone!(move |_, _| {
+            let profile_status = profile.installed();
+            profile_install_button
+                .set_sensitive(!profile_status && profile_policy_denial.is_none());
+            profile_remove_button.set_sensitive(profile_content.removable && profile_status);
+            profile_status_icon.set_visible(profile_status);
+        }));
+    }
+    update_device_status_action.activate(None);
+
+    content_box.append(&available_profiles_group);
+    if !veiled_profiles.is_empty() {
+        let veiled_profiles_list_row = gtk::ListBox::builder()
+            .vexpand(true)
+            .hexpand(true)
+            .margin_top(20)
+            .margin_end(20)
+            .build();
+        veiled_profiles_list_row.add_css_class("boxed-list");
+        let label = gtk::Label::new(Some(&t!("viel_expander_label")));
+        label.add_css_class("title-1");
+        let veil_expander = gtk::Expander::builder()
+            .child(&veiled_profiles_list_row)
+            .valign(gtk::Align::Start)
+            .vexpand(true)
+            .label_widget(&label)
+            .margin_top(20)
+            .margin_bottom(20)
+            .margin_start(20)
+            .margin_end(20)
+            .build();
+        for widget in veiled_profiles {
+            veiled_profiles_list_row.append(&widget);
+        }
+        content_box.append(&veil_expander);
+    }
+}
+
+pub fn profile_modify<D: SysfsBusDevice>(
+    window: &adw::ApplicationWindow,
+    update_device_status_action: &gio::SimpleAction,
+    profile: &Arc<PreCheckedSysfsProfile<D>>,
+    all_profiles: &Rc<Vec<Arc<PreCheckedSysfsProfile<D>>>>,
+    opreation: &'static str,
+) {
+    let profile_summary = profile.summary();
+    info!(
+        target: "executor",
+        "Starting {} of {} profile {}",
+        opreation,
+        D::BUS,
+        profile_summary.codename
+    );
+    let script = match get_profile_modify_commands(&profile_summary, opreation) {
+        Ok(t) => t,
+        Err(reason) => {
+            error_dialog(window.clone(), &t!("policy_denied_dialog_heading"), &reason);
+            return;
+        }
+    };
+    privileged_script_dialog(
+        window,
+        &t!(format!("profile_{}_dialog_heading", opreation)),
+        &t!(format!("profile_{}_dialog_body_successful", opreation)),
+        &t!(format!("profile_{}_dialog_body_failed", opreation)),
+        script,
+        true,
+        clone!(
+            #[strong]
+            update_device_status_action,
+            #[strong]
+            all_profiles,
+            move || {
+                for a_profile in all_profiles.iter() {
+                    a_profile.update_installed();
+                }
+                update_device_status_action.activate(None);
+            }
+        ),
+    );
+}
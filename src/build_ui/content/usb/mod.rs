@@ -1,24 +1,34 @@
 use crate::{
     build_ui::{color_badge::ColorBadge, colored_circle::ColoredCircle},
-    cfhdb::usb::{PreCheckedUsbDevice, PreCheckedUsbProfile},
-    config::distro_package_manager,
+    cfhdb::{
+        get_profile_modify_commands,
+        policy::PROFILE_POLICY,
+        recommendation::recommended_profile,
+        usb::{get_usb_interfaces, PreCheckedUsbDevice, PreCheckedUsbProfile},
+        PreCheckedProfile,
+    },
     ChannelMsg,
 };
 use adw::{prelude::*, *};
 use gtk::{
     gdk::RGBA,
-    glib::{clone, MainContext},
+    glib::{self, clone, MainContext},
     Align, Orientation,
     Orientation::Vertical,
     ScrolledWindow, SelectionMode,
 };
 
 use libcfhdb::usb::CfhdbUsbDevice;
-use std::{process::Command, rc::Rc, sync::Arc, thread};
+use log::info;
+use std::{cell::RefCell, process::Command, rc::Rc, sync::Arc, thread};
 
 use users::get_current_username;
 
-use super::{error_dialog, run_in_lock_script};
+use super::{
+    add_profile_row_badges, driver_binding::driver_binding_group, error_dialog, get_icon_for_class,
+    kernel_modules::kernel_modules_group, missing_firmware::missing_firmware_group,
+    run_in_lock_script,
+};
 
 pub fn create_usb_class(
     window: &ApplicationWindow,
@@ -26,6 +36,7 @@ pub fn create_usb_class(
     class: &str,
     theme_changed_action: &gio::SimpleAction,
     update_device_status_action: &gio::SimpleAction,
+    push_device_page_action: &gio::SimpleAction,
 ) -> ScrolledWindow {
     // Update all profiles' installation status before creating the UI
     for device in devices {
@@ -55,6 +66,7 @@ pub fn create_usb_class(
     );
     let devices_navigation_page = adw::NavigationPage::builder()
         .title(class)
+        .tag("devices_list")
         .child(&devices_navigation_page_toolbar)
         .build();
     //
@@ -66,6 +78,17 @@ pub fn create_usb_class(
         .hscrollbar_policy(gtk::PolicyType::Never)
         .child(&navigation_view)
         .build();
+    push_device_page_action.connect_activate(clone!(
+        #[weak]
+        navigation_view,
+        move |_, param| {
+            if let Some(device_tag) = param.and_then(|x| x.get::<String>()) {
+                if navigation_view.find_page(&device_tag).is_some() {
+                    navigation_view.replace_with_tags(&["devices_list", &device_tag]);
+                }
+            }
+        }
+    ));
     //
     for device in devices {
         let device_content = &device.device;
@@ -93,6 +116,7 @@ pub fn create_usb_class(
         );
         let device_navigation_page = adw::NavigationPage::builder()
             .title(&device_title)
+            .tag(&device_content.sysfs_busid)
             .child(&device_navigation_page_toolbar)
             .build();
         navigation_view.add(&device_navigation_page);
@@ -249,12 +273,17 @@ fn usb_device_page(
     let rows_size_group = gtk::SizeGroup::new(gtk::SizeGroupMode::Both);
 
     let mut profiles = device.profiles.clone();
+    let recommended_profile = recommended_profile(&device.profiles);
     profiles.sort_by_key(|x| x.profile().priority);
 
     let mut normal_profiles = vec![];
     let mut veiled_profiles = vec![];
 
     for profile in profiles.clone() {
+        // Installed profiles stay listed so they can still be removed
+        if PROFILE_POLICY.hides(&profile.summary()) && !profile.installed() {
+            continue;
+        }
         let profile_content = profile.profile();
         let (profiles_color_badges_size_group0, profiles_color_badges_size_group1) = (
             gtk::SizeGroup::new(gtk::SizeGroupMode::Both),
@@ -297,6 +326,15 @@ fn usb_device_page(
         profile_expander_row.add_suffix(&profile_status_icon);
         profile_expander_row.set_title(&profile_content.i18n_desc);
         profile_expander_row.set_subtitle(&profile_content.codename);
+        let profile_policy_denial = add_profile_row_badges(
+            &profile_expander_row,
+            &profile_content_row,
+            &profile_install_button,
+            &profile.summary(),
+            recommended_profile
+                .as_ref()
+                .is_some_and(|x| Arc::ptr_eq(x, &profile)),
+        );
         //
         let color_badge_experimental = ColorBadge::new();
         color_badge_experimental.set_label0(textwrap::fill(&t!("profile_experimental"), 10));
@@ -381,7 +419,8 @@ fn usb_device_page(
         //
         update_device_status_action.connect_activate(clone!(move |_, _| {
             let profile_status = profile.installed();
-            profile_install_button.set_sensitive(!profile_status);
+            profile_install_button
+                .set_sensitive(!profile_status && profile_policy_denial.is_none());
             if profile_content.removable {
                 profile_remove_button.set_sensitive(profile_status);
             } else {
@@ -438,7 +477,7 @@ fn usb_device_page(
                 "background-red-bg"
             });
             driver_color_badge
-                .set_label1(textwrap::fill(&device_content.kernel_driver.as_str(), 10));
+                .set_label1(textwrap::fill(updated_device.kernel_driver.as_str(), 10));
             sysfs_busid_color_badge
                 .set_label1(textwrap::fill(&device_content.sysfs_busid.as_str(), 10));
             vendor_id_color_badge
@@ -480,10 +519,89 @@ fn usb_device_page(
         }
         content_box.append(&veil_expander);
     }
+    content_box.append(&missing_firmware_group(
+        "usb",
+        &device_content.sysfs_busid,
+        device.profiles.iter().map(|x| x.summary()).collect(),
+        update_device_status_action,
+    ));
+    content_box.append(&interfaces_group(
+        &device_content.sysfs_busid,
+        update_device_status_action,
+    ));
+    content_box.append(&kernel_modules_group(
+        window,
+        "usb",
+        &device_content.sysfs_busid,
+        update_device_status_action,
+    ));
+    content_box.append(&driver_binding_group(
+        window,
+        "usb",
+        &device_content.sysfs_busid,
+        update_device_status_action,
+    ));
 
     content_box
 }
 
+/// Composite devices have an interface per function, a webcam has video and audio, each with its own driver.
+fn interfaces_group(
+    sysfs_busid: &str,
+    update_device_status_action: &gio::SimpleAction,
+) -> PreferencesGroup {
+    let interfaces_group = PreferencesGroup::builder()
+        .margin_top(20)
+        .margin_bottom(20)
+        .margin_start(20)
+        .margin_end(20)
+        .title(t!("usb_interfaces_title"))
+        .description(t!("usb_interfaces_subtitle"))
+        .hexpand(true)
+        .build();
+
+    let interface_rows: Rc<RefCell<Vec<ActionRow>>> = Rc::new(RefCell::new(vec![]));
+    let sysfs_busid = sysfs_busid.to_string();
+    let update_interfaces = clone!(
+        #[strong]
+        interfaces_group,
+        move || {
+            for row in interface_rows.borrow_mut().drain(..) {
+                interfaces_group.remove(&row);
+            }
+            for interface in get_usb_interfaces(&sysfs_busid) {
+                let interface_row = ActionRow::builder()
+                    .title(glib::markup_escape_text(&format!(
+                        "{} - {}",
+                        interface.sysfs_busid,
+                        t!(format!("usb_class_name_{}", interface.class_code))
+                    )))
+                    .subtitle(glib::markup_escape_text(&t!(
+                        "usb_interface_subtitle",
+                        class = interface.class_code,
+                        sub_class = interface.sub_class_code,
+                        protocol = interface.protocol_code,
+                        driver = interface
+                            .driver
+                            .unwrap_or_else(|| t!("inventory_no_driver").to_string())
+                    )))
+                    .subtitle_selectable(true)
+                    .build();
+                interface_row.add_prefix(&gtk::Image::from_icon_name(
+                    get_icon_for_class(&format!("usb_class_name_{}", interface.class_code))
+                        .unwrap_or("drive-harddisk-usb-symbolic"),
+                ));
+                interfaces_group.add(&interface_row);
+                interface_rows.borrow_mut().push(interface_row);
+            }
+        }
+    );
+    update_interfaces();
+    update_device_status_action.connect_activate(move |_, _| update_interfaces());
+
+    interfaces_group
+}
+
 pub fn profile_modify(
     window: ApplicationWindow,
     update_device_status_action: &gio::SimpleAction,
@@ -497,6 +615,13 @@ pub fn profile_modify(
 
     let profile_content = profile.profile();
 
+    info!(
+        target: "executor",
+        "Starting {} of USB profile {}",
+        opreation,
+        profile_content.codename
+    );
+
     let profile_modify_log_terminal_buffer = gtk::TextBuffer::builder().build();
 
     let profile_modify_log_terminal = gtk::TextView::builder()
@@ -537,52 +662,29 @@ pub fn profile_modify(
     //
 
     let string_opreation = String::from(opreation);
+    let profile_summary = profile.summary();
 
     thread::spawn(clone!(
         #[strong]
-        profile_content,
+        profile_summary,
         #[strong]
         string_opreation,
         move || {
-            let script = match string_opreation.as_str() {
-                "install" => profile_content.install_script,
-                "remove" => profile_content.remove_script,
-                _ => panic!(),
-            };
-            match script {
-                Some(t) => match profile_content.packages {
-                    Some(a) => {
-                        let package_list = a.join(" ");
-                        let modify_command =
-                            distro_package_manager(&string_opreation, &package_list);
-                        run_in_lock_script(
-                            &log_loop_sender,
-                            &format!("#! /bin/bash\nset -e\n{}\n{}", modify_command, t),
-                        );
-                    }
-                    None => {
-                        run_in_lock_script(
-                            &log_loop_sender,
-                            &format!("#! /bin/bash\nset -e\n{}", t),
-                        );
-                    }
-                },
-                None => match profile_content.packages {
-                    Some(a) => {
-                        let package_list = a.join(" ");
-                        let modify_command =
-                            distro_package_manager(&string_opreation, &package_list);
-                        run_in_lock_script(
-                            &log_loop_sender,
-                            &format!("#! /bin/bash\nset -e\n{}", modify_command),
-                        );
-                    }
-                    None => {
-                        log_loop_sender
-                            .send_blocking(ChannelMsg::SuccessMsg)
-                            .unwrap();
-                    }
-                },
+            match get_profile_modify_commands(&profile_summary, &string_opreation) {
+                Err(reason) => {
+                    log_loop_sender
+                        .send_blocking(ChannelMsg::OutputLine(reason))
+                        .unwrap();
+                    log_loop_sender.send_blocking(ChannelMsg::FailMsg).unwrap();
+                }
+                Ok(Some(t)) => {
+                    run_in_lock_script(&log_loop_sender, &format!("#! /bin/bash\nset -e\n{}", t));
+                }
+                Ok(None) => {
+                    log_loop_sender
+                        .send_blocking(ChannelMsg::SuccessMsg)
+                        .unwrap();
+                }
             }
         }
     ));
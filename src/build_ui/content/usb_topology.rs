@@ -0,0 +1,183 @@
+use crate::cfhdb::{
+    usb::PreCheckedUsbDevice,
+    usb_topology::{get_usb_topology, usb_speed_label, UsbTopologyNode},
+};
+use adw::prelude::*;
+use gtk::{
+    gio,
+    glib::{self, clone},
+};
+use std::{collections::HashMap, rc::Rc};
+
+use super::get_icon_for_class;
+
+pub const USB_TOPOLOGY_PAGE_NAME: &str = "usb_topology";
+
+pub fn usb_topology_page(
+    hashmap_usb: &Option<Vec<(String, Vec<PreCheckedUsbDevice>)>>,
+    update_device_status_action: &gio::SimpleAction,
+    show_page_action: &gio::SimpleAction,
+) -> gtk::ScrolledWindow {
+    let topology_box = gtk::Box::builder()
+        .orientation(gtk::Orientation::Vertical)
+        .margin_top(20)
+        .margin_bottom(20)
+        .margin_start(20)
+        .margin_end(20)
+        .spacing(20)
+        .build();
+
+    let topology_scroll = gtk::ScrolledWindow::builder()
+        .max_content_width(650)
+        .min_content_width(300)
+        .hscrollbar_policy(gtk::PolicyType::Never)
+        .vexpand(true)
+        .child(&topology_box)
+        .build();
+
+    // Device pages of the known USB devices, by sysfs bus ID
+    let mut usb_page_names = HashMap::new();
+    if let Some(hashmap_usb) = hashmap_usb {
+        for (class, devices) in hashmap_usb {
+            for device in devices {
+                usb_page_names.insert(
+                    device.device.sysfs_busid.clone(),
+                    format!("usb_class_name_{}", class),
+                );
+            }
+        }
+    }
+    let usb_page_names = Rc::new(usb_page_names);
+
+    let refresh_topology = clone!(
+        #[weak]
+        topology_box,
+        #[strong]
+        show_page_action,
+        move || {
+            while let Some(child) = topology_box.first_child() {
+                topology_box.remove(&child);
+            }
+
+            let controllers = get_usb_topology();
+            if controllers.is_empty() {
+                topology_box.append(
+                    &adw::StatusPage::builder()
+                        .icon_name("dialog-information-symbolic")
+                        .title(t!("usb_topology_empty_title"))
+                        .description(t!("usb_topology_empty_description"))
+                        .vexpand(true)
+                        .build(),
+                );
+                return;
+            }
+
+            for controller in controllers {
+                let controller_group = adw::PreferencesGroup::builder()
+                    .title(t!(
+                        "usb_topology_controller_title",
+                        controller = controller.sysfs_busid
+                    ))
+                    .build();
+                for root_hub in &controller.root_hubs {
+                    controller_group.add(&topology_node_row(
+                        root_hub,
+                        &usb_page_names,
+                        &show_page_action,
+                    ));
+                }
+                topology_box.append(&controller_group);
+            }
+        }
+    );
+
+    topology_scroll.connect_map(clone!(
+        #[strong]
+        refresh_topology,
+        move |_| refresh_topology()
+    ));
+
+    update_device_status_action.connect_activate(move |_, _| refresh_topology());
+
+    topology_scroll
+}
+
+/// A hub or device with its interfaces, then whatever is plugged into its ports.
+fn topology_node_row(
+    node: &UsbTopologyNode,
+    usb_page_names: &HashMap<String, String>,
+    show_page_action: &gio::SimpleAction,
+) -> adw::ExpanderRow {
+    let name = node.name.clone().unwrap_or_else(|| {
+        if node.is_hub() {
+            t!("usb_topology_unnamed_hub").to_string()
+        } else {
+            t!("usb_topology_unnamed_device").to_string()
+        }
+    });
+    let mut subtitle = vec![format!("{}:{}", node.vendor_id, node.product_id)];
+    if let Some(speed) = &node.speed {
+        subtitle.push(usb_speed_label(speed));
+    }
+    if let Some(max_power) = &node.max_power {
+        subtitle.push(t!("usb_topology_max_power", power = max_power).to_string());
+    }
+    let node_expander_row = adw::ExpanderRow::builder()
+        .title(glib::markup_escape_text(&match &node.port {
+            Some(port) => t!("usb_topology_port_title", port = port, name = name).to_string(),
+            None => name,
+        }))
+        .subtitle(glib::markup_escape_text(&subtitle.join(" - ")))
+        .build();
+    node_expander_row.add_prefix(&gtk::Image::from_icon_name(
+        get_icon_for_class(&format!("usb_class_name_{}", node.class_code))
+            .unwrap_or("drive-harddisk-usb-symbolic"),
+    ));
+
+    if let Some(page_name) = usb_page_names.get(&node.sysfs_busid) {
+        let open_page_button = gtk::Button::builder()
+            .valign(gtk::Align::Center)
+            .icon_name("go-next-symbolic")
+            .tooltip_text(t!("usb_topology_open_device_tooltip_text"))
+            .build();
+        open_page_button.add_css_class("flat");
+        let (page_name, device_tag) = (page_name.clone(), node.sysfs_busid.clone());
+        open_page_button.connect_clicked(clone!(
+            #[strong]
+            show_page_action,
+            move |_| {
+                show_page_action
+                    .activate(Some(&(page_name.clone(), device_tag.clone()).to_variant()));
+            }
+        ));
+        node_expander_row.add_suffix(&open_page_button);
+    }
+
+    // Hubs only have the hub interface, the ports below them are what matters
+    let shows_interfaces = !node.is_hub() && !node.interfaces.is_empty();
+    if shows_interfaces {
+        for interface in &node.interfaces {
+            let interface_row = adw::ActionRow::builder()
+                .title(glib::markup_escape_text(&t!(format!(
+                    "usb_class_name_{}",
+                    interface.class_code
+                ))))
+                .subtitle(glib::markup_escape_text(&format!(
+                    "{} - {}",
+                    interface.sysfs_busid,
+                    interface
+                        .driver
+                        .clone()
+                        .unwrap_or_else(|| t!("inventory_no_driver").to_string())
+                )))
+                .build();
+            node_expander_row.add_row(&interface_row);
+        }
+    }
+    for child in &node.children {
+        node_expander_row.add_row(&topology_node_row(child, usb_page_names, show_page_action));
+    }
+    node_expander_row.set_enable_expansion(shows_interfaces || !node.children.is_empty());
+
+    node_expander_row
+}
@@ -9,6 +9,7 @@ use crate::{
         pci::{
             get_pci_devices, get_pci_profiles_from_url, PreCheckedPciDevice, PreCheckedPciProfile,
         },
+        sysfs_bus::SysfsBuses,
         usb::{
             get_usb_devices, get_usb_profiles_from_url, PreCheckedUsbDevice, PreCheckedUsbProfile,
         },
@@ -21,6 +22,7 @@ use gtk::{
     glib::{clone, MainContext},
     Orientation,
 };
+use log::{debug, error, info};
 use rayon;
 use rayon::prelude::*;
 
@@ -39,6 +41,11 @@ pub fn loading_content(
     window: &ApplicationWindow,
     about_action: &gio::SimpleAction,
     showallprofiles_action: &gio::SimpleAction,
+    installrecommended_action: &gio::SimpleAction,
+    exportmanifest_action: &gio::SimpleAction,
+    applymanifest_action: &gio::SimpleAction,
+    saveinventory_action: &gio::SimpleAction,
+    compareinventory_action: &gio::SimpleAction,
 ) {
     let (status_sender, status_receiver) = async_channel::unbounded::<ChannelMsg>();
     let loading_box = gtk::Box::builder()
@@ -101,6 +108,16 @@ pub fn loading_content(
         about_action,
         #[strong]
         showallprofiles_action,
+        #[strong]
+        installrecommended_action,
+        #[strong]
+        exportmanifest_action,
+        #[strong]
+        applymanifest_action,
+        #[strong]
+        saveinventory_action,
+        #[strong]
+        compareinventory_action,
         async move {
             while let Ok(state) = status_receiver.recv().await {
                 match state {
@@ -116,6 +133,7 @@ pub fn loading_content(
                         usb_profiles,
                         dmi_profiles,
                         bt_profiles,
+                        sysfs_buses,
                     ) => {
                         window.set_content(Some(&main_content(
                             &window,
@@ -127,8 +145,14 @@ pub fn loading_content(
                             usb_profiles,
                             dmi_profiles,
                             bt_profiles,
+                            sysfs_buses,
                             &about_action,
                             &showallprofiles_action,
+                            &installrecommended_action,
+                            &exportmanifest_action,
+                            &applymanifest_action,
+                            &saveinventory_action,
+                            &compareinventory_action,
                         )));
                     }
                     ChannelMsg::FailMsg => {}
@@ -150,7 +174,7 @@ pub fn loading_content(
     window.set_content(Some(&window_toolbar));
 }
 
-fn load_cfhdb(status_sender: async_channel::Sender<ChannelMsg>) {
+pub fn load_cfhdb(status_sender: async_channel::Sender<ChannelMsg>) {
     std::thread::spawn(move || {
         let total_start = Instant::now();
 
@@ -158,6 +182,7 @@ fn load_cfhdb(status_sender: async_channel::Sender<ChannelMsg>) {
         duct::cmd!("bash", "-c", PERM_FIX_PROG).run().unwrap();
 
         // Step 1: Download profiles
+        info!(target: "loader", "Downloading profiles");
         status_sender
             .send_blocking(ChannelMsg::OutputLine(format!(
                 "[{}] {}",
@@ -170,38 +195,39 @@ fn load_cfhdb(status_sender: async_channel::Sender<ChannelMsg>) {
         let dmi_start = Instant::now();
         let dmi_profiles_result = get_dmi_profiles_from_url(&status_sender);
         let dmi_download_time = dmi_start.elapsed();
-        println!("[PERF] DMI profiles download took: {:?}", dmi_download_time);
+        debug!(target: "loader", "DMI profiles download took: {:?}", dmi_download_time);
 
         // Get PCI profiles
         let pci_start = Instant::now();
         let pci_profiles_result = get_pci_profiles_from_url(&status_sender);
         let pci_download_time = pci_start.elapsed();
-        println!("[PERF] PCI profiles download took: {:?}", pci_download_time);
+        debug!(target: "loader", "PCI profiles download took: {:?}", pci_download_time);
 
         // Get USB profiles
         let usb_start = Instant::now();
         let usb_profiles_result = get_usb_profiles_from_url(&status_sender);
         let usb_download_time = usb_start.elapsed();
-        println!("[PERF] USB profiles download took: {:?}", usb_download_time);
+        debug!(target: "loader", "USB profiles download took: {:?}", usb_download_time);
 
         // Get BT profiles
         let bt_start = Instant::now();
         let bt_profiles_result = get_bt_profiles_from_url(&status_sender);
         let bt_download_time = bt_start.elapsed();
-        println!("[PERF] BT profiles download took: {:?}", bt_download_time);
+        debug!(target: "loader", "BT profiles download took: {:?}", bt_download_time);
 
         // Process DMI profiles
         let dmi_process_start = Instant::now();
         let dmi_profiles: Vec<Arc<PreCheckedDmiProfile>> = match dmi_profiles_result {
             Ok(t) => t
                 .into_par_iter()
-                .map(|x| {
-                    let profile = PreCheckedDmiProfile::new(x);
+                .map(|(x, extras)| {
+                    let profile = PreCheckedDmiProfile::new(x, extras);
                     profile.update_installed();
                     Arc::new(profile)
                 })
                 .collect(),
             Err(e) => {
+                error!(target: "loader", "{}", e);
                 status_sender
                     .send_blocking(ChannelMsg::OutputLine(e.to_string()))
                     .expect("Channel closed");
@@ -212,23 +238,21 @@ fn load_cfhdb(status_sender: async_channel::Sender<ChannelMsg>) {
             }
         };
         let dmi_process_time = dmi_process_start.elapsed();
-        println!(
-            "[PERF] DMI profiles processing took: {:?}",
-            dmi_process_time
-        );
+        debug!(target: "loader", "DMI profiles processing took: {:?}", dmi_process_time);
 
         // Process PCI profiles
         let pci_process_start = Instant::now();
         let pci_profiles: Vec<Arc<PreCheckedPciProfile>> = match pci_profiles_result {
             Ok(t) => t
                 .into_par_iter()
-                .map(|x| {
-                    let profile = PreCheckedPciProfile::new(x);
+                .map(|(x, extras)| {
+                    let profile = PreCheckedPciProfile::new(x, extras);
                     profile.update_installed();
                     Arc::new(profile)
                 })
                 .collect(),
             Err(e) => {
+                error!(target: "loader", "{}", e);
                 status_sender
                     .send_blocking(ChannelMsg::OutputLine(e.to_string()))
                     .expect("Channel closed");
@@ -239,23 +263,21 @@ fn load_cfhdb(status_sender: async_channel::Sender<ChannelMsg>) {
             }
         };
         let pci_process_time = pci_process_start.elapsed();
-        println!(
-            "[PERF] PCI profiles processing took: {:?}",
-            pci_process_time
-        );
+        debug!(target: "loader", "PCI profiles processing took: {:?}", pci_process_time);
 
         // Process USB profiles
         let usb_process_start = Instant::now();
         let usb_profiles: Vec<Arc<PreCheckedUsbProfile>> = match usb_profiles_result {
             Ok(t) => t
                 .into_par_iter()
-                .map(|x| {
-                    let profile = PreCheckedUsbProfile::new(x);
+                .map(|(x, extras)| {
+                    let profile = PreCheckedUsbProfile::new(x, extras);
                     profile.update_installed();
                     Arc::new(profile)
                 })
                 .collect(),
             Err(e) => {
+                error!(target: "loader", "{}", e);
                 status_sender
                     .send_blocking(ChannelMsg::OutputLine(e.to_string()))
                     .expect("Channel closed");
@@ -266,23 +288,21 @@ fn load_cfhdb(status_sender: async_channel::Sender<ChannelMsg>) {
             }
         };
         let usb_process_time = usb_process_start.elapsed();
-        println!(
-            "[PERF] USB profiles processing took: {:?}",
-            usb_process_time
-        );
+        debug!(target: "loader", "USB profiles processing took: {:?}", usb_process_time);
 
         // Process BT profiles
         let bt_process_start = Instant::now();
         let bt_profiles: Vec<Arc<PreCheckedBtProfile>> = match bt_profiles_result {
             Ok(t) => t
                 .into_par_iter()
-                .map(|x| {
-                    let profile = PreCheckedBtProfile::new(x);
+                .map(|(x, extras)| {
+                    let profile = PreCheckedBtProfile::new(x, extras);
                     profile.update_installed();
                     Arc::new(profile)
                 })
                 .collect(),
             Err(e) => {
+                error!(target: "loader", "{}", e);
                 status_sender
                     .send_blocking(ChannelMsg::OutputLine(e.to_string()))
                     .expect("Channel closed");
@@ -293,9 +313,10 @@ fn load_cfhdb(status_sender: async_channel::Sender<ChannelMsg>) {
             }
         };
         let bt_process_time = bt_process_start.elapsed();
-        println!("[PERF] BT profiles processing took: {:?}", bt_process_time);
+        debug!(target: "loader", "BT profiles processing took: {:?}", bt_process_time);
 
         // Step 2: Process devices
+        info!(target: "loader", "Processing devices");
         status_sender
             .send_blocking(ChannelMsg::OutputLine(format!(
                 "[{}] {}",
@@ -308,25 +329,31 @@ fn load_cfhdb(status_sender: async_channel::Sender<ChannelMsg>) {
         let dmi_info_start = Instant::now();
         let dmi_info = get_dmi_info(&dmi_profiles);
         let dmi_info_time = dmi_info_start.elapsed();
-        println!("[PERF] DMI Info processing took: {:?}", dmi_info_time);
+        debug!(target: "loader", "DMI Info processing took: {:?}", dmi_info_time);
 
         // Process PCI devices
         let pci_devices_start = Instant::now();
         let hashmap_pci_result = get_pci_devices(pci_profiles.as_slice());
         let pci_devices_time = pci_devices_start.elapsed();
-        println!("[PERF] PCI devices processing took: {:?}", pci_devices_time);
+        debug!(target: "loader", "PCI devices processing took: {:?}", pci_devices_time);
 
         // Process USB devices - this is likely the bottleneck
         let usb_devices_start = Instant::now();
         let hashmap_usb_result = get_usb_devices(usb_profiles.as_slice());
         let usb_devices_time = usb_devices_start.elapsed();
-        println!("[PERF] USB devices processing took: {:?}", usb_devices_time);
+        debug!(target: "loader", "USB devices processing took: {:?}", usb_devices_time);
 
         // Process BT devices
         let bt_devices_start = Instant::now();
         let hashmap_bt_result = get_bt_devices(bt_profiles.as_slice());
         let bt_devices_time = bt_devices_start.elapsed();
-        println!("[PERF] BT devices processing took: {:?}", bt_devices_time);
+        debug!(target: "loader", "BT devices processing took: {:?}", bt_devices_time);
+
+        // Download profiles for and process the sysfs enumerated buses, storage and the like
+        let sysfs_buses_start = Instant::now();
+        let sysfs_buses = SysfsBuses::load(&status_sender);
+        let sysfs_buses_time = sysfs_buses_start.elapsed();
+        debug!(target: "loader", "Sysfs buses processing took: {:?}", sysfs_buses_time);
 
         status_sender
             .send_blocking(ChannelMsg::OutputLine(format!(
@@ -363,13 +390,9 @@ fn load_cfhdb(status_sender: async_channel::Sender<ChannelMsg>) {
         };
 
         let convert_time = convert_start.elapsed();
-        println!(
-            "[PERF] Converting hashmaps to vectors took: {:?}",
-            convert_time
-        );
+        debug!(target: "loader", "Converting hashmaps to vectors took: {:?}", convert_time);
 
         let sort_start = Instant::now();
-        let sort_time = sort_start.elapsed();
 
         match (pci_vec.as_ref(), usb_vec.as_ref(), bt_vec.as_ref()) {
             (Some(pci_vec), Some(usb_vec), Some(bt_vec)) => {
@@ -521,10 +544,11 @@ fn load_cfhdb(status_sender: async_channel::Sender<ChannelMsg>) {
             (None, None, None) => {}
         }
 
-        println!("[PERF] Sorting vectors took: {:?}", sort_time);
+        let sort_time = sort_start.elapsed();
+        debug!(target: "loader", "Sorting vectors took: {:?}", sort_time);
 
         let total_time = total_start.elapsed();
-        println!("[PERF] Total loading time: {:?}", total_time);
+        info!(target: "loader", "Loading complete in {:?}", total_time);
         status_sender
             .send_blocking(ChannelMsg::OutputLine(format!(
                 "[{}] {}",
@@ -552,6 +576,7 @@ fn load_cfhdb(status_sender: async_channel::Sender<ChannelMsg>) {
                 usb_profiles,
                 dmi_profiles,
                 bt_profiles,
+                sysfs_buses,
             ))
             .expect("Channel closed");
     });
@@ -0,0 +1,127 @@
+use crate::logger::get_log_entries;
+use adw::{prelude::*, *};
+use gtk::glib::clone;
+use log::LevelFilter;
+
+// Up to the max level init_logger sets, nothing below it is ever recorded
+const LOG_VIEWER_LEVELS: [LevelFilter; 4] = [
+    LevelFilter::Error,
+    LevelFilter::Warn,
+    LevelFilter::Info,
+    LevelFilter::Debug,
+];
+
+pub fn log_viewer_window(window: &ApplicationWindow) {
+    let log_level_names: Vec<String> = LOG_VIEWER_LEVELS
+        .iter()
+        .map(|x| t!(format!("log_viewer_level_{}", x.as_str().to_lowercase())).to_string())
+        .collect();
+    let log_level_names_str: Vec<&str> = log_level_names.iter().map(|x| x.as_str()).collect();
+
+    let log_level_dropdown = gtk::DropDown::builder()
+        .model(&gtk::StringList::new(&log_level_names_str))
+        .selected(3)
+        .tooltip_text(t!("log_viewer_level_dropdown_tooltip"))
+        .build();
+
+    let log_refresh_button = gtk::Button::builder()
+        .icon_name("view-refresh-symbolic")
+        .tooltip_text(t!("log_viewer_refresh_button_tooltip"))
+        .build();
+
+    let log_copy_button = gtk::Button::builder()
+        .icon_name("edit-copy-symbolic")
+        .tooltip_text(t!("log_viewer_copy_button_tooltip"))
+        .build();
+
+    let log_text_buffer = gtk::TextBuffer::builder().build();
+
+    let log_text_view = gtk::TextView::builder()
+        .vexpand(true)
+        .hexpand(true)
+        .editable(false)
+        .monospace(true)
+        .top_margin(10)
+        .bottom_margin(10)
+        .left_margin(10)
+        .right_margin(10)
+        .buffer(&log_text_buffer)
+        .build();
+
+    let log_text_view_scroll = gtk::ScrolledWindow::builder()
+        .vexpand(true)
+        .hexpand(true)
+        .child(&log_text_view)
+        .build();
+
+    let log_toast_overlay = ToastOverlay::new();
+    log_toast_overlay.set_child(Some(&log_text_view_scroll));
+
+    let log_headerbar = HeaderBar::builder().build();
+    log_headerbar.pack_start(&log_level_dropdown);
+    log_headerbar.pack_end(&log_copy_button);
+    log_headerbar.pack_end(&log_refresh_button);
+
+    let log_toolbar = ToolbarView::builder()
+        .content(&log_toast_overlay)
+        .top_bar_style(ToolbarStyle::Flat)
+        .build();
+    log_toolbar.add_top_bar(&log_headerbar);
+
+    let log_viewer_window = adw::Window::builder()
+        .title(t!("log_viewer_window_title"))
+        .transient_for(window)
+        .default_width(900)
+        .default_height(550)
+        .content(&log_toolbar)
+        .build();
+
+    let fill_log_buffer = clone!(
+        #[weak]
+        log_text_buffer,
+        #[weak]
+        log_level_dropdown,
+        move || {
+            let max_level = LOG_VIEWER_LEVELS
+                .get(log_level_dropdown.selected() as usize)
+                .cloned()
+                .unwrap_or(LevelFilter::Debug);
+            let text: Vec<String> = get_log_entries()
+                .iter()
+                .filter(|x| x.level <= max_level)
+                .map(|x| x.to_line())
+                .collect();
+            log_text_buffer.set_text(&text.join("\n"));
+        }
+    );
+
+    fill_log_buffer();
+
+    log_level_dropdown.connect_selected_notify(clone!(
+        #[strong]
+        fill_log_buffer,
+        move |_| fill_log_buffer()
+    ));
+
+    log_refresh_button.connect_clicked(clone!(
+        #[strong]
+        fill_log_buffer,
+        move |_| fill_log_buffer()
+    ));
+
+    log_copy_button.connect_clicked(clone!(
+        #[weak]
+        log_text_buffer,
+        #[weak]
+        log_toast_overlay,
+        move |button| {
+            let (start, end) = log_text_buffer.bounds();
+            button
+                .clipboard()
+                .set_text(&log_text_buffer.text(&start, &end, false));
+            log_toast_overlay.add_toast(Toast::new(&t!("log_viewer_copied_toast")));
+        }
+    ));
+
+    log_viewer_window.present();
+}
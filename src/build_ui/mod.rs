@@ -2,9 +2,13 @@ mod color_badge;
 mod colored_circle;
 mod content;
 mod loading;
+mod log_viewer;
+
+pub use content::run_in_lock_script;
+pub use loading::load_cfhdb;
 
 use crate::{
-    build_ui::loading::loading_content,
+    build_ui::{loading::loading_content, log_viewer::log_viewer_window},
     config::{APP_ICON, APP_ID},
 };
 use adw::{glib::clone, prelude::*, *};
@@ -58,6 +62,34 @@ pub fn build_ui(app: &adw::Application) {
     let file_menu = gio::Menu::new();
     file_menu_item.set_submenu(Some(&file_menu));
 
+    file_menu.append(
+        Some(&t!("file_menu_item_app_exportmanifest_label")),
+        Some("app.exportmanifest"),
+    );
+    let exportmanifest_action = gio::SimpleAction::new("exportmanifest", None);
+    app.add_action(&exportmanifest_action);
+
+    file_menu.append(
+        Some(&t!("file_menu_item_app_applymanifest_label")),
+        Some("app.applymanifest"),
+    );
+    let applymanifest_action = gio::SimpleAction::new("applymanifest", None);
+    app.add_action(&applymanifest_action);
+
+    file_menu.append(
+        Some(&t!("file_menu_item_app_saveinventory_label")),
+        Some("app.saveinventory"),
+    );
+    let saveinventory_action = gio::SimpleAction::new("saveinventory", None);
+    app.add_action(&saveinventory_action);
+
+    file_menu.append(
+        Some(&t!("file_menu_item_app_compareinventory_label")),
+        Some("app.compareinventory"),
+    );
+    let compareinventory_action = gio::SimpleAction::new("compareinventory", None);
+    app.add_action(&compareinventory_action);
+
     file_menu.append(Some(&t!("file_menu_item_app_quit_label")), Some("app.quit"));
 
     let quit_action = gio::SimpleAction::new("quit", None);
@@ -77,6 +109,13 @@ pub fn build_ui(app: &adw::Application) {
     let showallprofiles_action = gio::SimpleAction::new("showallprofiles", None);
     app.add_action(&showallprofiles_action);
 
+    view_menu.append(
+        Some(&t!("view_menu_item_app_installrecommended")),
+        Some("app.installrecommended"),
+    );
+    let installrecommended_action = gio::SimpleAction::new("installrecommended", None);
+    app.add_action(&installrecommended_action);
+
     // == Help menu ==
 
     let help_menu_item = gio::MenuItem::new(Some(&t!("help_menu_item_label")), None);
@@ -84,6 +123,14 @@ pub fn build_ui(app: &adw::Application) {
     let help_menu = gio::Menu::new();
     help_menu_item.set_submenu(Some(&help_menu));
 
+    help_menu.append(
+        Some(&t!("help_menu_item_app_showlogs_label")),
+        Some("app.showlogs"),
+    );
+
+    let showlogs_action = gio::SimpleAction::new("showlogs", None);
+    app.add_action(&showlogs_action);
+
     help_menu.append(
         Some(&t!("help_menu_item_app_about_label")),
         Some("app.about"),
@@ -122,11 +169,28 @@ pub fn build_ui(app: &adw::Application) {
         std::process::exit(0);
     });
 
-    loading_content(&window, &about_action, &showallprofiles_action);
+    loading_content(
+        &window,
+        &about_action,
+        &showallprofiles_action,
+        &installrecommended_action,
+        &exportmanifest_action,
+        &applymanifest_action,
+        &saveinventory_action,
+        &compareinventory_action,
+    );
 
     // show the window
     window.present();
 
+    showlogs_action.connect_activate(clone!(
+        #[strong]
+        window,
+        move |_, _| {
+            log_viewer_window(&window);
+        }
+    ));
+
     quit_action.connect_activate(clone!(
         #[strong]
         window,
@@ -0,0 +1,267 @@
+use gtk::{
+    gio,
+    glib::{self, prelude::*},
+};
+use log::error;
+use std::collections::HashMap;
+
+use super::{kmod::bound_module, sysroot::sysroot_path};
+
+const BLUEZ_SERVICE: &str = "org.bluez";
+const ADAPTER_INTERFACE: &str = "org.bluez.Adapter1";
+const DEVICE_INTERFACE: &str = "org.bluez.Device1";
+
+/// A Bluetooth adapter as BlueZ exports it.
+pub struct BluezAdapter {
+    /// D-Bus object path, `/org/bluez/hci0`
+    pub path: String,
+    /// The HCI name, `hci0`, which is what `CfhdbBtDevice::adapter` holds
+    pub name: String,
+    pub address: String,
+    /// The name other devices see
+    pub alias: String,
+    pub powered: bool,
+    pub discoverable: bool,
+    pub pairable: bool,
+    /// Controller details, (i18n key suffix, value), the ones BlueZ or sysfs have
+    pub controller: Vec<(String, String)>,
+}
+
+/// A device BlueZ knows about, either paired before or seen during discovery.
+pub struct BluezDevice {
+    pub adapter: String,
+    pub address: String,
+    pub alias: String,
+    pub icon: Option<String>,
+    /// Only set while discovery sees the device
+    pub rssi: Option<i16>,
+    pub paired: bool,
+}
+
+type Properties = HashMap<String, glib::Variant>;
+
+fn system_bus() -> Result<gio::DBusConnection, String> {
+    gio::bus_get_sync(gio::BusType::System, gio::Cancellable::NONE).map_err(|e| e.to_string())
+}
+
+fn call(
+    object_path: &str,
+    interface_name: &str,
+    method_name: &str,
+    parameters: Option<&glib::Variant>,
+) -> Result<glib::Variant, String> {
+    system_bus()?
+        .call_sync(
+            Some(BLUEZ_SERVICE),
+            object_path,
+            interface_name,
+            method_name,
+            parameters,
+            None,
+            gio::DBusCallFlags::NONE,
+            -1,
+            gio::Cancellable::NONE,
+        )
+        .map_err(|e| e.to_string())
+}
+
+fn string_property(properties: &Properties, name: &str) -> Option<String> {
+    properties.get(name).and_then(|x| x.str().map(String::from))
+}
+
+fn bool_property(properties: &Properties, name: &str) -> bool {
+    properties
+        .get(name)
+        .and_then(|x| x.get::<bool>())
+        .unwrap_or_default()
+}
+
+fn controller_details(adapter_path: &str, properties: &Properties) -> Vec<(String, String)> {
+    let hci_name = adapter_path.rsplit('/').next().unwrap_or_default();
+    let device_dir = sysroot_path(&format!("/sys/class/bluetooth/{}/device", hci_name));
+    let mut details = vec![];
+    if let Some(t) = string_property(properties, "Modalias") {
+        details.push(("modalias".to_string(), t));
+    }
+    // Manufacturer and HCI version are only exported by newer BlueZ releases
+    if let Some(t) = properties.get("Manufacturer").and_then(|x| x.get::<u16>()) {
+        details.push(("manufacturer".to_string(), format!("0x{:04x}", t)));
+    }
+    if let Some(t) = properties.get("Version").and_then(|x| x.get::<u8>()) {
+        details.push(("version".to_string(), t.to_string()));
+    }
+    if let Some((driver, _)) = bound_module(&device_dir) {
+        details.push(("driver".to_string(), driver));
+    }
+    if let Ok(t) = std::fs::canonicalize(&device_dir) {
+        if let Some(busid) = t.file_name() {
+            details.push(("busid".to_string(), busid.to_string_lossy().to_string()));
+        }
+    }
+    details
+}
+
+/// Every adapter and device from the BlueZ object manager, in one round trip.
+pub fn get_bluez_objects() -> Result<(Vec<BluezAdapter>, Vec<BluezDevice>), String> {
+    let reply = call(
+        "/",
+        "org.freedesktop.DBus.ObjectManager",
+        "GetManagedObjects",
+        None,
+    )?;
+    let mut adapters = vec![];
+    let mut devices = vec![];
+    for object in reply.child_value(0).iter() {
+        let path = object.child_value(0).str().unwrap_or_default().to_string();
+        let Some(interfaces) = object.child_value(1).get::<HashMap<String, Properties>>() else {
+            continue;
+        };
+        if let Some(properties) = interfaces.get(ADAPTER_INTERFACE) {
+            adapters.push(BluezAdapter {
+                name: path.rsplit('/').next().unwrap_or_default().to_string(),
+                path: path.clone(),
+                address: string_property(properties, "Address").unwrap_or_default(),
+                alias: string_property(properties, "Alias").unwrap_or_default(),
+                powered: bool_property(properties, "Powered"),
+                discoverable: bool_property(properties, "Discoverable"),
+                pairable: bool_property(properties, "Pairable"),
+                controller: controller_details(&path, properties),
+            });
+        }
+        if let Some(properties) = interfaces.get(DEVICE_INTERFACE) {
+            devices.push(BluezDevice {
+                adapter: string_property(properties, "Adapter")
+                    .and_then(|x| x.rsplit('/').next().map(String::from))
+                    .unwrap_or_default(),
+                address: string_property(properties, "Address").unwrap_or_default(),
+                alias: string_property(properties, "Alias").unwrap_or_default(),
+                icon: string_property(properties, "Icon"),
+                rssi: properties.get("RSSI").and_then(|x| x.get::<i16>()),
+                paired: bool_property(properties, "Paired"),
+            });
+        }
+    }
+    adapters.sort_by(|a, b| a.path.cmp(&b.path));
+    Ok((adapters, devices))
+}
+
+/// BlueZ keeps discovering for as long as the D-Bus connection that asked for it is open,
+/// which for the shared system bus connection is until it is stopped or the app exits.
+pub fn set_discovery(adapter_path: &str, discovering: bool) -> Result<(), String> {
+    call(
+        adapter_path,
+        ADAPTER_INTERFACE,
+        if discovering {
+            "StartDiscovery"
+        } else {
+            "StopDiscovery"
+        },
+        None,
+    )
+    .map(|_| ())
+}
+
+/// Calls `callback` with the object path on the main loop whenever a BlueZ object appears,
+/// goes away or changes.
+pub fn subscribe_bluez_changes(callback: impl Fn(&str) + Clone + 'static) {
+    let connection = match system_bus() {
+        Ok(t) => t,
+        Err(e) => {
+            error!(target: "bt", "Could not connect to the system bus: {}", e);
+            return;
+        }
+    };
+    for (interface_name, member) in [
+        ("org.freedesktop.DBus.Properties", "PropertiesChanged"),
+        ("org.freedesktop.DBus.ObjectManager", "InterfacesAdded"),
+        ("org.freedesktop.DBus.ObjectManager", "InterfacesRemoved"),
+    ] {
+        let callback = callback.clone();
+        connection.signal_subscribe(
+            Some(BLUEZ_SERVICE),
+            Some(interface_name),
+            Some(member),
+            None,
+            None,
+            gio::DBusSignalFlags::NONE,
+            move |_, _, object_path, _, member, parameters| {
+                if member == "PropertiesChanged" {
+                    callback(object_path)
+                } else {
+                    // The object manager sends these from `/`, the object is the first argument
+                    callback(parameters.child_value(0).str().unwrap_or_default())
+                }
+            },
+        );
+    }
+}
+
+/// BlueZ names device objects after the adapter and the address, `/org/bluez/hci0/dev_AA_BB_CC_DD_EE_FF`.
+fn device_path(adapter: &str, address: &str) -> String {
+    format!("/org/bluez/{}/dev_{}", adapter, address.replace(':', "_"))
+}
+
+/// Removes the pairing and everything BlueZ stored about the device.
+pub fn remove_device(adapter: &str, address: &str) -> Result<(), String> {
+    let device_path = glib::variant::ObjectPath::try_from(device_path(adapter, address))
+        .map_err(|e| e.to_string())?;
+    call(
+        &format!("/org/bluez/{}", adapter),
+        ADAPTER_INTERFACE,
+        "RemoveDevice",
+        Some(&(device_path,).to_variant()),
+    )
+    .map(|_| ())
+}
+
+fn set_property(
+    object_path: &str,
+    interface_name: &str,
+    name: &str,
+    value: glib::Variant,
+) -> Result<(), String> {
+    call(
+        object_path,
+        "org.freedesktop.DBus.Properties",
+        "Set",
+        Some(&(interface_name, name, value).to_variant()),
+    )
+    .map(|_| ())
+}
+
+pub fn set_adapter_property(
+    adapter_path: &str,
+    name: &str,
+    value: glib::Variant,
+) -> Result<(), String> {
+    set_property(adapter_path, ADAPTER_INTERFACE, name, value)
+}
+
+/// The battery level BlueZ reads from devices that report one, None for the others.
+pub fn get_device_battery(adapter: &str, address: &str) -> Option<u8> {
+    call(
+        &device_path(adapter, address),
+        "org.freedesktop.DBus.Properties",
+        "Get",
+        Some(&("org.bluez.Battery1", "Percentage").to_variant()),
+    )
+    .ok()?
+    .child_value(0)
+    .as_variant()?
+    .get::<u8>()
+}
+
+/// Whether a change signal is about the device, or one of the interfaces below it.
+pub fn is_device_object(object_path: &str, adapter: &str, address: &str) -> bool {
+    object_path.starts_with(&device_path(adapter, address))
+}
+
+/// An empty alias makes BlueZ fall back to the name the device reports.
+pub fn set_device_alias(adapter: &str, address: &str, alias: &str) -> Result<(), String> {
+    set_property(
+        &device_path(adapter, address),
+        DEVICE_INTERFACE,
+        "Alias",
+        alias.to_variant(),
+    )
+}
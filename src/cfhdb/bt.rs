@@ -1,5 +1,7 @@
+use super::{PreCheckedProfile, ProfileExtras, ProfileSummary};
 use crate::{config::*, ChannelMsg};
 use libcfhdb::bt::*;
+use log::{debug, error, info, warn};
 use std::{
     collections::HashMap,
     fs,
@@ -15,14 +17,16 @@ pub struct PreCheckedBtDevice {
 
 pub struct PreCheckedBtProfile {
     profile: CfhdbBtProfile,
+    extras: ProfileExtras,
     installed: Arc<Mutex<bool>>,
     pub used: Arc<Mutex<bool>>,
 }
 
 impl PreCheckedBtProfile {
-    pub fn new(profile: CfhdbBtProfile) -> Self {
+    pub fn new(profile: CfhdbBtProfile, extras: ProfileExtras) -> Self {
         Self {
             profile,
+            extras,
             installed: Arc::new(Mutex::new(false)),
             used: Arc::new(Mutex::new(false)),
         }
@@ -38,6 +42,33 @@ impl PreCheckedBtProfile {
     }
 }
 
+impl PreCheckedProfile for PreCheckedBtProfile {
+    fn summary(&self) -> ProfileSummary {
+        ProfileSummary {
+            bus: "bt",
+            codename: self.profile.codename.clone(),
+            i18n_desc: self.profile.i18n_desc.clone(),
+            icon_name: self.profile.icon_name.clone(),
+            license: self.profile.license.clone(),
+            packages: self.profile.packages.clone(),
+            install_script: self.profile.install_script.clone(),
+            remove_script: self.profile.remove_script.clone(),
+            experimental: self.profile.experimental,
+            removable: self.profile.removable,
+            veiled: self.profile.veiled,
+            priority: self.profile.priority,
+            kernel_args: self.extras.kernel_args.clone(),
+            provides_firmware: self.extras.provides_firmware.clone(),
+        }
+    }
+    fn is_installed(&self) -> bool {
+        self.installed()
+    }
+    fn refresh_installed(&self) {
+        self.update_installed();
+    }
+}
+
 pub fn get_bt_devices(
     profiles: &[Arc<PreCheckedBtProfile>],
 ) -> Option<HashMap<String, Vec<PreCheckedBtDevice>>> {
@@ -114,6 +145,12 @@ fn get_pre_checked_device(
         };
 
         if matching {
+            debug!(
+                target: "matcher",
+                "Bluetooth device {} matched profile {}",
+                device.address,
+                profile.codename
+            );
             available_profiles.push(profile_arc.clone());
         }
     }
@@ -125,7 +162,7 @@ fn get_pre_checked_device(
 
 pub fn get_bt_profiles_from_url(
     sender: &async_channel::Sender<ChannelMsg>,
-) -> Result<Vec<CfhdbBtProfile>, std::io::Error> {
+) -> Result<Vec<(CfhdbBtProfile, ProfileExtras)>, std::io::Error> {
     let cached_db_path = Path::new("/var/cache/cfhdb/bt.json");
     sender
         .send_blocking(ChannelMsg::OutputLine(format!(
@@ -138,8 +175,10 @@ pub fn get_bt_profiles_from_url(
         .timeout(std::time::Duration::from_secs(5))
         .build()
         .unwrap();
+    info!(target: "bt", "Downloading profiles from {}", *BT_PROFILE_JSON_URL);
     let data = match client.get(BT_PROFILE_JSON_URL.clone()).send() {
         Ok(t) => {
+            info!(target: "bt", "Profile download successful");
             sender
                 .send_blocking(ChannelMsg::OutputLine(format!(
                     "[{}] {}",
@@ -152,7 +191,8 @@ pub fn get_bt_profiles_from_url(
             let _ = fs::write(cached_db_path, &cache);
             cache
         }
-        Err(_) => {
+        Err(e) => {
+            warn!(target: "bt", "Profile download failed: {}", e);
             sender
                 .send_blocking(ChannelMsg::OutputLine(format!(
                     "[{}] {}",
@@ -161,6 +201,11 @@ pub fn get_bt_profiles_from_url(
                 )))
                 .expect("Channel closed");
             if cached_db_path.exists() {
+                info!(
+                    target: "bt",
+                    "Using cached profiles from {}",
+                    cached_db_path.display()
+                );
                 sender
                     .send_blocking(ChannelMsg::OutputLine(format!(
                         "[{}] {}",
@@ -170,6 +215,7 @@ pub fn get_bt_profiles_from_url(
                     .expect("Channel closed");
                 fs::read_to_string(cached_db_path).unwrap()
             } else {
+                error!(target: "bt", "No cached profiles found, giving up");
                 sender
                     .send_blocking(ChannelMsg::OutputLine(format!(
                         "[{}] {}",
@@ -350,8 +396,8 @@ pub fn get_bt_profiles_from_url(
                 veiled,
                 priority: priority as i32,
             };
-            profiles_array.push(profile_struct);
-            profiles_array.sort_by_key(|x| x.priority);
+            profiles_array.push((profile_struct, ProfileExtras::from_json(profile)));
+            profiles_array.sort_by_key(|x| x.0.priority);
         }
     }
     Ok(profiles_array)
@@ -0,0 +1,116 @@
+use log::info;
+use std::process::Command;
+
+/// A card profile, `a2dp-sink` or `headset-head-unit` for most headsets.
+pub struct BtAudioProfile {
+    pub name: String,
+    pub description: String,
+    pub available: bool,
+}
+
+/// The sound server card of a connected Bluetooth audio device.
+pub struct BtAudioCard {
+    pub name: String,
+    pub profiles: Vec<BtAudioProfile>,
+    pub active_profile: Option<String>,
+    /// (name, description) of the codecs the active profile can use, empty if it cannot switch
+    pub codecs: Vec<(String, String)>,
+    pub active_codec: Option<String>,
+}
+
+/// The major class in bits 8 to 12 of the class of device, 4 is audio/video.
+pub fn is_audio_class(class_id: &str) -> bool {
+    class_id.parse::<u32>().is_ok_and(|x| (x >> 8) & 0x1f == 4)
+}
+
+/// Runs pactl, which talks to PulseAudio and to PipeWire through pipewire-pulse alike.
+fn pactl(args: &[&str]) -> Result<String, String> {
+    let output = Command::new("pactl")
+        .args(args)
+        .output()
+        .map_err(|e| e.to_string())?;
+    if output.status.success() {
+        Ok(String::from_utf8_lossy(&output.stdout).to_string())
+    } else {
+        Err(String::from_utf8_lossy(&output.stderr).trim().to_string())
+    }
+}
+
+/// Codecs are only reachable through the message API of the card, replies are JSON.
+fn card_message(card: &str, message: &str) -> Option<serde_json::Value> {
+    let object_path = format!("/card/{}/bluez", card);
+    serde_json::from_str(&pactl(&["send-message", &object_path, message]).ok()?).ok()
+}
+
+/// Returns the card of the device, None if it is not connected with an audio profile.
+pub fn get_bt_audio_card(address: &str) -> Result<Option<BtAudioCard>, String> {
+    let card_name = format!("bluez_card.{}", address.replace(':', "_"));
+    let cards: serde_json::Value =
+        serde_json::from_str(&pactl(&["--format=json", "list", "cards"])?)
+            .map_err(|e| e.to_string())?;
+    let Some(card) = cards
+        .as_array()
+        .and_then(|x| x.iter().find(|y| y["name"].as_str() == Some(&card_name)))
+    else {
+        return Ok(None);
+    };
+
+    let mut profiles: Vec<(i64, BtAudioProfile)> = card["profiles"]
+        .as_object()
+        .map(|x| {
+            x.iter()
+                .map(|(name, profile)| {
+                    (
+                        profile["priority"].as_i64().unwrap_or_default(),
+                        BtAudioProfile {
+                            name: name.clone(),
+                            description: profile["description"]
+                                .as_str()
+                                .unwrap_or(name)
+                                .to_string(),
+                            available: profile["available"].as_bool().unwrap_or(true),
+                        },
+                    )
+                })
+                .collect()
+        })
+        .unwrap_or_default();
+    profiles.sort_by_key(|x| std::cmp::Reverse(x.0));
+
+    let codecs = card_message(&card_name, "list-codecs")
+        .and_then(|x| x.as_array().cloned())
+        .unwrap_or_default()
+        .iter()
+        .filter_map(|x| {
+            let name = x["name"].as_str()?.to_string();
+            let description = x["description"].as_str().unwrap_or(&name).to_string();
+            Some((name, description))
+        })
+        .collect();
+
+    Ok(Some(BtAudioCard {
+        active_profile: card["active_profile"].as_str().map(String::from),
+        active_codec: card_message(&card_name, "get-codec")
+            .and_then(|x| x.as_str().map(String::from)),
+        profiles: profiles.into_iter().map(|x| x.1).collect(),
+        codecs,
+        name: card_name,
+    }))
+}
+
+pub fn set_bt_audio_profile(card: &str, profile: &str) -> Result<(), String> {
+    info!(target: "bt", "Switching {} to profile {}", card, profile);
+    pactl(&["set-card-profile", card, profile]).map(|_| ())
+}
+
+pub fn set_bt_audio_codec(card: &str, codec: &str) -> Result<(), String> {
+    info!(target: "bt", "Switching {} to codec {}", card, codec);
+    let object_path = format!("/card/{}/bluez", card);
+    pactl(&[
+        "send-message",
+        &object_path,
+        "set-codec",
+        &format!("\"{}\"", codec),
+    ])
+    .map(|_| ())
+}
@@ -0,0 +1,284 @@
+use super::{sysfs_bus::SysfsBusDevice, sysroot::sysroot_path, usb::read_sysfs_value};
+use crate::config::CPU_PROFILE_JSON_URL;
+use std::collections::BTreeMap;
+
+/// A CPU package from /proc/cpuinfo, with the frequency scaling and vulnerability state from sysfs.
+#[derive(Clone)]
+pub struct CpuDevice {
+    /// The `physical id` of the package, 0 on single socket systems
+    pub package: String,
+    /// `GenuineIntel` or `AuthenticAMD`
+    pub vendor: String,
+    pub model_name: String,
+    /// Family, model and stepping in decimal, as /proc/cpuinfo prints them
+    pub family: String,
+    pub model: String,
+    pub stepping: String,
+    pub microcode: Option<String>,
+    pub cores: usize,
+    pub threads: usize,
+    pub scaling_driver: Option<String>,
+    pub scaling_governor: Option<String>,
+    pub energy_performance_preference: Option<String>,
+    /// (vulnerability, state), `Not affected`, `Mitigation: ...` or `Vulnerable...`
+    pub vulnerabilities: Vec<(String, String)>,
+}
+
+/// Splits /proc/cpuinfo into one key value map per logical CPU.
+fn parse_cpuinfo(cpuinfo: &str) -> Vec<BTreeMap<String, String>> {
+    cpuinfo
+        .split("\n\n")
+        .map(|block| {
+            block
+                .lines()
+                .filter_map(|x| x.split_once(':'))
+                .map(|(key, value)| (key.trim().to_string(), value.trim().to_string()))
+                .collect::<BTreeMap<String, String>>()
+        })
+        .filter(|x| x.contains_key("processor"))
+        .collect()
+}
+
+fn read_vulnerabilities() -> Vec<(String, String)> {
+    let vulnerabilities_dir = sysroot_path("/sys/devices/system/cpu/vulnerabilities");
+    let mut vulnerabilities: Vec<(String, String)> = match std::fs::read_dir(&vulnerabilities_dir) {
+        Ok(entries) => entries
+            .flatten()
+            .filter_map(|x| {
+                let name = x.file_name().to_string_lossy().to_string();
+                Some((name.clone(), read_sysfs_value(&vulnerabilities_dir, &name)?))
+            })
+            .collect(),
+        Err(_) => vec![],
+    };
+    vulnerabilities.sort();
+    vulnerabilities
+}
+
+impl CpuDevice {
+    pub fn is_vulnerable(state: &str) -> bool {
+        state.starts_with("Vulnerable")
+    }
+}
+
+impl SysfsBusDevice for CpuDevice {
+    const BUS: &'static str = "cpu";
+    const MATCH_KEYS: &'static [&'static str] = &["vendors", "families", "models"];
+
+    fn enumerate() -> Vec<Self> {
+        let cpuinfo = std::fs::read_to_string(sysroot_path("/proc/cpuinfo")).unwrap_or_default();
+        let mut packages: BTreeMap<String, Vec<BTreeMap<String, String>>> = BTreeMap::new();
+        for processor in parse_cpuinfo(&cpuinfo) {
+            let package = processor
+                .get("physical id")
+                .cloned()
+                .unwrap_or("0".to_string());
+            packages.entry(package).or_default().push(processor);
+        }
+        // Scaling and vulnerabilities are the same for every CPU in practice, cpu0 speaks for all
+        let cpufreq_dir = sysroot_path("/sys/devices/system/cpu/cpu0/cpufreq");
+        let vulnerabilities = read_vulnerabilities();
+        packages
+            .into_iter()
+            .map(|(package, processors)| {
+                let first = &processors[0];
+                let value = |key: &str| first.get(key).cloned();
+                let mut core_ids: Vec<&String> =
+                    processors.iter().filter_map(|x| x.get("core id")).collect();
+                core_ids.sort();
+                core_ids.dedup();
+                CpuDevice {
+                    package,
+                    vendor: value("vendor_id").unwrap_or_default(),
+                    model_name: value("model name").unwrap_or_default(),
+                    family: value("cpu family").unwrap_or_default(),
+                    model: value("model").unwrap_or_default(),
+                    stepping: value("stepping").unwrap_or_default(),
+                    microcode: value("microcode"),
+                    cores: core_ids.len().max(1),
+                    threads: processors.len(),
+                    scaling_driver: read_sysfs_value(&cpufreq_dir, "scaling_driver"),
+                    scaling_governor: read_sysfs_value(&cpufreq_dir, "scaling_governor"),
+                    energy_performance_preference: read_sysfs_value(
+                        &cpufreq_dir,
+                        "energy_performance_preference",
+                    ),
+                    vulnerabilities: vulnerabilities.clone(),
+                }
+            })
+            .collect()
+    }
+
+    fn profile_url() -> Option<String> {
+        CPU_PROFILE_JSON_URL.clone()
+    }
+
+    fn id(&self) -> String {
+        format!("package{}", self.package)
+    }
+
+    fn class(&self) -> String {
+        match self.vendor.as_str() {
+            "GenuineIntel" => "intel",
+            "AuthenticAMD" => "amd",
+            _ => "other",
+        }
+        .to_string()
+    }
+
+    fn title(&self) -> String {
+        self.model_name.clone()
+    }
+
+    fn subtitle(&self) -> String {
+        t!("cpu_subtitle", cores = self.cores, threads = self.threads).to_string()
+    }
+
+    fn details(&self) -> Vec<(String, String)> {
+        let mut details = vec![
+            ("cpu_detail_vendor".to_string(), self.vendor.clone()),
+            ("cpu_detail_model_name".to_string(), self.model_name.clone()),
+            ("cpu_detail_family".to_string(), self.family.clone()),
+            ("cpu_detail_model".to_string(), self.model.clone()),
+            ("cpu_detail_stepping".to_string(), self.stepping.clone()),
+        ];
+        for (key, value) in [
+            ("cpu_detail_microcode", &self.microcode),
+            ("cpu_detail_scaling_driver", &self.scaling_driver),
+            ("cpu_detail_scaling_governor", &self.scaling_governor),
+            (
+                "cpu_detail_energy_performance_preference",
+                &self.energy_performance_preference,
+            ),
+        ] {
+            if let Some(value) = value {
+                details.push((key.to_string(), value.clone()));
+            }
+        }
+        details
+    }
+
+    fn match_values(&self, key: &str) -> Vec<String> {
+        match key {
+            "vendors" => vec![self.vendor.clone()],
+            "families" => vec![self.family.clone()],
+            "models" => vec![self.model.clone()],
+            _ => vec![],
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::cfhdb::sysroot::FakeSysroot;
+
+    fn processor(processor: usize, physical_id: usize, core_id: usize) -> String {
+        format!(
+            "processor\t: {}\nvendor_id\t: GenuineIntel\ncpu family\t: 6\nmodel\t\t: 85\n\
+             model name\t: Intel(R) Xeon(R) Gold 6130 CPU @ 2.10GHz\nstepping\t: 4\n\
+             microcode\t: 0x2007006\nphysical id\t: {}\ncore id\t\t: {}\n\
+             flags\t\t: fpu vme de pse tsc msr\n",
+            processor, physical_id, core_id
+        )
+    }
+
+    #[test]
+    fn two_packages_with_hyperthreading() {
+        let sysroot = FakeSysroot::new("cpu-packages");
+        // Two sockets with two cores of two threads each, listed interleaved like the kernel does
+        let cpuinfo: Vec<String> = (0..8).map(|x| processor(x, x % 2, (x / 2) % 2)).collect();
+        sysroot
+            .write("/proc/cpuinfo", &(cpuinfo.join("\n") + "\n"))
+            .write(
+                "/sys/devices/system/cpu/cpu0/cpufreq/scaling_driver",
+                "intel_pstate\n",
+            )
+            .write(
+                "/sys/devices/system/cpu/cpu0/cpufreq/scaling_governor",
+                "powersave\n",
+            )
+            .write(
+                "/sys/devices/system/cpu/vulnerabilities/spectre_v2",
+                "Mitigation: Enhanced IBRS\n",
+            )
+            .write(
+                "/sys/devices/system/cpu/vulnerabilities/meltdown",
+                "Not affected\n",
+            );
+        let cpus = sysroot.run(CpuDevice::enumerate);
+
+        assert_eq!(cpus.len(), 2);
+        for (cpu, package) in cpus.iter().zip(["0", "1"]) {
+            assert_eq!(cpu.package, package);
+            assert_eq!(cpu.id(), format!("package{}", package));
+            assert_eq!(cpu.class(), "intel");
+            assert_eq!(cpu.vendor, "GenuineIntel");
+            assert_eq!(cpu.title(), "Intel(R) Xeon(R) Gold 6130 CPU @ 2.10GHz");
+            assert_eq!((cpu.family.as_str(), cpu.model.as_str()), ("6", "85"));
+            assert_eq!(cpu.stepping, "4");
+            assert_eq!(cpu.microcode.as_deref(), Some("0x2007006"));
+            assert_eq!((cpu.cores, cpu.threads), (2, 4));
+            assert_eq!(cpu.scaling_driver.as_deref(), Some("intel_pstate"));
+            assert_eq!(cpu.scaling_governor.as_deref(), Some("powersave"));
+            assert_eq!(cpu.energy_performance_preference, None);
+            assert_eq!(
+                cpu.vulnerabilities,
+                vec![
+                    (String::from("meltdown"), String::from("Not affected")),
+                    (
+                        String::from("spectre_v2"),
+                        String::from("Mitigation: Enhanced IBRS")
+                    ),
+                ]
+            );
+            assert_eq!(cpu.match_values("families"), vec!["6"]);
+            assert_eq!(cpu.match_values("models"), vec!["85"]);
+        }
+    }
+
+    #[test]
+    fn missing_fields_and_sysfs_files() {
+        let sysroot = FakeSysroot::new("cpu-minimal");
+        // Virtual machines and other architectures leave most fields out
+        sysroot.write(
+            "/proc/cpuinfo",
+            "processor\t: 0\nvendor_id\t: AuthenticAMD\n\nprocessor\t: 1\nvendor_id\t: AuthenticAMD\n\n",
+        );
+        let cpus = sysroot.run(CpuDevice::enumerate);
+
+        assert_eq!(cpus.len(), 1);
+        let cpu = &cpus[0];
+        assert_eq!(cpu.package, "0");
+        assert_eq!(cpu.class(), "amd");
+        assert_eq!(cpu.microcode, None);
+        assert_eq!((cpu.cores, cpu.threads), (1, 2));
+        assert_eq!(cpu.scaling_driver, None);
+        assert!(cpu.vulnerabilities.is_empty());
+        // Only the details that are known are listed
+        let details: Vec<String> = cpu.details().into_iter().map(|x| x.0).collect();
+        assert_eq!(
+            details,
+            vec![
+                "cpu_detail_vendor",
+                "cpu_detail_model_name",
+                "cpu_detail_family",
+                "cpu_detail_model",
+                "cpu_detail_stepping",
+            ]
+        );
+    }
+
+    #[test]
+    fn no_cpuinfo() {
+        let sysroot = FakeSysroot::new("cpu-empty");
+        assert!(sysroot.run(CpuDevice::enumerate).is_empty());
+    }
+
+    #[test]
+    fn vulnerable_states() {
+        assert!(CpuDevice::is_vulnerable("Vulnerable: No microcode"));
+        assert!(!CpuDevice::is_vulnerable("Mitigation: PTI"));
+        assert!(!CpuDevice::is_vulnerable("Not affected"));
+    }
+}
@@ -0,0 +1,452 @@
+use super::{
+    storage::parent_bus_device, sysfs_bus::SysfsBusDevice, sysroot::sysroot_path,
+    usb::read_sysfs_value,
+};
+use crate::config::DISPLAY_PROFILE_JSON_URL;
+use std::path::PathBuf;
+
+const EDID_HEADER: [u8; 8] = [0x00, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0x00];
+const EDID_BLOCK_SIZE: usize = 128;
+
+/// What the EDID of a monitor says about it, the base block and any CTA-861 extensions.
+#[derive(Clone, Default)]
+pub struct Edid {
+    /// The three letter PNP ID, `SAM` or `DEL`
+    pub manufacturer_code: String,
+    /// Four upper case hex digits, as the kernel and `edid-decode` print them
+    pub product_code: String,
+    pub serial_number: Option<String>,
+    pub model_name: Option<String>,
+    /// (week, year), the week is 0 if only the year is known
+    pub manufacture_date: (u8, u16),
+    pub version: String,
+    /// (width, height) in centimeters, zero for projectors
+    pub physical_size: (u8, u8),
+    pub preferred_mode: Option<String>,
+    /// (min, max) refresh rate if the monitor advertises variable refresh
+    pub vrr_range: Option<(u16, u16)>,
+    /// The HDR transfer functions of the HDR static metadata block, `pq` and `hlg`
+    pub hdr_transfer_functions: Vec<String>,
+}
+
+/// Manufacturer IDs are three letters of five bits each, `A` being 1, packed big endian.
+fn manufacturer_code(high: u8, low: u8) -> String {
+    let packed = u16::from_be_bytes([high, low]);
+    [10, 5, 0]
+        .iter()
+        .map(|x| (b'A' - 1 + ((packed >> x) & 0x1f) as u8) as char)
+        .collect()
+}
+
+fn descriptor_text(descriptor: &[u8]) -> String {
+    String::from_utf8_lossy(&descriptor[5..18])
+        .split('\n')
+        .next()
+        .unwrap_or_default()
+        .trim()
+        .to_string()
+}
+
+/// A detailed timing descriptor as `WIDTHxHEIGHT@RATE`.
+fn detailed_timing_mode(descriptor: &[u8]) -> Option<String> {
+    let pixel_clock = u16::from_le_bytes([descriptor[0], descriptor[1]]) as f64 * 10_000.0;
+    let h_active = descriptor[2] as u32 | ((descriptor[4] as u32 & 0xf0) << 4);
+    let h_blank = descriptor[3] as u32 | ((descriptor[4] as u32 & 0x0f) << 8);
+    let v_active = descriptor[5] as u32 | ((descriptor[7] as u32 & 0xf0) << 4);
+    let v_blank = descriptor[6] as u32 | ((descriptor[7] as u32 & 0x0f) << 8);
+    let total = ((h_active + h_blank) * (v_active + v_blank)) as f64;
+    (total > 0.0).then(|| format!("{}x{}@{:.0}", h_active, v_active, pixel_clock / total))
+}
+
+/// Walks the data blocks of a CTA-861 extension, calls `found` with the tag and payload of each.
+fn cta_data_blocks(extension: &[u8], mut found: impl FnMut(u8, &[u8])) {
+    let data_end = (extension[2] as usize).min(EDID_BLOCK_SIZE - 1);
+    let mut offset = 4;
+    while offset < data_end {
+        let tag = extension[offset] >> 5;
+        let length = (extension[offset] & 0x1f) as usize;
+        let Some(payload) = extension.get(offset + 1..offset + 1 + length) else {
+            break;
+        };
+        found(tag, payload);
+        offset += 1 + length;
+    }
+}
+
+/// Parses an EDID blob, None if it does not start with a valid base block.
+pub fn parse_edid(bytes: &[u8]) -> Option<Edid> {
+    if bytes.len() < EDID_BLOCK_SIZE || bytes[..8] != EDID_HEADER {
+        return None;
+    }
+    let mut edid = Edid {
+        manufacturer_code: manufacturer_code(bytes[8], bytes[9]),
+        product_code: format!("{:04X}", u16::from_le_bytes([bytes[10], bytes[11]])),
+        manufacture_date: (bytes[16], 1990 + bytes[17] as u16),
+        version: format!("{}.{}", bytes[18], bytes[19]),
+        physical_size: (bytes[21], bytes[22]),
+        ..Default::default()
+    };
+    let serial_number = u32::from_le_bytes([bytes[12], bytes[13], bytes[14], bytes[15]]);
+    if serial_number != 0 {
+        edid.serial_number = Some(serial_number.to_string());
+    }
+
+    let mut range_limits = None;
+    for descriptor in bytes[54..126].chunks(18) {
+        if descriptor[0] != 0 || descriptor[1] != 0 {
+            // The first detailed timing is the preferred mode
+            if edid.preferred_mode.is_none() {
+                edid.preferred_mode = detailed_timing_mode(descriptor);
+            }
+            continue;
+        }
+        match descriptor[3] {
+            0xfc => edid.model_name = Some(descriptor_text(descriptor)),
+            // A serial number string is more useful than the numeric one, which is often made up
+            0xff => edid.serial_number = Some(descriptor_text(descriptor)),
+            0xfd => {
+                // EDID 1.4 adds 255 to the rates when the offset flags are set
+                let min_offset = if descriptor[4] & 0x02 != 0 { 255 } else { 0 };
+                let max_offset = if descriptor[4] & 0x08 != 0 { 255 } else { 0 };
+                range_limits = Some((
+                    descriptor[5] as u16 + min_offset,
+                    descriptor[6] as u16 + max_offset,
+                ));
+            }
+            _ => {}
+        }
+    }
+
+    // The continuous frequency flag of EDID 1.4 is what DisplayPort adaptive sync monitors set
+    let mut hdmi_vrr_range = None;
+    let mut variable_refresh = bytes[18] == 1 && bytes[19] >= 4 && bytes[24] & 0x01 != 0;
+    let extensions = (bytes[126] as usize).min(bytes.len() / EDID_BLOCK_SIZE - 1);
+    for extension in bytes[EDID_BLOCK_SIZE..]
+        .chunks_exact(EDID_BLOCK_SIZE)
+        .take(extensions)
+        .filter(|x| x[0] == 0x02)
+    {
+        cta_data_blocks(extension, |tag, payload| match (tag, payload) {
+            // Vendor specific data blocks, AMD's one is how HDMI monitors announce FreeSync
+            (3, [0x1a, 0x00, 0x00, ..]) => variable_refresh = true,
+            // The HDMI Forum one has a VRR range from HDMI 2.1 on
+            (3, [0xd8, 0x5d, 0xc4, _, _, _, _, _, vrr_min, vrr_max_low, ..]) => {
+                // The two high bits of the maximum are stored above the six bits of the minimum
+                let vrr_max = ((*vrr_min as u16 & 0xc0) << 2) | *vrr_max_low as u16;
+                if vrr_max != 0 {
+                    hdmi_vrr_range = Some((*vrr_min as u16 & 0x3f, vrr_max));
+                }
+            }
+            // Extended tag 6, the HDR static metadata block
+            (7, [0x06, eotf, ..]) => {
+                if eotf & 0x04 != 0 {
+                    edid.hdr_transfer_functions.push("pq".to_string());
+                }
+                if eotf & 0x08 != 0 {
+                    edid.hdr_transfer_functions.push("hlg".to_string());
+                }
+            }
+            _ => {}
+        });
+    }
+    // The HDMI Forum range is the one HDMI 2.1 VRR uses, the range limits can be narrower
+    edid.vrr_range = hdmi_vrr_range
+        .or(range_limits.filter(|_| variable_refresh))
+        .filter(|(min, max)| min < max);
+
+    Some(edid)
+}
+
+/// A connected display output from /sys/class/drm, with the EDID of the monitor on it.
+#[derive(Clone)]
+pub struct Display {
+    /// The connector directory, `card1-DP-2`
+    pub connector: String,
+    pub card: String,
+    pub connector_type: String,
+    pub enabled: bool,
+    /// The modes the kernel found, best first
+    pub modes: Vec<String>,
+    pub edid: Edid,
+    /// The GPU driving the connector
+    pub parent: Option<(String, String)>,
+}
+
+impl Display {
+    /// Connectors are grouped into built-in panels and the external connector kinds.
+    fn connector_class(&self) -> &'static str {
+        match self.connector_type.as_str() {
+            "eDP" | "LVDS" | "DSI" => "internal",
+            "DP" => "displayport",
+            "HDMI-A" | "HDMI-B" => "hdmi",
+            "DVI-I" | "DVI-D" | "DVI-A" => "dvi",
+            "VGA" => "vga",
+            _ => "other",
+        }
+    }
+}
+
+fn read_display(connector_dir: PathBuf) -> Option<Display> {
+    let connector = connector_dir.file_name()?.to_string_lossy().to_string();
+    // Connector directories are `<card>-<type>-<index>`, the type can have a dash itself
+    let (card, connector_name) = connector.split_once('-')?;
+    let connector_type = connector_name.rsplit_once('-')?.0.to_string();
+    if read_sysfs_value(&connector_dir, "status").as_deref() != Some("connected") {
+        return None;
+    }
+    let edid = parse_edid(&std::fs::read(connector_dir.join("edid")).ok()?)?;
+    let mut modes: Vec<String> = vec![];
+    for mode in read_sysfs_value(&connector_dir, "modes")
+        .unwrap_or_default()
+        .lines()
+    {
+        if !modes.iter().any(|x| x == mode) {
+            modes.push(mode.to_string());
+        }
+    }
+    Some(Display {
+        card: card.to_string(),
+        connector_type,
+        enabled: read_sysfs_value(&connector_dir, "enabled").as_deref() == Some("enabled"),
+        modes,
+        edid,
+        parent: parent_bus_device(&connector_dir.join("device")),
+        connector,
+    })
+}
+
+impl SysfsBusDevice for Display {
+    const BUS: &'static str = "display";
+    const MATCH_KEYS: &'static [&'static str] = &["manufacturer_codes", "product_codes"];
+
+    fn enumerate() -> Vec<Self> {
+        match std::fs::read_dir(sysroot_path("/sys/class/drm")) {
+            Ok(entries) => entries
+                .flatten()
+                .filter(|x| x.file_name().to_string_lossy().contains('-'))
+                .filter_map(|x| read_display(x.path()))
+                .collect(),
+            Err(_) => vec![],
+        }
+    }
+
+    fn profile_url() -> Option<String> {
+        DISPLAY_PROFILE_JSON_URL.clone()
+    }
+
+    fn id(&self) -> String {
+        self.connector.clone()
+    }
+
+    fn class(&self) -> String {
+        self.connector_class().to_string()
+    }
+
+    fn title(&self) -> String {
+        self.edid.model_name.clone().unwrap_or_else(|| {
+            format!("{} {}", self.edid.manufacturer_code, self.edid.product_code)
+        })
+    }
+
+    fn subtitle(&self) -> String {
+        match &self.edid.preferred_mode {
+            Some(t) => format!("{} - {} Hz", self.connector, t),
+            None => self.connector.clone(),
+        }
+    }
+
+    fn details(&self) -> Vec<(String, String)> {
+        let edid = &self.edid;
+        let mut details = vec![
+            (
+                "display_detail_connector".to_string(),
+                self.connector.clone(),
+            ),
+            ("display_detail_card".to_string(), self.card.clone()),
+            (
+                "display_detail_enabled".to_string(),
+                if self.enabled {
+                    t!("status_yes")
+                } else {
+                    t!("status_no")
+                }
+                .to_string(),
+            ),
+            (
+                "display_detail_manufacturer_code".to_string(),
+                edid.manufacturer_code.clone(),
+            ),
+            (
+                "display_detail_product_code".to_string(),
+                edid.product_code.clone(),
+            ),
+        ];
+        for (key, value) in [
+            ("display_detail_model_name", &edid.model_name),
+            ("display_detail_serial_number", &edid.serial_number),
+        ] {
+            if let Some(value) = value {
+                details.push((key.to_string(), value.clone()));
+            }
+        }
+        let (week, year) = edid.manufacture_date;
+        details.push((
+            "display_detail_manufacture_date".to_string(),
+            match week {
+                1..=54 => t!("display_manufacture_week", week = week, year = year).to_string(),
+                _ => year.to_string(),
+            },
+        ));
+        details.push((
+            "display_detail_edid_version".to_string(),
+            edid.version.clone(),
+        ));
+        if edid.physical_size != (0, 0) {
+            details.push((
+                "display_detail_physical_size".to_string(),
+                format!("{} x {} cm", edid.physical_size.0, edid.physical_size.1),
+            ));
+        }
+        if let Some(preferred_mode) = &edid.preferred_mode {
+            details.push((
+                "display_detail_preferred_mode".to_string(),
+                format!("{} Hz", preferred_mode),
+            ));
+        }
+        if !self.modes.is_empty() {
+            details.push(("display_detail_modes".to_string(), self.modes.join(", ")));
+        }
+        details.push((
+            "display_detail_vrr".to_string(),
+            match edid.vrr_range {
+                Some((min, max)) => format!("{} - {} Hz", min, max),
+                None => t!("display_not_supported").to_string(),
+            },
+        ));
+        details.push((
+            "display_detail_hdr".to_string(),
+            match edid.hdr_transfer_functions.is_empty() {
+                true => t!("display_not_supported").to_string(),
+                false => edid
+                    .hdr_transfer_functions
+                    .iter()
+                    .map(|x| t!(format!("display_hdr_{}", x)).to_string())
+                    .collect::<Vec<String>>()
+                    .join(", "),
+            },
+        ));
+        details
+    }
+
+    fn match_values(&self, key: &str) -> Vec<String> {
+        match key {
+            "manufacturer_codes" => vec![self.edid.manufacturer_code.clone()],
+            "product_codes" => vec![self.edid.product_code.clone()],
+            _ => vec![],
+        }
+    }
+
+    fn parent_device(&self) -> Option<(String, String)> {
+        self.parent.clone()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Laid out like the EDIDs of a Dell S2721DGF on HDMI, EDID 1.3 with FreeSync announced through
+    // the AMD VSDB, and of an ASUS VG27AQM, EDID 1.4 with HDMI 2.1 VRR in the HDMI Forum VSDB
+    const FREESYNC_EDID: &str = concat!(
+        "00ffffffffffff0010acf1a031334c4c0c1f0103803c22780aee91a3544c9926",
+        "0f5054210800d1c00101010101010101010101010101023a801871382d40582c",
+        "450056502100001e000000ff0037595a314836330a2020202020000000fc0044",
+        "454c4c205332373231444746000000fd0030901ea01e000a2020202020200194",
+        "02031370681a00000101309000e3060d01411000000000000000000000000000",
+        "0000000000000000000000000000000000000000000000000000000000000000",
+        "0000000000000000000000000000000000000000000000000000000000000000",
+        "00000000000000000000000000000000000000000000000000000000000000ec",
+    );
+    const HDMI_FORUM_EDID: &str = concat!(
+        "00ffffffffffff0006b3b12740e2010014200104a53c227806ee91a3544c9926",
+        "0f5054210800d1c0010101010101010101010101010132e500a0a0a03a503020",
+        "350055502100001e000000fc005647323741514d0a2020202020000000fd0018",
+        "3c1ea03c000a20202020202000000010000a2020202020202020202020200177",
+        "020315706ad85dc401780000007018e306050141100000000000000000000000",
+        "0000000000000000000000000000000000000000000000000000000000000000",
+        "0000000000000000000000000000000000000000000000000000000000000000",
+        "00000000000000000000000000000000000000000000000000000000000000d2",
+    );
+
+    fn blob(hex: &str) -> Vec<u8> {
+        (0..hex.len())
+            .step_by(2)
+            .map(|x| u8::from_str_radix(&hex[x..x + 2], 16).unwrap())
+            .collect()
+    }
+
+    #[test]
+    fn invalid_header_is_rejected() {
+        assert!(parse_edid(&[0; EDID_BLOCK_SIZE]).is_none());
+        assert!(parse_edid(&blob(FREESYNC_EDID)[..64]).is_none());
+    }
+
+    #[test]
+    fn base_block() {
+        let edid = parse_edid(&blob(FREESYNC_EDID)).unwrap();
+        assert_eq!(edid.manufacturer_code, "DEL");
+        assert_eq!(edid.product_code, "A0F1");
+        assert_eq!(edid.manufacture_date, (12, 2021));
+        assert_eq!(edid.version, "1.3");
+        assert_eq!(edid.physical_size, (60, 34));
+        assert_eq!(edid.preferred_mode.as_deref(), Some("1920x1080@60"));
+
+        let edid = parse_edid(&blob(HDMI_FORUM_EDID)).unwrap();
+        assert_eq!(edid.manufacturer_code, "AUS");
+        assert_eq!(edid.product_code, "27B1");
+        assert_eq!(edid.version, "1.4");
+        assert_eq!(edid.preferred_mode.as_deref(), Some("2560x1440@144"));
+    }
+
+    #[test]
+    fn serial_and_name_descriptors() {
+        let edid = parse_edid(&blob(FREESYNC_EDID)).unwrap();
+        // The serial number descriptor wins over the numeric serial number
+        assert_eq!(edid.serial_number.as_deref(), Some("7YZ1H63"));
+        assert_eq!(edid.model_name.as_deref(), Some("DELL S2721DGF"));
+
+        let edid = parse_edid(&blob(HDMI_FORUM_EDID)).unwrap();
+        assert_eq!(edid.serial_number.as_deref(), Some("123456"));
+        assert_eq!(edid.model_name.as_deref(), Some("VG27AQM"));
+    }
+
+    #[test]
+    fn freesync_vsdb_uses_the_range_limits() {
+        let edid = parse_edid(&blob(FREESYNC_EDID)).unwrap();
+        assert_eq!(edid.vrr_range, Some((48, 144)));
+    }
+
+    #[test]
+    fn range_limits_alone_are_not_vrr() {
+        // Only the base block, without the AMD VSDB nor the continuous frequency flag
+        let mut bytes = blob(FREESYNC_EDID)[..EDID_BLOCK_SIZE].to_vec();
+        bytes[126] = 0;
+        let edid = parse_edid(&bytes).unwrap();
+        assert_eq!(edid.vrr_range, None);
+    }
+
+    #[test]
+    fn hdmi_forum_vsdb_range() {
+        // 48 to 280 Hz, the range limits descriptor only says 24 to 60
+        let edid = parse_edid(&blob(HDMI_FORUM_EDID)).unwrap();
+        assert_eq!(edid.vrr_range, Some((48, 280)));
+    }
+
+    #[test]
+    fn hdr_static_metadata() {
+        let edid = parse_edid(&blob(FREESYNC_EDID)).unwrap();
+        assert_eq!(edid.hdr_transfer_functions, vec!["pq", "hlg"]);
+        let edid = parse_edid(&blob(HDMI_FORUM_EDID)).unwrap();
+        assert_eq!(edid.hdr_transfer_functions, vec!["pq"]);
+    }
+}
@@ -1,5 +1,7 @@
+use super::{PreCheckedProfile, ProfileExtras, ProfileSummary};
 use crate::{config::*, ChannelMsg};
 use libcfhdb::dmi::*;
+use log::{debug, error, info, warn};
 use std::{
     fs,
     path::Path,
@@ -14,14 +16,16 @@ pub struct PreCheckedDmiInfo {
 
 pub struct PreCheckedDmiProfile {
     profile: CfhdbDmiProfile,
+    extras: ProfileExtras,
     installed: Arc<Mutex<bool>>,
     pub used: Arc<Mutex<bool>>,
 }
 
 impl PreCheckedDmiProfile {
-    pub fn new(profile: CfhdbDmiProfile) -> Self {
+    pub fn new(profile: CfhdbDmiProfile, extras: ProfileExtras) -> Self {
         Self {
             profile,
+            extras,
             installed: Arc::new(Mutex::new(false)),
             used: Arc::new(Mutex::new(false)),
         }
@@ -37,6 +41,33 @@ impl PreCheckedDmiProfile {
     }
 }
 
+impl PreCheckedProfile for PreCheckedDmiProfile {
+    fn summary(&self) -> ProfileSummary {
+        ProfileSummary {
+            bus: "dmi",
+            codename: self.profile.codename.clone(),
+            i18n_desc: self.profile.i18n_desc.clone(),
+            icon_name: self.profile.icon_name.clone(),
+            license: self.profile.license.clone(),
+            packages: self.profile.packages.clone(),
+            install_script: self.profile.install_script.clone(),
+            remove_script: self.profile.remove_script.clone(),
+            experimental: self.profile.experimental,
+            removable: self.profile.removable,
+            veiled: self.profile.veiled,
+            priority: self.profile.priority,
+            kernel_args: self.extras.kernel_args.clone(),
+            provides_firmware: self.extras.provides_firmware.clone(),
+        }
+    }
+    fn is_installed(&self) -> bool {
+        self.installed()
+    }
+    fn refresh_installed(&self) {
+        self.update_installed();
+    }
+}
+
 pub fn get_dmi_info(profiles: &[Arc<PreCheckedDmiProfile>]) -> PreCheckedDmiInfo {
     let info = CfhdbDmiInfo::get_dmi();
     get_pre_checked_info(profiles, info.clone())
@@ -98,6 +129,12 @@ fn get_pre_checked_info(
         };
 
         if matching {
+            debug!(
+                target: "matcher",
+                "DMI system {} matched profile {}",
+                info.product_name,
+                profile.codename
+            );
             available_profiles.push(profile_arc.clone());
         }
     }
@@ -109,7 +146,7 @@ fn get_pre_checked_info(
 
 pub fn get_dmi_profiles_from_url(
     sender: &async_channel::Sender<ChannelMsg>,
-) -> Result<Vec<CfhdbDmiProfile>, std::io::Error> {
+) -> Result<Vec<(CfhdbDmiProfile, ProfileExtras)>, std::io::Error> {
     let cached_db_path = Path::new("/var/cache/cfhdb/dmi.json");
     sender
         .send_blocking(ChannelMsg::OutputLine(format!(
@@ -122,8 +159,10 @@ pub fn get_dmi_profiles_from_url(
         .timeout(std::time::Duration::from_secs(5))
         .build()
         .unwrap();
+    info!(target: "dmi", "Downloading profiles from {}", *DMI_PROFILE_JSON_URL);
     let data = match client.get(DMI_PROFILE_JSON_URL.clone()).send() {
         Ok(t) => {
+            info!(target: "dmi", "Profile download successful");
             sender
                 .send_blocking(ChannelMsg::OutputLine(format!(
                     "[{}] {}",
@@ -136,7 +175,8 @@ pub fn get_dmi_profiles_from_url(
             let _ = fs::write(cached_db_path, &cache);
             cache
         }
-        Err(_) => {
+        Err(e) => {
+            warn!(target: "dmi", "Profile download failed: {}", e);
             sender
                 .send_blocking(ChannelMsg::OutputLine(format!(
                     "[{}] {}",
@@ -145,6 +185,11 @@ pub fn get_dmi_profiles_from_url(
                 )))
                 .expect("Channel closed");
             if cached_db_path.exists() {
+                info!(
+                    target: "dmi",
+                    "Using cached profiles from {}",
+                    cached_db_path.display()
+                );
                 sender
                     .send_blocking(ChannelMsg::OutputLine(format!(
                         "[{}] {}",
@@ -154,6 +199,7 @@ pub fn get_dmi_profiles_from_url(
                     .expect("Channel closed");
                 fs::read_to_string(cached_db_path).unwrap()
             } else {
+                error!(target: "dmi", "No cached profiles found, giving up");
                 sender
                     .send_blocking(ChannelMsg::OutputLine(format!(
                         "[{}] {}",
@@ -292,8 +338,8 @@ pub fn get_dmi_profiles_from_url(
                 veiled,
                 priority: priority as i32,
             };
-            profiles_array.push(profile_struct);
-            profiles_array.sort_by_key(|x| x.priority);
+            profiles_array.push((profile_struct, ProfileExtras::from_json(profile)));
+            profiles_array.sort_by_key(|x| x.0.priority);
         }
     }
     Ok(profiles_array)
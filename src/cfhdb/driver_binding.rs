@@ -0,0 +1,258 @@
+use super::{kmod::device_sysfs_dirs, sysroot::sysroot_path};
+
+/// A device, or for USB one of its interfaces, that a driver can be bound to.
+pub struct BindTarget {
+    pub sysfs_busid: String,
+    pub driver: Option<String>,
+    /// Only PCI has driver_override, so this is None where the file does not exist
+    pub driver_override: Option<String>,
+}
+
+fn is_valid_name(name: &str) -> bool {
+    !name.is_empty()
+        && name
+            .chars()
+            .all(|x| x.is_ascii_alphanumeric() || "_-.:".contains(x))
+}
+
+fn check_names(bus: &str, target_busid: &str, driver: &str) -> Result<(), String> {
+    if !is_valid_name(bus) {
+        return Err(t!("driver_binding_invalid_name", name = bus).to_string());
+    }
+    if !is_valid_name(target_busid) {
+        return Err(t!("driver_binding_invalid_name", name = target_busid).to_string());
+    }
+    if !driver.is_empty() && !is_valid_name(driver) {
+        return Err(t!("driver_binding_invalid_name", name = driver).to_string());
+    }
+    Ok(())
+}
+
+pub fn get_bind_targets(bus: &str, sysfs_busid: &str) -> Vec<BindTarget> {
+    device_sysfs_dirs(bus, sysfs_busid)
+        .into_iter()
+        .filter_map(|dir| {
+            let target_busid = dir.file_name()?.to_string_lossy().to_string();
+            let driver = std::fs::read_link(dir.join("driver"))
+                .ok()
+                .and_then(|x| x.file_name().map(|y| y.to_string_lossy().to_string()));
+            // The kernel reports an unset override as "(null)"
+            let driver_override = std::fs::read_to_string(dir.join("driver_override"))
+                .ok()
+                .map(|x| x.trim().to_string())
+                .map(|x| if x == "(null)" { String::new() } else { x });
+            Some(BindTarget {
+                sysfs_busid: target_busid,
+                driver,
+                driver_override,
+            })
+        })
+        .collect()
+}
+
+/// Every driver registered on the bus, loaded or built in.
+pub fn get_bus_drivers(bus: &str) -> Vec<String> {
+    let mut drivers: Vec<String> =
+        match std::fs::read_dir(sysroot_path(&format!("/sys/bus/{}/drivers", bus))) {
+            Ok(entries) => entries
+                .flatten()
+                .map(|x| x.file_name().to_string_lossy().to_string())
+                .collect(),
+            Err(_) => vec![],
+        };
+    drivers.sort();
+    drivers
+}
+
+fn unbind_command(bus: &str, target_busid: &str) -> String {
+    format!(
+        "if [ -e /sys/bus/{bus}/devices/{id}/driver ]; then\n  echo -n '{id}' > /sys/bus/{bus}/devices/{id}/driver/unbind\nfi",
+        bus = bus,
+        id = target_busid
+    )
+}
+
+pub fn get_unbind_commands(bus: &str, target_busid: &str) -> Result<String, String> {
+    check_names(bus, target_busid, "")?;
+    Ok(unbind_command(bus, target_busid))
+}
+
+/// Unbinds the current driver and binds the given one, loading its module first if needed.
+pub fn get_bind_commands(bus: &str, target_busid: &str, driver: &str) -> Result<String, String> {
+    check_names(bus, target_busid, driver)?;
+    if driver.is_empty() {
+        return Err(t!("driver_binding_invalid_name", name = driver).to_string());
+    }
+    Ok(format!(
+        "if [ ! -e /sys/bus/{bus}/drivers/{driver} ]; then\n  modprobe '{driver}'\nfi\n{unbind}\necho -n '{id}' > /sys/bus/{bus}/drivers/{driver}/bind",
+        bus = bus,
+        driver = driver,
+        unbind = unbind_command(bus, target_busid),
+        id = target_busid
+    ))
+}
+
+/// Sets driver_override, or clears it if `driver` is empty, then reprobes the device so it moves
+/// to the overriding driver right away.
+pub fn get_driver_override_commands(
+    bus: &str,
+    target_busid: &str,
+    driver: &str,
+) -> Result<String, String> {
+    check_names(bus, target_busid, driver)?;
+    let load_command = if driver.is_empty() {
+        String::new()
+    } else {
+        format!(
+            "if [ ! -e /sys/bus/{bus}/drivers/{driver} ]; then\n  modprobe '{driver}'\nfi\n",
+            bus = bus,
+            driver = driver
+        )
+    };
+    Ok(format!(
+        "{load}echo '{driver}' > /sys/bus/{bus}/devices/{id}/driver_override\n{unbind}\necho -n '{id}' > /sys/bus/{bus}/drivers_probe",
+        load = load_command,
+        driver = driver,
+        bus = bus,
+        id = target_busid,
+        unbind = unbind_command(bus, target_busid)
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::cfhdb::sysroot::FakeSysroot;
+
+    fn sysroot() -> FakeSysroot {
+        let sysroot = FakeSysroot::new("driver-binding");
+        sysroot
+            .symlink(
+                "/sys/bus/pci/devices/0000:01:00.0/driver",
+                "../../../bus/pci/drivers/nvidia",
+            )
+            .write(
+                "/sys/bus/pci/devices/0000:01:00.0/driver_override",
+                "(null)\n",
+            )
+            .write(
+                "/sys/bus/pci/devices/0000:02:00.0/driver_override",
+                "vfio-pci\n",
+            )
+            .mkdir("/sys/bus/pci/drivers/vfio-pci")
+            .mkdir("/sys/bus/pci/drivers/nvidia")
+            .mkdir("/sys/bus/pci/drivers/ahci")
+            .symlink(
+                "/sys/bus/usb/devices/1-2/driver",
+                "../../../bus/usb/drivers/usb",
+            )
+            .symlink(
+                "/sys/bus/usb/devices/1-2/1-2:1.0/driver",
+                "../../../../bus/usb/drivers/btusb",
+            )
+            .mkdir("/sys/bus/usb/devices/1-2/1-2:1.1")
+            .mkdir("/sys/bus/usb/devices/1-2/power");
+        sysroot
+    }
+
+    fn describe(targets: Vec<BindTarget>) -> Vec<(String, Option<String>, Option<String>)> {
+        targets
+            .into_iter()
+            .map(|x| (x.sysfs_busid, x.driver, x.driver_override))
+            .collect()
+    }
+
+    #[test]
+    fn pci_bind_targets() {
+        let sysroot = sysroot();
+        let (bound, overridden, missing) = sysroot.run(|| {
+            (
+                get_bind_targets("pci", "0000:01:00.0"),
+                get_bind_targets("pci", "0000:02:00.0"),
+                get_bind_targets("pci", "0000:03:00.0"),
+            )
+        });
+        // An unset override reads "(null)" and is shown as empty
+        assert_eq!(
+            describe(bound),
+            vec![(
+                String::from("0000:01:00.0"),
+                Some(String::from("nvidia")),
+                Some(String::new())
+            )]
+        );
+        assert_eq!(
+            describe(overridden),
+            vec![(
+                String::from("0000:02:00.0"),
+                None,
+                Some(String::from("vfio-pci"))
+            )]
+        );
+        assert_eq!(
+            describe(missing),
+            vec![(String::from("0000:03:00.0"), None, None)]
+        );
+    }
+
+    #[test]
+    fn usb_bind_targets_include_the_interfaces() {
+        let sysroot = sysroot();
+        assert_eq!(
+            describe(sysroot.run(|| get_bind_targets("usb", "1-2"))),
+            vec![
+                (String::from("1-2"), Some(String::from("usb")), None),
+                (String::from("1-2:1.0"), Some(String::from("btusb")), None),
+                (String::from("1-2:1.1"), None, None),
+            ]
+        );
+    }
+
+    #[test]
+    fn bus_drivers_are_sorted() {
+        let sysroot = sysroot();
+        assert_eq!(
+            sysroot.run(|| get_bus_drivers("pci")),
+            vec!["ahci", "nvidia", "vfio-pci"]
+        );
+        assert!(sysroot.run(|| get_bus_drivers("usb")).is_empty());
+    }
+
+    #[test]
+    fn invalid_names_are_rejected() {
+        for name in ["", "0000:01:00.0; reboot", "../../x", "a b", "a'b", "$(id)"] {
+            assert!(get_unbind_commands("pci", name).is_err(), "{:?}", name);
+            assert!(
+                get_unbind_commands(name, "0000:01:00.0").is_err(),
+                "{:?}",
+                name
+            );
+            assert!(
+                get_bind_commands("pci", name, "vfio-pci").is_err(),
+                "{:?}",
+                name
+            );
+            assert!(get_bind_commands(name, "0000:01:00.0", "vfio-pci").is_err());
+            assert!(get_driver_override_commands(name, "0000:01:00.0", "").is_err());
+        }
+        for driver in ["vfio pci", "vfio-pci'", "nvidia;reboot", "a/b"] {
+            assert!(get_bind_commands("pci", "0000:01:00.0", driver).is_err());
+            assert!(get_driver_override_commands("pci", "0000:01:00.0", driver).is_err());
+        }
+        // Binding needs a driver, an empty override clears it
+        assert!(get_bind_commands("pci", "0000:01:00.0", "").is_err());
+        assert!(get_driver_override_commands("pci", "0000:01:00.0", "").is_ok());
+    }
+
+    #[test]
+    fn bind_command() {
+        assert_eq!(
+            get_bind_commands("usb", "1-2:1.0", "btusb"),
+            Ok(String::from(
+                "if [ ! -e /sys/bus/usb/drivers/btusb ]; then\n  modprobe 'btusb'\nfi\n\
+                 if [ -e /sys/bus/usb/devices/1-2:1.0/driver ]; then\n  echo -n '1-2:1.0' > /sys/bus/usb/devices/1-2:1.0/driver/unbind\nfi\n\
+                 echo -n '1-2:1.0' > /sys/bus/usb/drivers/btusb/bind"
+            ))
+        );
+    }
+}
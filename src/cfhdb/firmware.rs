@@ -0,0 +1,258 @@
+use super::{
+    glob_match,
+    kmod::{bound_module, device_sysfs_dirs, normalize_module_name},
+    sysroot::sysroot_path,
+    ProfileSummary,
+};
+use log::{info, warn};
+use std::{collections::HashMap, process::Command, sync::Mutex};
+
+const FIRMWARE_DIRS: &[&str] = &["/lib/firmware/updates", "/lib/firmware"];
+const FIRMWARE_SUFFIXES: &[&str] = &["", ".xz", ".zst"];
+// Classes whose devices log under their own name, `hci0` rather than the USB interface
+const NAMED_DEVICE_CLASSES: &[&str] = &["bluetooth", "net", "drm", "sound"];
+
+/// A load the kernel log says failed, `iwlwifi 0000:00:14.3: firmware: failed to load ...`.
+struct FirmwareLoadFailure {
+    /// None for subsystem prefixes like `Bluetooth: hci0:`
+    driver: Option<String>,
+    /// The kernel device names the failure could belong to, the logged one and the one it resolves to
+    device_names: Vec<String>,
+    file: String,
+}
+
+/// Firmware a module bound to a device needs but that is not installed.
+#[derive(Clone)]
+pub struct MissingFirmware {
+    pub module: String,
+    pub files: Vec<String>,
+    /// True if the kernel log shows the load failing, false if modinfo lists firmware for the
+    /// module and none of it is installed
+    pub load_failed: bool,
+}
+
+lazy_static::lazy_static! {
+    /// The kernel log is read once, installing firmware later is seen through the file checks.
+    static ref FIRMWARE_LOAD_FAILURES: Vec<FirmwareLoadFailure> = parse_kernel_log(&read_kernel_log());
+    static ref MODULE_FIRMWARE: Mutex<HashMap<String, Vec<String>>> = Mutex::new(HashMap::new());
+}
+
+/// Reads `provides_firmware` of a profile, globs of the firmware files it installs.
+pub fn profile_provides_firmware(profile: &serde_json::Value) -> Vec<String> {
+    match profile["provides_firmware"].as_array() {
+        Some(t) => t
+            .iter()
+            .filter_map(|x| x.as_str())
+            .map(|x| x.to_string())
+            .collect(),
+        None => vec![],
+    }
+}
+
+fn read_kernel_log() -> String {
+    for (program, args) in [
+        ("journalctl", vec!["-k", "-b", "-o", "cat", "--no-pager"]),
+        ("dmesg", vec![]),
+    ] {
+        match Command::new(program).args(&args).output() {
+            Ok(output) if output.status.success() && !output.stdout.is_empty() => {
+                info!(target: "firmware", "Read the kernel log through {}", program);
+                return String::from_utf8_lossy(&output.stdout).to_string();
+            }
+            Ok(_) => warn!(target: "firmware", "{} gave no kernel log", program),
+            Err(e) => warn!(target: "firmware", "Could not run {}: {}", program, e),
+        }
+    }
+    String::new()
+}
+
+fn resolve_device_names(device: &str) -> Vec<String> {
+    let mut device_names = vec![device.to_string()];
+    for class in NAMED_DEVICE_CLASSES {
+        let device_dir = sysroot_path(&format!("/sys/class/{}/{}/device", class, device));
+        if let Some(name) = std::fs::canonicalize(device_dir)
+            .ok()
+            .and_then(|x| Some(x.file_name()?.to_string_lossy().to_string()))
+        {
+            device_names.push(name);
+        }
+    }
+    device_names
+}
+
+/// Picks the failed loads out of the kernel log, both the `firmware: failed to load` error and
+/// the `Direct firmware load for ... failed` warning, once per device and file.
+fn parse_kernel_log(log: &str) -> Vec<FirmwareLoadFailure> {
+    let mut failures: Vec<FirmwareLoadFailure> = vec![];
+    for line in log.lines() {
+        // dmesg prefixes a timestamp, journalctl -o cat does not
+        let line = match line.trim_start().strip_prefix('[') {
+            Some(t) => t.split_once("] ").map(|x| x.1).unwrap_or(t),
+            None => line,
+        };
+        let Some((prefix, file)) = line
+            .split_once(": firmware: failed to load ")
+            .or_else(|| line.split_once(": Direct firmware load for "))
+        else {
+            continue;
+        };
+        let Some(file) = file.split_whitespace().next() else {
+            continue;
+        };
+        // `<driver> <device>`, or `<subsystem>: <device>` for subsystems like Bluetooth
+        let mut prefix_fields = prefix.split_whitespace();
+        let (Some(driver), Some(device)) = (prefix_fields.next(), prefix_fields.last()) else {
+            continue;
+        };
+        if failures
+            .iter()
+            .any(|x| x.device_names[0] == device && x.file == file)
+        {
+            continue;
+        }
+        failures.push(FirmwareLoadFailure {
+            driver: (!driver.ends_with(':')).then(|| normalize_module_name(driver)),
+            device_names: resolve_device_names(device),
+            file: file.to_string(),
+        });
+    }
+    info!(target: "firmware", "Found {} failed firmware loads in the kernel log", failures.len());
+    failures
+}
+
+/// Looks for a firmware file where the kernel does, compressed or not.
+pub fn firmware_installed(file: &str) -> bool {
+    FIRMWARE_DIRS.iter().any(|dir| {
+        FIRMWARE_SUFFIXES
+            .iter()
+            .any(|suffix| sysroot_path(&format!("{}/{}{}", dir, file, suffix)).exists())
+    })
+}
+
+/// Drivers like iwlwifi ask for the newest API version first and fall back to older ones, so a
+/// failed `<name>-<version>.<ext>` load does not count if another version of the file is installed.
+fn other_version_installed(file: &str) -> bool {
+    let (dir, name) = file.rsplit_once('/').unwrap_or(("", file));
+    let Some((stem, version)) = name
+        .split_once('.')
+        .unwrap_or((name, ""))
+        .0
+        .rsplit_once('-')
+    else {
+        return false;
+    };
+    if version.is_empty() || !version.chars().all(|x| x.is_ascii_digit()) {
+        return false;
+    }
+    FIRMWARE_DIRS.iter().any(|firmware_dir| {
+        std::fs::read_dir(sysroot_path(&format!("{}/{}", firmware_dir, dir)))
+            .map(|entries| {
+                entries.flatten().any(|x| {
+                    x.file_name()
+                        .to_string_lossy()
+                        .starts_with(&format!("{}-", stem))
+                })
+            })
+            .unwrap_or_default()
+    })
+}
+
+/// The firmware files `modinfo` lists for a module, looked up once per module.
+fn module_firmware(module: &str) -> Vec<String> {
+    let mut cache = MODULE_FIRMWARE.lock().unwrap();
+    if let Some(files) = cache.get(module) {
+        return files.clone();
+    }
+    let basedir = sysroot_path("/");
+    let files: Vec<String> = match Command::new("modinfo")
+        .arg("-b")
+        .arg(&basedir)
+        .args(["-F", "firmware", module])
+        .output()
+    {
+        Ok(output) if output.status.success() => String::from_utf8_lossy(&output.stdout)
+            .lines()
+            .map(|x| x.trim().to_string())
+            .filter(|x| !x.is_empty())
+            .collect(),
+        _ => vec![],
+    };
+    cache.insert(module.to_string(), files.clone());
+    files
+}
+
+/// Returns the firmware missing for a device, from failed loads in the kernel log and from the
+/// firmware lists of the modules bound to it.
+pub fn get_device_missing_firmware(bus: &str, sysfs_busid: &str) -> Vec<MissingFirmware> {
+    let dirs = device_sysfs_dirs(bus, sysfs_busid);
+    let device_names: Vec<String> = dirs
+        .iter()
+        .filter_map(|x| Some(x.file_name()?.to_string_lossy().to_string()))
+        .collect();
+    let mut modules: Vec<String> = vec![];
+    for dir in &dirs {
+        if let Some((module, _)) = bound_module(dir) {
+            if !modules.contains(&module) {
+                modules.push(module);
+            }
+        }
+    }
+
+    let mut missing_firmware: Vec<MissingFirmware> = vec![];
+    for failure in FIRMWARE_LOAD_FAILURES.iter() {
+        if !failure
+            .device_names
+            .iter()
+            .any(|x| device_names.contains(x))
+            || firmware_installed(&failure.file)
+            || other_version_installed(&failure.file)
+        {
+            continue;
+        }
+        let Some(module) = failure.driver.clone().or_else(|| modules.first().cloned()) else {
+            continue;
+        };
+        match missing_firmware.iter_mut().find(|x| x.module == module) {
+            Some(t) => t.files.push(failure.file.clone()),
+            None => missing_firmware.push(MissingFirmware {
+                module,
+                files: vec![failure.file.clone()],
+                load_failed: true,
+            }),
+        }
+    }
+
+    for module in modules {
+        if missing_firmware.iter().any(|x| x.module == module) {
+            continue;
+        }
+        let files = module_firmware(&module);
+        if !files.is_empty() && !files.iter().any(|x| firmware_installed(x)) {
+            missing_firmware.push(MissingFirmware {
+                module,
+                files,
+                load_failed: false,
+            });
+        }
+    }
+
+    missing_firmware
+}
+
+/// The first profile whose `provides_firmware` covers one of the missing files.
+pub fn providing_profile(
+    missing_firmware: &MissingFirmware,
+    profiles: &[ProfileSummary],
+) -> Option<ProfileSummary> {
+    profiles
+        .iter()
+        .find(|profile| {
+            profile.provides_firmware.iter().any(|pattern| {
+                missing_firmware
+                    .files
+                    .iter()
+                    .any(|file| glob_match(pattern, file))
+            })
+        })
+        .cloned()
+}
@@ -0,0 +1,355 @@
+use super::{
+    kmod::bound_module, storage::parent_bus_device, sysfs_bus::SysfsBusDevice,
+    sysroot::sysroot_path, usb::read_sysfs_value,
+};
+use crate::config::INPUT_PROFILE_JSON_URL;
+use std::path::{Path, PathBuf};
+
+/// An input device from /sys/class/input, or a HID device only reachable through hidraw.
+#[derive(Clone)]
+pub struct InputDevice {
+    /// `input12`, or `hidraw3` for HID devices without an input device
+    pub id: String,
+    pub name: String,
+    /// `usb`, `bluetooth`, `i2c`, `i8042` and so on, from the bus type of the input ID
+    pub bus_type: String,
+    /// Four lower case hex digits like the USB vendor IDs, the same for a controller on USB and BT
+    pub vendor_id: String,
+    pub product_id: String,
+    pub version: Option<String>,
+    /// The name the HID descriptor reports, which can differ from the input device name
+    pub hid_name: Option<String>,
+    pub hidraw_nodes: Vec<String>,
+    pub event_nodes: Vec<String>,
+    /// What the device can do, `joystick`, `tablet`, `keyboard` and so on, the first one is its class
+    pub capabilities: Vec<String>,
+    pub driver: Option<String>,
+    pub phys: Option<String>,
+    /// The Bluetooth address for Bluetooth devices, a serial number or nothing for the others
+    pub uniq: Option<String>,
+    pub parent: Option<(String, String)>,
+}
+
+// Event types, keys and properties from linux/input-event-codes.h
+const EV_KEY: usize = 0x01;
+const EV_REL: usize = 0x02;
+const EV_ABS: usize = 0x03;
+const EV_SW: usize = 0x05;
+const EV_FF: usize = 0x15;
+const ABS_X: usize = 0x00;
+const ABS_Y: usize = 0x01;
+const REL_X: usize = 0x00;
+const REL_Y: usize = 0x01;
+const BTN_MOUSE: usize = 0x110;
+const BTN_JOYSTICK: usize = 0x120;
+const BTN_GAMEPAD: usize = 0x130;
+const BTN_TOOL_PEN: usize = 0x140;
+const BTN_TOOL_FINGER: usize = 0x145;
+const BTN_TOUCH: usize = 0x14a;
+const BTN_STYLUS: usize = 0x14b;
+const INPUT_PROP_DIRECT: usize = 0x01;
+const INPUT_PROP_ACCELEROMETER: usize = 0x06;
+
+/// A capability bitmap as sysfs prints it, hex longs with the most significant one first.
+struct Bitmap(Vec<u64>);
+
+impl Bitmap {
+    fn read(dir: &Path, file: &str) -> Self {
+        let words = read_sysfs_value(dir, file).unwrap_or_default();
+        Self(
+            words
+                .split_whitespace()
+                .rev()
+                .map(|x| u64::from_str_radix(x, 16).unwrap_or_default())
+                .collect(),
+        )
+    }
+
+    fn has(&self, bit: usize) -> bool {
+        self.0
+            .get(bit / 64)
+            .is_some_and(|x| x & (1 << (bit % 64)) != 0)
+    }
+}
+
+/// Classifies the device the way udev's input_id does, so the names match what users see elsewhere.
+fn capabilities(input_dir: &Path) -> Vec<String> {
+    let capabilities_dir = input_dir.join("capabilities");
+    let ev = Bitmap::read(&capabilities_dir, "ev");
+    let key = Bitmap::read(&capabilities_dir, "key");
+    let abs = Bitmap::read(&capabilities_dir, "abs");
+    let rel = Bitmap::read(&capabilities_dir, "rel");
+    let properties = Bitmap::read(input_dir, "properties");
+
+    let mut capabilities = vec![];
+    let has_abs_xy = ev.has(EV_ABS) && abs.has(ABS_X) && abs.has(ABS_Y);
+    if properties.has(INPUT_PROP_ACCELEROMETER) {
+        capabilities.push("accelerometer");
+    } else if has_abs_xy {
+        if key.has(BTN_STYLUS) || key.has(BTN_TOOL_PEN) {
+            capabilities.push("tablet");
+        } else if key.has(BTN_TOOL_FINGER) && !properties.has(INPUT_PROP_DIRECT) {
+            capabilities.push("touchpad");
+        } else if key.has(BTN_TOUCH) || properties.has(INPUT_PROP_DIRECT) {
+            capabilities.push("touchscreen");
+        } else if key.has(BTN_MOUSE) {
+            capabilities.push("mouse");
+        }
+    }
+    if (BTN_JOYSTICK..BTN_GAMEPAD + 0x10).any(|x| key.has(x)) {
+        capabilities.insert(0, "joystick");
+    }
+    if ev.has(EV_REL)
+        && rel.has(REL_X)
+        && rel.has(REL_Y)
+        && key.has(BTN_MOUSE)
+        && !capabilities.contains(&"mouse")
+    {
+        capabilities.push("mouse");
+    }
+    // A full keyboard has every key of the first block, power buttons and remotes only have a few keys
+    if ev.has(EV_KEY) {
+        if (1..32).all(|x| key.has(x)) {
+            capabilities.push("keyboard");
+        } else if (1..BTN_MOUSE).any(|x| key.has(x)) {
+            capabilities.push("keys");
+        }
+    }
+    if ev.has(EV_SW) {
+        capabilities.push("switch");
+    }
+    if ev.has(EV_FF) {
+        capabilities.push("force_feedback");
+    }
+    capabilities.into_iter().map(String::from).collect()
+}
+
+/// The bus types of linux/input.h, the ones an input device is likely to use.
+fn bus_type_name(bus_type: &str) -> String {
+    match u16::from_str_radix(bus_type, 16).unwrap_or_default() {
+        0x01 => "pci",
+        0x03 => "usb",
+        0x05 => "bluetooth",
+        0x06 => "virtual",
+        0x11 => "i8042",
+        0x18 => "i2c",
+        0x19 => "host",
+        0x1c => "spi",
+        0x1f => "rmi",
+        _ => "unknown",
+    }
+    .to_string()
+}
+
+fn hex_id(dir: &Path, file: &str) -> String {
+    format!(
+        "{:04x}",
+        u16::from_str_radix(&read_sysfs_value(dir, file).unwrap_or_default(), 16)
+            .unwrap_or_default()
+    )
+}
+
+/// HID devices are named after their ID, `0005:045E:02FD.0003`, the uevent has the rest.
+fn hid_uevent_value(hid_dir: &Path, key: &str) -> Option<String> {
+    read_sysfs_value(hid_dir, "uevent")?
+        .lines()
+        .find_map(|x| x.strip_prefix(&format!("{}=", key)).map(String::from))
+        .filter(|x| !x.is_empty())
+}
+
+fn child_nodes(dir: &Path, prefix: &str) -> Vec<String> {
+    let mut nodes: Vec<String> = match std::fs::read_dir(dir) {
+        Ok(entries) => entries
+            .flatten()
+            .map(|x| x.file_name().to_string_lossy().to_string())
+            .filter(|x| x.starts_with(prefix))
+            .collect(),
+        Err(_) => vec![],
+    };
+    nodes.sort();
+    nodes
+}
+
+/// Bluetooth devices link to their Bluetooth device page, the others to the USB or PCI device.
+fn parent_device(
+    device_dir: &Path,
+    bus_type: &str,
+    uniq: &Option<String>,
+) -> Option<(String, String)> {
+    match (bus_type, uniq) {
+        ("bluetooth", Some(address)) => Some(("bt".to_string(), address.to_uppercase())),
+        ("bluetooth", None) | ("virtual", _) => None,
+        _ => parent_bus_device(device_dir),
+    }
+}
+
+fn read_input_device(input_dir: PathBuf) -> Option<InputDevice> {
+    let id = input_dir.file_name()?.to_string_lossy().to_string();
+    let id_dir = input_dir.join("id");
+    let bus_type = bus_type_name(&read_sysfs_value(&id_dir, "bustype")?);
+    // uinput devices are made up by user space tools, the real device is listed on its own
+    if bus_type == "virtual" {
+        return None;
+    }
+    let device_dir = input_dir.join("device");
+    let uniq = read_sysfs_value(&input_dir, "uniq");
+    Some(InputDevice {
+        name: read_sysfs_value(&input_dir, "name").unwrap_or_else(|| id.clone()),
+        vendor_id: hex_id(&id_dir, "vendor"),
+        product_id: hex_id(&id_dir, "product"),
+        version: read_sysfs_value(&id_dir, "version"),
+        hid_name: hid_uevent_value(&device_dir, "HID_NAME"),
+        hidraw_nodes: child_nodes(&device_dir.join("hidraw"), "hidraw"),
+        event_nodes: child_nodes(&input_dir, "event"),
+        capabilities: capabilities(&input_dir),
+        driver: bound_module(&device_dir).map(|x| x.0),
+        phys: read_sysfs_value(&input_dir, "phys"),
+        parent: parent_device(&device_dir, &bus_type, &uniq),
+        uniq,
+        bus_type,
+        id,
+    })
+}
+
+/// A HID device without an input device, a vendor specific interface or one no driver claimed yet.
+fn read_hidraw_device(hidraw_dir: PathBuf) -> Option<InputDevice> {
+    let id = hidraw_dir.file_name()?.to_string_lossy().to_string();
+    let hid_dir = hidraw_dir.join("device");
+    if hid_dir.join("input").exists() {
+        return None;
+    }
+    // HID_ID is bus, vendor and product, each zero padded to eight hex digits
+    let hid_id = hid_uevent_value(&hid_dir, "HID_ID")?;
+    let mut hid_id_parts = hid_id.split(':');
+    let mut next_part =
+        || u32::from_str_radix(hid_id_parts.next().unwrap_or_default(), 16).unwrap_or_default();
+    let (bus_type, vendor_id, product_id) = (next_part(), next_part(), next_part());
+    let bus_type = bus_type_name(&format!("{:x}", bus_type));
+    let uniq = hid_uevent_value(&hid_dir, "HID_UNIQ");
+    let hid_name = hid_uevent_value(&hid_dir, "HID_NAME");
+    Some(InputDevice {
+        name: hid_name.clone().unwrap_or_else(|| id.clone()),
+        vendor_id: format!("{:04x}", vendor_id),
+        product_id: format!("{:04x}", product_id),
+        version: None,
+        hid_name,
+        hidraw_nodes: vec![id.clone()],
+        event_nodes: vec![],
+        capabilities: vec![],
+        driver: bound_module(&hid_dir).map(|x| x.0),
+        phys: hid_uevent_value(&hid_dir, "HID_PHYS"),
+        parent: parent_device(&hid_dir, &bus_type, &uniq),
+        uniq,
+        bus_type,
+        id,
+    })
+}
+
+fn read_class_devices(
+    class: &str,
+    read_device: fn(PathBuf) -> Option<InputDevice>,
+) -> Vec<InputDevice> {
+    match std::fs::read_dir(sysroot_path(&format!("/sys/class/{}", class))) {
+        Ok(entries) => entries
+            .flatten()
+            // /sys/class/input also has the event and js nodes of each input device
+            .filter(|x| x.file_name().to_string_lossy().starts_with(class))
+            .filter_map(|x| read_device(x.path()))
+            .collect(),
+        Err(_) => vec![],
+    }
+}
+
+impl SysfsBusDevice for InputDevice {
+    const BUS: &'static str = "input";
+    const MATCH_KEYS: &'static [&'static str] =
+        &["vendor_ids", "product_ids", "names", "capabilities"];
+
+    fn enumerate() -> Vec<Self> {
+        let mut devices = read_class_devices("input", read_input_device);
+        devices.extend(read_class_devices("hidraw", read_hidraw_device));
+        devices
+    }
+
+    fn profile_url() -> Option<String> {
+        INPUT_PROFILE_JSON_URL.clone()
+    }
+
+    fn id(&self) -> String {
+        self.id.clone()
+    }
+
+    fn class(&self) -> String {
+        self.capabilities
+            .first()
+            .cloned()
+            .unwrap_or_else(|| "other".to_string())
+    }
+
+    fn title(&self) -> String {
+        self.name.clone()
+    }
+
+    fn subtitle(&self) -> String {
+        format!(
+            "{} - {}:{} - {}",
+            self.id,
+            self.vendor_id,
+            self.product_id,
+            t!(format!("input_bus_type_{}", self.bus_type))
+        )
+    }
+
+    fn details(&self) -> Vec<(String, String)> {
+        let mut details = vec![
+            ("input_detail_name".to_string(), self.name.clone()),
+            (
+                "input_detail_bus_type".to_string(),
+                t!(format!("input_bus_type_{}", self.bus_type)).to_string(),
+            ),
+            ("input_detail_vendor_id".to_string(), self.vendor_id.clone()),
+            (
+                "input_detail_product_id".to_string(),
+                self.product_id.clone(),
+            ),
+        ];
+        for (key, value) in [
+            ("input_detail_version", &self.version),
+            ("input_detail_hid_name", &self.hid_name),
+            ("input_detail_driver", &self.driver),
+            ("input_detail_phys", &self.phys),
+            ("input_detail_uniq", &self.uniq),
+        ] {
+            if let Some(value) = value {
+                details.push((key.to_string(), value.clone()));
+            }
+        }
+        for (key, values) in [
+            ("input_detail_capabilities", &self.capabilities),
+            ("input_detail_event_nodes", &self.event_nodes),
+            ("input_detail_hidraw_nodes", &self.hidraw_nodes),
+        ] {
+            if !values.is_empty() {
+                details.push((key.to_string(), values.join(", ")));
+            }
+        }
+        details
+    }
+
+    fn match_values(&self, key: &str) -> Vec<String> {
+        match key {
+            "vendor_ids" => vec![self.vendor_id.clone()],
+            "product_ids" => vec![self.product_id.clone()],
+            "names" => [Some(self.name.clone()), self.hid_name.clone()]
+                .into_iter()
+                .flatten()
+                .collect(),
+            "capabilities" => self.capabilities.clone(),
+            _ => vec![],
+        }
+    }
+
+    fn parent_device(&self) -> Option<(String, String)> {
+        self.parent.clone()
+    }
+}
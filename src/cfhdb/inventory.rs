@@ -0,0 +1,306 @@
+use super::{
+    bt::PreCheckedBtDevice,
+    dmi::PreCheckedDmiInfo,
+    pci::{get_pci_advanced_details, PreCheckedPciDevice},
+    sysfs_bus::SysfsBuses,
+    usb::PreCheckedUsbDevice,
+    PreCheckedProfile,
+};
+use std::sync::Arc;
+
+pub const INVENTORY_VERSION: u32 = 1;
+
+/// The hardware of one machine as seen by the driver manager, saved so it can be compared with another machine.
+#[derive(serde::Serialize, serde::Deserialize)]
+pub struct InventorySnapshot {
+    pub version: u32,
+    /// (field, value) of every DMI field, in the order of the DMI page
+    pub dmi: Vec<(String, String)>,
+    pub devices: Vec<InventoryDevice>,
+    /// `bus:codename` of every installed profile
+    pub installed_profiles: Vec<String>,
+}
+
+#[derive(serde::Serialize, serde::Deserialize, Clone)]
+pub struct InventoryDevice {
+    pub bus: String,
+    pub class: String,
+    /// `bus:vendor:device`, or `bus:id` for the sysfs buses, the key devices are compared by
+    pub id: String,
+    pub name: String,
+    pub kernel_driver: String,
+    /// (field, value) of the PCI advanced details, empty for other buses and older snapshots
+    #[serde(default)]
+    pub details: Vec<(String, String)>,
+}
+
+impl InventoryDevice {
+    fn describe(&self) -> String {
+        format!("[{}] {} ({})", self.bus.to_uppercase(), self.name, self.id)
+    }
+}
+
+pub struct InventoryDiff {
+    /// (field, local value, other value)
+    pub dmi: Vec<(String, String, String)>,
+    pub only_local: Vec<InventoryDevice>,
+    pub only_other: Vec<InventoryDevice>,
+    /// (local device, the other machine's driver for the same device)
+    pub driver_differences: Vec<(InventoryDevice, String)>,
+    pub profiles_only_local: Vec<String>,
+    pub profiles_only_other: Vec<String>,
+}
+
+impl InventoryDiff {
+    pub fn is_empty(&self) -> bool {
+        self.dmi.is_empty()
+            && self.only_local.is_empty()
+            && self.only_other.is_empty()
+            && self.driver_differences.is_empty()
+            && self.profiles_only_local.is_empty()
+            && self.profiles_only_other.is_empty()
+    }
+
+    /// A unified diff like rendering, `-` is this machine and `+` the other one.
+    pub fn to_text(&self, other_name: &str) -> String {
+        let mut lines = vec![
+            format!("--- {}", t!("inventory_this_machine")),
+            format!("+++ {}", other_name),
+        ];
+        if self.is_empty() {
+            lines.push(t!("inventory_no_differences").to_string());
+        }
+        if !self.dmi.is_empty() {
+            lines.push(format!("\n{}", t!("inventory_dmi_title")));
+            for (field, local_value, other_value) in &self.dmi {
+                let field_name = t!(format!("info_{}", field));
+                lines.push(format!("- {}: {}", field_name, local_value));
+                lines.push(format!("+ {}: {}", field_name, other_value));
+            }
+        }
+        if !self.only_local.is_empty() || !self.only_other.is_empty() {
+            lines.push(format!("\n{}", t!("inventory_devices_title")));
+            for device in &self.only_local {
+                lines.push(format!("- {}", device.describe()));
+            }
+            for device in &self.only_other {
+                lines.push(format!("+ {}", device.describe()));
+            }
+        }
+        if !self.driver_differences.is_empty() {
+            lines.push(format!("\n{}", t!("inventory_drivers_title")));
+            for (device, other_driver) in &self.driver_differences {
+                lines.push(format!(
+                    "~ {}: {} -> {}",
+                    device.describe(),
+                    driver_or_none(&device.kernel_driver),
+                    driver_or_none(other_driver)
+                ));
+            }
+        }
+        if !self.profiles_only_local.is_empty() || !self.profiles_only_other.is_empty() {
+            lines.push(format!("\n{}", t!("inventory_profiles_title")));
+            for profile in &self.profiles_only_local {
+                lines.push(format!("- {}", profile));
+            }
+            for profile in &self.profiles_only_other {
+                lines.push(format!("+ {}", profile));
+            }
+        }
+        lines.join("\n")
+    }
+}
+
+pub fn driver_or_none(kernel_driver: &str) -> String {
+    if kernel_driver.trim().is_empty() {
+        t!("inventory_no_driver").to_string()
+    } else {
+        kernel_driver.to_string()
+    }
+}
+
+pub fn get_inventory_snapshot(
+    hashmap_pci: &Option<Vec<(String, Vec<PreCheckedPciDevice>)>>,
+    hashmap_usb: &Option<Vec<(String, Vec<PreCheckedUsbDevice>)>>,
+    dmi_info: &PreCheckedDmiInfo,
+    hashmap_bt: &Option<Vec<(String, Vec<PreCheckedBtDevice>)>>,
+    sysfs_buses: &SysfsBuses,
+    all_profiles: &[Arc<dyn PreCheckedProfile>],
+) -> InventorySnapshot {
+    let info = &dmi_info.info;
+    let dmi = [
+        ("bios_date", &info.bios_date),
+        ("bios_release", &info.bios_release),
+        ("bios_vendor", &info.bios_vendor),
+        ("bios_version", &info.bios_version),
+        ("board_asset_tag", &info.board_asset_tag),
+        ("board_name", &info.board_name),
+        ("board_vendor", &info.board_vendor),
+        ("board_version", &info.board_version),
+        ("product_family", &info.product_family),
+        ("product_name", &info.product_name),
+        ("product_sku", &info.product_sku),
+        ("product_version", &info.product_version),
+        ("sys_vendor", &info.sys_vendor),
+    ]
+    .into_iter()
+    .map(|(field, value)| (field.to_string(), value.clone()))
+    .collect();
+
+    let mut devices = vec![];
+    if let Some(hashmap_pci) = hashmap_pci {
+        for (class, class_devices) in hashmap_pci {
+            for device in class_devices {
+                let device = &device.device;
+                devices.push(InventoryDevice {
+                    bus: String::from("pci"),
+                    class: class.clone(),
+                    id: format!("pci:{}:{}", device.vendor_id, device.device_id),
+                    name: format!("{} - {}", device.vendor_name, device.device_name),
+                    kernel_driver: device.kernel_driver.clone(),
+                    details: get_pci_advanced_details(&device.sysfs_busid),
+                });
+            }
+        }
+    }
+    if let Some(hashmap_usb) = hashmap_usb {
+        for (class, class_devices) in hashmap_usb {
+            for device in class_devices {
+                let device = &device.device;
+                devices.push(InventoryDevice {
+                    bus: String::from("usb"),
+                    class: class.clone(),
+                    id: format!("usb:{}:{}", device.vendor_id, device.product_id),
+                    name: format!(
+                        "{} - {}",
+                        device.manufacturer_string_index, device.product_string_index
+                    ),
+                    kernel_driver: device.kernel_driver.clone(),
+                    details: vec![],
+                });
+            }
+        }
+    }
+    if let Some(hashmap_bt) = hashmap_bt {
+        for (class, class_devices) in hashmap_bt {
+            for device in class_devices {
+                let device = &device.device;
+                devices.push(InventoryDevice {
+                    bus: String::from("bt"),
+                    class: class.clone(),
+                    id: format!(
+                        "bt:{}:{}",
+                        device.modalias_vendor_id, device.modalias_product_id
+                    ),
+                    name: format!("{} - {}", device.alias, device.name),
+                    kernel_driver: String::new(),
+                    details: vec![],
+                });
+            }
+        }
+    }
+    for bus in sysfs_buses.buses() {
+        for device in bus.device_summaries() {
+            devices.push(InventoryDevice {
+                bus: bus.bus().to_string(),
+                class: device.class,
+                id: format!("{}:{}", bus.bus(), device.id),
+                name: device.title,
+                kernel_driver: String::new(),
+                details: vec![],
+            });
+        }
+    }
+
+    let installed_profiles = all_profiles
+        .iter()
+        .filter(|x| x.is_installed())
+        .map(|x| {
+            let summary = x.summary();
+            format!("{}:{}", summary.bus, summary.codename)
+        })
+        .collect();
+
+    InventorySnapshot {
+        version: INVENTORY_VERSION,
+        dmi,
+        devices,
+        installed_profiles,
+    }
+}
+
+pub fn read_inventory_snapshot(file_path: &std::path::Path) -> Result<InventorySnapshot, String> {
+    let json_content = std::fs::read_to_string(file_path).map_err(|e| e.to_string())?;
+    let snapshot: InventorySnapshot =
+        serde_json::from_str(&json_content).map_err(|e| e.to_string())?;
+    if snapshot.version > INVENTORY_VERSION {
+        return Err(t!("inventory_unsupported_version", version = snapshot.version).to_string());
+    }
+    Ok(snapshot)
+}
+
+pub fn write_inventory_snapshot(
+    file_path: &std::path::Path,
+    snapshot: &InventorySnapshot,
+) -> Result<(), String> {
+    let json_content = serde_json::to_string_pretty(snapshot).map_err(|e| e.to_string())?;
+    std::fs::write(file_path, json_content + "\n").map_err(|e| e.to_string())
+}
+
+pub fn compare_inventory_snapshots(
+    local: &InventorySnapshot,
+    other: &InventorySnapshot,
+) -> InventoryDiff {
+    let dmi = local
+        .dmi
+        .iter()
+        .filter_map(|(field, local_value)| {
+            let other_value = other
+                .dmi
+                .iter()
+                .find(|(other_field, _)| other_field == field)
+                .map(|(_, x)| x.clone())
+                .unwrap_or_default();
+            (*local_value != other_value).then(|| (field.clone(), local_value.clone(), other_value))
+        })
+        .collect();
+
+    // Identical devices can show up more than once, so every device of the other machine is paired at most once
+    let mut other_unpaired: Vec<&InventoryDevice> = other.devices.iter().collect();
+    let mut only_local = vec![];
+    let mut driver_differences = vec![];
+    for device in &local.devices {
+        match other_unpaired.iter().position(|x| x.id == device.id) {
+            Some(index) => {
+                let other_device = other_unpaired.remove(index);
+                if other_device.kernel_driver != device.kernel_driver {
+                    driver_differences.push((device.clone(), other_device.kernel_driver.clone()));
+                }
+            }
+            None => only_local.push(device.clone()),
+        }
+    }
+    let only_other = other_unpaired.into_iter().cloned().collect();
+
+    let profiles_only_local = local
+        .installed_profiles
+        .iter()
+        .filter(|x| !other.installed_profiles.contains(x))
+        .cloned()
+        .collect();
+    let profiles_only_other = other
+        .installed_profiles
+        .iter()
+        .filter(|x| !local.installed_profiles.contains(x))
+        .cloned()
+        .collect();
+
+    InventoryDiff {
+        dmi,
+        only_local,
+        only_other,
+        driver_differences,
+        profiles_only_local,
+        profiles_only_other,
+    }
+}
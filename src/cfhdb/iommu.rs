@@ -0,0 +1,245 @@
+use super::{
+    kargs::{get_cmdline_args, get_kernel_args_commands, is_ostree_booted},
+    kmod::{bound_module, normalize_module_name},
+    sysroot::sysroot_path,
+};
+
+pub const VFIO_MODPROBE_PATH: &str = "/etc/modprobe.d/nobara-driver-manager-vfio.conf";
+pub const VFIO_DRACUT_PATH: &str = "/etc/dracut.conf.d/nobara-driver-manager-vfio.conf";
+
+pub struct IommuDevice {
+    pub sysfs_busid: String,
+    pub vendor_id: String,
+    pub device_id: String,
+    /// Base class and subclass, `0300` for a VGA controller
+    pub class_id: String,
+    /// The module of the bound driver, None if unbound or built in
+    pub module: Option<String>,
+}
+
+impl IommuDevice {
+    /// Bridges stay with the host, only the devices behind them are passed through.
+    pub fn is_bridge(&self) -> bool {
+        self.class_id.starts_with("06")
+    }
+}
+
+pub struct IommuGroup {
+    pub id: u32,
+    pub devices: Vec<IommuDevice>,
+}
+
+/// A file the VFIO setup writes, with what is there now so the change can be previewed.
+pub struct FileChange {
+    pub path: String,
+    pub old_content: Option<String>,
+    pub new_content: String,
+}
+
+impl FileChange {
+    /// A line based diff, `-` for removed lines and `+` for added ones.
+    pub fn preview(&self) -> String {
+        let old_lines: Vec<&str> = self
+            .old_content
+            .as_deref()
+            .map(|x| x.lines().collect())
+            .unwrap_or_default();
+        let new_lines: Vec<&str> = self.new_content.lines().collect();
+        let mut preview: Vec<String> = old_lines
+            .iter()
+            .filter(|x| !new_lines.contains(x))
+            .map(|x| format!("- {}", x))
+            .collect();
+        preview.extend(new_lines.iter().map(|x| {
+            if old_lines.contains(x) {
+                format!("  {}", x)
+            } else {
+                format!("+ {}", x)
+            }
+        }));
+        preview.join("\n")
+    }
+}
+
+pub struct VfioPlan {
+    /// `vendor:device` IDs vfio-pci claims, including the ones set up before
+    pub ids: Vec<String>,
+    pub file_changes: Vec<FileChange>,
+    /// Kernel arguments that are not on the command line yet
+    pub kernel_args_add: Vec<String>,
+    pub script: String,
+}
+
+fn read_sysfs_hex(path: &std::path::Path) -> String {
+    std::fs::read_to_string(path)
+        .unwrap_or_default()
+        .trim()
+        .trim_start_matches("0x")
+        .to_string()
+}
+
+pub fn get_iommu_groups() -> Vec<IommuGroup> {
+    let Ok(entries) = std::fs::read_dir(sysroot_path("/sys/kernel/iommu_groups")) else {
+        return vec![];
+    };
+    let mut groups: Vec<IommuGroup> = entries
+        .flatten()
+        .filter_map(|group_entry| {
+            let id = group_entry.file_name().to_string_lossy().parse().ok()?;
+            let mut devices: Vec<IommuDevice> =
+                std::fs::read_dir(group_entry.path().join("devices"))
+                    .ok()?
+                    .flatten()
+                    .map(|device_entry| {
+                        let device_dir = device_entry.path();
+                        let class = read_sysfs_hex(&device_dir.join("class"));
+                        IommuDevice {
+                            sysfs_busid: device_entry.file_name().to_string_lossy().to_string(),
+                            vendor_id: read_sysfs_hex(&device_dir.join("vendor")),
+                            device_id: read_sysfs_hex(&device_dir.join("device")),
+                            class_id: class.chars().take(4).collect(),
+                            module: bound_module(&device_dir)
+                                .and_then(|(name, builtin)| (!builtin).then_some(name)),
+                        }
+                    })
+                    .collect();
+            devices.sort_by(|a, b| a.sysfs_busid.cmp(&b.sysfs_busid));
+            Some(IommuGroup { id, devices })
+        })
+        .collect();
+    groups.sort_by_key(|x| x.id);
+    groups
+}
+
+/// The IOMMU related arguments on the kernel command line.
+pub fn get_iommu_cmdline_args() -> Vec<String> {
+    get_cmdline_args()
+        .into_iter()
+        .filter(|x| {
+            x.starts_with("intel_iommu=")
+                || x.starts_with("amd_iommu=")
+                || x.starts_with("iommu=")
+                || x.starts_with("iommu.")
+                || x.starts_with("vfio-pci.")
+                || x.starts_with("vfio_pci.")
+        })
+        .collect()
+}
+
+fn cpu_vendor() -> String {
+    std::fs::read_to_string(sysroot_path("/proc/cpuinfo"))
+        .unwrap_or_default()
+        .lines()
+        .find_map(|line| {
+            let (key, value) = line.split_once(':')?;
+            (key.trim() == "vendor_id").then(|| value.trim().to_string())
+        })
+        .unwrap_or_default()
+}
+
+/// Works out the files and kernel arguments that make vfio-pci claim every device of the group at boot.
+/// Groups set up before are kept, since the modprobe configuration is shared.
+pub fn plan_vfio_passthrough(group: &IommuGroup) -> Result<VfioPlan, String> {
+    let passthrough_devices: Vec<&IommuDevice> =
+        group.devices.iter().filter(|x| !x.is_bridge()).collect();
+    if passthrough_devices.is_empty() {
+        return Err(t!("iommu_vfio_no_devices", group = group.id).to_string());
+    }
+
+    let old_modprobe_content = std::fs::read_to_string(sysroot_path(VFIO_MODPROBE_PATH)).ok();
+    let mut ids: Vec<String> = vec![];
+    let mut softdeps: Vec<String> = vec![];
+    for line in old_modprobe_content.as_deref().unwrap_or_default().lines() {
+        let fields: Vec<&str> = line.split_whitespace().collect();
+        match fields.as_slice() {
+            ["options", "vfio-pci" | "vfio_pci", options @ ..] => {
+                for option in options {
+                    if let Some(x) = option.strip_prefix("ids=") {
+                        ids.extend(x.split(',').map(|y| y.to_string()));
+                    }
+                }
+            }
+            ["softdep", module, "pre:", ..] => softdeps.push(module.to_string()),
+            _ => {}
+        }
+    }
+    for device in &passthrough_devices {
+        let id = format!("{}:{}", device.vendor_id, device.device_id);
+        if !ids.contains(&id) {
+            ids.push(id);
+        }
+        // The host driver has to wait for vfio-pci, or it claims the device first
+        if let Some(module) = &device.module {
+            if module != "vfio_pci" && !softdeps.contains(module) {
+                softdeps.push(module.clone());
+            }
+        }
+    }
+
+    let mut modprobe_lines = vec![
+        String::from("# Managed by Nobara Driver Manager"),
+        format!("options vfio-pci ids={}", ids.join(",")),
+    ];
+    modprobe_lines.extend(
+        softdeps
+            .iter()
+            .map(|x| format!("softdep {} pre: vfio-pci", normalize_module_name(x))),
+    );
+    let file_changes = vec![
+        FileChange {
+            path: VFIO_MODPROBE_PATH.to_string(),
+            old_content: old_modprobe_content,
+            new_content: modprobe_lines.join("\n") + "\n",
+        },
+        FileChange {
+            path: VFIO_DRACUT_PATH.to_string(),
+            old_content: std::fs::read_to_string(sysroot_path(VFIO_DRACUT_PATH)).ok(),
+            new_content: String::from(
+                "# Managed by Nobara Driver Manager\nforce_drivers+=\" vfio_pci vfio vfio_iommu_type1 \"\n",
+            ),
+        },
+    ];
+
+    let cmdline_args = get_cmdline_args();
+    let mut wanted_args = vec![];
+    match cpu_vendor().as_str() {
+        "GenuineIntel" => wanted_args.push("intel_iommu=on"),
+        "AuthenticAMD" => wanted_args.push("amd_iommu=on"),
+        _ => {}
+    }
+    wanted_args.extend(["iommu=pt", "rd.driver.pre=vfio-pci"]);
+    let kernel_args_add: Vec<String> = wanted_args
+        .into_iter()
+        .filter(|x| !cmdline_args.iter().any(|y| y == x))
+        .map(|x| x.to_string())
+        .collect();
+
+    let mut script_lines = vec![];
+    for file_change in &file_changes {
+        script_lines.push(format!(
+            "mkdir -p {}\ncat > {} <<'NDM_VFIO_EOF'\n{}NDM_VFIO_EOF",
+            std::path::Path::new(&file_change.path)
+                .parent()
+                .unwrap()
+                .display(),
+            file_change.path,
+            file_change.new_content
+        ));
+    }
+    if let Some(t) = get_kernel_args_commands(&kernel_args_add, &[])? {
+        script_lines.push(t);
+    }
+    script_lines.push(String::from(if is_ostree_booted() {
+        // Already enabled client side regeneration picks up the new files on the next deployment
+        "rpm-ostree initramfs | grep -q 'regeneration: enabled' || rpm-ostree initramfs --enable"
+    } else {
+        "dracut -f"
+    }));
+
+    Ok(VfioPlan {
+        ids,
+        file_changes,
+        kernel_args_add,
+        script: script_lines.join("\n"),
+    })
+}
@@ -0,0 +1,216 @@
+use super::{sysroot::sysroot_path, PreCheckedProfile};
+use std::sync::Arc;
+
+/// rpm-ostree systems keep kernel arguments in the deployment instead of the bootloader entries.
+pub fn is_ostree_booted() -> bool {
+    sysroot_path("/run/ostree-booted").exists()
+}
+
+/// The arguments the running kernel was booted with.
+pub fn get_cmdline_args() -> Vec<String> {
+    std::fs::read_to_string(sysroot_path("/proc/cmdline"))
+        .unwrap_or_default()
+        .split_whitespace()
+        .map(|x| x.to_string())
+        .collect()
+}
+
+fn is_valid_kernel_arg(arg: &str) -> bool {
+    !arg.is_empty()
+        && arg
+            .chars()
+            .all(|x| x.is_ascii_graphic() && !"'\"\\`$;&|<>".contains(x))
+}
+
+/// Returns the command adding and removing kernel arguments for every installed kernel, or None if
+/// there is nothing to change. Both grubby and rpm-ostree leave arguments that are already in the
+/// wanted state alone, so running it twice is harmless.
+pub fn get_kernel_args_commands(
+    add: &[String],
+    remove: &[String],
+) -> Result<Option<String>, String> {
+    if let Some(arg) = add.iter().chain(remove).find(|x| !is_valid_kernel_arg(x)) {
+        return Err(t!("kernel_args_invalid", arg = arg).to_string());
+    }
+    if add.is_empty() && remove.is_empty() {
+        return Ok(None);
+    }
+    let command = if is_ostree_booted() {
+        let mut command = String::from("rpm-ostree kargs");
+        for arg in add {
+            command.push_str(&format!(" --append-if-missing='{}'", arg));
+        }
+        for arg in remove {
+            command.push_str(&format!(" --delete-if-present='{}'", arg));
+        }
+        command
+    } else {
+        let mut command = String::from("grubby --update-kernel=ALL");
+        if !add.is_empty() {
+            command.push_str(&format!(" --args='{}'", add.join(" ")));
+        }
+        if !remove.is_empty() {
+            command.push_str(&format!(" --remove-args='{}'", remove.join(" ")));
+        }
+        command
+    };
+    Ok(Some(command))
+}
+
+/// A kernel argument an installed profile changed that the running kernel was not booted with yet.
+pub struct PendingKernelArg {
+    pub arg: String,
+    /// Whether the argument is being added or removed
+    pub add: bool,
+    pub codename: String,
+}
+
+/// `key` matches any value of the key, `key=value` only that exact argument.
+fn cmdline_has_arg(cmdline_args: &[String], arg: &str) -> bool {
+    cmdline_args
+        .iter()
+        .any(|x| x == arg || (!arg.contains('=') && x.starts_with(&format!("{}=", arg))))
+}
+
+/// Returns the kernel argument changes of installed profiles that only take effect after a reboot.
+pub fn get_pending_kernel_args(
+    all_profiles: &[Arc<dyn PreCheckedProfile>],
+) -> Vec<PendingKernelArg> {
+    let cmdline_args = get_cmdline_args();
+    let mut pending = vec![];
+    for profile in all_profiles.iter().filter(|x| x.is_installed()) {
+        let summary = profile.summary();
+        for arg in &summary.kernel_args.add {
+            if !cmdline_has_arg(&cmdline_args, arg) {
+                pending.push(PendingKernelArg {
+                    arg: arg.clone(),
+                    add: true,
+                    codename: summary.codename.clone(),
+                });
+            }
+        }
+        for arg in &summary.kernel_args.remove {
+            if cmdline_has_arg(&cmdline_args, arg) {
+                pending.push(PendingKernelArg {
+                    arg: arg.clone(),
+                    add: false,
+                    codename: summary.codename.clone(),
+                });
+            }
+        }
+    }
+    pending
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::cfhdb::sysroot::FakeSysroot;
+
+    fn args(args: &[&str]) -> Vec<String> {
+        args.iter().map(|x| x.to_string()).collect()
+    }
+
+    #[test]
+    fn grubby_command() {
+        let sysroot = FakeSysroot::new("kargs-grubby");
+        sysroot.mkdir("/run");
+        let command = sysroot.run(|| {
+            get_kernel_args_commands(
+                &args(&["nvidia-drm.modeset=1", "rd.driver.blacklist=nouveau"]),
+                &args(&["nomodeset"]),
+            )
+        });
+        assert_eq!(
+            command,
+            Ok(Some(String::from(
+                "grubby --update-kernel=ALL --args='nvidia-drm.modeset=1 rd.driver.blacklist=nouveau' --remove-args='nomodeset'"
+            )))
+        );
+        assert_eq!(
+            sysroot.run(|| get_kernel_args_commands(&[], &args(&["quiet"]))),
+            Ok(Some(String::from(
+                "grubby --update-kernel=ALL --remove-args='quiet'"
+            )))
+        );
+    }
+
+    #[test]
+    fn rpm_ostree_command() {
+        let sysroot = FakeSysroot::new("kargs-ostree");
+        sysroot.write("/run/ostree-booted", "");
+        let command = sysroot.run(|| {
+            get_kernel_args_commands(
+                &args(&["nvidia-drm.modeset=1", "rd.driver.blacklist=nouveau"]),
+                &args(&["nomodeset"]),
+            )
+        });
+        assert_eq!(
+            command,
+            Ok(Some(String::from(
+                "rpm-ostree kargs --append-if-missing='nvidia-drm.modeset=1' --append-if-missing='rd.driver.blacklist=nouveau' --delete-if-present='nomodeset'"
+            )))
+        );
+    }
+
+    #[test]
+    fn nothing_to_change() {
+        assert_eq!(get_kernel_args_commands(&[], &[]), Ok(None));
+    }
+
+    #[test]
+    fn invalid_args_are_rejected() {
+        for arg in [
+            "",
+            "a b",
+            "quiet;reboot",
+            "x=$(id)",
+            "x=`id`",
+            "x='y'",
+            "x=\"y\"",
+            "a&b",
+            "a|b",
+            "a>b",
+            "a\\b",
+            "a\tb",
+        ] {
+            assert!(!is_valid_kernel_arg(arg), "{:?} was accepted", arg);
+            assert!(get_kernel_args_commands(&args(&[arg]), &[]).is_err());
+            assert!(get_kernel_args_commands(&[], &args(&[arg])).is_err());
+        }
+        assert!(is_valid_kernel_arg("nvidia-drm.modeset=1"));
+        assert!(is_valid_kernel_arg("iommu=pt"));
+        assert!(is_valid_kernel_arg("vfio-pci.ids=10de:2684,10de:22ba"));
+    }
+
+    #[test]
+    fn key_matches_any_value() {
+        let cmdline_args = args(&["quiet", "nvidia-drm.modeset=1", "root=UUID=abc"]);
+        assert!(cmdline_has_arg(&cmdline_args, "quiet"));
+        assert!(cmdline_has_arg(&cmdline_args, "nvidia-drm.modeset"));
+        assert!(cmdline_has_arg(&cmdline_args, "root"));
+        assert!(!cmdline_has_arg(&cmdline_args, "nvidia-drm"));
+        assert!(!cmdline_has_arg(&cmdline_args, "nomodeset"));
+    }
+
+    #[test]
+    fn key_value_matches_exactly() {
+        let cmdline_args = args(&["quiet", "nvidia-drm.modeset=1"]);
+        assert!(cmdline_has_arg(&cmdline_args, "nvidia-drm.modeset=1"));
+        assert!(!cmdline_has_arg(&cmdline_args, "nvidia-drm.modeset=0"));
+        assert!(!cmdline_has_arg(&cmdline_args, "quiet=1"));
+    }
+
+    #[test]
+    fn cmdline_is_read_from_proc() {
+        let sysroot = FakeSysroot::new("kargs-cmdline");
+        sysroot.write(
+            "/proc/cmdline",
+            "BOOT_IMAGE=/vmlinuz root=UUID=abc ro  quiet\n",
+        );
+        assert_eq!(
+            sysroot.run(get_cmdline_args),
+            args(&["BOOT_IMAGE=/vmlinuz", "root=UUID=abc", "ro", "quiet"])
+        );
+    }
+}
@@ -0,0 +1,275 @@
+use super::{glob_match, sysroot::sysroot_path};
+use log::{error, info};
+use std::path::{Path, PathBuf};
+
+/// Our own modprobe.d drop-in for a module is this prefix, the module name and `.conf`.
+pub const MODPROBE_DROPIN_PREFIX: &str = "/etc/modprobe.d/nobara-driver-manager-";
+
+const MODPROBE_DIRS: [&str; 4] = [
+    "/etc/modprobe.d",
+    "/run/modprobe.d",
+    "/usr/lib/modprobe.d",
+    "/lib/modprobe.d",
+];
+
+lazy_static::lazy_static! {
+    /// (modalias glob, module) of the running kernel, parsed once since every device page needs it.
+    static ref MODULE_ALIASES: Vec<(String, String)> = load_module_aliases();
+}
+
+/// A kernel module bound to a device, or one whose aliases say it could drive it.
+pub struct KernelModule {
+    pub name: String,
+    pub bound: bool,
+    /// Built into the kernel, so it can neither be blacklisted nor given modprobe options
+    pub builtin: bool,
+    pub loaded: bool,
+    pub blacklisted: bool,
+    /// Blacklisted by our own drop-in, so it can be undone here
+    pub managed_blacklist: bool,
+    /// (name, current value), None if the parameter is write only
+    pub parameters: Vec<(String, Option<String>)>,
+    /// The options line of our own drop-in
+    pub persistent_options: String,
+}
+
+/// modprobe treats `-` and `_` in module names the same.
+pub fn normalize_module_name(name: &str) -> String {
+    name.replace('-', "_")
+}
+
+fn load_module_aliases() -> Vec<(String, String)> {
+    let kernel_release = std::fs::read_to_string(sysroot_path("/proc/sys/kernel/osrelease"))
+        .unwrap_or_default()
+        .trim()
+        .to_string();
+    let file_path = sysroot_path(&format!("/lib/modules/{}/modules.alias", kernel_release));
+    match std::fs::read_to_string(&file_path) {
+        Ok(t) => {
+            let aliases: Vec<(String, String)> = t
+                .lines()
+                .filter_map(|line| {
+                    let mut fields = line.split_whitespace();
+                    match (fields.next(), fields.next(), fields.next()) {
+                        (Some("alias"), Some(pattern), Some(module)) => {
+                            Some((pattern.to_string(), normalize_module_name(module)))
+                        }
+                        _ => None,
+                    }
+                })
+                .collect();
+            info!(
+                target: "kmod",
+                "Loaded {} module aliases from {}",
+                aliases.len(),
+                file_path.display()
+            );
+            aliases
+        }
+        Err(e) => {
+            error!(target: "kmod", "Could not read {}: {}", file_path.display(), e);
+            vec![]
+        }
+    }
+}
+
+/// The sysfs directory of a device and, for USB, of its interfaces, which is where drivers bind.
+pub fn device_sysfs_dirs(bus: &str, sysfs_busid: &str) -> Vec<PathBuf> {
+    let device_dir = sysroot_path(&format!("/sys/bus/{}/devices/{}", bus, sysfs_busid));
+    let mut dirs = vec![device_dir.clone()];
+    if bus == "usb" {
+        if let Ok(entries) = std::fs::read_dir(&device_dir) {
+            let mut interface_dirs: Vec<PathBuf> = entries
+                .flatten()
+                .filter(|x| {
+                    x.file_name()
+                        .to_string_lossy()
+                        .starts_with(&format!("{}:", sysfs_busid))
+                })
+                .map(|x| x.path())
+                .collect();
+            interface_dirs.sort();
+            dirs.extend(interface_dirs);
+        }
+    }
+    dirs
+}
+
+/// Returns the module behind the driver bound in `dir`, or the driver name if it is built in.
+pub fn bound_module(dir: &Path) -> Option<(String, bool)> {
+    let driver = std::fs::read_link(dir.join("driver")).ok()?;
+    let driver_name = driver.file_name()?.to_string_lossy().to_string();
+    match std::fs::read_link(dir.join("driver/module")) {
+        Ok(module) => Some((
+            normalize_module_name(&module.file_name()?.to_string_lossy()),
+            false,
+        )),
+        Err(_) => Some((normalize_module_name(&driver_name), true)),
+    }
+}
+
+fn read_parameters(module: &str) -> Vec<(String, Option<String>)> {
+    let parameters_dir = sysroot_path(&format!("/sys/module/{}/parameters", module));
+    let mut parameters: Vec<(String, Option<String>)> = match std::fs::read_dir(parameters_dir) {
+        Ok(entries) => entries
+            .flatten()
+            .map(|x| {
+                (
+                    x.file_name().to_string_lossy().to_string(),
+                    std::fs::read_to_string(x.path())
+                        .ok()
+                        .map(|y| y.trim().to_string()),
+                )
+            })
+            .collect(),
+        Err(_) => vec![],
+    };
+    parameters.sort_by(|a, b| a.0.cmp(&b.0));
+    parameters
+}
+
+fn modprobe_conf_blacklists(content: &str, module: &str) -> bool {
+    content.lines().any(|line| {
+        let mut fields = line.split_whitespace();
+        fields.next() == Some("blacklist")
+            && fields
+                .next()
+                .is_some_and(|x| normalize_module_name(x) == module)
+    })
+}
+
+fn is_blacklisted(module: &str) -> bool {
+    for dir in MODPROBE_DIRS {
+        let Ok(entries) = std::fs::read_dir(sysroot_path(dir)) else {
+            continue;
+        };
+        for entry in entries.flatten() {
+            if !entry.file_name().to_string_lossy().ends_with(".conf") {
+                continue;
+            }
+            if modprobe_conf_blacklists(
+                &std::fs::read_to_string(entry.path()).unwrap_or_default(),
+                module,
+            ) {
+                return true;
+            }
+        }
+    }
+    // module_blacklist= and modprobe.blacklist= on the kernel command line
+    std::fs::read_to_string(sysroot_path("/proc/cmdline"))
+        .unwrap_or_default()
+        .split_whitespace()
+        .filter_map(|x| {
+            x.strip_prefix("module_blacklist=")
+                .or_else(|| x.strip_prefix("modprobe.blacklist="))
+        })
+        .flat_map(|x| x.split(','))
+        .any(|x| normalize_module_name(x) == module)
+}
+
+fn dropin_path(module: &str) -> String {
+    format!("{}{}.conf", MODPROBE_DROPIN_PREFIX, module)
+}
+
+/// Returns the modules bound to the device, then the ones modules.alias matches it with.
+pub fn get_device_kernel_modules(bus: &str, sysfs_busid: &str) -> Vec<KernelModule> {
+    let dirs = device_sysfs_dirs(bus, sysfs_busid);
+
+    let mut names: Vec<(String, bool, bool)> = vec![];
+    for dir in &dirs {
+        if let Some((name, builtin)) = bound_module(dir) {
+            if !names.iter().any(|x| x.0 == name) {
+                names.push((name, true, builtin));
+            }
+        }
+    }
+    let modaliases: Vec<String> = dirs
+        .iter()
+        .filter_map(|x| std::fs::read_to_string(x.join("modalias")).ok())
+        .map(|x| x.trim().to_string())
+        .filter(|x| !x.is_empty())
+        .collect();
+    for (pattern, module) in MODULE_ALIASES.iter() {
+        if names.iter().any(|x| &x.0 == module) {
+            continue;
+        }
+        if modaliases.iter().any(|x| glob_match(pattern, x)) {
+            names.push((module.clone(), false, false));
+        }
+    }
+
+    names
+        .into_iter()
+        .map(|(name, bound, builtin)| {
+            let dropin_content =
+                std::fs::read_to_string(sysroot_path(&dropin_path(&name))).unwrap_or_default();
+            let persistent_options = dropin_content
+                .lines()
+                .find_map(|line| {
+                    let mut fields = line.splitn(3, char::is_whitespace);
+                    match (fields.next(), fields.next()) {
+                        (Some("options"), Some(x)) if normalize_module_name(x) == name => {
+                            Some(fields.next().unwrap_or_default().trim().to_string())
+                        }
+                        _ => None,
+                    }
+                })
+                .unwrap_or_default();
+            KernelModule {
+                bound,
+                builtin,
+                loaded: sysroot_path(&format!("/sys/module/{}", name)).exists(),
+                blacklisted: is_blacklisted(&name),
+                managed_blacklist: modprobe_conf_blacklists(&dropin_content, &name),
+                parameters: read_parameters(&name),
+                persistent_options,
+                name,
+            }
+        })
+        .collect()
+}
+
+/// Returns the commands that rewrite our drop-in for a module, or remove it if it would be empty,
+/// then rebuild the initramfs if the module is part of it.
+pub fn get_module_dropin_commands(
+    module: &str,
+    blacklist: bool,
+    options: &str,
+) -> Result<String, String> {
+    if module.is_empty()
+        || !module
+            .chars()
+            .all(|x| x.is_ascii_alphanumeric() || x == '_' || x == '-')
+    {
+        return Err(t!("kernel_module_invalid_name", name = module).to_string());
+    }
+    let options = options.trim();
+    if options.chars().any(|x| x.is_control()) {
+        return Err(t!("kernel_module_invalid_options").to_string());
+    }
+    let module = normalize_module_name(module);
+    let file_path = dropin_path(&module);
+
+    let mut lines = vec![];
+    if blacklist {
+        lines.push(format!("blacklist {}", module));
+    }
+    if !options.is_empty() {
+        lines.push(format!("options {} {}", module, options));
+    }
+    let write_command = if lines.is_empty() {
+        format!("rm -f {}", file_path)
+    } else {
+        format!(
+            "cat > {} <<'NDM_MODPROBE_EOF'\n# Managed by Nobara Driver Manager\n{}\nNDM_MODPROBE_EOF",
+            file_path,
+            lines.join("\n")
+        )
+    };
+    // The initramfs carries its own copy of modprobe.d, which only matters if the module is in it
+    Ok(format!(
+        "{}\nif lsinitrd 2>/dev/null | grep -qE '/{}\\.ko'; then\n  echo 'Regenerating the initramfs'\n  dracut -f\nfi",
+        write_command,
+        module.replace('_', "[-_]")
+    ))
+}
@@ -0,0 +1,565 @@
+use super::{
+    bt::PreCheckedBtDevice,
+    dmi::PreCheckedDmiInfo,
+    pci::PreCheckedPciDevice,
+    policy::PROFILE_POLICY,
+    sysfs_bus::{SysfsBus, SysfsBuses},
+    usb::PreCheckedUsbDevice,
+    PreCheckedProfile, ProfileSummary,
+};
+use std::sync::Arc;
+
+pub const MANIFEST_VERSION: u32 = 1;
+
+/// The driver set of one machine: its installed profiles, the devices they matched and the DMI identity,
+/// so it can be reproduced on identical machines.
+#[derive(serde::Serialize, serde::Deserialize)]
+pub struct Manifest {
+    pub version: u32,
+    pub dmi: ManifestDmi,
+    pub profiles: Vec<ManifestProfile>,
+}
+
+#[derive(serde::Serialize, serde::Deserialize)]
+pub struct ManifestDmi {
+    pub sys_vendor: String,
+    pub product_family: String,
+    pub product_name: String,
+    pub board_vendor: String,
+    pub board_name: String,
+}
+
+#[derive(serde::Serialize, serde::Deserialize, Clone)]
+pub struct ManifestProfile {
+    pub bus: String,
+    pub codename: String,
+    /// `bus:vendor:device` IDs of the devices the profile matched on the exporting machine
+    pub devices: Vec<String>,
+}
+
+/// What applying a manifest would change on this machine.
+pub struct ManifestPlan {
+    /// (field, manifest value, local value) of every DMI field that differs
+    pub dmi_differences: Vec<(&'static str, String, String)>,
+    pub to_install: Vec<Arc<dyn PreCheckedProfile>>,
+    pub to_remove: Vec<Arc<dyn PreCheckedProfile>>,
+    /// Manifest profiles that cannot be installed here, with the reason
+    pub not_applicable: Vec<(ManifestProfile, String)>,
+}
+
+impl ManifestPlan {
+    pub fn is_empty(&self) -> bool {
+        self.to_install.is_empty() && self.to_remove.is_empty()
+    }
+
+    /// Removals go first so a replaced driver is gone before its successor is installed.
+    pub fn jobs(&self) -> Vec<(Arc<dyn PreCheckedProfile>, String)> {
+        self.to_remove
+            .iter()
+            .map(|x| (x.clone(), String::from("remove")))
+            .chain(
+                self.to_install
+                    .iter()
+                    .map(|x| (x.clone(), String::from("install"))),
+            )
+            .collect()
+    }
+}
+
+/// A profile compatible with the local hardware and the devices it matched.
+struct MatchedProfile {
+    profile: Arc<dyn PreCheckedProfile>,
+    devices: Vec<String>,
+}
+
+fn push_matched_profile(
+    matched_profiles: &mut Vec<MatchedProfile>,
+    device: &str,
+    profile: Arc<dyn PreCheckedProfile>,
+) {
+    let summary = profile.summary();
+    match matched_profiles.iter_mut().find(|x| {
+        let other_summary = x.profile.summary();
+        other_summary.bus == summary.bus && other_summary.codename == summary.codename
+    }) {
+        Some(t) => {
+            if !t.devices.iter().any(|x| x == device) {
+                t.devices.push(device.to_string())
+            }
+        }
+        None => matched_profiles.push(MatchedProfile {
+            profile,
+            devices: vec![device.to_string()],
+        }),
+    }
+}
+
+fn push_matched<P: PreCheckedProfile + 'static>(
+    matched_profiles: &mut Vec<MatchedProfile>,
+    device: String,
+    profiles: &[Arc<P>],
+) {
+    for profile in profiles {
+        push_matched_profile(
+            matched_profiles,
+            &device,
+            profile.clone() as Arc<dyn PreCheckedProfile>,
+        );
+    }
+}
+
+/// Sysfs bus devices are recorded as `bus:id`, the ID the device has on its own page.
+fn push_sysfs_bus_matched(matched_profiles: &mut Vec<MatchedProfile>, buses: &[&dyn SysfsBus]) {
+    for bus in buses {
+        for device in bus.device_summaries() {
+            let device_id = format!("{}:{}", bus.bus(), device.id);
+            for profile in device.profiles {
+                push_matched_profile(matched_profiles, &device_id, profile);
+            }
+        }
+    }
+}
+
+fn get_matched_profiles(
+    hashmap_pci: &Option<Vec<(String, Vec<PreCheckedPciDevice>)>>,
+    hashmap_usb: &Option<Vec<(String, Vec<PreCheckedUsbDevice>)>>,
+    dmi_info: &PreCheckedDmiInfo,
+    hashmap_bt: &Option<Vec<(String, Vec<PreCheckedBtDevice>)>>,
+    sysfs_buses: &SysfsBuses,
+) -> Vec<MatchedProfile> {
+    let mut matched_profiles = vec![];
+
+    push_matched(
+        &mut matched_profiles,
+        format!("dmi:{}", dmi_info.info.product_name),
+        &dmi_info.profiles,
+    );
+    if let Some(hashmap_pci) = hashmap_pci {
+        for (_, devices) in hashmap_pci {
+            for device in devices {
+                push_matched(
+                    &mut matched_profiles,
+                    format!(
+                        "pci:{}:{}",
+                        device.device.vendor_id, device.device.device_id
+                    ),
+                    &device.profiles,
+                );
+            }
+        }
+    }
+    if let Some(hashmap_usb) = hashmap_usb {
+        for (_, devices) in hashmap_usb {
+            for device in devices {
+                push_matched(
+                    &mut matched_profiles,
+                    format!(
+                        "usb:{}:{}",
+                        device.device.vendor_id, device.device.product_id
+                    ),
+                    &device.profiles,
+                );
+            }
+        }
+    }
+    if let Some(hashmap_bt) = hashmap_bt {
+        for (_, devices) in hashmap_bt {
+            for device in devices {
+                push_matched(
+                    &mut matched_profiles,
+                    format!(
+                        "bt:{}:{}",
+                        device.device.modalias_vendor_id, device.device.modalias_product_id
+                    ),
+                    &device.profiles,
+                );
+            }
+        }
+    }
+    push_sysfs_bus_matched(&mut matched_profiles, &sysfs_buses.buses());
+
+    matched_profiles
+}
+
+fn get_manifest_dmi(dmi_info: &PreCheckedDmiInfo) -> ManifestDmi {
+    ManifestDmi {
+        sys_vendor: dmi_info.info.sys_vendor.clone(),
+        product_family: dmi_info.info.product_family.clone(),
+        product_name: dmi_info.info.product_name.clone(),
+        board_vendor: dmi_info.info.board_vendor.clone(),
+        board_name: dmi_info.info.board_name.clone(),
+    }
+}
+
+pub fn export_manifest(
+    hashmap_pci: &Option<Vec<(String, Vec<PreCheckedPciDevice>)>>,
+    hashmap_usb: &Option<Vec<(String, Vec<PreCheckedUsbDevice>)>>,
+    dmi_info: &PreCheckedDmiInfo,
+    hashmap_bt: &Option<Vec<(String, Vec<PreCheckedBtDevice>)>>,
+    sysfs_buses: &SysfsBuses,
+    all_profiles: &[Arc<dyn PreCheckedProfile>],
+) -> Manifest {
+    build_manifest(
+        get_manifest_dmi(dmi_info),
+        &get_matched_profiles(hashmap_pci, hashmap_usb, dmi_info, hashmap_bt, sysfs_buses),
+        all_profiles,
+    )
+}
+
+fn build_manifest(
+    dmi: ManifestDmi,
+    matched_profiles: &[MatchedProfile],
+    all_profiles: &[Arc<dyn PreCheckedProfile>],
+) -> Manifest {
+    let profiles = all_profiles
+        .iter()
+        .filter(|x| x.is_installed())
+        .map(|x| {
+            let summary = x.summary();
+            let devices = matched_profiles
+                .iter()
+                .find(|y| Arc::ptr_eq(&y.profile, x))
+                .map(|y| y.devices.clone())
+                .unwrap_or_default();
+            ManifestProfile {
+                bus: summary.bus.to_string(),
+                codename: summary.codename,
+                devices,
+            }
+        })
+        .collect();
+
+    Manifest {
+        version: MANIFEST_VERSION,
+        dmi,
+        profiles,
+    }
+}
+
+pub fn read_manifest(file_path: &std::path::Path) -> Result<Manifest, String> {
+    let json_content = std::fs::read_to_string(file_path).map_err(|e| e.to_string())?;
+    let manifest: Manifest = serde_json::from_str(&json_content).map_err(|e| e.to_string())?;
+    if manifest.version > MANIFEST_VERSION {
+        return Err(t!("manifest_unsupported_version", version = manifest.version).to_string());
+    }
+    Ok(manifest)
+}
+
+pub fn write_manifest(file_path: &std::path::Path, manifest: &Manifest) -> Result<(), String> {
+    let json_content = serde_json::to_string_pretty(manifest).map_err(|e| e.to_string())?;
+    std::fs::write(file_path, json_content + "\n").map_err(|e| e.to_string())
+}
+
+/// Checks every manifest profile against the local hardware and works out what applying it would change.
+pub fn plan_manifest(
+    manifest: &Manifest,
+    hashmap_pci: &Option<Vec<(String, Vec<PreCheckedPciDevice>)>>,
+    hashmap_usb: &Option<Vec<(String, Vec<PreCheckedUsbDevice>)>>,
+    dmi_info: &PreCheckedDmiInfo,
+    hashmap_bt: &Option<Vec<(String, Vec<PreCheckedBtDevice>)>>,
+    sysfs_buses: &SysfsBuses,
+    all_profiles: &[Arc<dyn PreCheckedProfile>],
+) -> ManifestPlan {
+    build_manifest_plan(
+        manifest,
+        &get_manifest_dmi(dmi_info),
+        &get_matched_profiles(hashmap_pci, hashmap_usb, dmi_info, hashmap_bt, sysfs_buses),
+        all_profiles,
+        |x| PROFILE_POLICY.check(x),
+    )
+}
+
+/// `check` returns why installing a profile is denied, like `ProfilePolicy::check`.
+fn build_manifest_plan(
+    manifest: &Manifest,
+    local_dmi: &ManifestDmi,
+    matched_profiles: &[MatchedProfile],
+    all_profiles: &[Arc<dyn PreCheckedProfile>],
+    check: impl Fn(&ProfileSummary) -> Option<String>,
+) -> ManifestPlan {
+    let dmi_differences = [
+        (
+            "sys_vendor",
+            &manifest.dmi.sys_vendor,
+            &local_dmi.sys_vendor,
+        ),
+        (
+            "product_family",
+            &manifest.dmi.product_family,
+            &local_dmi.product_family,
+        ),
+        (
+            "product_name",
+            &manifest.dmi.product_name,
+            &local_dmi.product_name,
+        ),
+        (
+            "board_vendor",
+            &manifest.dmi.board_vendor,
+            &local_dmi.board_vendor,
+        ),
+        (
+            "board_name",
+            &manifest.dmi.board_name,
+            &local_dmi.board_name,
+        ),
+    ]
+    .into_iter()
+    .filter(|(_, a, b)| a != b)
+    .map(|(field, a, b)| (field, a.clone(), b.clone()))
+    .collect();
+
+    let mut to_install: Vec<Arc<dyn PreCheckedProfile>> = vec![];
+    let mut not_applicable = vec![];
+    for manifest_profile in &manifest.profiles {
+        let is_same_profile = |x: &Arc<dyn PreCheckedProfile>| {
+            let summary = x.summary();
+            summary.bus == manifest_profile.bus && summary.codename == manifest_profile.codename
+        };
+        if !all_profiles.iter().any(is_same_profile) {
+            not_applicable.push((
+                manifest_profile.clone(),
+                t!("manifest_reason_unknown_profile").to_string(),
+            ));
+            continue;
+        }
+        let matched_profile = match matched_profiles
+            .iter()
+            .find(|x| is_same_profile(&x.profile))
+        {
+            Some(t) => t,
+            None => {
+                not_applicable.push((
+                    manifest_profile.clone(),
+                    t!("manifest_reason_no_device").to_string(),
+                ));
+                continue;
+            }
+        };
+        if matched_profile.profile.is_installed() {
+            continue;
+        }
+        if let Some(reason) = check(&matched_profile.profile.summary()) {
+            not_applicable.push((manifest_profile.clone(), reason));
+            continue;
+        }
+        to_install.push(matched_profile.profile.clone());
+    }
+
+    let to_remove = all_profiles
+        .iter()
+        .filter(|x| {
+            let summary = x.summary();
+            x.is_installed()
+                && summary.removable
+                && !manifest
+                    .profiles
+                    .iter()
+                    .any(|y| y.bus == summary.bus && y.codename == summary.codename)
+        })
+        .cloned()
+        .collect();
+
+    ManifestPlan {
+        dmi_differences,
+        to_install,
+        to_remove,
+        not_applicable,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::cfhdb::{sysfs_bus::SysfsBusDeviceSummary, ProfileKernelArgs};
+    use std::sync::Mutex;
+
+    struct TestProfile {
+        bus: &'static str,
+        codename: &'static str,
+        installed: Mutex<bool>,
+    }
+
+    impl PreCheckedProfile for TestProfile {
+        fn summary(&self) -> ProfileSummary {
+            ProfileSummary {
+                bus: self.bus,
+                codename: self.codename.to_string(),
+                i18n_desc: self.codename.to_string(),
+                icon_name: String::new(),
+                license: String::from("GPL-2.0"),
+                packages: None,
+                install_script: None,
+                remove_script: None,
+                experimental: false,
+                removable: true,
+                veiled: false,
+                priority: 0,
+                kernel_args: ProfileKernelArgs::default(),
+                provides_firmware: vec![],
+            }
+        }
+        fn is_installed(&self) -> bool {
+            *self.installed.lock().unwrap()
+        }
+        fn refresh_installed(&self) {}
+    }
+
+    struct TestBus {
+        bus: &'static str,
+        devices: Vec<(&'static str, Vec<Arc<dyn PreCheckedProfile>>)>,
+    }
+
+    impl SysfsBus for TestBus {
+        fn bus(&self) -> &'static str {
+            self.bus
+        }
+        fn profiles(&self) -> Vec<Arc<dyn PreCheckedProfile>> {
+            self.devices.iter().flat_map(|x| x.1.clone()).collect()
+        }
+        fn device_summaries(&self) -> Vec<SysfsBusDeviceSummary> {
+            self.devices
+                .iter()
+                .map(|(id, profiles)| SysfsBusDeviceSummary {
+                    class: String::new(),
+                    id: id.to_string(),
+                    title: id.to_string(),
+                    subtitle: String::new(),
+                    fields: vec![],
+                    parent: None,
+                    profiles: profiles.clone(),
+                })
+                .collect()
+        }
+    }
+
+    fn profile(bus: &'static str, codename: &'static str, installed: bool) -> Arc<TestProfile> {
+        Arc::new(TestProfile {
+            bus,
+            codename,
+            installed: Mutex::new(installed),
+        })
+    }
+
+    fn dmi() -> ManifestDmi {
+        ManifestDmi {
+            sys_vendor: String::from("Vendor"),
+            product_family: String::from("Family"),
+            product_name: String::from("Product"),
+            board_vendor: String::from("Vendor"),
+            board_name: String::from("Board"),
+        }
+    }
+
+    /// The matched and all profiles of a machine with one storage and one network device.
+    fn machine(
+        smart: &Arc<TestProfile>,
+        firmware: &Arc<TestProfile>,
+        extra: &Arc<TestProfile>,
+    ) -> (Vec<MatchedProfile>, Vec<Arc<dyn PreCheckedProfile>>) {
+        let storage = TestBus {
+            bus: "storage",
+            devices: vec![("nvme0n1", vec![smart.clone()])],
+        };
+        let network = TestBus {
+            bus: "network",
+            devices: vec![("wlp2s0", vec![firmware.clone(), extra.clone()])],
+        };
+        let buses: Vec<&dyn SysfsBus> = vec![&storage, &network];
+        let mut matched_profiles = vec![];
+        push_sysfs_bus_matched(&mut matched_profiles, &buses);
+        let all_profiles = buses.iter().flat_map(|x| x.profiles()).collect();
+        (matched_profiles, all_profiles)
+    }
+
+    #[test]
+    fn sysfs_bus_profiles_survive_a_round_trip() {
+        let (matched_profiles, all_profiles) = machine(
+            &profile("storage", "smart", true),
+            &profile("network", "firmware", true),
+            &profile("network", "extra", false),
+        );
+        let manifest = build_manifest(dmi(), &matched_profiles, &all_profiles);
+        let manifest: Manifest =
+            serde_json::from_str(&serde_json::to_string(&manifest).unwrap()).unwrap();
+
+        let devices: Vec<(&str, &str, Vec<String>)> = manifest
+            .profiles
+            .iter()
+            .map(|x| (x.bus.as_str(), x.codename.as_str(), x.devices.clone()))
+            .collect();
+        assert_eq!(
+            devices,
+            vec![
+                ("storage", "smart", vec![String::from("storage:nvme0n1")]),
+                ("network", "firmware", vec![String::from("network:wlp2s0")]),
+            ]
+        );
+
+        // Applied on an identical machine with nothing installed but a profile missing from the manifest
+        let (matched_profiles, all_profiles) = machine(
+            &profile("storage", "smart", false),
+            &profile("network", "firmware", false),
+            &profile("network", "extra", true),
+        );
+        let plan = build_manifest_plan(&manifest, &dmi(), &matched_profiles, &all_profiles, |_| {
+            None
+        });
+        assert!(plan.dmi_differences.is_empty());
+        assert!(plan.not_applicable.is_empty());
+        let codenames = |profiles: &[Arc<dyn PreCheckedProfile>]| -> Vec<String> {
+            profiles.iter().map(|x| x.summary().codename).collect()
+        };
+        assert_eq!(codenames(&plan.to_install), vec!["smart", "firmware"]);
+        assert_eq!(codenames(&plan.to_remove), vec!["extra"]);
+    }
+
+    #[test]
+    fn exporting_machine_has_nothing_to_apply() {
+        let (matched_profiles, all_profiles) = machine(
+            &profile("storage", "smart", true),
+            &profile("network", "firmware", true),
+            &profile("network", "extra", false),
+        );
+        let manifest = build_manifest(dmi(), &matched_profiles, &all_profiles);
+        let plan = build_manifest_plan(&manifest, &dmi(), &matched_profiles, &all_profiles, |_| {
+            None
+        });
+        assert!(plan.is_empty());
+        assert!(plan.not_applicable.is_empty());
+    }
+
+    #[test]
+    fn unmatched_and_denied_profiles_are_not_applicable() {
+        let (matched_profiles, all_profiles) = machine(
+            &profile("storage", "smart", false),
+            &profile("network", "firmware", false),
+            &profile("network", "extra", false),
+        );
+        let manifest = Manifest {
+            version: MANIFEST_VERSION,
+            dmi: dmi(),
+            profiles: ["smart", "firmware", "unknown"]
+                .into_iter()
+                .map(|x| ManifestProfile {
+                    bus: if x == "smart" { "storage" } else { "network" }.to_string(),
+                    codename: x.to_string(),
+                    devices: vec![],
+                })
+                .collect(),
+        };
+        let plan = build_manifest_plan(&manifest, &dmi(), &matched_profiles, &all_profiles, |x| {
+            (x.codename == "firmware").then(|| String::from("denied"))
+        });
+        let reasons: Vec<(&str, &str)> = plan
+            .not_applicable
+            .iter()
+            .map(|(x, reason)| (x.codename.as_str(), reason.as_str()))
+            .collect();
+        assert_eq!(reasons.len(), 2);
+        assert_eq!(reasons[0], ("firmware", "denied"));
+        assert_eq!(reasons[1].0, "unknown");
+        assert_eq!(plan.to_install.len(), 1);
+        assert_eq!(plan.to_install[0].summary().codename, "smart");
+    }
+}
@@ -1,4 +1,288 @@
+pub mod bluez;
 pub mod bt;
+pub mod bt_audio;
+pub mod cpu;
+pub mod display;
 pub mod dmi;
+pub mod driver_binding;
+pub mod firmware;
+pub mod input;
+pub mod inventory;
+pub mod iommu;
+pub mod kargs;
+pub mod kmod;
+pub mod manifest;
+pub mod network;
 pub mod pci;
+pub mod policy;
+pub mod recommendation;
+pub mod sound;
+pub mod storage;
+pub mod sysfs_bus;
+pub mod sysroot;
 pub mod usb;
+pub mod usb_topology;
+
+use crate::config::distro_package_manager;
+use kargs::get_kernel_args_commands;
+use policy::PROFILE_POLICY;
+
+/// The bus independent fields of a profile, whatever device database it came from.
+#[derive(Clone)]
+pub struct ProfileSummary {
+    pub bus: &'static str,
+    pub codename: String,
+    pub i18n_desc: String,
+    pub icon_name: String,
+    pub license: String,
+    pub packages: Option<Vec<String>>,
+    pub install_script: Option<String>,
+    pub remove_script: Option<String>,
+    pub experimental: bool,
+    pub removable: bool,
+    pub veiled: bool,
+    pub priority: i32,
+    pub kernel_args: ProfileKernelArgs,
+    /// Globs of the firmware files the profile installs, so missing firmware can point to it
+    pub provides_firmware: Vec<String>,
+}
+
+/// What a profile database entry carries beyond the libcfhdb profile structs, parsed once per profile.
+#[derive(Clone, Default)]
+pub struct ProfileExtras {
+    pub kernel_args: ProfileKernelArgs,
+    /// Interface classes a USB profile matches on, for composite devices whose device class is `00` or `EF`
+    pub interface_class_codes: Vec<String>,
+    /// Firmware file globs the profile installs, `provides_firmware` in the profile database
+    pub provides_firmware: Vec<String>,
+}
+
+impl ProfileExtras {
+    pub fn from_json(profile: &serde_json::Value) -> Self {
+        Self {
+            kernel_args: ProfileKernelArgs::from_json(profile),
+            interface_class_codes: match profile["interface_class_codes"].as_array() {
+                Some(t) => t
+                    .iter()
+                    .map(|x| x.as_str().unwrap_or_default().to_uppercase())
+                    .collect(),
+                None => vec![],
+            },
+            provides_firmware: firmware::profile_provides_firmware(profile),
+        }
+    }
+}
+
+/// Kernel command line arguments a profile adds or removes when installed, which the profile
+/// databases carry as `kernel_args_add` and `kernel_args_remove`.
+#[derive(Clone, Default)]
+pub struct ProfileKernelArgs {
+    pub add: Vec<String>,
+    pub remove: Vec<String>,
+}
+
+impl ProfileKernelArgs {
+    pub fn from_json(profile: &serde_json::Value) -> Self {
+        let strings = |key: &str| -> Vec<String> {
+            match profile[key].as_array() {
+                Some(t) => t
+                    .iter()
+                    .filter_map(|x| x.as_str())
+                    .map(|x| x.to_string())
+                    .collect(),
+                None => vec![],
+            }
+        };
+        Self {
+            add: strings("kernel_args_add"),
+            remove: strings("kernel_args_remove"),
+        }
+    }
+}
+
+/// Implemented by every PreChecked*Profile so bus independent code (recommendations, batch installs)
+/// can work on a mix of them.
+pub trait PreCheckedProfile: Send + Sync {
+    fn summary(&self) -> ProfileSummary;
+    fn is_installed(&self) -> bool;
+    fn refresh_installed(&self);
+}
+
+/// Returns the commands needed to install or remove a profile, or None if there is nothing to run.
+/// Kernel arguments are changed after the packages and scripts, so a failed install leaves them alone.
+/// Installs denied by the site policy are refused with the reason, removals are always allowed.
+pub fn get_profile_modify_commands(
+    profile: &ProfileSummary,
+    opreation: &str,
+) -> Result<Option<String>, String> {
+    if opreation == "install" {
+        if let Some(reason) = PROFILE_POLICY.check(profile) {
+            return Err(reason);
+        }
+    }
+    let script = match opreation {
+        "install" => profile.install_script.clone(),
+        "remove" => profile.remove_script.clone(),
+        _ => return Err(t!("profile_unknown_operation", operation = opreation).to_string()),
+    };
+    let package_command = profile
+        .packages
+        .as_ref()
+        .map(|x| distro_package_manager(opreation, &x.join(" ")));
+    // Removing a profile only takes back the arguments it added, what it removed is not known anymore
+    let kernel_args_command = match opreation {
+        "install" => {
+            get_kernel_args_commands(&profile.kernel_args.add, &profile.kernel_args.remove)?
+        }
+        _ => get_kernel_args_commands(&[], &profile.kernel_args.add)?,
+    };
+    let commands: Vec<String> = [package_command, script, kernel_args_command]
+        .into_iter()
+        .flatten()
+        .collect();
+    Ok((!commands.is_empty()).then(|| commands.join("\n")))
+}
+
+enum GlobToken {
+    Char(char),
+    Any,
+    Star,
+    /// `[...]` with its ranges and whether it starts with `!`
+    Set(Vec<(char, char)>, bool),
+}
+
+fn glob_tokens(pattern: &str) -> Vec<GlobToken> {
+    let mut tokens = vec![];
+    let mut chars = pattern.chars().peekable();
+    while let Some(c) = chars.next() {
+        tokens.push(match c {
+            '*' => GlobToken::Star,
+            '?' => GlobToken::Any,
+            '[' => {
+                // Like fnmatch, a `[` without its `]` is an ordinary character
+                let mut set_chars = chars.clone();
+                let negated = set_chars.next_if_eq(&'!').is_some();
+                let mut ranges = vec![];
+                let mut terminated = false;
+                while let Some(start) = set_chars.next() {
+                    if start == ']' && !ranges.is_empty() {
+                        terminated = true;
+                        break;
+                    }
+                    let end = match set_chars.next_if_eq(&'-') {
+                        Some(_) => set_chars.next().unwrap_or(start),
+                        None => start,
+                    };
+                    ranges.push((start, end));
+                }
+                if terminated {
+                    chars = set_chars;
+                    GlobToken::Set(ranges, negated)
+                } else {
+                    GlobToken::Char(c)
+                }
+            }
+            _ => GlobToken::Char(c),
+        });
+    }
+    tokens
+}
+
+/// Shell style glob matching: `*` matches any run of characters, `?` a single one and `[a-z]` a set.
+pub fn glob_match(pattern: &str, text: &str) -> bool {
+    let pattern = glob_tokens(pattern);
+    let text: Vec<char> = text.chars().collect();
+    let matches_one = |token: &GlobToken, c: char| match token {
+        GlobToken::Char(x) => *x == c,
+        GlobToken::Any => true,
+        GlobToken::Set(ranges, negated) => {
+            ranges
+                .iter()
+                .any(|(start, end)| (*start..=*end).contains(&c))
+                != *negated
+        }
+        GlobToken::Star => false,
+    };
+    let (mut p, mut t) = (0, 0);
+    // Position of the last `*` in the pattern and the text position it was tried at
+    let mut backtrack: Option<(usize, usize)> = None;
+    while t < text.len() {
+        if p < pattern.len() && matches_one(&pattern[p], text[t]) {
+            p += 1;
+            t += 1;
+        } else if p < pattern.len() && matches!(pattern[p], GlobToken::Star) {
+            backtrack = Some((p, t));
+            p += 1;
+        } else if let Some((star_p, star_t)) = backtrack {
+            p = star_p + 1;
+            t = star_t + 1;
+            backtrack = Some((star_p, star_t + 1));
+        } else {
+            return false;
+        }
+    }
+    pattern[p..].iter().all(|x| matches!(x, GlobToken::Star))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn glob_star_and_any() {
+        assert!(glob_match("nvidia-*", "nvidia-open"));
+        assert!(glob_match("nvidia-*", "nvidia-"));
+        assert!(glob_match("*", ""));
+        assert!(glob_match("*open*", "nvidia-open-dkms"));
+        assert!(!glob_match("nvidia-*", "mesa"));
+        assert!(glob_match("beta?", "beta1"));
+        assert!(!glob_match("beta?", "beta"));
+        assert!(!glob_match("beta?", "beta10"));
+        assert!(glob_match("a*b*c", "aXbYbZc"));
+        assert!(!glob_match("a*b*c", "aXbYbZ"));
+    }
+
+    #[test]
+    fn glob_sets() {
+        assert!(glob_match("sd[a-z]", "sdb"));
+        assert!(!glob_match("sd[a-z]", "sd1"));
+        assert!(glob_match("nvme[0-9]n[0-9]", "nvme0n1"));
+        assert!(glob_match("[ab]x", "bx"));
+        assert!(glob_match("[!x]y", "ay"));
+        assert!(!glob_match("[!x]y", "xy"));
+        // A `]` right after the opening bracket belongs to the set
+        assert!(glob_match("[]]", "]"));
+    }
+
+    #[test]
+    fn glob_unterminated_set_is_literal() {
+        assert!(glob_match("[abc", "[abc"));
+        assert!(!glob_match("[abc", "a"));
+        assert!(glob_match("x[*", "x[yz"));
+        assert!(glob_match("[!", "[!"));
+    }
+
+    #[test]
+    fn glob_modules_alias_patterns() {
+        let nvidia = "pci:v000010DEd00002684sv00001043sd000088E2bc03sc00i00";
+        assert!(glob_match("pci:v000010DEd*sv*sd*bc03sc*i*", nvidia));
+        assert!(!glob_match("pci:v00001002d*sv*sd*bc03sc*i*", nvidia));
+        assert!(glob_match(
+            "pci:v00008086d*sv*sd*bc0Csc03i30*",
+            "pci:v00008086d00007AE0sv00001043sd00008694bc0Csc03i30"
+        ));
+        assert!(glob_match(
+            "usb:v0BDAp8179d*dc*dsc*dp*ic*isc*ip*in*",
+            "usb:v0BDAp8179d0000dcFFdscFFdpFFicFFiscFFipFFin00"
+        ));
+        assert!(!glob_match(
+            "usb:v0BDAp8178d*dc*dsc*dp*ic*isc*ip*in*",
+            "usb:v0BDAp8179d0000dcFFdscFFdpFFicFFiscFFipFFin00"
+        ));
+        assert!(glob_match("acpi*:PNP0C09:*", "acpi:PNP0C09:"));
+        assert!(glob_match(
+            "of:N*T*Cnvidia,tegra[0-9][0-9]*",
+            "of:NgpuT(null)Cnvidia,tegra194-gv11b"
+        ));
+    }
+}
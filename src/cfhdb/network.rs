@@ -0,0 +1,152 @@
+use super::{
+    kmod::bound_module, storage::parent_bus_device, sysfs_bus::SysfsBusDevice,
+    sysroot::sysroot_path, usb::read_sysfs_value,
+};
+use crate::config::NETWORK_PROFILE_JSON_URL;
+use std::{path::PathBuf, process::Command};
+
+// The Ethernet link type from linux/if_arp.h, wireless interfaces have it too
+const ARPHRD_ETHER: &str = "1";
+
+/// A network interface from /sys/class/net that belongs to a device.
+#[derive(Clone)]
+pub struct NetworkInterface {
+    /// The interface name, `enp5s0` or `wlp3s0`
+    pub name: String,
+    /// `wired`, `wireless` or `other`, the class of the interface
+    pub kind: String,
+    pub mac_address: Option<String>,
+    /// The RFC 2863 state, `up`, `down` or `dormant`
+    pub operstate: String,
+    /// The negotiated speed in Mb/s, only known for wired links that are up
+    pub speed: Option<String>,
+    pub mtu: Option<String>,
+    /// The module bound to the device, from its `driver` link
+    pub driver: Option<String>,
+    /// The firmware version sysfs shows, the one profiles match on
+    pub firmware_version: Option<String>,
+    pub parent: Option<(String, String)>,
+}
+
+/// The `firmware-version` of `ethtool -i`, which any user can query. Running it for every
+/// interface holds up loading, so it is only asked for when the device page is shown.
+pub fn ethtool_firmware_version(name: &str) -> Option<String> {
+    let output = Command::new("ethtool").args(["-i", name]).output().ok()?;
+    if !output.status.success() {
+        return None;
+    }
+    String::from_utf8_lossy(&output.stdout)
+        .lines()
+        .find_map(|x| x.strip_prefix("firmware-version:"))
+        .map(|x| x.trim().to_string())
+        .filter(|x| !x.is_empty() && x != "N/A")
+}
+
+fn read_network_interface(net_dir: PathBuf) -> Option<NetworkInterface> {
+    let name = net_dir.file_name()?.to_string_lossy().to_string();
+    // Loopback, bridges, tunnels and the like have no device behind them
+    let device_dir = net_dir.join("device");
+    if !device_dir.exists() {
+        return None;
+    }
+    let kind = if net_dir.join("wireless").exists() || net_dir.join("phy80211").exists() {
+        "wireless"
+    } else if read_sysfs_value(&net_dir, "type").as_deref() == Some(ARPHRD_ETHER) {
+        "wired"
+    } else {
+        "other"
+    };
+    // A few drivers put the firmware version in sysfs, the others only tell ethtool
+    let firmware_version = ["fw_version", "firmware_version"]
+        .iter()
+        .find_map(|x| read_sysfs_value(&device_dir, x));
+    Some(NetworkInterface {
+        kind: kind.to_string(),
+        mac_address: read_sysfs_value(&net_dir, "address"),
+        operstate: read_sysfs_value(&net_dir, "operstate").unwrap_or("unknown".to_string()),
+        // Reading the speed fails with EINVAL while the link is down
+        speed: read_sysfs_value(&net_dir, "speed").filter(|x| !x.starts_with('-')),
+        mtu: read_sysfs_value(&net_dir, "mtu"),
+        driver: bound_module(&device_dir).map(|x| x.0),
+        firmware_version,
+        parent: parent_bus_device(&device_dir),
+        name,
+    })
+}
+
+impl SysfsBusDevice for NetworkInterface {
+    const BUS: &'static str = "network";
+    const MATCH_KEYS: &'static [&'static str] = &["drivers", "firmware_versions"];
+
+    fn enumerate() -> Vec<Self> {
+        match std::fs::read_dir(sysroot_path("/sys/class/net")) {
+            Ok(entries) => entries
+                .flatten()
+                .filter_map(|x| read_network_interface(x.path()))
+                .collect(),
+            Err(_) => vec![],
+        }
+    }
+
+    fn profile_url() -> Option<String> {
+        NETWORK_PROFILE_JSON_URL.clone()
+    }
+
+    fn id(&self) -> String {
+        self.name.clone()
+    }
+
+    fn class(&self) -> String {
+        self.kind.clone()
+    }
+
+    fn title(&self) -> String {
+        self.name.clone()
+    }
+
+    fn subtitle(&self) -> String {
+        let state = t!(format!("network_operstate_{}", self.operstate)).to_string();
+        match &self.driver {
+            Some(driver) => format!("{} - {}", driver, state),
+            None => state,
+        }
+    }
+
+    fn details(&self) -> Vec<(String, String)> {
+        let mut details = vec![("network_detail_name".to_string(), self.name.clone())];
+        for (key, value) in [
+            ("network_detail_driver", &self.driver),
+            ("network_detail_mac_address", &self.mac_address),
+        ] {
+            if let Some(value) = value {
+                details.push((key.to_string(), value.clone()));
+            }
+        }
+        details.push((
+            "network_detail_operstate".to_string(),
+            t!(format!("network_operstate_{}", self.operstate)).to_string(),
+        ));
+        if let Some(speed) = &self.speed {
+            details.push((
+                "network_detail_speed".to_string(),
+                format!("{} Mb/s", speed),
+            ));
+        }
+        if let Some(mtu) = &self.mtu {
+            details.push(("network_detail_mtu".to_string(), mtu.clone()));
+        }
+        details
+    }
+
+    fn match_values(&self, key: &str) -> Vec<String> {
+        match key {
+            "drivers" => self.driver.iter().cloned().collect(),
+            "firmware_versions" => self.firmware_version.iter().cloned().collect(),
+            _ => vec![],
+        }
+    }
+
+    fn parent_device(&self) -> Option<(String, String)> {
+        self.parent.clone()
+    }
+}
@@ -1,5 +1,7 @@
+use super::{sysroot::sysroot_path, PreCheckedProfile, ProfileExtras, ProfileSummary};
 use crate::{config::*, ChannelMsg};
 use libcfhdb::pci::*;
+use log::{debug, error, info, warn};
 use std::{
     collections::HashMap,
     fs,
@@ -15,14 +17,16 @@ pub struct PreCheckedPciDevice {
 
 pub struct PreCheckedPciProfile {
     profile: CfhdbPciProfile,
+    extras: ProfileExtras,
     installed: Arc<Mutex<bool>>,
     pub used: Arc<Mutex<bool>>,
 }
 
 impl PreCheckedPciProfile {
-    pub fn new(profile: CfhdbPciProfile) -> Self {
+    pub fn new(profile: CfhdbPciProfile, extras: ProfileExtras) -> Self {
         Self {
             profile,
+            extras,
             installed: Arc::new(Mutex::new(false)),
             used: Arc::new(Mutex::new(false)),
         }
@@ -38,6 +42,33 @@ impl PreCheckedPciProfile {
     }
 }
 
+impl PreCheckedProfile for PreCheckedPciProfile {
+    fn summary(&self) -> ProfileSummary {
+        ProfileSummary {
+            bus: "pci",
+            codename: self.profile.codename.clone(),
+            i18n_desc: self.profile.i18n_desc.clone(),
+            icon_name: self.profile.icon_name.clone(),
+            license: self.profile.license.clone(),
+            packages: self.profile.packages.clone(),
+            install_script: self.profile.install_script.clone(),
+            remove_script: self.profile.remove_script.clone(),
+            experimental: self.profile.experimental,
+            removable: self.profile.removable,
+            veiled: self.profile.veiled,
+            priority: self.profile.priority,
+            kernel_args: self.extras.kernel_args.clone(),
+            provides_firmware: self.extras.provides_firmware.clone(),
+        }
+    }
+    fn is_installed(&self) -> bool {
+        self.installed()
+    }
+    fn refresh_installed(&self) {
+        self.update_installed();
+    }
+}
+
 pub fn get_pci_devices(
     profiles: &[Arc<PreCheckedPciProfile>],
 ) -> Option<HashMap<String, Vec<PreCheckedPciDevice>>> {
@@ -88,6 +119,12 @@ fn get_pre_checked_device(
         };
 
         if matching {
+            debug!(
+                target: "matcher",
+                "PCI device {} matched profile {}",
+                device.sysfs_busid,
+                profile.codename
+            );
             available_profiles.push(profile_arc.clone());
         }
     }
@@ -99,7 +136,7 @@ fn get_pre_checked_device(
 
 pub fn get_pci_profiles_from_url(
     sender: &async_channel::Sender<ChannelMsg>,
-) -> Result<Vec<CfhdbPciProfile>, std::io::Error> {
+) -> Result<Vec<(CfhdbPciProfile, ProfileExtras)>, std::io::Error> {
     let cached_db_path = Path::new("/var/cache/cfhdb/pci.json");
     sender
         .send_blocking(ChannelMsg::OutputLine(format!(
@@ -112,8 +149,10 @@ pub fn get_pci_profiles_from_url(
         .timeout(std::time::Duration::from_secs(5))
         .build()
         .unwrap();
+    info!(target: "pci", "Downloading profiles from {}", *PCI_PROFILE_JSON_URL);
     let data = match client.get(PCI_PROFILE_JSON_URL.clone()).send() {
         Ok(t) => {
+            info!(target: "pci", "Profile download successful");
             sender
                 .send_blocking(ChannelMsg::OutputLine(format!(
                     "[{}] {}",
@@ -126,7 +165,8 @@ pub fn get_pci_profiles_from_url(
             let _ = fs::write(cached_db_path, &cache);
             cache
         }
-        Err(_) => {
+        Err(e) => {
+            warn!(target: "pci", "Profile download failed: {}", e);
             sender
                 .send_blocking(ChannelMsg::OutputLine(format!(
                     "[{}] {}",
@@ -135,6 +175,11 @@ pub fn get_pci_profiles_from_url(
                 )))
                 .expect("Channel closed");
             if cached_db_path.exists() {
+                info!(
+                    target: "pci",
+                    "Using cached profiles from {}",
+                    cached_db_path.display()
+                );
                 sender
                     .send_blocking(ChannelMsg::OutputLine(format!(
                         "[{}] {}",
@@ -144,6 +189,7 @@ pub fn get_pci_profiles_from_url(
                     .expect("Channel closed");
                 fs::read_to_string(cached_db_path).unwrap()
             } else {
+                error!(target: "pci", "No cached profiles found, giving up");
                 sender
                     .send_blocking(ChannelMsg::OutputLine(format!(
                         "[{}] {}",
@@ -289,9 +335,135 @@ pub fn get_pci_profiles_from_url(
                 veiled,
                 priority: priority as i32,
             };
-            profiles_array.push(profile_struct);
-            profiles_array.sort_by_key(|x| x.priority);
+            profiles_array.push((profile_struct, ProfileExtras::from_json(profile)));
+            profiles_array.sort_by_key(|x| x.0.priority);
         }
     }
     Ok(profiles_array)
 }
+
+fn read_sysfs_value(device_dir: &Path, file: &str) -> Option<String> {
+    std::fs::read_to_string(device_dir.join(file))
+        .ok()
+        .map(|x| x.trim().to_string())
+        .filter(|x| !x.is_empty())
+}
+
+/// Describes the BARs from the sysfs `resource` file, one `start end flags` line per region.
+fn read_bars(device_dir: &Path) -> Vec<String> {
+    const IORESOURCE_IO: u64 = 0x100;
+    const IORESOURCE_PREFETCH: u64 = 0x2000;
+    const IORESOURCE_MEM_64: u64 = 0x100000;
+    let parse_hex =
+        |x: &str| u64::from_str_radix(x.trim_start_matches("0x"), 16).unwrap_or_default();
+    std::fs::read_to_string(device_dir.join("resource"))
+        .unwrap_or_default()
+        .lines()
+        // Only the first six regions are BARs, the rest are the ROM and bridge windows
+        .take(6)
+        .enumerate()
+        .filter_map(|(index, line)| {
+            let fields: Vec<u64> = line.split_whitespace().map(parse_hex).collect();
+            let [start, end, flags] = fields[..] else {
+                return None;
+            };
+            if start == 0 && end == 0 {
+                return None;
+            }
+            let mut description = format!(
+                "BAR{} {:#x}-{:#x} ({} KiB, {}",
+                index,
+                start,
+                end,
+                (end - start + 1) / 1024,
+                if flags & IORESOURCE_IO != 0 {
+                    "I/O"
+                } else {
+                    "memory"
+                }
+            );
+            if flags & IORESOURCE_MEM_64 != 0 {
+                description.push_str(", 64-bit");
+            }
+            if flags & IORESOURCE_PREFETCH != 0 {
+                description.push_str(", prefetchable");
+            }
+            description.push(')');
+            Some(description)
+        })
+        .collect()
+}
+
+/// Link, power and resource details of a PCI device from sysfs, as (field, value) pairs in display order.
+/// Fields the device or kernel does not expose are left out.
+pub fn get_pci_advanced_details(sysfs_busid: &str) -> Vec<(String, String)> {
+    let device_dir = sysroot_path(&format!("/sys/bus/pci/devices/{}", sysfs_busid));
+    let mut details = vec![];
+    let link = |speed: &str, width: &str| match (
+        read_sysfs_value(&device_dir, speed),
+        read_sysfs_value(&device_dir, width),
+    ) {
+        (Some(speed), Some(width)) => Some(format!("{} x{}", speed, width)),
+        (Some(speed), None) => Some(speed),
+        _ => None,
+    };
+    let mut push = |field: &str, value: Option<String>| {
+        if let Some(value) = value {
+            details.push((field.to_string(), value));
+        }
+    };
+    push(
+        "current_link",
+        link("current_link_speed", "current_link_width"),
+    );
+    push("max_link", link("max_link_speed", "max_link_width"));
+    push("power_state", read_sysfs_value(&device_dir, "power_state"));
+    push(
+        "runtime_pm",
+        read_sysfs_value(&device_dir, "power/runtime_status").map(|status| match read_sysfs_value(
+            &device_dir,
+            "power/control",
+        ) {
+            Some(control) => format!("{} ({})", status, control),
+            None => status,
+        }),
+    );
+    push("irq", read_sysfs_value(&device_dir, "irq"));
+    // -1 means the platform has no NUMA information
+    push(
+        "numa_node",
+        read_sysfs_value(&device_dir, "numa_node").filter(|x| x != "-1"),
+    );
+    push("revision", read_sysfs_value(&device_dir, "revision"));
+    push(
+        "subsystem",
+        match (
+            read_sysfs_value(&device_dir, "subsystem_vendor"),
+            read_sysfs_value(&device_dir, "subsystem_device"),
+        ) {
+            (Some(vendor), Some(device)) => Some(format!(
+                "{}:{}",
+                vendor.trim_start_matches("0x"),
+                device.trim_start_matches("0x")
+            )),
+            _ => None,
+        },
+    );
+    // The sysfs device directory sits inside the one of the bridge it is behind
+    push(
+        "parent_bridge",
+        std::fs::canonicalize(&device_dir)
+            .ok()
+            .and_then(|x| {
+                x.parent()?
+                    .file_name()
+                    .map(|y| y.to_string_lossy().to_string())
+            })
+            .filter(|x| x.contains(':') && x.contains('.')),
+    );
+    let bars = read_bars(&device_dir);
+    if !bars.is_empty() {
+        push("bars", Some(bars.join("\n")));
+    }
+    details
+}
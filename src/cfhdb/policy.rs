@@ -0,0 +1,227 @@
+use super::{glob_match, ProfileSummary};
+use log::{error, info};
+
+pub const POLICY_FILE_PATH: &str = "/etc/cfhdb/policy.json";
+
+/// Site policy restricting which profiles may be installed, read from POLICY_FILE_PATH.
+///
+/// A profile is denied if it matches any `deny` rule, or if it fails a non empty `allow` rule.
+/// Without a policy file everything is allowed, an unreadable policy file denies every install.
+#[derive(serde::Deserialize, Default)]
+#[serde(default)]
+pub struct ProfilePolicy {
+    /// Hide denied profiles that are not installed instead of showing them locked
+    pub hide_denied: bool,
+    pub allow: PolicyRules,
+    pub deny: PolicyRules,
+    #[serde(skip)]
+    load_error: Option<String>,
+}
+
+#[derive(serde::Deserialize, Default)]
+#[serde(default)]
+pub struct PolicyRules {
+    pub licenses: Vec<String>,
+    /// Globs, `*` matches any run of characters and `?` a single one
+    pub codenames: Vec<String>,
+    pub buses: Vec<String>,
+    pub experimental: Option<bool>,
+}
+
+lazy_static::lazy_static! {
+    pub static ref PROFILE_POLICY: ProfilePolicy = ProfilePolicy::load(POLICY_FILE_PATH);
+}
+
+impl ProfilePolicy {
+    fn load(file_path: &str) -> Self {
+        let json_content = match std::fs::read_to_string(file_path) {
+            Ok(t) => t,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => {
+                info!(
+                    target: "policy",
+                    "No policy file at {}, all profiles are allowed",
+                    file_path
+                );
+                return Self::default();
+            }
+            Err(e) => {
+                error!(target: "policy", "Could not read {}: {}", file_path, e);
+                return Self {
+                    load_error: Some(e.to_string()),
+                    ..Default::default()
+                };
+            }
+        };
+        match serde_json::from_str::<Self>(&json_content) {
+            Ok(t) => {
+                info!(target: "policy", "Loaded policy from {}", file_path);
+                t
+            }
+            Err(e) => {
+                error!(target: "policy", "Could not parse {}: {}", file_path, e);
+                Self {
+                    load_error: Some(e.to_string()),
+                    ..Default::default()
+                }
+            }
+        }
+    }
+
+    /// Returns why installing the profile is denied, or None if it is allowed.
+    pub fn check(&self, profile: &ProfileSummary) -> Option<String> {
+        if let Some(e) = &self.load_error {
+            return Some(
+                t!("policy_denied_invalid", path = POLICY_FILE_PATH, error = e).to_string(),
+            );
+        }
+
+        let license_in = |list: &Vec<String>| {
+            list.iter()
+                .any(|x| x.eq_ignore_ascii_case(&profile.license))
+        };
+        let codename_in =
+            |list: &Vec<String>| list.iter().any(|x| glob_match(x, &profile.codename));
+        let bus_in = |list: &Vec<String>| list.iter().any(|x| x.eq_ignore_ascii_case(profile.bus));
+
+        if license_in(&self.deny.licenses)
+            || (!self.allow.licenses.is_empty() && !license_in(&self.allow.licenses))
+        {
+            return Some(t!("policy_denied_license", license = profile.license).to_string());
+        }
+        if profile.experimental
+            && (self.deny.experimental == Some(true) || self.allow.experimental == Some(false))
+        {
+            return Some(t!("policy_denied_experimental").to_string());
+        }
+        if codename_in(&self.deny.codenames)
+            || (!self.allow.codenames.is_empty() && !codename_in(&self.allow.codenames))
+        {
+            return Some(t!("policy_denied_codename", codename = profile.codename).to_string());
+        }
+        if bus_in(&self.deny.buses) || (!self.allow.buses.is_empty() && !bus_in(&self.allow.buses))
+        {
+            return Some(t!("policy_denied_bus", bus = profile.bus.to_uppercase()).to_string());
+        }
+        None
+    }
+
+    /// Whether a denied profile should be left out of the lists entirely.
+    pub fn hides(&self, profile: &ProfileSummary) -> bool {
+        self.hide_denied && self.check(profile).is_some()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::cfhdb::ProfileKernelArgs;
+
+    fn summary(bus: &'static str, codename: &str, license: &str) -> ProfileSummary {
+        ProfileSummary {
+            bus,
+            codename: codename.to_string(),
+            i18n_desc: codename.to_string(),
+            icon_name: String::new(),
+            license: license.to_string(),
+            packages: None,
+            install_script: None,
+            remove_script: None,
+            experimental: false,
+            removable: true,
+            veiled: false,
+            priority: 0,
+            kernel_args: ProfileKernelArgs::default(),
+            provides_firmware: vec![],
+        }
+    }
+
+    fn policy(json_content: &str) -> ProfilePolicy {
+        serde_json::from_str(json_content).unwrap()
+    }
+
+    #[test]
+    fn empty_policy_allows_everything() {
+        let policy = policy("{}");
+        assert!(policy
+            .check(&summary("pci", "nvidia-open", "proprietary"))
+            .is_none());
+        assert!(!policy.hides(&summary("pci", "nvidia-open", "proprietary")));
+    }
+
+    #[test]
+    fn deny_wins_over_allow() {
+        let policy = policy(
+            r#"{
+                "allow": { "codenames": ["nvidia-*"] },
+                "deny": { "codenames": ["nvidia-open"] }
+            }"#,
+        );
+        assert!(policy
+            .check(&summary("pci", "nvidia-open", "GPL-2.0"))
+            .is_some());
+        assert!(policy
+            .check(&summary("pci", "nvidia-closed", "GPL-2.0"))
+            .is_none());
+    }
+
+    #[test]
+    fn non_empty_allow_list_denies_the_rest() {
+        let policy = policy(r#"{ "allow": { "licenses": ["gpl-2.0"], "buses": ["PCI"] } }"#);
+        assert!(policy.check(&summary("pci", "mesa", "GPL-2.0")).is_none());
+        assert!(policy
+            .check(&summary("pci", "nvidia", "proprietary"))
+            .is_some());
+        assert!(policy.check(&summary("usb", "mesa", "GPL-2.0")).is_some());
+    }
+
+    #[test]
+    fn codename_rules_are_globs() {
+        let policy = policy(r#"{ "hide_denied": true, "deny": { "codenames": ["*-beta?"] } }"#);
+        assert!(policy
+            .check(&summary("pci", "nvidia-beta1", "MIT"))
+            .is_some());
+        assert!(policy.hides(&summary("pci", "nvidia-beta1", "MIT")));
+        assert!(policy
+            .check(&summary("pci", "nvidia-beta10", "MIT"))
+            .is_none());
+        assert!(policy
+            .check(&summary("pci", "nvidia-stable", "MIT"))
+            .is_none());
+    }
+
+    #[test]
+    fn experimental_rules() {
+        let mut experimental = summary("pci", "nvidia", "MIT");
+        experimental.experimental = true;
+        assert!(policy(r#"{ "deny": { "experimental": true } }"#)
+            .check(&experimental)
+            .is_some());
+        assert!(policy(r#"{ "allow": { "experimental": false } }"#)
+            .check(&experimental)
+            .is_some());
+        assert!(policy(r#"{ "deny": { "experimental": true } }"#)
+            .check(&summary("pci", "nvidia", "MIT"))
+            .is_none());
+    }
+
+    #[test]
+    fn missing_policy_file_allows_everything() {
+        let file_path = std::env::temp_dir().join("cfhdb-policy-test-missing.json");
+        let _ = std::fs::remove_file(&file_path);
+        let policy = ProfilePolicy::load(file_path.to_str().unwrap());
+        assert!(policy.check(&summary("pci", "mesa", "MIT")).is_none());
+    }
+
+    #[test]
+    fn malformed_policy_file_denies_everything() {
+        let file_path = std::env::temp_dir().join("cfhdb-policy-test-malformed.json");
+        std::fs::write(&file_path, r#"{ "allow": { "codenames": "mesa" } }"#).unwrap();
+        let policy = ProfilePolicy::load(file_path.to_str().unwrap());
+        let _ = std::fs::remove_file(&file_path);
+        assert!(policy.load_error.is_some());
+        assert!(policy.check(&summary("pci", "mesa", "MIT")).is_some());
+        assert!(policy
+            .check(&summary("usb", "anything", "GPL-2.0"))
+            .is_some());
+    }
+}
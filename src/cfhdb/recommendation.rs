@@ -0,0 +1,250 @@
+use super::{
+    bt::PreCheckedBtDevice, dmi::PreCheckedDmiInfo, pci::PreCheckedPciDevice,
+    policy::PROFILE_POLICY, sysfs_bus::SysfsBuses, usb::PreCheckedUsbDevice, PreCheckedProfile,
+    ProfileSummary,
+};
+use std::sync::Arc;
+
+pub struct Recommendation {
+    pub device: String,
+    pub profile: Arc<dyn PreCheckedProfile>,
+}
+
+/// The summaries of the profiles compatible with one device, as the selection sees them.
+pub struct DeviceProfiles {
+    pub profiles: Vec<ProfileSummary>,
+    /// True if any of the profiles is installed
+    pub installed: bool,
+}
+
+/// Index of the recommended profile out of the profiles compatible with one device: the lowest
+/// priority one that is neither experimental, veiled nor denied by `allowed`, ties go to the first listed.
+pub fn select_recommended_profile(
+    profiles: &[ProfileSummary],
+    allowed: impl Fn(&ProfileSummary) -> bool,
+) -> Option<usize> {
+    profiles
+        .iter()
+        .enumerate()
+        .filter(|(_, x)| !x.experimental && !x.veiled && allowed(x))
+        .min_by_key(|(_, x)| x.priority)
+        .map(|(i, _)| i)
+}
+
+/// (device index, profile index) of the recommendation of every device without an installed
+/// profile, a profile compatible with several devices is only recommended for the first one.
+pub fn select_recommendations(
+    devices: &[DeviceProfiles],
+    allowed: impl Fn(&ProfileSummary) -> bool,
+) -> Vec<(usize, usize)> {
+    let mut selected: Vec<(usize, usize)> = vec![];
+    for (device_index, device) in devices.iter().enumerate() {
+        // Devices that already have a profile installed are left alone, even if it is not the recommended one
+        if device.installed {
+            continue;
+        }
+        let Some(profile_index) = select_recommended_profile(&device.profiles, &allowed) else {
+            continue;
+        };
+        let summary = &device.profiles[profile_index];
+        if selected.iter().any(|(i, j)| {
+            let other_summary = &devices[*i].profiles[*j];
+            other_summary.bus == summary.bus && other_summary.codename == summary.codename
+        }) {
+            continue;
+        }
+        selected.push((device_index, profile_index));
+    }
+    selected
+}
+
+/// The recommended profile out of the profiles compatible with one device, under the site policy.
+pub fn recommended_profile<P: PreCheckedProfile>(profiles: &[Arc<P>]) -> Option<Arc<P>> {
+    let summaries: Vec<ProfileSummary> = profiles.iter().map(|x| x.summary()).collect();
+    select_recommended_profile(&summaries, |x| PROFILE_POLICY.check(x).is_none())
+        .map(|i| profiles[i].clone())
+}
+
+fn push_device<P: PreCheckedProfile + 'static>(
+    devices: &mut Vec<(String, Vec<Arc<dyn PreCheckedProfile>>)>,
+    device: String,
+    profiles: &[Arc<P>],
+) {
+    devices.push((
+        device,
+        profiles
+            .iter()
+            .map(|x| x.clone() as Arc<dyn PreCheckedProfile>)
+            .collect(),
+    ));
+}
+
+/// Collects the recommended profile of every device without an installed profile, each profile is listed once.
+pub fn get_pending_recommendations(
+    hashmap_pci: &Option<Vec<(String, Vec<PreCheckedPciDevice>)>>,
+    hashmap_usb: &Option<Vec<(String, Vec<PreCheckedUsbDevice>)>>,
+    dmi_info: &PreCheckedDmiInfo,
+    hashmap_bt: &Option<Vec<(String, Vec<PreCheckedBtDevice>)>>,
+    sysfs_buses: &SysfsBuses,
+) -> Vec<Recommendation> {
+    let mut devices = vec![];
+
+    push_device(
+        &mut devices,
+        format!(
+            "{} - {}",
+            dmi_info.info.sys_vendor, dmi_info.info.product_name
+        ),
+        &dmi_info.profiles,
+    );
+
+    if let Some(hashmap_pci) = hashmap_pci {
+        for (_, class_devices) in hashmap_pci {
+            for device in class_devices {
+                push_device(
+                    &mut devices,
+                    format!(
+                        "{} - {} ({})",
+                        device.device.vendor_name,
+                        device.device.device_name,
+                        device.device.sysfs_busid
+                    ),
+                    &device.profiles,
+                );
+            }
+        }
+    }
+
+    if let Some(hashmap_usb) = hashmap_usb {
+        for (_, class_devices) in hashmap_usb {
+            for device in class_devices {
+                push_device(
+                    &mut devices,
+                    format!(
+                        "{} - {} ({})",
+                        device.device.manufacturer_string_index,
+                        device.device.product_string_index,
+                        device.device.sysfs_busid
+                    ),
+                    &device.profiles,
+                );
+            }
+        }
+    }
+
+    if let Some(hashmap_bt) = hashmap_bt {
+        for (_, class_devices) in hashmap_bt {
+            for device in class_devices {
+                push_device(
+                    &mut devices,
+                    format!(
+                        "{} - {} ({})",
+                        device.device.alias, device.device.name, device.device.address
+                    ),
+                    &device.profiles,
+                );
+            }
+        }
+    }
+
+    for bus in sysfs_buses.buses() {
+        for device in bus.device_summaries() {
+            devices.push((format!("{} ({})", device.title, device.id), device.profiles));
+        }
+    }
+
+    let device_profiles: Vec<DeviceProfiles> = devices
+        .iter()
+        .map(|(_, profiles)| DeviceProfiles {
+            profiles: profiles.iter().map(|x| x.summary()).collect(),
+            installed: profiles.iter().any(|x| x.is_installed()),
+        })
+        .collect();
+    select_recommendations(&device_profiles, |x| PROFILE_POLICY.check(x).is_none())
+        .into_iter()
+        .map(|(i, j)| Recommendation {
+            device: devices[i].0.clone(),
+            profile: devices[i].1[j].clone(),
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::cfhdb::ProfileKernelArgs;
+
+    fn summary(codename: &str, priority: i32) -> ProfileSummary {
+        ProfileSummary {
+            bus: "pci",
+            codename: codename.to_string(),
+            i18n_desc: codename.to_string(),
+            icon_name: String::new(),
+            license: String::from("GPL-2.0"),
+            packages: None,
+            install_script: None,
+            remove_script: None,
+            experimental: false,
+            removable: true,
+            veiled: false,
+            priority,
+            kernel_args: ProfileKernelArgs::default(),
+            provides_firmware: vec![],
+        }
+    }
+
+    fn device(profiles: Vec<ProfileSummary>) -> DeviceProfiles {
+        DeviceProfiles {
+            profiles,
+            installed: false,
+        }
+    }
+
+    #[test]
+    fn lowest_priority_wins() {
+        let profiles = vec![summary("a", 20), summary("b", 10), summary("c", 10)];
+        assert_eq!(select_recommended_profile(&profiles, |_| true), Some(1));
+    }
+
+    #[test]
+    fn experimental_and_veiled_profiles_are_skipped() {
+        let mut experimental = summary("experimental", 0);
+        experimental.experimental = true;
+        let mut veiled = summary("veiled", 1);
+        veiled.veiled = true;
+        let profiles = vec![experimental, veiled, summary("stable", 10)];
+        assert_eq!(select_recommended_profile(&profiles, |_| true), Some(2));
+    }
+
+    #[test]
+    fn denied_profiles_are_skipped() {
+        let profiles = vec![summary("denied", 0), summary("allowed", 10)];
+        assert_eq!(
+            select_recommended_profile(&profiles, |x| x.codename != "denied"),
+            Some(1)
+        );
+        assert_eq!(select_recommended_profile(&profiles, |_| false), None);
+    }
+
+    #[test]
+    fn installed_profile_means_no_recommendation() {
+        let devices = vec![DeviceProfiles {
+            profiles: vec![summary("a", 0), summary("b", 10)],
+            installed: true,
+        }];
+        assert!(select_recommendations(&devices, |_| true).is_empty());
+    }
+
+    #[test]
+    fn shared_profile_is_recommended_once() {
+        let devices = vec![
+            device(vec![summary("shared", 0)]),
+            device(vec![summary("other", 10), summary("shared", 0)]),
+            device(vec![summary("other", 0)]),
+        ];
+        assert_eq!(
+            select_recommendations(&devices, |_| true),
+            vec![(0, 0), (2, 0)]
+        );
+    }
+}
@@ -0,0 +1,242 @@
+use super::{
+    kmod::bound_module, storage::parent_bus_device, sysfs_bus::SysfsBusDevice,
+    sysroot::sysroot_path,
+};
+use crate::config::SOUND_PROFILE_JSON_URL;
+
+/// A sound card from /proc/asound/cards, the fields of its line there.
+#[derive(Clone)]
+pub struct SoundCard {
+    pub index: String,
+    /// The ALSA card ID, `PCH` or `Generic`
+    pub id: String,
+    /// The ALSA driver name, `HDA-Intel`, `sof-hda-dsp` or `USB-Audio`
+    pub driver: String,
+    pub name: String,
+    pub long_name: String,
+}
+
+/// An HDA codec from /proc/asound/card*/codec#*, the IDs quirks are keyed by.
+#[derive(Clone)]
+pub struct HdaCodec {
+    pub address: String,
+    pub name: String,
+    /// Eight lower case hex digits, `10ec0287` for a Realtek ALC287
+    pub vendor_id: String,
+    pub subsystem_id: String,
+    pub revision_id: String,
+}
+
+/// A codec on a sound card, or the card itself for cards without HDA codecs.
+#[derive(Clone)]
+pub struct SoundDevice {
+    pub card: SoundCard,
+    pub codec: Option<HdaCodec>,
+    /// The kernel module bound to the card's device
+    pub kernel_driver: Option<String>,
+    /// The HDA controller or USB device of the card
+    pub parent: Option<(String, String)>,
+}
+
+/// Parses /proc/asound/cards, two lines per card:
+/// ` 0 [PCH            ]: HDA-Intel - HDA Intel PCH` then the long name.
+fn parse_cards(cards: &str) -> Vec<SoundCard> {
+    let mut parsed_cards = vec![];
+    let mut lines = cards.lines();
+    while let Some(line) = lines.next() {
+        let Some((index, rest)) = line.trim_start().split_once(" [") else {
+            continue;
+        };
+        let Some((id, rest)) = rest.split_once("]: ") else {
+            continue;
+        };
+        let (driver, name) = rest.split_once(" - ").unwrap_or((rest, rest));
+        parsed_cards.push(SoundCard {
+            index: index.trim().to_string(),
+            id: id.trim().to_string(),
+            driver: driver.trim().to_string(),
+            name: name.trim().to_string(),
+            long_name: lines.next().unwrap_or_default().trim().to_string(),
+        });
+    }
+    parsed_cards
+}
+
+fn codec_value(codec: &str, key: &str) -> Option<String> {
+    codec
+        .lines()
+        .find_map(|x| x.strip_prefix(key)?.strip_prefix(": "))
+        .map(|x| x.trim().to_string())
+}
+
+fn codec_id(codec: &str, key: &str) -> String {
+    codec_value(codec, key)
+        .map(|x| x.trim_start_matches("0x").to_lowercase())
+        .unwrap_or_default()
+}
+
+fn read_codecs(card: &SoundCard) -> Vec<HdaCodec> {
+    let mut codecs: Vec<HdaCodec> =
+        match std::fs::read_dir(sysroot_path(&format!("/proc/asound/card{}", card.index))) {
+            Ok(entries) => entries
+                .flatten()
+                .filter(|x| x.file_name().to_string_lossy().starts_with("codec#"))
+                .filter_map(|x| std::fs::read_to_string(x.path()).ok())
+                .map(|codec| HdaCodec {
+                    address: codec_value(&codec, "Address").unwrap_or_default(),
+                    name: codec_value(&codec, "Codec").unwrap_or_default(),
+                    vendor_id: codec_id(&codec, "Vendor Id"),
+                    subsystem_id: codec_id(&codec, "Subsystem Id"),
+                    revision_id: codec_id(&codec, "Revision Id"),
+                })
+                .collect(),
+            Err(_) => vec![],
+        };
+    codecs.sort_by(|a, b| a.address.cmp(&b.address));
+    codecs
+}
+
+impl SoundDevice {
+    fn is_hdmi(&self) -> bool {
+        self.codec
+            .as_ref()
+            .is_some_and(|x| x.name.contains("HDMI") || x.name.contains("DisplayPort"))
+    }
+}
+
+impl SysfsBusDevice for SoundDevice {
+    const BUS: &'static str = "sound";
+    const MATCH_KEYS: &'static [&'static str] =
+        &["codec_vendor_ids", "codec_subsystem_ids", "card_drivers"];
+
+    fn enumerate() -> Vec<Self> {
+        let cards = std::fs::read_to_string(sysroot_path("/proc/asound/cards")).unwrap_or_default();
+        let mut devices = vec![];
+        for card in parse_cards(&cards) {
+            let device_dir = sysroot_path(&format!("/sys/class/sound/card{}/device", card.index));
+            let kernel_driver = bound_module(&device_dir).map(|x| x.0);
+            let parent = parent_bus_device(&device_dir);
+            let codecs = read_codecs(&card);
+            if codecs.is_empty() {
+                devices.push(SoundDevice {
+                    card,
+                    codec: None,
+                    kernel_driver,
+                    parent,
+                });
+                continue;
+            }
+            for codec in codecs {
+                devices.push(SoundDevice {
+                    card: card.clone(),
+                    codec: Some(codec),
+                    kernel_driver: kernel_driver.clone(),
+                    parent: parent.clone(),
+                });
+            }
+        }
+        devices
+    }
+
+    fn profile_url() -> Option<String> {
+        SOUND_PROFILE_JSON_URL.clone()
+    }
+
+    fn id(&self) -> String {
+        match &self.codec {
+            Some(codec) => format!("card{}-codec{}", self.card.index, codec.address),
+            None => format!("card{}", self.card.index),
+        }
+    }
+
+    fn class(&self) -> String {
+        if self.is_hdmi() {
+            "hdmi"
+        } else if self.codec.is_some() {
+            "hda"
+        } else if self.parent.as_ref().is_some_and(|x| x.0 == "usb") {
+            "usb"
+        } else {
+            "other"
+        }
+        .to_string()
+    }
+
+    fn title(&self) -> String {
+        match &self.codec {
+            Some(codec) if !codec.name.is_empty() => codec.name.clone(),
+            _ => self.card.name.clone(),
+        }
+    }
+
+    fn subtitle(&self) -> String {
+        match &self.codec {
+            Some(codec) => t!(
+                "sound_codec_subtitle",
+                card = self.card.name,
+                address = codec.address
+            )
+            .to_string(),
+            None => self.card.long_name.clone(),
+        }
+    }
+
+    fn details(&self) -> Vec<(String, String)> {
+        let mut details = vec![
+            (
+                "sound_detail_card_index".to_string(),
+                self.card.index.clone(),
+            ),
+            ("sound_detail_card_id".to_string(), self.card.id.clone()),
+            (
+                "sound_detail_card_name".to_string(),
+                self.card.long_name.clone(),
+            ),
+            (
+                "sound_detail_card_driver".to_string(),
+                self.card.driver.clone(),
+            ),
+        ];
+        if let Some(kernel_driver) = &self.kernel_driver {
+            details.push((
+                "sound_detail_kernel_driver".to_string(),
+                kernel_driver.clone(),
+            ));
+        }
+        if let Some(codec) = &self.codec {
+            details.extend([
+                ("sound_detail_codec_name".to_string(), codec.name.clone()),
+                (
+                    "sound_detail_codec_address".to_string(),
+                    codec.address.clone(),
+                ),
+                (
+                    "sound_detail_codec_vendor_id".to_string(),
+                    codec.vendor_id.clone(),
+                ),
+                (
+                    "sound_detail_codec_subsystem_id".to_string(),
+                    codec.subsystem_id.clone(),
+                ),
+                (
+                    "sound_detail_codec_revision_id".to_string(),
+                    codec.revision_id.clone(),
+                ),
+            ]);
+        }
+        details
+    }
+
+    fn match_values(&self, key: &str) -> Vec<String> {
+        match key {
+            "codec_vendor_ids" => self.codec.iter().map(|x| x.vendor_id.clone()).collect(),
+            "codec_subsystem_ids" => self.codec.iter().map(|x| x.subsystem_id.clone()).collect(),
+            "card_drivers" => vec![self.card.driver.clone()],
+            _ => vec![],
+        }
+    }
+
+    fn parent_device(&self) -> Option<(String, String)> {
+        self.parent.clone()
+    }
+}
@@ -0,0 +1,216 @@
+use super::{
+    kmod::bound_module, sysfs_bus::SysfsBusDevice, sysroot::sysroot_path, usb::read_sysfs_value,
+};
+use crate::config::STORAGE_PROFILE_JSON_URL;
+use gtk::glib;
+use std::path::{Path, PathBuf};
+
+/// A whole disk from /sys/class/block, an NVMe namespace, a SATA or USB drive or an MMC card.
+#[derive(Clone)]
+pub struct StorageDevice {
+    /// The block device name, `nvme0n1` or `sda`
+    pub name: String,
+    /// `nvme`, `sata`, `usb`, `mmc`, `virtio` or `scsi`, the class of the device
+    pub transport: String,
+    pub model: String,
+    /// The PCI vendor ID of the controller for NVMe, the vendor string of the drive otherwise
+    pub vendor: Option<String>,
+    pub firmware_revision: Option<String>,
+    pub serial: Option<String>,
+    pub namespace: Option<String>,
+    pub size_bytes: u64,
+    pub rotational: bool,
+    pub driver: Option<String>,
+    /// From the drive's hwmon sensor, NVMe always has one, SATA drives need the drivetemp module
+    pub temperature: Option<String>,
+    pub parent: Option<(String, String)>,
+}
+
+fn is_pci_busid(name: &str) -> bool {
+    let parts: Vec<&str> = name.split([':', '.']).collect();
+    parts.len() == 4
+        && [4, 2, 2, 1]
+            .iter()
+            .zip(&parts)
+            .all(|(len, part)| part.len() == *len && part.chars().all(|x| x.is_ascii_hexdigit()))
+}
+
+/// The nearest USB device or PCI function above a sysfs device directory, as (bus, sysfs bus ID).
+pub fn parent_bus_device(device_dir: &Path) -> Option<(String, String)> {
+    let device_dir = std::fs::canonicalize(device_dir).ok()?;
+    device_dir.ancestors().find_map(|x| {
+        let name = x.file_name()?.to_string_lossy().to_string();
+        if x.join("idVendor").exists() {
+            Some(("usb".to_string(), name))
+        } else if is_pci_busid(&name) {
+            Some(("pci".to_string(), name))
+        } else {
+            None
+        }
+    })
+}
+
+fn hwmon_temperature(device_dir: &Path) -> Option<String> {
+    // NVMe controllers have their hwmon directly below them, drivetemp puts it in a hwmon class directory
+    [device_dir.to_path_buf(), device_dir.join("hwmon")]
+        .iter()
+        .filter_map(|x| std::fs::read_dir(x).ok())
+        .flat_map(|x| x.flatten())
+        .filter(|x| x.file_name().to_string_lossy().starts_with("hwmon"))
+        .find_map(|x| read_sysfs_value(&x.path(), "temp1_input"))
+        .and_then(|x| x.parse::<f64>().ok())
+        .map(|x| format!("{:.1} °C", x / 1000.0))
+}
+
+fn transport(name: &str, block_dir: &Path) -> String {
+    let canonical_dir = std::fs::canonicalize(block_dir)
+        .unwrap_or_default()
+        .to_string_lossy()
+        .to_string();
+    if name.starts_with("nvme") {
+        "nvme"
+    } else if name.starts_with("mmcblk") {
+        "mmc"
+    } else if canonical_dir.contains("/usb") {
+        "usb"
+    } else if canonical_dir.contains("/ata") {
+        "sata"
+    } else if canonical_dir.contains("/virtio") {
+        "virtio"
+    } else {
+        "scsi"
+    }
+    .to_string()
+}
+
+fn read_storage_device(block_dir: PathBuf) -> Option<StorageDevice> {
+    let name = block_dir.file_name()?.to_string_lossy().to_string();
+    // Partitions are listed next to their disk, loop, zram and device mapper devices have no device
+    let device_dir = block_dir.join("device");
+    if block_dir.join("partition").exists() || !device_dir.exists() {
+        return None;
+    }
+    let transport = transport(&name, &block_dir);
+    let (model, vendor, firmware_revision, serial) = match transport.as_str() {
+        // The device of a namespace is its controller, the controller's device the PCI function
+        "nvme" => (
+            read_sysfs_value(&device_dir, "model"),
+            read_sysfs_value(&device_dir.join("device"), "vendor")
+                .map(|x| x.trim_start_matches("0x").to_string()),
+            read_sysfs_value(&device_dir, "firmware_rev"),
+            read_sysfs_value(&device_dir, "serial"),
+        ),
+        "mmc" => (
+            read_sysfs_value(&device_dir, "name"),
+            read_sysfs_value(&device_dir, "manfid"),
+            read_sysfs_value(&device_dir, "fwrev"),
+            read_sysfs_value(&device_dir, "serial"),
+        ),
+        _ => (
+            read_sysfs_value(&device_dir, "model"),
+            read_sysfs_value(&device_dir, "vendor"),
+            read_sysfs_value(&device_dir, "rev"),
+            None,
+        ),
+    };
+    Some(StorageDevice {
+        model: model.unwrap_or_else(|| name.clone()),
+        vendor,
+        firmware_revision,
+        serial,
+        namespace: read_sysfs_value(&block_dir, "nsid"),
+        size_bytes: read_sysfs_value(&block_dir, "size")
+            .and_then(|x| x.parse::<u64>().ok())
+            .unwrap_or_default()
+            * 512,
+        rotational: read_sysfs_value(&block_dir, "queue/rotational").as_deref() == Some("1"),
+        driver: bound_module(&device_dir)
+            .or_else(|| bound_module(&device_dir.join("device")))
+            .map(|x| x.0),
+        temperature: hwmon_temperature(&device_dir),
+        parent: parent_bus_device(&device_dir),
+        transport,
+        name,
+    })
+}
+
+impl SysfsBusDevice for StorageDevice {
+    const BUS: &'static str = "storage";
+    const MATCH_KEYS: &'static [&'static str] = &["models", "firmware_revisions", "vendors"];
+
+    fn enumerate() -> Vec<Self> {
+        match std::fs::read_dir(sysroot_path("/sys/class/block")) {
+            Ok(entries) => entries
+                .flatten()
+                .filter_map(|x| read_storage_device(x.path()))
+                .collect(),
+            Err(_) => vec![],
+        }
+    }
+
+    fn profile_url() -> Option<String> {
+        STORAGE_PROFILE_JSON_URL.clone()
+    }
+
+    fn id(&self) -> String {
+        self.name.clone()
+    }
+
+    fn class(&self) -> String {
+        self.transport.clone()
+    }
+
+    fn title(&self) -> String {
+        self.model.clone()
+    }
+
+    fn subtitle(&self) -> String {
+        format!("{} - {}", self.name, glib::format_size(self.size_bytes))
+    }
+
+    fn details(&self) -> Vec<(String, String)> {
+        let mut details = vec![
+            ("storage_detail_name".to_string(), self.name.clone()),
+            ("storage_detail_model".to_string(), self.model.clone()),
+        ];
+        for (key, value) in [
+            ("storage_detail_vendor", &self.vendor),
+            ("storage_detail_firmware_revision", &self.firmware_revision),
+            ("storage_detail_serial", &self.serial),
+            ("storage_detail_namespace", &self.namespace),
+            ("storage_detail_driver", &self.driver),
+            ("storage_detail_temperature", &self.temperature),
+        ] {
+            if let Some(value) = value {
+                details.push((key.to_string(), value.clone()));
+            }
+        }
+        details.push((
+            "storage_detail_size".to_string(),
+            glib::format_size(self.size_bytes).to_string(),
+        ));
+        details.push((
+            "storage_detail_media".to_string(),
+            if self.rotational {
+                t!("storage_media_rotational")
+            } else {
+                t!("storage_media_solid_state")
+            }
+            .to_string(),
+        ));
+        details
+    }
+
+    fn match_values(&self, key: &str) -> Vec<String> {
+        match key {
+            "models" => vec![self.model.clone()],
+            "firmware_revisions" => self.firmware_revision.iter().cloned().collect(),
+            "vendors" => self.vendor.iter().cloned().collect(),
+            _ => vec![],
+        }
+    }
+
+    fn parent_device(&self) -> Option<(String, String)> {
+        self.parent.clone()
+    }
+}
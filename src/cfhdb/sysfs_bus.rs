@@ -0,0 +1,458 @@
+use super::{
+    cpu::CpuDevice, display::Display, glob_match, input::InputDevice, network::NetworkInterface,
+    sound::SoundDevice, storage::StorageDevice, PreCheckedProfile, ProfileExtras, ProfileSummary,
+};
+use crate::ChannelMsg;
+use log::{debug, error, info, warn};
+use std::{
+    fs,
+    marker::PhantomData,
+    path::Path,
+    sync::{Arc, Mutex},
+};
+
+/// A device of a bus that is enumerated from sysfs here instead of by libcfhdb,
+/// with the profile keys it can be matched on.
+pub trait SysfsBusDevice: Clone + Send + Sync + 'static {
+    /// Bus name of the profile summaries and logs, and the prefix of the class i18n keys
+    const BUS: &'static str;
+    /// Profile keys matched against `match_values`, each also has a `blacklisted_` variant
+    const MATCH_KEYS: &'static [&'static str];
+
+    /// Every device of the bus present right now.
+    fn enumerate() -> Vec<Self>;
+    /// URL of the profile database, None if the distribution does not configure one
+    fn profile_url() -> Option<String>;
+    /// Unique on the bus, also the tag of the device page
+    fn id(&self) -> String;
+    /// Devices are grouped in sidebar pages by class, `<bus>_class_name_<class>` names them
+    fn class(&self) -> String;
+    fn title(&self) -> String;
+    fn subtitle(&self) -> String;
+    /// (i18n key, value) rows of the device page, only the ones known for this device
+    fn details(&self) -> Vec<(String, String)>;
+    /// The values `key` is matched against, empty if the device has none
+    fn match_values(&self, key: &str) -> Vec<String>;
+    /// The PCI or USB device this one hangs off, as (bus, sysfs bus ID)
+    fn parent_device(&self) -> Option<(String, String)> {
+        None
+    }
+}
+
+/// A profile of a sysfs enumerated bus, the fields every bus shares and the bus specific match lists.
+#[derive(Clone)]
+pub struct SysfsBusProfile {
+    pub codename: String,
+    pub i18n_desc: String,
+    pub icon_name: String,
+    pub license: String,
+    /// (key, patterns) for every key of `SysfsBusDevice::MATCH_KEYS`
+    pub matches: Vec<(String, Vec<String>)>,
+    pub blacklists: Vec<(String, Vec<String>)>,
+    pub packages: Option<Vec<String>>,
+    pub check_script: String,
+    pub install_script: Option<String>,
+    pub remove_script: Option<String>,
+    pub experimental: bool,
+    pub removable: bool,
+    pub veiled: bool,
+    pub priority: i32,
+}
+
+impl SysfsBusProfile {
+    fn from_json(profile: &serde_json::Value, match_keys: &[&str]) -> Self {
+        let strings = |key: &str| -> Vec<String> {
+            match profile[key].as_array() {
+                Some(t) => t
+                    .iter()
+                    .map(|x| x.as_str().unwrap_or_default().to_string())
+                    .collect(),
+                None => vec![],
+            }
+        };
+        let optional_script = |key: &str| match profile[key].as_str().unwrap_or_default() {
+            "Option::is_none" => None,
+            t => Some(t.to_string()),
+        };
+        let i18n_desc = match profile[format!("i18n_desc[{}]", &*rust_i18n::locale())].as_str() {
+            Some(t) if !t.is_empty() => t.to_string(),
+            _ => profile["i18n_desc"]
+                .as_str()
+                .unwrap_or_default()
+                .to_string(),
+        };
+        Self {
+            codename: profile["codename"].as_str().unwrap_or_default().to_string(),
+            i18n_desc,
+            icon_name: profile["icon_name"]
+                .as_str()
+                .unwrap_or("package-x-generic")
+                .to_string(),
+            license: profile["license"]
+                .as_str()
+                .unwrap_or(&t!("unknown"))
+                .to_string(),
+            matches: match_keys
+                .iter()
+                .map(|x| (x.to_string(), strings(x)))
+                .collect(),
+            blacklists: match_keys
+                .iter()
+                .map(|x| (x.to_string(), strings(&format!("blacklisted_{}", x))))
+                .collect(),
+            // A string like "Option::is_none", a missing key and an empty list all mean no packages
+            packages: Some(strings("packages")).filter(|x| !x.is_empty()),
+            check_script: profile["check_script"]
+                .as_str()
+                .unwrap_or("false")
+                .to_string(),
+            install_script: optional_script("install_script"),
+            remove_script: optional_script("remove_script"),
+            experimental: profile["experimental"].as_bool().unwrap_or_default(),
+            removable: profile["removable"].as_bool().unwrap_or_default(),
+            veiled: profile["veiled"].as_bool().unwrap_or_default(),
+            priority: profile["priority"].as_i64().unwrap_or_default() as i32,
+        }
+    }
+
+    pub fn get_status(&self) -> bool {
+        duct::cmd!("bash", "-c", &self.check_script)
+            .stdout_null()
+            .stderr_null()
+            .unchecked()
+            .run()
+            .is_ok_and(|x| x.status.success())
+    }
+
+    /// Like the libcfhdb buses, every key has to match and no blacklist may, `*` matches anything.
+    /// Patterns are globs, so one profile can cover a whole model line.
+    fn matches_device<D: SysfsBusDevice>(&self, device: &D) -> bool {
+        let any_match = |patterns: &Vec<String>, values: &Vec<String>| {
+            patterns.iter().any(|x| x == "*")
+                || patterns
+                    .iter()
+                    .any(|x| values.iter().any(|y| glob_match(x, y)))
+        };
+        let blacklisted = self
+            .blacklists
+            .iter()
+            .any(|(key, patterns)| any_match(patterns, &device.match_values(key)));
+        !blacklisted
+            && self
+                .matches
+                .iter()
+                .all(|(key, patterns)| any_match(patterns, &device.match_values(key)))
+    }
+}
+
+pub struct PreCheckedSysfsProfile<D> {
+    profile: SysfsBusProfile,
+    extras: ProfileExtras,
+    installed: Arc<Mutex<bool>>,
+    pub used: Arc<Mutex<bool>>,
+    bus: PhantomData<fn() -> D>,
+}
+
+impl<D: SysfsBusDevice> PreCheckedSysfsProfile<D> {
+    pub fn new(profile: SysfsBusProfile, extras: ProfileExtras) -> Self {
+        Self {
+            profile,
+            extras,
+            installed: Arc::new(Mutex::new(false)),
+            used: Arc::new(Mutex::new(false)),
+            bus: PhantomData,
+        }
+    }
+    pub fn profile(&self) -> SysfsBusProfile {
+        self.profile.clone()
+    }
+    pub fn installed(&self) -> bool {
+        *self.installed.lock().unwrap()
+    }
+    pub fn update_installed(&self) {
+        *self.installed.lock().unwrap() = self.profile.get_status();
+    }
+}
+
+impl<D: SysfsBusDevice> PreCheckedProfile for PreCheckedSysfsProfile<D> {
+    fn summary(&self) -> ProfileSummary {
+        ProfileSummary {
+            bus: D::BUS,
+            codename: self.profile.codename.clone(),
+            i18n_desc: self.profile.i18n_desc.clone(),
+            icon_name: self.profile.icon_name.clone(),
+            license: self.profile.license.clone(),
+            packages: self.profile.packages.clone(),
+            install_script: self.profile.install_script.clone(),
+            remove_script: self.profile.remove_script.clone(),
+            experimental: self.profile.experimental,
+            removable: self.profile.removable,
+            veiled: self.profile.veiled,
+            priority: self.profile.priority,
+            kernel_args: self.extras.kernel_args.clone(),
+            provides_firmware: self.extras.provides_firmware.clone(),
+        }
+    }
+    fn is_installed(&self) -> bool {
+        self.installed()
+    }
+    fn refresh_installed(&self) {
+        self.update_installed();
+    }
+}
+
+#[derive(Clone)]
+pub struct PreCheckedSysfsDevice<D> {
+    pub device: D,
+    pub profiles: Vec<Arc<PreCheckedSysfsProfile<D>>>,
+}
+
+/// Enumerates the bus and matches the profiles, grouped by class like the libcfhdb class hashmaps.
+pub fn get_sysfs_bus_devices<D: SysfsBusDevice>(
+    profiles: &[Arc<PreCheckedSysfsProfile<D>>],
+) -> Vec<(String, Vec<PreCheckedSysfsDevice<D>>)> {
+    let mut classes: Vec<(String, Vec<PreCheckedSysfsDevice<D>>)> = vec![];
+    for device in D::enumerate() {
+        let matching_profiles: Vec<_> = profiles
+            .iter()
+            .filter(|x| x.profile.matches_device(&device))
+            .cloned()
+            .collect();
+        for profile in &matching_profiles {
+            debug!(
+                target: "matcher",
+                "{} device {} matched profile {}",
+                D::BUS,
+                device.id(),
+                profile.profile.codename
+            );
+        }
+        let pre_checked_device = PreCheckedSysfsDevice {
+            device,
+            profiles: matching_profiles,
+        };
+        let class = pre_checked_device.device.class();
+        match classes.iter_mut().find(|x| x.0 == class) {
+            Some(t) => t.1.push(pre_checked_device),
+            None => classes.push((class, vec![pre_checked_device])),
+        }
+    }
+    classes.sort_by(|a, b| a.0.cmp(&b.0));
+    for (_, devices) in classes.iter_mut() {
+        devices.sort_by_key(|a| a.device.id());
+    }
+    classes
+}
+
+fn send_output_line(sender: &async_channel::Sender<ChannelMsg>, level: &str, line: String) {
+    sender
+        .send_blocking(ChannelMsg::OutputLine(format!("[{}] {}", t!(level), line)))
+        .expect("Channel closed");
+}
+
+/// Downloads the profile database of the bus, falling back to the cached copy like the other buses.
+pub fn get_sysfs_bus_profiles_from_url<D: SysfsBusDevice>(
+    sender: &async_channel::Sender<ChannelMsg>,
+) -> Result<Vec<(SysfsBusProfile, ProfileExtras)>, std::io::Error> {
+    let bus_i18n = t!(format!("{}_devices", D::BUS)).to_string();
+    let Some(profile_url) = D::profile_url() else {
+        info!(target: D::BUS, "No profile database configured, skipping download");
+        return Ok(vec![]);
+    };
+    let cached_db_path = Path::new("/var/cache/cfhdb").join(format!("{}.json", D::BUS));
+    send_output_line(
+        sender,
+        "info",
+        t!("sysfs_bus_download_starting", bus = bus_i18n).to_string(),
+    );
+    let client = reqwest::blocking::Client::builder()
+        .timeout(std::time::Duration::from_secs(5))
+        .build()
+        .unwrap();
+    info!(target: D::BUS, "Downloading profiles from {}", profile_url);
+    let data = match client.get(&profile_url).send().and_then(|x| x.text()) {
+        Ok(t) => {
+            info!(target: D::BUS, "Profile download successful");
+            send_output_line(
+                sender,
+                "info",
+                t!("sysfs_bus_download_successful", bus = bus_i18n).to_string(),
+            );
+            let _ = fs::write(&cached_db_path, &t);
+            t
+        }
+        Err(e) => {
+            warn!(target: D::BUS, "Profile download failed: {}", e);
+            send_output_line(
+                sender,
+                "warn",
+                t!("sysfs_bus_download_failed", bus = bus_i18n).to_string(),
+            );
+            match fs::read_to_string(&cached_db_path) {
+                Ok(t) => {
+                    info!(
+                        target: D::BUS,
+                        "Using cached profiles from {}",
+                        cached_db_path.display()
+                    );
+                    send_output_line(
+                        sender,
+                        "info",
+                        t!("sysfs_bus_download_cache_found", bus = bus_i18n).to_string(),
+                    );
+                    t
+                }
+                Err(_) => {
+                    error!(target: D::BUS, "No cached profiles found, giving up");
+                    let message = t!("sysfs_bus_download_cache_not_found", bus = bus_i18n);
+                    send_output_line(sender, "error", message.to_string());
+                    return Err(std::io::Error::new(std::io::ErrorKind::NotFound, message));
+                }
+            }
+        }
+    };
+    let res: serde_json::Value = serde_json::from_str(&data)
+        .map_err(|e| std::io::Error::new(std::io::ErrorKind::InvalidData, e))?;
+    let mut profiles_array: Vec<(SysfsBusProfile, ProfileExtras)> = match &res["profiles"] {
+        serde_json::Value::Array(profiles) => profiles
+            .iter()
+            .map(|x| {
+                (
+                    SysfsBusProfile::from_json(x, D::MATCH_KEYS),
+                    ProfileExtras::from_json(x),
+                )
+            })
+            .collect(),
+        _ => vec![],
+    };
+    profiles_array.sort_by_key(|x| x.0.priority);
+    Ok(profiles_array)
+}
+
+/// The devices and profiles of one sysfs enumerated bus.
+#[derive(Clone)]
+pub struct SysfsBusData<D> {
+    pub devices: Vec<(String, Vec<PreCheckedSysfsDevice<D>>)>,
+    pub profiles: Vec<Arc<PreCheckedSysfsProfile<D>>>,
+}
+
+impl<D: SysfsBusDevice> SysfsBusData<D> {
+    fn load(sender: &async_channel::Sender<ChannelMsg>) -> Self {
+        let profiles: Vec<Arc<PreCheckedSysfsProfile<D>>> =
+            match get_sysfs_bus_profiles_from_url::<D>(sender) {
+                Ok(t) => t
+                    .into_iter()
+                    .map(|(profile, extras)| {
+                        let profile = PreCheckedSysfsProfile::new(profile, extras);
+                        profile.update_installed();
+                        Arc::new(profile)
+                    })
+                    .collect(),
+                Err(_) => vec![],
+            };
+        let devices = get_sysfs_bus_devices(&profiles);
+        Self { devices, profiles }
+    }
+}
+
+/// A device of any sysfs enumerated bus, reduced to what is shown about it outside its own page.
+#[derive(Clone)]
+pub struct SysfsBusDeviceSummary {
+    pub class: String,
+    pub id: String,
+    pub title: String,
+    pub subtitle: String,
+    /// The detail values and the codenames and descriptions of the matching profiles
+    pub fields: Vec<String>,
+    pub parent: Option<(String, String)>,
+    pub profiles: Vec<Arc<dyn PreCheckedProfile>>,
+}
+
+/// The parts of `SysfsBusData` that do not depend on the device type.
+pub trait SysfsBus {
+    fn bus(&self) -> &'static str;
+    fn profiles(&self) -> Vec<Arc<dyn PreCheckedProfile>>;
+    fn device_summaries(&self) -> Vec<SysfsBusDeviceSummary>;
+}
+
+impl<D: SysfsBusDevice> SysfsBus for SysfsBusData<D> {
+    fn bus(&self) -> &'static str {
+        D::BUS
+    }
+
+    fn profiles(&self) -> Vec<Arc<dyn PreCheckedProfile>> {
+        self.profiles
+            .iter()
+            .map(|x| x.clone() as Arc<dyn PreCheckedProfile>)
+            .collect()
+    }
+
+    fn device_summaries(&self) -> Vec<SysfsBusDeviceSummary> {
+        self.devices
+            .iter()
+            .flat_map(|(class, devices)| {
+                devices.iter().map(|x| {
+                    let mut fields: Vec<String> =
+                        x.device.details().into_iter().map(|x| x.1).collect();
+                    for profile in &x.profiles {
+                        fields.push(profile.profile.codename.clone());
+                        fields.push(profile.profile.i18n_desc.clone());
+                    }
+                    SysfsBusDeviceSummary {
+                        class: class.clone(),
+                        id: x.device.id(),
+                        title: x.device.title(),
+                        subtitle: x.device.subtitle(),
+                        fields,
+                        parent: x.device.parent_device(),
+                        profiles: x
+                            .profiles
+                            .iter()
+                            .map(|x| x.clone() as Arc<dyn PreCheckedProfile>)
+                            .collect(),
+                    }
+                })
+            })
+            .collect()
+    }
+}
+
+/// Every sysfs enumerated bus, carried as one value from the loader to the UI.
+#[derive(Clone)]
+pub struct SysfsBuses {
+    pub storage: SysfsBusData<StorageDevice>,
+    pub input: SysfsBusData<InputDevice>,
+    pub display: SysfsBusData<Display>,
+    pub sound: SysfsBusData<SoundDevice>,
+    pub cpu: SysfsBusData<CpuDevice>,
+    pub network: SysfsBusData<NetworkInterface>,
+}
+
+impl SysfsBuses {
+    pub fn load(sender: &async_channel::Sender<ChannelMsg>) -> Self {
+        Self {
+            storage: SysfsBusData::load(sender),
+            input: SysfsBusData::load(sender),
+            display: SysfsBusData::load(sender),
+            sound: SysfsBusData::load(sender),
+            cpu: SysfsBusData::load(sender),
+            network: SysfsBusData::load(sender),
+        }
+    }
+
+    /// Every bus, in sidebar order, for code that handles them all alike.
+    pub fn buses(&self) -> Vec<&dyn SysfsBus> {
+        vec![
+            &self.cpu,
+            &self.network,
+            &self.storage,
+            &self.input,
+            &self.display,
+            &self.sound,
+        ]
+    }
+
+    pub fn all_profiles(&self) -> Vec<Arc<dyn PreCheckedProfile>> {
+        self.buses().iter().flat_map(|x| x.profiles()).collect()
+    }
+}
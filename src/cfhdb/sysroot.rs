@@ -0,0 +1,78 @@
+use std::path::PathBuf;
+
+/// Points sysfs, procfs and /lib/modules reads at a fake tree, so device handling can be tried
+/// without the hardware. Writes always go through the privileged scripts to the real system.
+pub const SYSROOT_ENV: &str = "NOBARA_DRIVER_MANAGER_SYSROOT";
+
+/// Returns where an absolute system path is read from, `path` itself unless SYSROOT_ENV is set.
+pub fn sysroot_path(path: &str) -> PathBuf {
+    match std::env::var_os(SYSROOT_ENV) {
+        Some(root) if !root.is_empty() => PathBuf::from(root).join(path.trim_start_matches('/')),
+        _ => PathBuf::from(path),
+    }
+}
+
+/// A throwaway tree under the temp dir for tests that read through `sysroot_path`.
+#[cfg(test)]
+pub struct FakeSysroot {
+    pub root: PathBuf,
+}
+
+#[cfg(test)]
+impl FakeSysroot {
+    pub fn new(name: &str) -> Self {
+        // Tests sharing a name still get a tree each
+        static COUNT: std::sync::atomic::AtomicUsize = std::sync::atomic::AtomicUsize::new(0);
+        let root = std::env::temp_dir().join(format!(
+            "nobara-driver-manager-test-{}-{}-{}",
+            name,
+            std::process::id(),
+            COUNT.fetch_add(1, std::sync::atomic::Ordering::Relaxed)
+        ));
+        let _ = std::fs::remove_dir_all(&root);
+        std::fs::create_dir_all(&root).unwrap();
+        Self { root }
+    }
+
+    fn path(&self, path: &str) -> PathBuf {
+        self.root.join(path.trim_start_matches('/'))
+    }
+
+    pub fn write(&self, path: &str, content: &str) -> &Self {
+        let path = self.path(path);
+        std::fs::create_dir_all(path.parent().unwrap()).unwrap();
+        std::fs::write(path, content).unwrap();
+        self
+    }
+
+    pub fn mkdir(&self, path: &str) -> &Self {
+        std::fs::create_dir_all(self.path(path)).unwrap();
+        self
+    }
+
+    /// `target` is taken as is, so relative links resolve like they do in sysfs.
+    pub fn symlink(&self, path: &str, target: &str) -> &Self {
+        let path = self.path(path);
+        std::fs::create_dir_all(path.parent().unwrap()).unwrap();
+        std::os::unix::fs::symlink(target, path).unwrap();
+        self
+    }
+
+    /// Runs `f` with SYSROOT_ENV pointing at the tree. The variable is process wide, so tests
+    /// using it take turns.
+    pub fn run<T>(&self, f: impl FnOnce() -> T) -> T {
+        static LOCK: std::sync::Mutex<()> = std::sync::Mutex::new(());
+        let _guard = LOCK.lock().unwrap_or_else(|e| e.into_inner());
+        std::env::set_var(SYSROOT_ENV, &self.root);
+        let result = std::panic::catch_unwind(std::panic::AssertUnwindSafe(f));
+        std::env::remove_var(SYSROOT_ENV);
+        result.unwrap_or_else(|e| std::panic::resume_unwind(e))
+    }
+}
+
+#[cfg(test)]
+impl Drop for FakeSysroot {
+    fn drop(&mut self) {
+        let _ = std::fs::remove_dir_all(&self.root);
+    }
+}
@@ -1,5 +1,7 @@
+use super::{sysroot::sysroot_path, PreCheckedProfile, ProfileExtras, ProfileSummary};
 use crate::{config::*, ChannelMsg};
 use libcfhdb::usb::*;
+use log::{debug, error, info, warn};
 use std::{
     collections::HashMap,
     fs,
@@ -15,14 +17,16 @@ pub struct PreCheckedUsbDevice {
 
 pub struct PreCheckedUsbProfile {
     profile: CfhdbUsbProfile,
+    extras: ProfileExtras,
     installed: Arc<Mutex<bool>>,
     pub used: Arc<Mutex<bool>>,
 }
 
 impl PreCheckedUsbProfile {
-    pub fn new(profile: CfhdbUsbProfile) -> Self {
+    pub fn new(profile: CfhdbUsbProfile, extras: ProfileExtras) -> Self {
         Self {
             profile,
+            extras,
             installed: Arc::new(Mutex::new(false)),
             used: Arc::new(Mutex::new(false)),
         }
@@ -30,6 +34,9 @@ impl PreCheckedUsbProfile {
     pub fn profile(&self) -> CfhdbUsbProfile {
         self.profile.clone()
     }
+    pub fn interface_class_codes(&self) -> Vec<String> {
+        self.extras.interface_class_codes.clone()
+    }
     pub fn installed(&self) -> bool {
         self.installed.lock().unwrap().clone()
     }
@@ -38,6 +45,33 @@ impl PreCheckedUsbProfile {
     }
 }
 
+impl PreCheckedProfile for PreCheckedUsbProfile {
+    fn summary(&self) -> ProfileSummary {
+        ProfileSummary {
+            bus: "usb",
+            codename: self.profile.codename.clone(),
+            i18n_desc: self.profile.i18n_desc.clone(),
+            icon_name: self.profile.icon_name.clone(),
+            license: self.profile.license.clone(),
+            packages: self.profile.packages.clone(),
+            install_script: self.profile.install_script.clone(),
+            remove_script: self.profile.remove_script.clone(),
+            experimental: self.profile.experimental,
+            removable: self.profile.removable,
+            veiled: self.profile.veiled,
+            priority: self.profile.priority,
+            kernel_args: self.extras.kernel_args.clone(),
+            provides_firmware: self.extras.provides_firmware.clone(),
+        }
+    }
+    fn is_installed(&self) -> bool {
+        self.installed()
+    }
+    fn refresh_installed(&self) {
+        self.update_installed();
+    }
+}
+
 pub fn get_usb_devices(
     profiles: &[Arc<PreCheckedUsbProfile>],
 ) -> Option<HashMap<String, Vec<PreCheckedUsbDevice>>> {
@@ -66,8 +100,13 @@ fn get_pre_checked_device(
     device: CfhdbUsbDevice,
 ) -> PreCheckedUsbDevice {
     let mut available_profiles = vec![];
+    let interface_class_codes: Vec<String> = get_usb_interfaces(&device.sysfs_busid)
+        .into_iter()
+        .map(|x| x.class_code)
+        .collect();
     for profile_arc in profile_data.iter() {
         let profile = profile_arc.profile();
+        let profile_interface_class_codes = profile_arc.interface_class_codes();
         let matching = {
             if (profile.blacklisted_class_codes.contains(&"*".to_owned())
                 || profile.blacklisted_class_codes.contains(&device.class_code))
@@ -79,7 +118,11 @@ fn get_pre_checked_device(
                 false
             } else {
                 (profile.class_codes.contains(&"*".to_owned())
-                    || profile.class_codes.contains(&device.class_code))
+                    || profile.class_codes.contains(&device.class_code)
+                    || profile_interface_class_codes.contains(&"*".to_owned())
+                    || interface_class_codes
+                        .iter()
+                        .any(|x| profile_interface_class_codes.contains(x)))
                     && (profile.vendor_ids.contains(&"*".to_owned())
                         || profile.vendor_ids.contains(&device.vendor_id))
                     && (profile.product_ids.contains(&"*".to_owned())
@@ -88,6 +131,12 @@ fn get_pre_checked_device(
         };
 
         if matching {
+            debug!(
+                target: "matcher",
+                "USB device {} matched profile {}",
+                device.sysfs_busid,
+                profile.codename
+            );
             available_profiles.push(profile_arc.clone());
         }
     }
@@ -99,7 +148,7 @@ fn get_pre_checked_device(
 
 pub fn get_usb_profiles_from_url(
     sender: &async_channel::Sender<ChannelMsg>,
-) -> Result<Vec<CfhdbUsbProfile>, std::io::Error> {
+) -> Result<Vec<(CfhdbUsbProfile, ProfileExtras)>, std::io::Error> {
     let cached_db_path = Path::new("/var/cache/cfhdb/usb.json");
     sender
         .send_blocking(ChannelMsg::OutputLine(format!(
@@ -112,8 +161,10 @@ pub fn get_usb_profiles_from_url(
         .timeout(std::time::Duration::from_secs(5))
         .build()
         .unwrap();
+    info!(target: "usb", "Downloading profiles from {}", *USB_PROFILE_JSON_URL);
     let data = match client.get(USB_PROFILE_JSON_URL.clone()).send() {
         Ok(t) => {
+            info!(target: "usb", "Profile download successful");
             sender
                 .send_blocking(ChannelMsg::OutputLine(format!(
                     "[{}] {}",
@@ -126,7 +177,8 @@ pub fn get_usb_profiles_from_url(
             let _ = fs::write(cached_db_path, &cache);
             cache
         }
-        Err(_) => {
+        Err(e) => {
+            warn!(target: "usb", "Profile download failed: {}", e);
             sender
                 .send_blocking(ChannelMsg::OutputLine(format!(
                     "[{}] {}",
@@ -135,6 +187,11 @@ pub fn get_usb_profiles_from_url(
                 )))
                 .expect("Channel closed");
             if cached_db_path.exists() {
+                info!(
+                    target: "usb",
+                    "Using cached profiles from {}",
+                    cached_db_path.display()
+                );
                 sender
                     .send_blocking(ChannelMsg::OutputLine(format!(
                         "[{}] {}",
@@ -144,6 +201,7 @@ pub fn get_usb_profiles_from_url(
                     .expect("Channel closed");
                 fs::read_to_string(cached_db_path).unwrap()
             } else {
+                error!(target: "usb", "No cached profiles found, giving up");
                 sender
                     .send_blocking(ChannelMsg::OutputLine(format!(
                         "[{}] {}",
@@ -289,9 +347,62 @@ pub fn get_usb_profiles_from_url(
                 veiled,
                 priority: priority as i32,
             };
-            profiles_array.push(profile_struct);
-            profiles_array.sort_by_key(|x| x.priority);
+            profiles_array.push((profile_struct, ProfileExtras::from_json(profile)));
+            profiles_array.sort_by_key(|x| x.0.priority);
         }
     }
     Ok(profiles_array)
 }
+
+/// One interface of a USB device, which is what drivers bind to.
+pub struct UsbInterface {
+    pub sysfs_busid: String,
+    /// Upper case like the class codes of the device database, `FF` for vendor specific
+    pub class_code: String,
+    pub sub_class_code: String,
+    pub protocol_code: String,
+    pub driver: Option<String>,
+}
+
+pub(crate) fn read_sysfs_value(dir: &Path, file: &str) -> Option<String> {
+    std::fs::read_to_string(dir.join(file))
+        .ok()
+        .map(|x| x.trim().to_string())
+        .filter(|x| !x.is_empty())
+}
+
+/// Returns the interfaces of the active configuration, from the `<busid>:<config>.<interface>` directories.
+pub fn get_usb_interfaces(sysfs_busid: &str) -> Vec<UsbInterface> {
+    let device_dir = sysroot_path(&format!("/sys/bus/usb/devices/{}", sysfs_busid));
+    let Ok(entries) = std::fs::read_dir(&device_dir) else {
+        return vec![];
+    };
+    let mut interfaces: Vec<UsbInterface> = entries
+        .flatten()
+        .filter(|x| {
+            x.file_name()
+                .to_string_lossy()
+                .starts_with(&format!("{}:", sysfs_busid))
+        })
+        .map(|x| {
+            let interface_dir = x.path();
+            UsbInterface {
+                sysfs_busid: x.file_name().to_string_lossy().to_string(),
+                class_code: read_sysfs_value(&interface_dir, "bInterfaceClass")
+                    .unwrap_or_default()
+                    .to_uppercase(),
+                sub_class_code: read_sysfs_value(&interface_dir, "bInterfaceSubClass")
+                    .unwrap_or_default()
+                    .to_uppercase(),
+                protocol_code: read_sysfs_value(&interface_dir, "bInterfaceProtocol")
+                    .unwrap_or_default()
+                    .to_uppercase(),
+                driver: std::fs::read_link(interface_dir.join("driver"))
+                    .ok()
+                    .and_then(|y| y.file_name().map(|z| z.to_string_lossy().to_string())),
+            }
+        })
+        .collect();
+    interfaces.sort_by(|a, b| a.sysfs_busid.cmp(&b.sysfs_busid));
+    interfaces
+}
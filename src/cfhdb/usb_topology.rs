@@ -0,0 +1,150 @@
+use super::{
+    sysroot::sysroot_path,
+    usb::{get_usb_interfaces, read_sysfs_value, UsbInterface},
+};
+use std::path::Path;
+
+/// A device in the USB tree, root hubs included.
+pub struct UsbTopologyNode {
+    pub sysfs_busid: String,
+    pub name: Option<String>,
+    pub vendor_id: String,
+    pub product_id: String,
+    /// Upper case like the class codes of the device database, `09` for hubs
+    pub class_code: String,
+    /// Negotiated speed in Mbit/s as the kernel reports it, `480` for high speed
+    pub speed: Option<String>,
+    pub max_power: Option<String>,
+    /// The port of the parent hub the device is plugged into, None for root hubs
+    pub port: Option<String>,
+    pub interfaces: Vec<UsbInterface>,
+    pub children: Vec<UsbTopologyNode>,
+}
+
+impl UsbTopologyNode {
+    pub fn is_hub(&self) -> bool {
+        self.class_code == "09"
+    }
+}
+
+/// A host controller and the root hubs it exposes, usually one per USB generation.
+pub struct UsbController {
+    /// The sysfs name of the parent device, a PCI address for most controllers
+    pub sysfs_busid: String,
+    pub root_hubs: Vec<UsbTopologyNode>,
+}
+
+/// The parent of `1-2.3` is `1-2`, the parent of `1-2` is the root hub `usb1`.
+fn parent_busid(sysfs_busid: &str) -> Option<String> {
+    if sysfs_busid.starts_with("usb") {
+        return None;
+    }
+    match sysfs_busid.rsplit_once('.') {
+        Some((parent, _)) => Some(parent.to_string()),
+        None => Some(format!("usb{}", sysfs_busid.split_once('-')?.0)),
+    }
+}
+
+fn read_node(device_dir: &Path, sysfs_busid: &str) -> UsbTopologyNode {
+    let port = (!sysfs_busid.starts_with("usb")).then(|| {
+        sysfs_busid
+            .rsplit(['.', '-'])
+            .next()
+            .unwrap_or_default()
+            .to_string()
+    });
+    UsbTopologyNode {
+        sysfs_busid: sysfs_busid.to_string(),
+        name: read_sysfs_value(device_dir, "product"),
+        vendor_id: read_sysfs_value(device_dir, "idVendor").unwrap_or_default(),
+        product_id: read_sysfs_value(device_dir, "idProduct").unwrap_or_default(),
+        class_code: read_sysfs_value(device_dir, "bDeviceClass")
+            .unwrap_or_default()
+            .to_uppercase(),
+        speed: read_sysfs_value(device_dir, "speed"),
+        max_power: read_sysfs_value(device_dir, "bMaxPower"),
+        port,
+        interfaces: get_usb_interfaces(sysfs_busid),
+        children: vec![],
+    }
+}
+
+fn attach_children(node: &mut UsbTopologyNode, nodes: &mut Vec<UsbTopologyNode>) {
+    let (mut children, rest): (Vec<UsbTopologyNode>, Vec<UsbTopologyNode>) = nodes
+        .drain(..)
+        .partition(|x| parent_busid(&x.sysfs_busid).as_deref() == Some(&node.sysfs_busid));
+    *nodes = rest;
+    for child in &mut children {
+        attach_children(child, nodes);
+    }
+    children.sort_by_key(|x| x.port.as_ref().and_then(|y| y.parse::<u32>().ok()));
+    node.children = children;
+}
+
+/// Builds the controller, hub, port and device tree from `/sys/bus/usb/devices`.
+pub fn get_usb_topology() -> Vec<UsbController> {
+    let devices_dir = sysroot_path("/sys/bus/usb/devices");
+    let Ok(entries) = std::fs::read_dir(&devices_dir) else {
+        return vec![];
+    };
+    // Interfaces have a `:` in their name and are read along with their device
+    let mut nodes: Vec<UsbTopologyNode> = entries
+        .flatten()
+        .map(|x| x.file_name().to_string_lossy().to_string())
+        .filter(|x| !x.contains(':'))
+        .map(|x| read_node(&devices_dir.join(&x), &x))
+        .collect();
+
+    let mut root_hubs: Vec<UsbTopologyNode> = vec![];
+    let mut index = 0;
+    while index < nodes.len() {
+        if parent_busid(&nodes[index].sysfs_busid).is_none() {
+            root_hubs.push(nodes.remove(index));
+        } else {
+            index += 1;
+        }
+    }
+    root_hubs.sort_by_key(|x| {
+        x.sysfs_busid
+            .trim_start_matches("usb")
+            .parse::<u32>()
+            .unwrap_or_default()
+    });
+
+    let mut controllers: Vec<UsbController> = vec![];
+    for mut root_hub in root_hubs {
+        attach_children(&mut root_hub, &mut nodes);
+        let controller_busid = std::fs::canonicalize(devices_dir.join(&root_hub.sysfs_busid))
+            .ok()
+            .and_then(|x| {
+                x.parent()
+                    .and_then(|y| y.file_name())
+                    .map(|y| y.to_string_lossy().to_string())
+            })
+            .unwrap_or_default();
+        match controllers
+            .iter_mut()
+            .find(|x| x.sysfs_busid == controller_busid)
+        {
+            Some(controller) => controller.root_hubs.push(root_hub),
+            None => controllers.push(UsbController {
+                sysfs_busid: controller_busid,
+                root_hubs: vec![root_hub],
+            }),
+        }
+    }
+    controllers
+}
+
+/// A readable label for the speed sysfs reports.
+pub fn usb_speed_label(speed: &str) -> String {
+    match speed {
+        "1.5" => t!("usb_topology_speed_low").to_string(),
+        "12" => t!("usb_topology_speed_full").to_string(),
+        "480" => t!("usb_topology_speed_high").to_string(),
+        "5000" => t!("usb_topology_speed_super").to_string(),
+        "10000" => t!("usb_topology_speed_super_plus").to_string(),
+        "20000" => t!("usb_topology_speed_super_plus_2x2").to_string(),
+        _ => t!("usb_topology_speed_other", speed = speed).to_string(),
+    }
+}
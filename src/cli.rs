@@ -0,0 +1,479 @@
+use crate::{
+    build_ui::{load_cfhdb, run_in_lock_script},
+    cfhdb::{
+        bt::PreCheckedBtDevice,
+        dmi::PreCheckedDmiInfo,
+        get_profile_modify_commands,
+        inventory::{
+            compare_inventory_snapshots, get_inventory_snapshot, read_inventory_snapshot,
+            write_inventory_snapshot,
+        },
+        manifest::{export_manifest, plan_manifest, read_manifest, write_manifest},
+        pci::PreCheckedPciDevice,
+        recommendation::get_pending_recommendations,
+        sysfs_bus::SysfsBuses,
+        usb::PreCheckedUsbDevice,
+        PreCheckedProfile,
+    },
+    config::VERSION,
+    ChannelMsg,
+};
+use log::{error, info};
+use std::{
+    path::{Path, PathBuf},
+    sync::Arc,
+};
+
+enum CliAction {
+    InstallRecommended,
+    ExportManifest(PathBuf),
+    ApplyManifest(PathBuf),
+    SaveInventory(PathBuf),
+    CompareInventory(PathBuf),
+}
+
+/// Everything load_cfhdb fetches, as the GUI gets it through ChannelMsg::SuccessMsgDeviceFetch.
+struct LoadedDevices {
+    hashmap_pci: Option<Vec<(String, Vec<PreCheckedPciDevice>)>>,
+    hashmap_usb: Option<Vec<(String, Vec<PreCheckedUsbDevice>)>>,
+    dmi_info: PreCheckedDmiInfo,
+    hashmap_bt: Option<Vec<(String, Vec<PreCheckedBtDevice>)>>,
+    sysfs_buses: SysfsBuses,
+    all_profiles: Vec<Arc<dyn PreCheckedProfile>>,
+}
+
+// Any of these makes the command line ours, anything else is left to GApplication
+const CLI_FLAGS: &[&str] = &[
+    "--install-recommended",
+    "--export-manifest",
+    "--apply-manifest",
+    "--save-inventory",
+    "--compare-inventory",
+    "--dry-run",
+    "--help",
+    "-h",
+];
+
+/// Handles the non-interactive command line flags, returns None if the GUI should be started instead.
+pub fn run_cli(args: &[String]) -> Option<i32> {
+    if !args.iter().skip(1).any(|x| CLI_FLAGS.contains(&x.as_str())) {
+        return None;
+    }
+
+    let mut action = None;
+    let mut dry_run = false;
+
+    let mut args_iter = args.iter().skip(1);
+    while let Some(arg) = args_iter.next() {
+        let new_action = match arg.as_str() {
+            "--install-recommended" => CliAction::InstallRecommended,
+            "--export-manifest"
+            | "--apply-manifest"
+            | "--save-inventory"
+            | "--compare-inventory" => {
+                let Some(file_path) = args_iter.next() else {
+                    eprintln!("{}", t!("cli_missing_file_argument", argument = arg));
+                    return Some(2);
+                };
+                let file_path = PathBuf::from(file_path);
+                match arg.as_str() {
+                    "--export-manifest" => CliAction::ExportManifest(file_path),
+                    "--apply-manifest" => CliAction::ApplyManifest(file_path),
+                    "--save-inventory" => CliAction::SaveInventory(file_path),
+                    _ => CliAction::CompareInventory(file_path),
+                }
+            }
+            "--dry-run" => {
+                dry_run = true;
+                continue;
+            }
+            "--help" | "-h" => {
+                print_help(&args[0]);
+                return Some(0);
+            }
+            _ => {
+                eprintln!("{}", t!("cli_unknown_argument", argument = arg));
+                print_help(&args[0]);
+                return Some(2);
+            }
+        };
+        if action.is_some() {
+            eprintln!("{}", t!("cli_multiple_actions"));
+            return Some(2);
+        }
+        action = Some(new_action);
+    }
+
+    match action {
+        Some(CliAction::InstallRecommended) => Some(install_recommended_cli(dry_run)),
+        Some(CliAction::ApplyManifest(file_path)) => Some(apply_manifest_cli(&file_path, dry_run)),
+        Some(CliAction::ExportManifest(_))
+        | Some(CliAction::SaveInventory(_))
+        | Some(CliAction::CompareInventory(_))
+        | None
+            if dry_run =>
+        {
+            eprintln!("{}", t!("cli_dry_run_without_action"));
+            Some(2)
+        }
+        Some(CliAction::ExportManifest(file_path)) => Some(export_manifest_cli(&file_path)),
+        Some(CliAction::SaveInventory(file_path)) => Some(save_inventory_cli(&file_path)),
+        Some(CliAction::CompareInventory(file_path)) => Some(compare_inventory_cli(&file_path)),
+        None => None,
+    }
+}
+
+fn print_help(program: &str) {
+    println!("nobara-driver-manager {}", VERSION);
+    println!();
+    println!("{}", t!("cli_help_usage", program = program));
+    println!();
+    println!(
+        "  --install-recommended     {}",
+        t!("cli_help_install_recommended")
+    );
+    println!(
+        "  --export-manifest FILE    {}",
+        t!("cli_help_export_manifest")
+    );
+    println!(
+        "  --apply-manifest FILE     {}",
+        t!("cli_help_apply_manifest")
+    );
+    println!(
+        "  --save-inventory FILE     {}",
+        t!("cli_help_save_inventory")
+    );
+    println!(
+        "  --compare-inventory FILE  {}",
+        t!("cli_help_compare_inventory")
+    );
+    println!("  --dry-run                 {}", t!("cli_help_dry_run"));
+    println!("  -h, --help                {}", t!("cli_help_help"));
+}
+
+fn load_devices() -> Option<LoadedDevices> {
+    let (status_sender, status_receiver) = async_channel::unbounded::<ChannelMsg>();
+    load_cfhdb(status_sender);
+
+    loop {
+        match status_receiver.recv_blocking() {
+            // Progress goes to stderr so stdout stays the result of the action
+            Ok(ChannelMsg::OutputLine(line)) => eprintln!("{}", line),
+            Ok(ChannelMsg::SuccessMsgDeviceFetch(
+                hashmap_pci,
+                hashmap_usb,
+                dmi_info,
+                hashmap_bt,
+                pci_profiles,
+                usb_profiles,
+                dmi_profiles,
+                bt_profiles,
+                sysfs_buses,
+            )) => {
+                let mut all_profiles: Vec<Arc<dyn PreCheckedProfile>> = vec![];
+                all_profiles.extend(
+                    dmi_profiles
+                        .into_iter()
+                        .map(|x| x as Arc<dyn PreCheckedProfile>),
+                );
+                all_profiles.extend(
+                    pci_profiles
+                        .into_iter()
+                        .map(|x| x as Arc<dyn PreCheckedProfile>),
+                );
+                all_profiles.extend(
+                    usb_profiles
+                        .into_iter()
+                        .map(|x| x as Arc<dyn PreCheckedProfile>),
+                );
+                all_profiles.extend(
+                    bt_profiles
+                        .into_iter()
+                        .map(|x| x as Arc<dyn PreCheckedProfile>),
+                );
+                all_profiles.extend(sysfs_buses.all_profiles());
+                return Some(LoadedDevices {
+                    hashmap_pci,
+                    hashmap_usb,
+                    dmi_info,
+                    hashmap_bt,
+                    sysfs_buses,
+                    all_profiles,
+                });
+            }
+            // Like the GUI, a failed step is reported through OutputLine and loading goes on,
+            // the channel only closes early if the loader thread gave up
+            Ok(ChannelMsg::FailMsg) => {}
+            Err(_) => {
+                error!(target: "loader", "Loading the device database failed");
+                eprintln!("{}", t!("cli_load_failed"));
+                return None;
+            }
+            Ok(ChannelMsg::UpdateMsg) => {}
+            Ok(ChannelMsg::SuccessMsg) => {
+                error!(target: "loader", "Unexpected message from the loader thread");
+                eprintln!("{}", t!("cli_load_failed"));
+                return None;
+            }
+        }
+    }
+}
+
+/// Runs the profile operations in one privileged script, the same way the GUI batch dialog does.
+fn run_jobs(jobs: Vec<(Arc<dyn PreCheckedProfile>, String)>, dry_run: bool) -> i32 {
+    let mut script = String::new();
+    for (profile, opreation) in &jobs {
+        let profile_summary = profile.summary();
+        match get_profile_modify_commands(&profile_summary, opreation) {
+            Ok(Some(t)) => script.push_str(&format!(
+                "echo '==> {} {}'\n{}\n",
+                opreation, profile_summary.codename, t
+            )),
+            Ok(None) => {}
+            Err(reason) => {
+                eprintln!("{}: {}", profile_summary.codename, reason);
+                return 1;
+            }
+        }
+    }
+
+    if dry_run {
+        println!("{}", t!("cli_dry_run_done"));
+        return 0;
+    }
+
+    if script.is_empty() {
+        return 0;
+    }
+
+    info!(
+        target: "executor",
+        "Running {} profile operation(s) from the command line",
+        jobs.len()
+    );
+    let (log_loop_sender, log_loop_receiver) = async_channel::unbounded::<ChannelMsg>();
+    std::thread::spawn(move || {
+        run_in_lock_script(
+            &log_loop_sender,
+            &format!("#! /bin/bash\nset -e\n{}", script),
+        );
+    });
+
+    while let Ok(state) = log_loop_receiver.recv_blocking() {
+        match state {
+            ChannelMsg::OutputLine(line) => println!("{}", line),
+            ChannelMsg::SuccessMsg => {
+                println!("{}", t!("batch_profile_modify_dialog_body_successful"));
+                return 0;
+            }
+            ChannelMsg::FailMsg => {
+                eprintln!("{}", t!("batch_profile_modify_dialog_body_failed"));
+                return 1;
+            }
+            ChannelMsg::UpdateMsg => {}
+            ChannelMsg::SuccessMsgDeviceFetch(..) => {
+                error!(target: "executor", "Unexpected message from the executor thread");
+                eprintln!("{}", t!("batch_profile_modify_dialog_body_failed"));
+                return 1;
+            }
+        }
+    }
+
+    1
+}
+
+fn install_recommended_cli(dry_run: bool) -> i32 {
+    let Some(devices) = load_devices() else {
+        return 1;
+    };
+
+    let recommendations = get_pending_recommendations(
+        &devices.hashmap_pci,
+        &devices.hashmap_usb,
+        &devices.dmi_info,
+        &devices.hashmap_bt,
+        &devices.sysfs_buses,
+    );
+
+    if recommendations.is_empty() {
+        println!("{}", t!("install_recommended_dialog_body_empty"));
+        return 0;
+    }
+
+    for recommendation in &recommendations {
+        let profile_summary = recommendation.profile.summary();
+        println!(
+            "{}",
+            t!(
+                "cli_recommended_profile",
+                bus = profile_summary.bus.to_uppercase(),
+                codename = profile_summary.codename,
+                device = recommendation.device
+            )
+        );
+    }
+
+    run_jobs(
+        recommendations
+            .into_iter()
+            .map(|x| (x.profile, String::from("install")))
+            .collect(),
+        dry_run,
+    )
+}
+
+fn export_manifest_cli(file_path: &Path) -> i32 {
+    let Some(devices) = load_devices() else {
+        return 1;
+    };
+
+    let manifest = export_manifest(
+        &devices.hashmap_pci,
+        &devices.hashmap_usb,
+        &devices.dmi_info,
+        &devices.hashmap_bt,
+        &devices.sysfs_buses,
+        &devices.all_profiles,
+    );
+    match write_manifest(file_path, &manifest) {
+        Ok(_) => {
+            println!(
+                "{}",
+                t!(
+                    "manifest_export_done_body",
+                    count = manifest.profiles.len(),
+                    path = file_path.display()
+                )
+            );
+            0
+        }
+        Err(e) => {
+            error!(target: "manifest", "Could not write {}: {}", file_path.display(), e);
+            eprintln!("{}: {}", t!("manifest_export_failed_heading"), e);
+            1
+        }
+    }
+}
+
+fn apply_manifest_cli(file_path: &Path, dry_run: bool) -> i32 {
+    let manifest = match read_manifest(file_path) {
+        Ok(t) => t,
+        Err(e) => {
+            error!(target: "manifest", "Could not read {}: {}", file_path.display(), e);
+            eprintln!("{}: {}", t!("manifest_apply_failed_heading"), e);
+            return 1;
+        }
+    };
+
+    let Some(devices) = load_devices() else {
+        return 1;
+    };
+
+    let plan = plan_manifest(
+        &manifest,
+        &devices.hashmap_pci,
+        &devices.hashmap_usb,
+        &devices.dmi_info,
+        &devices.hashmap_bt,
+        &devices.sysfs_buses,
+        &devices.all_profiles,
+    );
+
+    for (field, manifest_value, local_value) in &plan.dmi_differences {
+        println!(
+            "! {}: {}",
+            t!(format!("info_{}", field)),
+            t!(
+                "manifest_dmi_difference_subtitle",
+                manifest = manifest_value,
+                local = local_value
+            )
+        );
+    }
+    for profile in &plan.to_remove {
+        let summary = profile.summary();
+        println!("- [{}] {}", summary.bus.to_uppercase(), summary.codename);
+    }
+    for profile in &plan.to_install {
+        let summary = profile.summary();
+        println!("+ [{}] {}", summary.bus.to_uppercase(), summary.codename);
+    }
+    for (manifest_profile, reason) in &plan.not_applicable {
+        println!(
+            "x [{}] {}: {}",
+            manifest_profile.bus.to_uppercase(),
+            manifest_profile.codename,
+            reason
+        );
+    }
+
+    if plan.is_empty() {
+        println!("{}", t!("manifest_plan_dialog_body_empty"));
+        return 0;
+    }
+
+    run_jobs(plan.jobs(), dry_run)
+}
+
+fn save_inventory_cli(file_path: &Path) -> i32 {
+    let Some(devices) = load_devices() else {
+        return 1;
+    };
+
+    let snapshot = get_inventory_snapshot(
+        &devices.hashmap_pci,
+        &devices.hashmap_usb,
+        &devices.dmi_info,
+        &devices.hashmap_bt,
+        &devices.sysfs_buses,
+        &devices.all_profiles,
+    );
+    match write_inventory_snapshot(file_path, &snapshot) {
+        Ok(_) => {
+            info!(
+                target: "inventory",
+                "Saved inventory snapshot to {}",
+                file_path.display()
+            );
+            0
+        }
+        Err(e) => {
+            error!(target: "inventory", "Could not write {}: {}", file_path.display(), e);
+            eprintln!("{}: {}", t!("inventory_save_failed_heading"), e);
+            1
+        }
+    }
+}
+
+/// Prints the text diff between this machine and the snapshot, exits with 1 if they differ like diff(1) does.
+fn compare_inventory_cli(file_path: &Path) -> i32 {
+    let other_snapshot = match read_inventory_snapshot(file_path) {
+        Ok(t) => t,
+        Err(e) => {
+            error!(target: "inventory", "Could not read {}: {}", file_path.display(), e);
+            eprintln!("{}: {}", t!("inventory_compare_failed_heading"), e);
+            return 2;
+        }
+    };
+
+    let Some(devices) = load_devices() else {
+        return 2;
+    };
+
+    let local_snapshot = get_inventory_snapshot(
+        &devices.hashmap_pci,
+        &devices.hashmap_usb,
+        &devices.dmi_info,
+        &devices.hashmap_bt,
+        &devices.sysfs_buses,
+        &devices.all_profiles,
+    );
+    let diff = compare_inventory_snapshots(&local_snapshot, &other_snapshot);
+    println!("{}", diff.to_text(&file_path.display().to_string()));
+    if diff.is_empty() {
+        0
+    } else {
+        1
+    }
+}
@@ -13,6 +13,20 @@ pub struct ProfileUrlConfig {
     usb_json_url: String,
     dmi_json_url: String,
     bt_json_url: String,
+    // The sysfs bus databases are optional so existing configs keep working, a bus without one
+    // is listed without profiles
+    #[serde(default)]
+    storage_json_url: Option<String>,
+    #[serde(default)]
+    input_json_url: Option<String>,
+    #[serde(default)]
+    display_json_url: Option<String>,
+    #[serde(default)]
+    sound_json_url: Option<String>,
+    #[serde(default)]
+    cpu_json_url: Option<String>,
+    #[serde(default)]
+    network_json_url: Option<String>,
 }
 
 pub fn distro_package_manager(opreation: &str, package_list: &str) -> String {
@@ -24,6 +38,12 @@ lazy_static::lazy_static! {
     pub static ref USB_PROFILE_JSON_URL: String = get_profile_url_config().usb_json_url;
     pub static ref DMI_PROFILE_JSON_URL: String = get_profile_url_config().dmi_json_url;
     pub static ref BT_PROFILE_JSON_URL: String = get_profile_url_config().bt_json_url;
+    pub static ref STORAGE_PROFILE_JSON_URL: Option<String> = get_profile_url_config().storage_json_url;
+    pub static ref INPUT_PROFILE_JSON_URL: Option<String> = get_profile_url_config().input_json_url;
+    pub static ref DISPLAY_PROFILE_JSON_URL: Option<String> = get_profile_url_config().display_json_url;
+    pub static ref SOUND_PROFILE_JSON_URL: Option<String> = get_profile_url_config().sound_json_url;
+    pub static ref CPU_PROFILE_JSON_URL: Option<String> = get_profile_url_config().cpu_json_url;
+    pub static ref NETWORK_PROFILE_JSON_URL: Option<String> = get_profile_url_config().network_json_url;
 }
 
 fn get_profile_url_config() -> ProfileUrlConfig {
@@ -0,0 +1,186 @@
+// Logging
+
+use log::{Level, LevelFilter, Log, Metadata, Record};
+use std::{
+    collections::VecDeque,
+    fs,
+    io::Write,
+    path::{Path, PathBuf},
+    sync::Mutex,
+};
+use systemd_journal_logger::JournalLog;
+
+const JOURNAL_SOCKET_PATH: &str = "/run/systemd/journal/socket";
+const LOG_DIR_NAME: &str = "nobara-driver-manager";
+const LOG_FILE_NAME: &str = "nobara-driver-manager.log";
+const LOG_FILE_MAX_SIZE: u64 = 1024 * 1024;
+const LOG_FILE_ROTATIONS: usize = 3;
+const LOG_BUFFER_MAX_ENTRIES: usize = 5000;
+
+#[derive(Clone)]
+pub struct LogEntry {
+    pub timestamp: String,
+    pub level: Level,
+    pub target: String,
+    pub message: String,
+}
+
+impl LogEntry {
+    pub fn to_line(&self) -> String {
+        format!(
+            "{} [{}] {}: {}",
+            self.timestamp, self.level, self.target, self.message
+        )
+    }
+}
+
+struct RotatingFile {
+    path: PathBuf,
+    file: Option<fs::File>,
+    size: u64,
+}
+
+impl RotatingFile {
+    fn open(path: PathBuf) -> Self {
+        if let Ok(metadata) = fs::metadata(&path) {
+            if metadata.len() > LOG_FILE_MAX_SIZE {
+                rotate_log_files(&path);
+            }
+        }
+        let file = fs::OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(&path)
+            .ok();
+        let size = fs::metadata(&path).map(|x| x.len()).unwrap_or(0);
+        Self { path, file, size }
+    }
+
+    fn write_line(&mut self, line: &str) {
+        if self.size > LOG_FILE_MAX_SIZE {
+            *self = Self::open(self.path.clone());
+        }
+        if let Some(file) = self.file.as_mut() {
+            if writeln!(file, "{}", line).is_ok() {
+                self.size += line.len() as u64 + 1;
+            }
+        }
+    }
+}
+
+enum LogSink {
+    Journal(JournalLog),
+    File(Mutex<RotatingFile>),
+    None,
+}
+
+struct AppLogger {
+    sink: LogSink,
+    buffer: Mutex<VecDeque<LogEntry>>,
+}
+
+impl Log for AppLogger {
+    fn enabled(&self, metadata: &Metadata) -> bool {
+        metadata.level() <= log::max_level()
+    }
+
+    fn log(&self, record: &Record) {
+        if !self.enabled(record.metadata()) {
+            return;
+        }
+        let entry = LogEntry {
+            timestamp: current_timestamp(),
+            level: record.level(),
+            target: record.target().to_string(),
+            message: record.args().to_string(),
+        };
+        match &self.sink {
+            LogSink::Journal(journal) => journal.log(record),
+            LogSink::File(file) => file.lock().unwrap().write_line(&entry.to_line()),
+            LogSink::None => {}
+        }
+        let mut buffer = self.buffer.lock().unwrap();
+        if buffer.len() >= LOG_BUFFER_MAX_ENTRIES {
+            buffer.pop_front();
+        }
+        buffer.push_back(entry);
+    }
+
+    fn flush(&self) {
+        match &self.sink {
+            LogSink::Journal(journal) => journal.flush(),
+            LogSink::File(file) => {
+                if let Some(file) = file.lock().unwrap().file.as_mut() {
+                    let _ = file.flush();
+                }
+            }
+            LogSink::None => {}
+        }
+    }
+}
+
+lazy_static::lazy_static! {
+    static ref LOGGER: AppLogger = AppLogger {
+        sink: get_log_sink(),
+        buffer: Mutex::new(VecDeque::new()),
+    };
+}
+
+/// Installs the application logger, messages go to the systemd journal when it is running
+/// and to a rotating file under $XDG_STATE_HOME/nobara-driver-manager otherwise.
+pub fn init_logger() {
+    match log::set_logger(&*LOGGER) {
+        Ok(_) => log::set_max_level(LevelFilter::Debug),
+        Err(e) => eprintln!("could not install logger: {}", e),
+    }
+}
+
+/// Returns a copy of the messages logged during this session, oldest first.
+pub fn get_log_entries() -> Vec<LogEntry> {
+    LOGGER.buffer.lock().unwrap().iter().cloned().collect()
+}
+
+fn get_log_sink() -> LogSink {
+    if Path::new(JOURNAL_SOCKET_PATH).exists() {
+        match JournalLog::new() {
+            Ok(journal) => {
+                return LogSink::Journal(journal.with_syslog_identifier(LOG_DIR_NAME.to_string()))
+            }
+            Err(e) => eprintln!("could not connect to the systemd journal: {}", e),
+        }
+    }
+    match get_log_dir() {
+        Some(log_dir) => match fs::create_dir_all(&log_dir) {
+            Ok(_) => LogSink::File(Mutex::new(RotatingFile::open(log_dir.join(LOG_FILE_NAME)))),
+            Err(e) => {
+                eprintln!("could not create {}: {}", log_dir.display(), e);
+                LogSink::None
+            }
+        },
+        None => LogSink::None,
+    }
+}
+
+fn get_log_dir() -> Option<PathBuf> {
+    let state_home = match std::env::var("XDG_STATE_HOME") {
+        Ok(t) if !t.is_empty() => PathBuf::from(t),
+        _ => PathBuf::from(std::env::var("HOME").ok()?).join(".local/state"),
+    };
+    Some(state_home.join(LOG_DIR_NAME))
+}
+
+fn rotate_log_files(path: &Path) {
+    let rotated_path = |index: usize| PathBuf::from(format!("{}.{}", path.display(), index));
+    let _ = fs::remove_file(rotated_path(LOG_FILE_ROTATIONS));
+    for index in (1..LOG_FILE_ROTATIONS).rev() {
+        let _ = fs::rename(rotated_path(index), rotated_path(index + 1));
+    }
+    let _ = fs::rename(path, rotated_path(1));
+}
+
+fn current_timestamp() -> String {
+    gtk::glib::DateTime::now_local()
+        .and_then(|x| x.format("%F %T"))
+        .map(|x| x.to_string())
+        .unwrap_or_default()
+}
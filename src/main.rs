@@ -25,6 +25,7 @@ pub enum ChannelMsg {
         Vec<Arc<PreCheckedUsbProfile>>,
         Vec<Arc<PreCheckedDmiProfile>>,
         Vec<Arc<PreCheckedBtProfile>>,
+        SysfsBuses,
     ),
     FailMsg,
 }
@@ -32,12 +33,15 @@ pub enum ChannelMsg {
 // application crates
 mod build_ui;
 mod cfhdb;
+mod cli;
+mod logger;
 
 use crate::{
     build_ui::build_ui,
     cfhdb::{
         bt::{PreCheckedBtDevice, PreCheckedBtProfile},
         dmi::{PreCheckedDmiInfo, PreCheckedDmiProfile},
+        sysfs_bus::SysfsBuses,
     },
 };
 
@@ -48,11 +52,19 @@ i18n!("locales", fallback = "en_US");
 
 /// main function
 fn main() {
+    logger::init_logger();
+
     let current_locale = get_locale()
         .unwrap_or_else(|| String::from("en-US"))
         .replace("-", "_");
 
     rust_i18n::set_locale(&current_locale);
+
+    let args: Vec<String> = env::args().collect();
+    if let Some(exit_code) = cli::run_cli(&args) {
+        std::process::exit(exit_code);
+    }
+
     let application = adw::Application::new(Some(APP_ID), Default::default());
     application.connect_startup(|app| {
         // The CSS "magic" happens here.